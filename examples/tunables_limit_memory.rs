@@ -4,8 +4,8 @@ use std::sync::Arc;
 use wasmer::{
     imports,
     vm::{self, MemoryError, MemoryStyle, TableStyle, VMMemoryDefinition, VMTableDefinition},
-    wat2wasm, BaseTunables, Instance, Memory, MemoryType, Module, Pages, Store, TableType, Target,
-    Tunables,
+    wat2wasm, BaseTunables, Instance, Memory, MemoryType, Module, ModuleLimits, Pages, Store,
+    TableType, Target, Tunables,
 };
 use wasmer_compiler_cranelift::Cranelift;
 use wasmer_engine_jit::JIT;
@@ -79,6 +79,13 @@ impl<T: Tunables> Tunables for LimitingTunables<T> {
         self.base.table_style(table)
     }
 
+    /// Get the [`ModuleLimits`] of the modules to compile.
+    ///
+    /// Delegated to base.
+    fn module_limits(&self) -> ModuleLimits {
+        self.base.module_limits()
+    }
+
     /// Create a memory owned by the host given a [`MemoryType`] and a [`MemoryStyle`].
     ///
     /// The requested memory type is validated, adjusted to the limited and then passed to base.
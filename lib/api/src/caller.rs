@@ -0,0 +1,163 @@
+//! The caller module contains the [`Caller`], which gives host functions
+//! access to the instance calling them.
+use crate::exports::Exports;
+use crate::externals::{Extern, Function, Global, Memory, Table};
+use crate::store::Store;
+use crate::{HostEnvInitError, Instance, WasmerEnv};
+use std::fmt;
+use wasmer_vm::{InstanceHandle, WeakInstanceRef};
+
+/// A `Caller` gives a host function access to the exports of the
+/// instance calling it.
+///
+/// Every instance importing a host function gets its own clone of the
+/// function environment, and a `Caller` in it is bound to that instance
+/// by [`WasmerEnv::init_with_instance`], before the start function is
+/// called. Since WebAssembly code can only call a host function through
+/// the import of its own instance, the instance importing the host
+/// function is the caller: when an instance calls the function of
+/// another instance, through an import, the host functions called by
+/// the latter get the latter as their caller. It works for host
+/// functions imported by several instances, and doesn't need any
+/// declaration up front.
+///
+/// The `Caller` doesn't keep the instance alive, to avoid a cycle
+/// between the instance and its host environments.
+///
+/// A `Caller` is a [`WasmerEnv`]; it can be the environment of a host
+/// function, as with [`Function::new_native_with_caller`], or a field of
+/// another environment.
+///
+/// ```
+/// # use wasmer::{imports, Caller, Function, Instance, Module, Store};
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let module = Module::new(&store, r#"
+///     (module
+///       (import "host" "peek" (func $peek (param i32) (result i32)))
+///       (memory (export "memory") 1)
+///       (data (i32.const 8) "\2a")
+///       (func (export "run") (result i32)
+///         i32.const 8
+///         call $peek))
+/// "#)?;
+/// let peek = Function::new_native_with_caller(&store, |caller: &Caller, offset: i32| {
+///     let memory = caller.get_memory("memory").expect("the caller exports a memory");
+///     memory.view::<u8>()[offset as usize].get() as i32
+/// });
+/// let instance = Instance::new(&module, &imports! { "host" => { "peek" => peek } })?;
+///
+/// let run = instance.exports.get_native_function::<(), i32>("run")?;
+/// assert_eq!(run.call()?, 42);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct Caller {
+    /// The instance importing the host function, once bound.
+    instance: Option<(Store, WeakInstanceRef)>,
+}
+
+impl Caller {
+    /// Creates a new `Caller`, bound to no instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the `Caller` is bound to an instance which is still
+    /// alive.
+    ///
+    /// Otherwise, all the getters return `None`.
+    pub fn is_active(&self) -> bool {
+        self.with_instance(|_, _| ()).is_some()
+    }
+
+    /// Returns the [`Store`] of the calling instance.
+    pub fn store(&self) -> Option<Store> {
+        self.with_instance(|store, _| store.clone())
+    }
+
+    /// Returns all the exports of the calling instance.
+    pub fn exports(&self) -> Option<Exports> {
+        self.with_instance(|store, handle| {
+            handle
+                .exports()
+                .map(|(name, index)| {
+                    let export = handle.lookup_by_declaration(index);
+                    (
+                        name.to_string(),
+                        Extern::from_vm_export(store, export.into()),
+                    )
+                })
+                .collect()
+        })
+    }
+
+    /// Gets the export `name` of the calling instance.
+    pub fn get_export(&self, name: &str) -> Option<Extern> {
+        self.with_instance(|store, handle| {
+            handle
+                .lookup(name)
+                .map(|export| Extern::from_vm_export(store, export.into()))
+        })
+        .flatten()
+    }
+
+    /// Gets the exported function `name` of the calling instance.
+    pub fn get_function(&self, name: &str) -> Option<Function> {
+        match self.get_export(name)? {
+            Extern::Function(function) => Some(function),
+            _ => None,
+        }
+    }
+
+    /// Gets the exported global `name` of the calling instance.
+    pub fn get_global(&self, name: &str) -> Option<Global> {
+        match self.get_export(name)? {
+            Extern::Global(global) => Some(global),
+            _ => None,
+        }
+    }
+
+    /// Gets the exported memory `name` of the calling instance.
+    pub fn get_memory(&self, name: &str) -> Option<Memory> {
+        match self.get_export(name)? {
+            Extern::Memory(memory) => Some(memory),
+            _ => None,
+        }
+    }
+
+    /// Gets the exported table `name` of the calling instance.
+    pub fn get_table(&self, name: &str) -> Option<Table> {
+        match self.get_export(name)? {
+            Extern::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    fn with_instance<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&Store, &InstanceHandle) -> R,
+    {
+        let (store, instance) = self.instance.as_ref()?;
+        let handle = InstanceHandle::from(instance.upgrade()?);
+
+        Some(f(store, &handle))
+    }
+}
+
+impl WasmerEnv for Caller {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        self.instance = Some((instance.store().clone(), instance.downgrade()));
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Caller {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Caller")
+            .field("is_active", &self.is_active())
+            .finish()
+    }
+}
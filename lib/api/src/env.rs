@@ -82,8 +82,9 @@ pub trait WasmerEnv: Clone + Send + Sync {
     /// The function that Wasmer will call on your type to let it finish
     /// setting up the environment with data from the `Instance`.
     ///
-    /// This function is called after `Instance` is created but before it is
-    /// returned to the user via `Instance::new`.
+    /// This function is called after `Instance` is created but before its
+    /// start function is called, and before it is returned to the user via
+    /// `Instance::new`.
     fn init_with_instance(&mut self, _instance: &Instance) -> Result<(), HostEnvInitError> {
         Ok(())
     }
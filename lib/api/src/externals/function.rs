@@ -1,3 +1,4 @@
+use crate::caller::Caller;
 use crate::exports::{ExportError, Exportable};
 use crate::externals::Extern;
 use crate::store::Store;
@@ -16,9 +17,9 @@ use std::fmt;
 use std::sync::Arc;
 use wasmer_engine::{Export, ExportFunction, ExportFunctionMetadata};
 use wasmer_vm::{
-    raise_user_trap, resume_panic, wasmer_call_trampoline, ImportInitializerFuncPtr,
-    VMCallerCheckedAnyfunc, VMDynamicFunctionContext, VMExportFunction, VMFunctionBody,
-    VMFunctionEnvironment, VMFunctionKind, VMTrampoline,
+    raise_user_trap, resume_panic, root_externref, wasmer_call_trampoline, with_externref_roots,
+    ImportInitializerFuncPtr, VMCallerCheckedAnyfunc, VMDynamicFunctionContext, VMExportFunction,
+    VMFunctionBody, VMFunctionEnvironment, VMFunctionKind, VMTrampoline,
 };
 
 /// A function defined in the Wasm module
@@ -349,6 +350,31 @@ impl Function {
         }
     }
 
+    /// Creates a new host `Function` (dynamic) with the provided
+    /// signature, receiving the [`Caller`] of the function.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let signature = FunctionType::new(vec![Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_with_caller(&store, &signature, |caller, args| {
+    ///     let memory = caller.get_memory("memory").expect("the caller exports a memory");
+    ///     let value = memory.view::<u8>()[args[0].unwrap_i32() as usize].get();
+    ///     Ok(vec![Value::I32(value as i32)])
+    /// });
+    /// ```
+    pub fn new_with_caller<FT, F>(store: &Store, ty: FT, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&Caller, &[Val]) -> Result<Vec<Val>, RuntimeError> + 'static + Send + Sync,
+    {
+        Self::new_with_env(store, ty, Caller::new(), func)
+    }
+
     /// Creates a new host `Function` from a native function and a provided environment.
     ///
     /// The function signature is automatically retrieved using the
@@ -408,6 +434,31 @@ impl Function {
         }
     }
 
+    /// Creates a new host `Function` from a native function receiving
+    /// the [`Caller`] of the function as first argument.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Caller, Function, Store};
+    /// # let store = Store::default();
+    /// #
+    /// fn peek(caller: &Caller, offset: i32) -> i32 {
+    ///     let memory = caller.get_memory("memory").expect("the caller exports a memory");
+    ///     memory.view::<u8>()[offset as usize].get() as i32
+    /// }
+    ///
+    /// let f = Function::new_native_with_caller(&store, peek);
+    /// ```
+    pub fn new_native_with_caller<F, Args, Rets>(store: &Store, func: F) -> Self
+    where
+        F: HostFunction<Args, Rets, WithEnv, Caller>,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        Self::new_native_with_env(store, Caller::new(), func)
+    }
+
     /// Function used by the deprecated API to call a function with a `&mut` Env.
     ///
     /// This is not a stable API and may be broken at any time.
@@ -528,27 +579,30 @@ impl Function {
             }
         }
 
-        // Call the trampoline.
-        if let Err(error) = unsafe {
-            wasmer_call_trampoline(
-                self.exported.vm_function.vmctx,
-                func.trampoline,
-                self.exported.vm_function.address,
-                values_vec.as_mut_ptr() as *mut u8,
-            )
-        } {
-            return Err(RuntimeError::from_trap(error));
-        }
+        // The `externref` results are rooted until they are loaded.
+        with_externref_roots(|| {
+            // Call the trampoline.
+            if let Err(error) = unsafe {
+                wasmer_call_trampoline(
+                    self.exported.vm_function.vmctx,
+                    func.trampoline,
+                    self.exported.vm_function.address,
+                    values_vec.as_mut_ptr() as *mut u8,
+                )
+            } {
+                return Err(RuntimeError::from_trap(error));
+            }
 
-        // Load the return values out of `values_vec`.
-        for (index, &value_type) in signature.results().iter().enumerate() {
-            unsafe {
-                let ptr = values_vec.as_ptr().add(index);
-                results[index] = Val::read_value_from(ptr, value_type);
+            // Load the return values out of `values_vec`.
+            for (index, &value_type) in signature.results().iter().enumerate() {
+                unsafe {
+                    let ptr = values_vec.as_ptr().add(index);
+                    results[index] = Val::read_value_from(ptr, value_type);
+                }
             }
-        }
 
-        Ok(())
+            Ok(())
+        })
     }
 
     /// Returns the number of parameters that this function takes.
@@ -906,6 +960,9 @@ impl<T: VMDynamicFunction> VMDynamicFunctionCall<T> for VMDynamicFunctionContext
                 )));
             }
             for (i, ret) in returns.iter().enumerate() {
+                if let Val::ExternRef(extern_ref) = ret {
+                    root_externref(extern_ref);
+                }
                 ret.write_value_to(values_vec.add(i));
             }
             Ok(())
@@ -927,8 +984,8 @@ mod inner {
     use std::error::Error;
     use std::marker::PhantomData;
     use std::panic::{self, AssertUnwindSafe};
-    use wasmer_types::{FunctionType, NativeWasmType, Type};
-    use wasmer_vm::{raise_user_trap, resume_panic, VMFunctionBody};
+    use wasmer_types::{ExternRef, FunctionType, NativeWasmType, RawExternRef, Type};
+    use wasmer_vm::{raise_user_trap, resume_panic, root_externref, VMFunctionBody};
 
     /// A trait to convert a Rust value to a `WasmNativeType` value,
     /// or to convert `WasmNativeType` value to a Rust value.
@@ -937,7 +994,7 @@ mod inner {
     /// `FromNativeWasmType` and `ToNativeWasmType` but it creates a
     /// non-negligible complexity in the `WasmTypeList`
     /// implementation.
-    pub unsafe trait FromToNativeWasmType: Copy
+    pub unsafe trait FromToNativeWasmType
     where
         Self: Sized,
     {
@@ -1019,6 +1076,24 @@ mod inner {
         f64 => f64
     );
 
+    /// `ExternRef`s are handed over to WebAssembly as borrowed
+    /// [`RawExternRef`]s, kept alive until the call returns.
+    unsafe impl FromToNativeWasmType for ExternRef {
+        type Native = RawExternRef;
+
+        #[inline]
+        fn from_native(native: Self::Native) -> Self {
+            // The raw reference is kept alive by the call it comes from.
+            unsafe { Self::clone_from_raw(native) }
+        }
+
+        #[inline]
+        fn to_native(self) -> Self::Native {
+            root_externref(&self);
+            self.as_raw()
+        }
+    }
+
     #[cfg(test)]
     mod test_from_to_native_wasm_type {
         use super::*;
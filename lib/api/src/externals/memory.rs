@@ -3,12 +3,27 @@ use crate::externals::Extern;
 use crate::store::Store;
 use crate::{MemoryType, MemoryView};
 use std::convert::TryInto;
+use std::mem::{self, MaybeUninit};
 use std::slice;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
-use wasmer_engine::{Export, ExportMemory};
+use thiserror::Error;
+use wasmer_engine::{Export, ExportMemory, Tunables};
 use wasmer_types::{Pages, ValueType};
 use wasmer_vm::{Memory as RuntimeMemory, MemoryError, VMExportMemory};
 
+/// An error while reading or writing the contents of a [`Memory`].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccessError {
+    /// The access goes past the end of the memory.
+    #[error("memory access out of bounds")]
+    HeapOutOfBounds,
+
+    /// The end of the access overflows the address space.
+    #[error("address calculation overflow")]
+    Overflow,
+}
+
 /// A WebAssembly `memory` instance.
 ///
 /// A memory instance is the runtime representation of a linear memory.
@@ -44,7 +59,7 @@ impl Memory {
     /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
     /// ```
     pub fn new(store: &Store, ty: MemoryType) -> Result<Self, MemoryError> {
-        let tunables = store.tunables();
+        let tunables = store.limited_tunables();
         let style = tunables.memory_style(&ty);
         let memory = tunables.create_host_memory(&ty, &style)?;
 
@@ -109,23 +124,21 @@ impl Memory {
     /// by resizing this Memory.
     #[allow(clippy::mut_from_ref)]
     pub unsafe fn data_unchecked_mut(&self) -> &mut [u8] {
-        let definition = self.memory.vmmemory();
-        let def = definition.as_ref();
-        slice::from_raw_parts_mut(def.base, def.current_length.try_into().unwrap())
+        let definition = self.memory.vmmemory_definition();
+        slice::from_raw_parts_mut(
+            definition.base,
+            definition.current_length.try_into().unwrap(),
+        )
     }
 
     /// Returns the pointer to the raw bytes of the `Memory`.
     pub fn data_ptr(&self) -> *mut u8 {
-        let definition = self.memory.vmmemory();
-        let def = unsafe { definition.as_ref() };
-        def.base
+        self.memory.vmmemory_definition().base
     }
 
     /// Returns the size (in bytes) of the `Memory`.
     pub fn data_size(&self) -> u64 {
-        let definition = self.memory.vmmemory();
-        let def = unsafe { definition.as_ref() };
-        def.current_length.into()
+        self.memory.vmmemory_definition().current_length.into()
     }
 
     /// Returns the size (in [`Pages`]) of the `Memory`.
@@ -220,6 +233,172 @@ impl Memory {
         unsafe { MemoryView::new(base as _, length as u32) }
     }
 
+    /// Reads `buf.len()` bytes of the memory, starting at `offset`, into
+    /// `buf`.
+    ///
+    /// The access is bounds-checked against the current size of the
+    /// memory. Unlike a [`MemoryView`], no reference to the memory
+    /// outlives the call, so the memory can safely grow afterwards; with
+    /// a shared memory, the bytes are read atomically one by one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.write(0x10, b"hello").unwrap();
+    ///
+    /// let mut buf = [0u8; 5];
+    /// m.read(0x10, &mut buf).unwrap();
+    /// assert_eq!(&buf, b"hello");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MemoryAccessError`] if the bytes are not all in the
+    /// memory; `buf` is left untouched then.
+    pub fn read(&self, offset: u64, buf: &mut [u8]) -> Result<(), MemoryAccessError> {
+        unsafe { self.read_raw(offset, buf.as_mut_ptr(), buf.len()) }
+    }
+
+    /// Writes the bytes of `data` to the memory, starting at `offset`.
+    ///
+    /// The access is bounds-checked like [`Memory::read`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MemoryAccessError`] if the bytes don't all fit in the
+    /// memory; the memory is left untouched then.
+    pub fn write(&self, offset: u64, data: &[u8]) -> Result<(), MemoryAccessError> {
+        unsafe { self.write_raw(offset, data.as_ptr(), data.len()) }
+    }
+
+    /// Reads a `T` from the memory, at `offset`.
+    ///
+    /// The offset doesn't have to be aligned for `T`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.write_struct(0x10, &0x1234_5678u32).unwrap();
+    ///
+    /// assert_eq!(m.read_struct::<u32>(0x10), Ok(0x1234_5678));
+    /// assert_eq!(m.read_struct::<u8>(0x10), Ok(0x78));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MemoryAccessError`] if the value is not entirely in
+    /// the memory.
+    pub fn read_struct<T: ValueType>(&self, offset: u64) -> Result<T, MemoryAccessError> {
+        let mut value = MaybeUninit::<T>::uninit();
+
+        unsafe {
+            self.read_raw(offset, value.as_mut_ptr() as *mut u8, mem::size_of::<T>())?;
+
+            // SAFETY: all the bytes have been written, and all the bit
+            // patterns are valid for a `ValueType`.
+            Ok(value.assume_init())
+        }
+    }
+
+    /// Writes `value` to the memory, at `offset`.
+    ///
+    /// The offset doesn't have to be aligned for `T`. The padding bytes
+    /// of `value`, if any, are written as zeros.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MemoryAccessError`] if the value doesn't entirely fit
+    /// in the memory.
+    pub fn write_struct<T: ValueType>(
+        &self,
+        offset: u64,
+        value: &T,
+    ) -> Result<(), MemoryAccessError> {
+        let mut bytes = MaybeUninit::new(*value);
+
+        unsafe {
+            value.zero_padding_bytes(slice::from_raw_parts_mut(
+                bytes.as_mut_ptr() as *mut MaybeUninit<u8>,
+                mem::size_of::<T>(),
+            ));
+
+            self.write_raw(offset, bytes.as_ptr() as *const u8, mem::size_of::<T>())
+        }
+    }
+
+    /// Returns a pointer to the `len` bytes of the memory at `offset`,
+    /// after checking that they are in bounds.
+    ///
+    /// The pointer is only valid until the memory grows: a dynamic memory
+    /// may move then. It must not be kept beyond the current access.
+    fn checked_ptr(&self, offset: u64, len: usize) -> Result<*mut u8, MemoryAccessError> {
+        let end = offset
+            .checked_add(len as u64)
+            .ok_or(MemoryAccessError::Overflow)?;
+
+        // The base and the bounds are read together, as growing the
+        // memory may change both.
+        let definition = self.memory.vmmemory_definition();
+
+        if end > definition.current_length as u64 {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+
+        Ok(unsafe { definition.base.add(offset as usize) })
+    }
+
+    /// # Safety
+    ///
+    /// `dst` must be valid for `len` bytes of writes.
+    unsafe fn read_raw(
+        &self,
+        offset: u64,
+        dst: *mut u8,
+        len: usize,
+    ) -> Result<(), MemoryAccessError> {
+        let src = self.checked_ptr(offset, len)?;
+
+        if self.ty().shared {
+            for i in 0..len {
+                *dst.add(i) = (*(src.add(i) as *const AtomicU8)).load(Ordering::Relaxed);
+            }
+        } else {
+            std::ptr::copy(src, dst, len);
+        }
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// `src` must be valid for `len` bytes of reads.
+    unsafe fn write_raw(
+        &self,
+        offset: u64,
+        src: *const u8,
+        len: usize,
+    ) -> Result<(), MemoryAccessError> {
+        let dst = self.checked_ptr(offset, len)?;
+
+        if self.ty().shared {
+            for i in 0..len {
+                (*(dst.add(i) as *const AtomicU8)).store(*src.add(i), Ordering::Relaxed);
+            }
+        } else {
+            std::ptr::copy(src, dst, len);
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn from_vm_export(store: &Store, wasmer_export: ExportMemory) -> Self {
         Self {
             store: store.clone(),
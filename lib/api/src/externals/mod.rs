@@ -10,7 +10,7 @@ pub use self::function::{
 #[cfg(feature = "deprecated")]
 pub use self::function::{UnsafeMutableEnv, WithUnsafeMutableEnv};
 pub use self::global::Global;
-pub use self::memory::Memory;
+pub use self::memory::{Memory, MemoryAccessError};
 pub use self::table::Table;
 
 use crate::exports::{ExportError, Exportable};
@@ -1,17 +1,22 @@
 use crate::exports::{ExportError, Exportable};
 use crate::externals::Extern;
 use crate::store::Store;
-use crate::types::{Val, ValFuncRef};
+use crate::types::{Val, ValFuncRef, ValType};
 use crate::RuntimeError;
 use crate::TableType;
 use std::sync::Arc;
-use wasmer_engine::{Export, ExportTable};
-use wasmer_vm::{Table as RuntimeTable, VMCallerCheckedAnyfunc, VMExportTable};
+use wasmer_engine::{Export, ExportTable, Tunables};
+use wasmer_vm::{Table as RuntimeTable, TableElement, VMExportTable};
 
 /// A WebAssembly `table` instance.
 ///
 /// The `Table` struct is an array-like structure representing a WebAssembly Table,
-/// which stores function references.
+/// which stores function references, or [`ExternRef`]s.
+///
+/// The `ExternRef`s stored in a table are released when they are
+/// overwritten, or when the table is dropped.
+///
+/// [`ExternRef`]: crate::ExternRef
 ///
 /// A table created by the host or in WebAssembly code will be accessible and
 /// mutable from both host and WebAssembly.
@@ -26,11 +31,30 @@ pub struct Table {
 fn set_table_item(
     table: &dyn RuntimeTable,
     item_index: u32,
-    item: VMCallerCheckedAnyfunc,
+    item: TableElement,
 ) -> Result<(), RuntimeError> {
     table.set(item_index, item).map_err(|e| e.into())
 }
 
+fn value_to_table_element(
+    store: &Store,
+    ty: &TableType,
+    val: Val,
+) -> Result<TableElement, RuntimeError> {
+    match (ty.ty, val) {
+        (ValType::ExternRef, Val::ExternRef(extern_ref)) => Ok(TableElement::ExternRef(extern_ref)),
+        (ValType::ExternRef, _) => Err(RuntimeError::new("val is not externref")),
+        (_, val) => Ok(TableElement::FuncRef(val.into_checked_anyfunc(store)?)),
+    }
+}
+
+fn value_from_table_element(store: &Store, item: TableElement) -> Val {
+    match item {
+        TableElement::FuncRef(anyfunc) => ValFuncRef::from_checked_anyfunc(anyfunc, store),
+        TableElement::ExternRef(extern_ref) => Val::ExternRef(extern_ref),
+    }
+}
+
 impl Table {
     /// Creates a new `Table` with the provided [`TableType`] definition.
     ///
@@ -39,8 +63,8 @@ impl Table {
     /// This function will construct the `Table` using the store
     /// [`BaseTunables`][crate::tunables::BaseTunables].
     pub fn new(store: &Store, ty: TableType, init: Val) -> Result<Self, RuntimeError> {
-        let item = init.into_checked_anyfunc(store)?;
-        let tunables = store.tunables();
+        let item = value_to_table_element(store, &ty, init)?;
+        let tunables = store.limited_tunables();
         let style = tunables.table_style(&ty);
         let table = tunables
             .create_host_table(&ty, &style)
@@ -70,12 +94,12 @@ impl Table {
     /// Retrieves an element of the table at the provided `index`.
     pub fn get(&self, index: u32) -> Option<Val> {
         let item = self.table.get(index)?;
-        Some(ValFuncRef::from_checked_anyfunc(item, &self.store))
+        Some(value_from_table_element(&self.store, item))
     }
 
     /// Sets an element `val` in the Table at the provided `index`.
     pub fn set(&self, index: u32, val: Val) -> Result<(), RuntimeError> {
-        let item = val.into_checked_anyfunc(&self.store)?;
+        let item = value_to_table_element(&self.store, self.ty(), val)?;
         set_table_item(self.table.as_ref(), index, item)
     }
 
@@ -94,14 +118,9 @@ impl Table {
     ///
     /// Returns an error if the `delta` is out of bounds for the table.
     pub fn grow(&self, delta: u32, init: Val) -> Result<u32, RuntimeError> {
-        let item = init.into_checked_anyfunc(&self.store)?;
-        match self.table.grow(delta) {
-            Some(len) => {
-                for i in 0..delta {
-                    set_table_item(self.table.as_ref(), len + i, item.clone())?;
-                }
-                Ok(len)
-            }
+        let item = value_to_table_element(&self.store, self.ty(), init)?;
+        match self.table.grow(delta, item) {
+            Some(len) => Ok(len),
             None => Err(RuntimeError::new(format!(
                 "failed to grow table by `{}`",
                 delta
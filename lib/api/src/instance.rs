@@ -1,13 +1,16 @@
 use crate::exports::Exports;
 use crate::externals::Extern;
+use crate::limiter::LimitedInstance;
 use crate::module::Module;
 use crate::store::Store;
 use crate::{HostEnvInitError, LinkError, RuntimeError};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use wasmer_engine::Resolver;
-use wasmer_vm::{InstanceHandle, VMContext};
+use wasmer_vm::{with_externref_roots, Imports, InstanceHandle, VMContext, WeakInstanceRef};
 
 /// A WebAssembly Instance is a stateful, executable
 /// instance of a WebAssembly [`Module`].
@@ -25,6 +28,23 @@ pub struct Instance {
     pub exports: Exports,
 }
 
+/// The host state of an instance, dropped along with it.
+pub(crate) struct InstanceHostState {
+    /// Releases the instance from the resource limiter, if any.
+    _limited: Option<LimitedInstance>,
+    /// The data attached to the instance by [`Instance::host_data`].
+    data: Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl InstanceHostState {
+    pub(crate) fn new(limited: Option<LimitedInstance>) -> Self {
+        Self {
+            _limited: limited,
+            data: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod send_test {
     use super::*;
@@ -112,8 +132,25 @@ impl Instance {
     ///  * Link errors that happen when plugging the imports into the instance
     ///  * Runtime errors that happen when running the module `start` function.
     pub fn new(module: &Module, resolver: &dyn Resolver) -> Result<Self, InstantiationError> {
+        Self::from_handle(module, module.instantiate(resolver)?)
+    }
+
+    /// Creates a new `Instance` with the `imports` resolved by
+    /// [`Module::resolve_imports`].
+    pub(crate) fn new_with_imports(
+        module: &Module,
+        imports: Imports,
+    ) -> Result<Self, InstantiationError> {
+        Self::from_handle(module, module.instantiate_with_imports(imports)?)
+    }
+
+    /// Creates the `Instance` of a newly instantiated `module`, and
+    /// finishes its instantiation.
+    fn from_handle(
+        module: &Module,
+        mut handle: InstanceHandle,
+    ) -> Result<Self, InstantiationError> {
         let store = module.store();
-        let handle = module.instantiate(resolver)?;
         let exports = module
             .exports()
             .map(|export| {
@@ -125,7 +162,7 @@ impl Instance {
             .collect::<Exports>();
 
         let instance = Self {
-            handle: Arc::new(Mutex::new(handle)),
+            handle: Arc::new(Mutex::new(handle.clone())),
             module: module.clone(),
             exports,
         };
@@ -139,16 +176,22 @@ impl Instance {
         // correct error type returned by `WasmerEnv::init_with_instance` as a generic
         // parameter.
         unsafe {
-            instance
-                .handle
-                .lock()
-                .unwrap()
-                .initialize_host_envs::<HostEnvInitError>(&instance as *const _ as *const _)?;
+            handle.initialize_host_envs::<HostEnvInitError>(&instance as *const _ as *const _)?;
         }
 
+        // The start function is called once the host environments are
+        // initialized, so that the host functions it calls can use them.
+        with_externref_roots(|| module.finish_instantiation(&handle))?;
+
         Ok(instance)
     }
 
+    /// Creates a [`WeakInstanceRef`] to the instance, which doesn't keep
+    /// it alive.
+    pub(crate) fn downgrade(&self) -> WeakInstanceRef {
+        self.handle.lock().unwrap().downgrade()
+    }
+
     /// Gets the [`Module`] associated with this instance.
     pub fn module(&self) -> &Module {
         &self.module
@@ -159,6 +202,47 @@ impl Instance {
         self.module.store()
     }
 
+    /// Returns the data of type `T` attached to this instance, creating
+    /// it with `T::default()` the first time.
+    ///
+    /// The data is shared by the clones of the instance, and dropped
+    /// along with it. It lets the host keep its own state per instance.
+    ///
+    /// ```
+    /// # use std::sync::atomic::{AtomicU32, Ordering};
+    /// # use wasmer::{imports, Instance, Module, Store};
+    /// # fn main() -> anyhow::Result<()> {
+    /// #[derive(Default)]
+    /// struct Calls(AtomicU32);
+    ///
+    /// let store = Store::default();
+    /// let module = Module::new(&store, "(module)")?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// instance.host_data::<Calls>().0.fetch_add(1, Ordering::SeqCst);
+    /// assert_eq!(instance.clone().host_data::<Calls>().0.load(Ordering::SeqCst), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn host_data<T>(&self) -> Arc<T>
+    where
+        T: Any + Default + Send + Sync,
+    {
+        let handle = self.handle.lock().unwrap();
+        let host_state = handle
+            .host_state()
+            .downcast_ref::<InstanceHostState>()
+            .expect("the host state of an instance");
+        let data = host_state
+            .data
+            .lock()
+            .unwrap()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Arc::new(T::default()))
+            .clone();
+        data.downcast()
+            .unwrap_or_else(|_| unreachable!("the data is keyed by its type"))
+    }
+
     #[doc(hidden)]
     pub fn vmctx_ptr(&self) -> *mut VMContext {
         self.handle.lock().unwrap().vmctx_ptr()
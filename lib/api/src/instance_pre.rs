@@ -0,0 +1,143 @@
+use crate::instance::{Instance, InstantiationError};
+use crate::module::Module;
+use crate::LinkError;
+use std::fmt;
+use std::sync::Arc;
+use wasmer_engine::{Export, ImportError, Resolver};
+use wasmer_vm::Imports;
+
+/// A [`Module`] whose imports have been resolved and type-checked
+/// once, ready to be instantiated many times.
+///
+/// Instantiating an `InstancePre` doesn't look the imports up by name,
+/// nor resolve them, again, which makes it cheaper than [`Instance::new`]
+/// when the same module is instantiated with the same imports over and
+/// over, e.g. once per request.
+///
+/// All the instances share the imported items: an imported memory,
+/// table or global is shared by all of them, and every instance gets its
+/// own clone of the host function environments.
+///
+/// ```
+/// # use wasmer::{imports, Function, InstancePre, Module, Store};
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let module = Module::new(&store, r#"
+///     (module
+///       (import "host" "double" (func $double (param i32) (result i32)))
+///       (func (export "run") (result i32)
+///         i32.const 21
+///         call $double))
+/// "#)?;
+/// let imports = imports! {
+///     "host" => {
+///         "double" => Function::new_native(&store, |x: i32| x * 2),
+///     },
+/// };
+/// let instance_pre = InstancePre::new(&module, &imports)?;
+///
+/// for _ in 0..3 {
+///     let instance = instance_pre.instantiate()?;
+///     let run = instance.exports.get_native_function::<(), i32>("run")?;
+///     assert_eq!(run.call()?, 42);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InstancePre {
+    module: Module,
+    imports: Arc<PreResolvedImports>,
+}
+
+impl InstancePre {
+    /// Resolves the imports of `module` with `resolver`, and checks their
+    /// types.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an [`InstantiationError::Link`] for the first import that
+    /// can't be resolved or has an incompatible type.
+    pub fn new(module: &Module, resolver: &dyn Resolver) -> Result<Self, InstantiationError> {
+        let mut exports = Vec::with_capacity(module.info().imports.len());
+
+        for ((module_name, field, index), import) in
+            module.info().imports.keys().zip(module.imports())
+        {
+            // Import indexes are given in the order of the imports.
+            debug_assert_eq!(*index as usize, exports.len());
+
+            let export = resolver
+                .resolve(*index, module_name, field)
+                .ok_or_else(|| {
+                    InstantiationError::Link(LinkError::Import(
+                        module_name.to_string(),
+                        field.to_string(),
+                        ImportError::UnknownImport(import.ty().clone()),
+                    ))
+                })?;
+
+            exports.push(export);
+        }
+
+        // The types of the imports are checked by their resolution.
+        let imports = module.resolve_imports(&ResolvedImports(&exports))?;
+
+        Ok(Self {
+            module: module.clone(),
+            imports: Arc::new(PreResolvedImports {
+                imports,
+                _exports: exports,
+            }),
+        })
+    }
+
+    /// Gets the [`Module`] associated with this `InstancePre`.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Creates a new [`Instance`] of the module, with the resolved imports.
+    ///
+    /// ## Errors
+    ///
+    /// The function can return the same [`InstantiationError`]s as
+    /// [`Instance::new`], except for link errors on the imports.
+    pub fn instantiate(&self) -> Result<Instance, InstantiationError> {
+        // Every instance gets its own clone of the host function
+        // environments.
+        Instance::new_with_imports(&self.module, self.imports.imports.clone())
+    }
+}
+
+/// The imports resolved by an [`InstancePre`].
+struct PreResolvedImports {
+    imports: Imports,
+    /// The exports the imports have been resolved from, which keep the
+    /// instances they come from alive.
+    _exports: Vec<Export>,
+}
+
+// The host function environments of `imports` are `WasmerEnv`s, which
+// are `Send` and `Sync`, and its other pointers are kept valid by
+// `_exports`.
+unsafe impl Send for PreResolvedImports {}
+unsafe impl Sync for PreResolvedImports {}
+
+impl fmt::Debug for InstancePre {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InstancePre")
+            .field("module", &self.module)
+            .finish()
+    }
+}
+
+/// A resolver returning the exports found by [`InstancePre::new`], by
+/// index.
+struct ResolvedImports<'a>(&'a [Export]);
+
+impl<'a> Resolver for ResolvedImports<'a> {
+    fn resolve(&self, index: u32, _module: &str, _field: &str) -> Option<Export> {
+        self.0.get(index as usize).cloned()
+    }
+}
@@ -253,15 +253,21 @@
 //! [wasmer-llvm]: https://docs.rs/wasmer-llvm/*/wasmer_llvm/
 //! [wasmer-wasi]: https://docs.rs/wasmer-wasi/*/wasmer_wasi/
 
+mod caller;
 mod env;
 mod exports;
 mod externals;
 mod import_object;
 mod instance;
+mod instance_pre;
+mod limiter;
+mod linker;
+mod metadata;
 mod module;
 mod native;
 mod ptr;
 mod store;
+mod stub_resolver;
 mod tunables;
 mod types;
 mod utils;
@@ -271,6 +277,34 @@ mod utils;
 /// See the [`WasmerEnv`] trait for more information.
 pub use wasmer_derive::WasmerEnv;
 
+/// Implement [`ValueType`](trait@ValueType) for a `#[repr(C)]` struct
+/// with `#[derive(ValueType)]`.
+///
+/// All the fields must implement `ValueType`, and the struct must not
+/// have padding bytes, otherwise the derive fails to compile.
+///
+/// The layout of the struct in the guest, as laid out by the wasm32
+/// compiler, can be asserted at compile time with `#[value_type(size =
+/// N, align = N)]` on the struct and `#[value_type(offset = N)]` on the
+/// fields:
+///
+/// ```
+/// use wasmer::ValueType;
+///
+/// #[derive(ValueType, Clone, Copy)]
+/// #[repr(C)]
+/// #[value_type(size = 16, align = 8)]
+/// struct Timestamp {
+///     #[value_type(offset = 0)]
+///     seconds: u64,
+///     #[value_type(offset = 8)]
+///     nanoseconds: u32,
+///     #[value_type(offset = 12)]
+///     _reserved: u32,
+/// }
+/// ```
+pub use wasmer_derive::ValueType;
+
 #[doc(hidden)]
 pub mod internals {
     //! We use the internals module for exporting types that are only
@@ -283,21 +317,31 @@ pub mod internals {
     pub use crate::externals::{WithEnv, WithoutEnv};
 }
 
+pub use crate::caller::Caller;
 pub use crate::env::{HostEnvInitError, LazyInit, WasmerEnv};
 pub use crate::exports::{ExportError, Exportable, Exports, ExportsIterator};
 pub use crate::externals::{
-    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
+    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, MemoryAccessError, Table,
+    WasmTypeList,
 };
 pub use crate::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
 pub use crate::instance::{Instance, InstantiationError};
+pub use crate::instance_pre::InstancePre;
+pub use crate::limiter::ResourceLimiter;
+pub use crate::linker::{Linker, LinkerError, UnresolvedImport};
+pub use crate::metadata::{
+    MetadataError, ProducerVersion, Producers, TargetFeature, TargetFeaturePrefix,
+};
 pub use crate::module::Module;
 pub use crate::native::NativeFunc;
 pub use crate::ptr::{Array, Item, WasmPtr};
 pub use crate::store::{Store, StoreObject};
+pub use crate::stub_resolver::StubResolver;
 pub use crate::tunables::BaseTunables;
+#[allow(deprecated)]
 pub use crate::types::{
     ExportType, ExternRef, ExternType, FunctionType, GlobalType, HostInfo, HostRef, ImportType,
-    MemoryType, Mutability, TableType, Val, ValType,
+    MemoryType, Mutability, RawExternRef, TableType, Val, ValType,
 };
 pub use crate::types::{Val as Value, ValType as Type};
 pub use crate::utils::is_wasm;
@@ -305,18 +349,19 @@ pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Tripl
 #[cfg(feature = "compiler")]
 pub use wasmer_compiler::{
     wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareError, MiddlewareReaderState,
-    ModuleMiddleware,
+    ModuleAdditions, ModuleMiddleware,
 };
 pub use wasmer_compiler::{
-    CompileError, CpuFeature, Features, ParseCpuFeatureError, Target, WasmError, WasmResult,
+    CompileError, CpuFeature, Features, ModuleLimits, ParseCpuFeatureError, Target, WasmError,
+    WasmResult,
 };
 pub use wasmer_engine::{
     ChainableNamedResolver, DeserializeError, Engine, Export, FrameInfo, LinkError, NamedResolver,
     NamedResolverChain, Resolver, RuntimeError, SerializeError, Tunables,
 };
 pub use wasmer_types::{
-    Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
-    WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    Atomically, Bytes, ExportIndex, FunctionIndex, GlobalIndex, GlobalInit, LocalFunctionIndex,
+    MemoryView, Pages, ValueType, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 
 // TODO: should those be moved into wasmer::vm as well?
@@ -325,7 +370,8 @@ pub mod vm {
     //! The vm module re-exports wasmer-vm types.
 
     pub use wasmer_vm::{
-        Memory, MemoryError, MemoryStyle, Table, TableStyle, VMMemoryDefinition, VMTableDefinition,
+        Memory, MemoryError, MemoryStyle, ModuleInfo, Table, TableStyle, VMMemoryDefinition,
+        VMTableDefinition,
     };
 }
 
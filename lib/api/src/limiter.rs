@@ -0,0 +1,389 @@
+//! The limiter module contains the [`ResourceLimiter`], which accounts
+//! for the memories, tables and instances of a [`Store`].
+//!
+//! [`Store`]: crate::Store
+use crate::{GlobalType, MemoryType, Pages, TableType};
+use std::fmt;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+use wasmer_compiler::ModuleLimits;
+use wasmer_engine::Tunables;
+use wasmer_vm::{
+    Global, Memory, MemoryError, MemoryStyle, Table, TableElement, TableStyle, Trap,
+    VMMemoryDefinition, VMTableDefinition,
+};
+
+/// A `ResourceLimiter` allows or denies the creation and the growth of
+/// the memories and tables of a [`Store`], and the creation of its
+/// instances.
+///
+/// It is shared by all the memories, tables and instances of the store,
+/// so it can enforce a budget on their combined size, by keeping a
+/// running total of the sizes it allowed, minus the sizes released.
+///
+/// All the methods allow everything by default.
+///
+/// [`Store`]: crate::Store
+///
+/// # Example
+///
+/// ```
+/// # use wasmer::*;
+/// use std::sync::atomic::{AtomicU32, Ordering};
+///
+/// /// Limits the total size of the memories of a store.
+/// struct MemoryBudget {
+///     used: AtomicU32,
+///     budget: u32,
+/// }
+///
+/// impl ResourceLimiter for MemoryBudget {
+///     fn memory_growing(&self, current: Pages, desired: Pages, _maximum: Option<Pages>) -> bool {
+///         let delta = desired.0 - current.0;
+///         self.used
+///             .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+///                 Some(used + delta).filter(|&used| used <= self.budget)
+///             })
+///             .is_ok()
+///     }
+///
+///     fn memory_released(&self, size: Pages) {
+///         self.used.fetch_sub(size.0, Ordering::SeqCst);
+///     }
+/// }
+///
+/// let store = Store::default();
+/// store.set_resource_limiter(MemoryBudget {
+///     used: AtomicU32::new(0),
+///     budget: 3,
+/// });
+///
+/// let memory = Memory::new(&store, MemoryType::new(2, None, false)).unwrap();
+/// assert!(Memory::new(&store, MemoryType::new(2, None, false)).is_err());
+/// assert!(memory.grow(1).is_ok());
+/// assert!(memory.grow(1).is_err());
+///
+/// drop(memory);
+/// assert!(Memory::new(&store, MemoryType::new(3, None, false)).is_ok());
+/// ```
+pub trait ResourceLimiter: Send + Sync {
+    /// Whether a memory can grow from `current` to `desired` pages.
+    ///
+    /// It is also called when a memory is created, with `current` set to
+    /// 0 and `desired` to its minimum size.
+    fn memory_growing(&self, _current: Pages, _desired: Pages, _maximum: Option<Pages>) -> bool {
+        true
+    }
+
+    /// Releases `size` pages of memory: those of a memory being dropped,
+    /// or those of a growth allowed by [`ResourceLimiter::memory_growing`]
+    /// that failed.
+    fn memory_released(&self, _size: Pages) {}
+
+    /// Whether a table can grow from `current` to `desired` elements.
+    ///
+    /// It is also called when a table is created, with `current` set to 0
+    /// and `desired` to its minimum size.
+    fn table_growing(&self, _current: u32, _desired: u32, _maximum: Option<u32>) -> bool {
+        true
+    }
+
+    /// Releases `size` table elements: those of a table being dropped, or
+    /// those of a growth allowed by [`ResourceLimiter::table_growing`]
+    /// that failed.
+    fn table_released(&self, _size: u32) {}
+
+    /// Whether a new instance can be created.
+    fn instance_creating(&self) -> bool {
+        true
+    }
+
+    /// Releases an instance, once it is dropped.
+    fn instance_released(&self) {}
+}
+
+/// The [`Tunables`] of a store with a [`ResourceLimiter`]: the memories
+/// and tables it creates report to the limiter.
+pub(crate) struct LimitedTunables<'a> {
+    pub(crate) base: &'a dyn Tunables,
+    pub(crate) limiter: Option<Arc<dyn ResourceLimiter>>,
+}
+
+impl LimitedTunables<'_> {
+    fn limit_memory(
+        &self,
+        ty: &MemoryType,
+        create: impl FnOnce() -> Result<Arc<dyn Memory>, MemoryError>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        let limiter = match &self.limiter {
+            Some(limiter) => limiter,
+            None => return create(),
+        };
+
+        if !limiter.memory_growing(Pages(0), ty.minimum, ty.maximum) {
+            return Err(MemoryError::Generic(format!(
+                "the resource limiter denied the creation of a memory of {} pages",
+                ty.minimum.0
+            )));
+        }
+
+        match create() {
+            Ok(memory) => Ok(Arc::new(LimitedMemory {
+                memory,
+                limiter: limiter.clone(),
+                grow_lock: Mutex::new(()),
+            })),
+            Err(e) => {
+                limiter.memory_released(ty.minimum);
+                Err(e)
+            }
+        }
+    }
+
+    fn limit_table(
+        &self,
+        ty: &TableType,
+        create: impl FnOnce() -> Result<Arc<dyn Table>, String>,
+    ) -> Result<Arc<dyn Table>, String> {
+        let limiter = match &self.limiter {
+            Some(limiter) => limiter,
+            None => return create(),
+        };
+
+        if !limiter.table_growing(0, ty.minimum, ty.maximum) {
+            return Err(format!(
+                "the resource limiter denied the creation of a table of {} elements",
+                ty.minimum
+            ));
+        }
+
+        match create() {
+            Ok(table) => Ok(Arc::new(LimitedTable {
+                table,
+                limiter: limiter.clone(),
+                grow_lock: Mutex::new(()),
+            })),
+            Err(e) => {
+                limiter.table_released(ty.minimum);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Tunables for LimitedTunables<'_> {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.memory_style(memory)
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn module_limits(&self) -> ModuleLimits {
+        self.base.module_limits()
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        self.limit_memory(ty, || self.base.create_host_memory(ty, style))
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        self.limit_memory(ty, || {
+            self.base
+                .create_vm_memory(ty, style, vm_definition_location)
+        })
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.limit_table(ty, || self.base.create_host_table(ty, style))
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        self.limit_table(ty, || {
+            self.base.create_vm_table(ty, style, vm_definition_location)
+        })
+    }
+
+    fn create_global(&self, ty: GlobalType) -> Result<Arc<Global>, String> {
+        self.base.create_global(ty)
+    }
+}
+
+/// A memory reporting its growth to a [`ResourceLimiter`].
+struct LimitedMemory {
+    memory: Arc<dyn Memory>,
+    limiter: Arc<dyn ResourceLimiter>,
+    /// Serializes the growths, so that the size reported to the limiter
+    /// is the actual one.
+    grow_lock: Mutex<()>,
+}
+
+impl fmt::Debug for LimitedMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LimitedMemory")
+            .field("memory", &self.memory)
+            .finish()
+    }
+}
+
+impl Memory for LimitedMemory {
+    fn ty(&self) -> &MemoryType {
+        self.memory.ty()
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        self.memory.style()
+    }
+
+    fn size(&self) -> Pages {
+        self.memory.size()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let _grow_guard = self.grow_lock.lock().unwrap();
+        let current = self.memory.size();
+        if delta.0 == 0 {
+            return Ok(current);
+        }
+
+        let could_not_grow = MemoryError::CouldNotGrow {
+            current,
+            attempted_delta: delta,
+        };
+        let desired = current.checked_add(delta).ok_or(could_not_grow.clone())?;
+        if !self
+            .limiter
+            .memory_growing(current, desired, self.memory.ty().maximum)
+        {
+            return Err(could_not_grow);
+        }
+
+        self.memory.grow(delta).map_err(|e| {
+            self.limiter.memory_released(delta);
+            e
+        })
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.memory.vmmemory()
+    }
+
+    fn vmmemory_definition(&self) -> VMMemoryDefinition {
+        self.memory.vmmemory_definition()
+    }
+}
+
+impl Drop for LimitedMemory {
+    fn drop(&mut self) {
+        self.limiter.memory_released(self.memory.size());
+    }
+}
+
+/// A table reporting its growth to a [`ResourceLimiter`].
+struct LimitedTable {
+    table: Arc<dyn Table>,
+    limiter: Arc<dyn ResourceLimiter>,
+    /// Serializes the growths, so that the size reported to the limiter
+    /// is the actual one.
+    grow_lock: Mutex<()>,
+}
+
+impl fmt::Debug for LimitedTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LimitedTable")
+            .field("table", &self.table)
+            .finish()
+    }
+}
+
+impl Table for LimitedTable {
+    fn style(&self) -> &TableStyle {
+        self.table.style()
+    }
+
+    fn ty(&self) -> &TableType {
+        self.table.ty()
+    }
+
+    fn size(&self) -> u32 {
+        self.table.size()
+    }
+
+    fn grow(&self, delta: u32, init: TableElement) -> Option<u32> {
+        let _grow_guard = self.grow_lock.lock().unwrap();
+        let current = self.table.size();
+        if delta == 0 {
+            return self.table.grow(delta, init);
+        }
+
+        let desired = current.checked_add(delta)?;
+        if !self
+            .limiter
+            .table_growing(current, desired, self.table.ty().maximum)
+        {
+            return None;
+        }
+
+        let previous = self.table.grow(delta, init);
+        if previous.is_none() {
+            self.limiter.table_released(delta);
+        }
+        previous
+    }
+
+    fn get(&self, index: u32) -> Option<TableElement> {
+        self.table.get(index)
+    }
+
+    fn set(&self, index: u32, element: TableElement) -> Result<(), Trap> {
+        self.table.set(index, element)
+    }
+
+    fn vmtable(&self) -> NonNull<VMTableDefinition> {
+        self.table.vmtable()
+    }
+
+    fn copy(
+        &self,
+        src_table: &dyn Table,
+        dst_index: u32,
+        src_index: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
+        self.table.copy(src_table, dst_index, src_index, len)
+    }
+}
+
+impl Drop for LimitedTable {
+    fn drop(&mut self) {
+        self.limiter.table_released(self.table.size());
+    }
+}
+
+/// Releases an instance when dropped, as its host state.
+pub(crate) struct LimitedInstance(pub(crate) Arc<dyn ResourceLimiter>);
+
+impl Drop for LimitedInstance {
+    fn drop(&mut self) {
+        self.0.instance_released();
+    }
+}
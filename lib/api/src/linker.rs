@@ -0,0 +1,362 @@
+//! The linker module contains the [`Linker`], which resolves the imports
+//! of modules by name, from host items and from the exports of other
+//! instances.
+use crate::exports::{Exportable, Exports};
+use crate::externals::Extern;
+use crate::import_object::ImportObject;
+use crate::instance::{Instance, InstantiationError};
+use crate::instance_pre::InstancePre;
+use crate::module::Module;
+use crate::store::{Store, StoreObject};
+use indexmap::IndexMap;
+use std::fmt;
+use thiserror::Error;
+use wasmer_engine::{Export, NamedResolver};
+use wasmer_types::ExternType;
+
+/// An import that the [`Linker`] can't resolve.
+#[derive(Debug, Clone)]
+pub struct UnresolvedImport {
+    /// The module name of the import.
+    pub module: String,
+    /// The field name of the import.
+    pub name: String,
+    /// The type expected by the module.
+    pub expected: ExternType,
+    /// The type of the item defined under that name, if any.
+    pub found: Option<ExternType>,
+}
+
+impl fmt::Display for UnresolvedImport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.found {
+            None => write!(
+                f,
+                "{:?}.{:?}: unknown import. Expected {:?}",
+                self.module, self.name, self.expected
+            ),
+            Some(found) => write!(
+                f,
+                "{:?}.{:?}: incompatible import type. Expected {:?} but received {:?}",
+                self.module, self.name, self.expected, found
+            ),
+        }
+    }
+}
+
+/// An error while defining items in a [`Linker`], or while
+/// instantiating a module with it.
+#[derive(Error, Debug)]
+pub enum LinkerError {
+    /// An item is already defined under that name, and shadowing is not
+    /// allowed.
+    #[error("{0:?}.{1:?} is already defined")]
+    AlreadyDefined(String, String),
+
+    /// The item defined under that name comes from another store than
+    /// the linker's.
+    #[error("{0:?}.{1:?} comes from another store than the linker")]
+    StoreMismatch(String, String),
+
+    /// The module to instantiate comes from another store than the
+    /// linker's.
+    #[error("the module comes from another store than the linker")]
+    ModuleStoreMismatch,
+
+    /// Some imports of the module can't be resolved.
+    #[error("{}", display_unresolved_imports(.0))]
+    UnresolvedImports(Vec<UnresolvedImport>),
+
+    /// The instantiation failed, after all the imports have been resolved.
+    #[error(transparent)]
+    Instantiation(#[from] InstantiationError),
+}
+
+fn display_unresolved_imports(imports: &[UnresolvedImport]) -> String {
+    let mut message = format!("{} import(s) can't be resolved:", imports.len());
+
+    for import in imports {
+        message.push_str(&format!("\n  - {}", import));
+    }
+
+    message
+}
+
+/// A `Linker` resolves the imports of modules by name.
+///
+/// Items are defined under a module name and a field name, either one
+/// by one with [`Linker::define`], or all the exports of an instance at
+/// once with [`Linker::instance`]. Instantiating modules through the
+/// linker with [`Linker::module`] makes their exports available to the
+/// next modules.
+///
+/// By default, defining an item twice is an error; with
+/// [`Linker::allow_shadowing`], the latest definition wins.
+///
+/// ```
+/// # use wasmer::{Function, Linker, Module, Store};
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let mut linker = Linker::new(&store);
+/// linker.define("host", "double", Function::new_native(&store, |x: i32| x * 2))?;
+///
+/// let library = Module::new(&store, r#"
+///     (module
+///       (import "host" "double" (func $double (param i32) (result i32)))
+///       (func (export "quadruple") (param i32) (result i32)
+///         local.get 0
+///         call $double
+///         call $double))
+/// "#)?;
+/// linker.module("library", &library)?;
+///
+/// let program = Module::new(&store, r#"
+///     (module
+///       (import "library" "quadruple" (func $quadruple (param i32) (result i32)))
+///       (func (export "run") (result i32)
+///         i32.const 5
+///         call $quadruple))
+/// "#)?;
+/// let instance = linker.instantiate(&program)?;
+///
+/// let run = instance.exports.get_native_function::<(), i32>("run")?;
+/// assert_eq!(run.call()?, 20);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Linker {
+    store: Store,
+    items: IndexMap<(String, String), Extern>,
+    allow_shadowing: bool,
+}
+
+impl Linker {
+    /// Creates a new, empty `Linker`.
+    pub fn new(store: &Store) -> Self {
+        Self {
+            store: store.clone(),
+            items: IndexMap::new(),
+            allow_shadowing: false,
+        }
+    }
+
+    /// Returns the [`Store`] of the linker.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Whether defining an item under a name that is already defined
+    /// replaces it (`true`), or is an error (`false`, the default).
+    pub fn allow_shadowing(&mut self, allow: bool) -> &mut Self {
+        self.allow_shadowing = allow;
+        self
+    }
+
+    /// Defines `item` under `module` and `name`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`LinkerError::AlreadyDefined`] if an item is already
+    /// defined under that name and shadowing is not allowed, or
+    /// [`LinkerError::StoreMismatch`] if `item` comes from another
+    /// store than the linker's.
+    pub fn define<E>(&mut self, module: &str, name: &str, item: E) -> Result<&mut Self, LinkerError>
+    where
+        E: Into<Extern>,
+    {
+        let item = item.into();
+        let key = (module.to_string(), name.to_string());
+
+        if !item.comes_from_same_store(&self.store) {
+            return Err(LinkerError::StoreMismatch(key.0, key.1));
+        }
+
+        if !self.allow_shadowing && self.items.contains_key(&key) {
+            return Err(LinkerError::AlreadyDefined(key.0, key.1));
+        }
+
+        self.items.insert(key, item);
+
+        Ok(self)
+    }
+
+    /// Defines all the `exports` under `module`.
+    pub fn define_exports(
+        &mut self,
+        module: &str,
+        exports: &Exports,
+    ) -> Result<&mut Self, LinkerError> {
+        self.check_shadowing(
+            exports
+                .iter()
+                .map(|(name, _)| (module.to_string(), name.clone())),
+        )?;
+
+        if let Some((name, _)) = exports
+            .iter()
+            .find(|(_, item)| !item.comes_from_same_store(&self.store))
+        {
+            return Err(LinkerError::StoreMismatch(
+                module.to_string(),
+                name.to_string(),
+            ));
+        }
+
+        for (name, item) in exports.iter() {
+            self.define(module, name, item.clone())?;
+        }
+
+        Ok(self)
+    }
+
+    /// Defines all the items of `import_object`, under their namespace.
+    pub fn define_import_object(
+        &mut self,
+        import_object: &ImportObject,
+    ) -> Result<&mut Self, LinkerError> {
+        let items: Vec<_> = import_object.clone().into_iter().collect();
+
+        self.check_shadowing(items.iter().map(|(key, _)| key.clone()))?;
+
+        for ((module, name), export) in items {
+            let item = Extern::from_vm_export(&self.store, export);
+            self.define(&module, &name, item)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Defines all the exports of `instance` under `module`, so that
+    /// other modules can import them.
+    pub fn instance(
+        &mut self,
+        module: &str,
+        instance: &Instance,
+    ) -> Result<&mut Self, LinkerError> {
+        self.define_exports(module, &instance.exports)
+    }
+
+    /// Instantiates `module` with the items of the linker, and defines
+    /// its exports under `name`.
+    pub fn module(&mut self, name: &str, module: &Module) -> Result<Instance, LinkerError> {
+        let instance = self.instantiate(module)?;
+        self.instance(name, &instance)?;
+
+        Ok(instance)
+    }
+
+    /// Gets the item defined under `module` and `name`.
+    pub fn get(&self, module: &str, name: &str) -> Option<&Extern> {
+        self.items.get(&(module.to_string(), name.to_string()))
+    }
+
+    /// Iterates over the items of the linker, in definition order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, &Extern)> {
+        self.items
+            .iter()
+            .map(|((module, name), item)| (module.as_str(), name.as_str(), item))
+    }
+
+    /// Lists the imports of `module` that can't be resolved by the
+    /// linker, because they are not defined or have an incompatible type.
+    pub fn unresolved_imports(&self, module: &Module) -> Vec<UnresolvedImport> {
+        module
+            .imports()
+            .filter_map(|import| {
+                let expected = import.ty().clone();
+                let found = self.get(import.module(), import.name()).map(Extern::ty);
+
+                match &found {
+                    Some(found) if found.is_compatible_with(&expected) => None,
+                    _ => Some(UnresolvedImport {
+                        module: import.module().to_string(),
+                        name: import.name().to_string(),
+                        expected,
+                        found,
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    /// Instantiates `module` with the items of the linker.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`LinkerError::ModuleStoreMismatch`] if `module` comes
+    /// from another store than the linker's,
+    /// [`LinkerError::UnresolvedImports`] with every import that can't
+    /// be resolved, or [`LinkerError::Instantiation`] if the
+    /// instantiation fails.
+    pub fn instantiate(&self, module: &Module) -> Result<Instance, LinkerError> {
+        self.check_module_store(module)?;
+        let unresolved_imports = self.unresolved_imports(module);
+
+        if !unresolved_imports.is_empty() {
+            return Err(LinkerError::UnresolvedImports(unresolved_imports));
+        }
+
+        Ok(Instance::new(module, self)?)
+    }
+
+    /// Resolves and type-checks the imports of `module` once, to
+    /// instantiate it many times with an [`InstancePre`].
+    ///
+    /// The items defined later in the linker are not seen by the
+    /// returned `InstancePre`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`LinkerError::ModuleStoreMismatch`] if `module` comes
+    /// from another store than the linker's, or
+    /// [`LinkerError::UnresolvedImports`] with every import that can't
+    /// be resolved.
+    pub fn instantiate_pre(&self, module: &Module) -> Result<InstancePre, LinkerError> {
+        self.check_module_store(module)?;
+        let unresolved_imports = self.unresolved_imports(module);
+
+        if !unresolved_imports.is_empty() {
+            return Err(LinkerError::UnresolvedImports(unresolved_imports));
+        }
+
+        Ok(InstancePre::new(module, self)?)
+    }
+
+    fn check_module_store(&self, module: &Module) -> Result<(), LinkerError> {
+        if Store::same(module.store(), &self.store) {
+            Ok(())
+        } else {
+            Err(LinkerError::ModuleStoreMismatch)
+        }
+    }
+
+    fn check_shadowing<I>(&self, keys: I) -> Result<(), LinkerError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        if self.allow_shadowing {
+            return Ok(());
+        }
+
+        match keys.into_iter().find(|key| self.items.contains_key(key)) {
+            Some((module, name)) => Err(LinkerError::AlreadyDefined(module, name)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl NamedResolver for Linker {
+    fn resolve_by_name(&self, module: &str, name: &str) -> Option<Export> {
+        self.get(module, name).map(|item| item.to_export())
+    }
+}
+
+impl fmt::Debug for Linker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Linker")
+            .field("items", &self.items.keys().collect::<Vec<_>>())
+            .field("allow_shadowing", &self.allow_shadowing)
+            .finish()
+    }
+}
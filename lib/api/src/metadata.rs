@@ -0,0 +1,188 @@
+//! The metadata module contains the parsed contents of the tooling
+//! custom sections of a module: `producers`, `target_features` and
+//! `sourceMappingURL`.
+//!
+//! See the [tool conventions] for their format.
+//!
+//! [tool conventions]: https://github.com/WebAssembly/tool-conventions
+use thiserror::Error;
+
+/// An error while parsing a custom section of a module.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("malformed `{section}` custom section: {message}")]
+pub struct MetadataError {
+    section: &'static str,
+    message: String,
+}
+
+impl MetadataError {
+    /// The name of the malformed custom section.
+    pub fn section(&self) -> &str {
+        self.section
+    }
+}
+
+/// A tool, and its version, listed in the `producers` section.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProducerVersion {
+    /// The name of the tool, e.g. `rustc`.
+    pub name: String,
+    /// The version of the tool, possibly empty.
+    pub version: String,
+}
+
+/// The contents of the `producers` section: the tools used to produce a
+/// module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Producers {
+    /// The source languages, e.g. `Rust`.
+    pub language: Vec<ProducerVersion>,
+    /// The tools that processed the module, e.g. `rustc` or `wasm-opt`.
+    pub processed_by: Vec<ProducerVersion>,
+    /// The SDKs used to produce the module, e.g. `Emscripten`.
+    pub sdk: Vec<ProducerVersion>,
+}
+
+/// How a module relies on a [`TargetFeature`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TargetFeaturePrefix {
+    /// The feature is used by the module (`+`).
+    Used,
+    /// The feature is not used by the module, and must not be used by the
+    /// modules it is linked with (`-`).
+    Disallowed,
+    /// The feature is used by the module, and must be used by the modules
+    /// it is linked with (`=`).
+    Required,
+}
+
+/// A feature listed in the `target_features` section, e.g. `simd128`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TargetFeature {
+    /// How the module relies on the feature.
+    pub prefix: TargetFeaturePrefix,
+    /// The name of the feature.
+    pub name: String,
+}
+
+/// A reader of the contents of a custom section.
+struct SectionReader<'a> {
+    section: &'static str,
+    data: &'a [u8],
+}
+
+impl<'a> SectionReader<'a> {
+    fn new(section: &'static str, data: &'a [u8]) -> Self {
+        Self { section, data }
+    }
+
+    fn error(&self, message: impl Into<String>) -> MetadataError {
+        MetadataError {
+            section: self.section,
+            message: message.into(),
+        }
+    }
+
+    fn read_u8(&mut self) -> Result<u8, MetadataError> {
+        let (&byte, rest) = self
+            .data
+            .split_first()
+            .ok_or_else(|| self.error("unexpected end of section"))?;
+        self.data = rest;
+        Ok(byte)
+    }
+
+    /// Reads an unsigned LEB128 integer.
+    fn read_var_u32(&mut self) -> Result<u32, MetadataError> {
+        let mut result = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            let value = u32::from(byte & 0x7f);
+            if shift == 28 && value > 0x0f {
+                return Err(self.error("integer too large"));
+            }
+            result |= value << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+            if shift > 28 {
+                return Err(self.error("integer representation too long"));
+            }
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, MetadataError> {
+        let len = self.read_var_u32()? as usize;
+        if len > self.data.len() {
+            return Err(self.error("unexpected end of section"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.error("invalid UTF-8 string"))
+    }
+
+    fn finish(&self) -> Result<(), MetadataError> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(self.error("unexpected data at the end of the section"))
+        }
+    }
+}
+
+pub(crate) fn parse_producers(data: &[u8]) -> Result<Producers, MetadataError> {
+    let mut reader = SectionReader::new("producers", data);
+    let mut producers = Producers::default();
+
+    for _ in 0..reader.read_var_u32()? {
+        let field = reader.read_string()?;
+        let mut values = Vec::new();
+        for _ in 0..reader.read_var_u32()? {
+            let name = reader.read_string()?;
+            let version = reader.read_string()?;
+            values.push(ProducerVersion { name, version });
+        }
+
+        match field.as_str() {
+            "language" => producers.language.extend(values),
+            "processed-by" => producers.processed_by.extend(values),
+            "sdk" => producers.sdk.extend(values),
+            // Unknown fields are allowed, for future extensions.
+            _ => {}
+        }
+    }
+
+    reader.finish()?;
+    Ok(producers)
+}
+
+pub(crate) fn parse_target_features(data: &[u8]) -> Result<Vec<TargetFeature>, MetadataError> {
+    let mut reader = SectionReader::new("target_features", data);
+    let mut features = Vec::new();
+
+    for _ in 0..reader.read_var_u32()? {
+        let prefix = match reader.read_u8()? {
+            b'+' => TargetFeaturePrefix::Used,
+            b'-' => TargetFeaturePrefix::Disallowed,
+            b'=' => TargetFeaturePrefix::Required,
+            prefix => {
+                return Err(reader.error(format!("invalid feature prefix `{}`", prefix as char)))
+            }
+        };
+        let name = reader.read_string()?;
+        features.push(TargetFeature { prefix, name });
+    }
+
+    reader.finish()?;
+    Ok(features)
+}
+
+pub(crate) fn parse_source_mapping_url(data: &[u8]) -> Result<String, MetadataError> {
+    let mut reader = SectionReader::new("sourceMappingURL", data);
+    let url = reader.read_string()?;
+
+    reader.finish()?;
+    Ok(url)
+}
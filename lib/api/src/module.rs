@@ -1,6 +1,13 @@
+use crate::instance::InstanceHostState;
+use crate::limiter::LimitedInstance;
+use crate::metadata::{
+    parse_producers, parse_source_mapping_url, parse_target_features, MetadataError, Producers,
+    TargetFeature,
+};
 use crate::store::Store;
 use crate::types::{ExportType, ImportType};
 use crate::InstantiationError;
+use std::any::Any;
 use std::fmt;
 use std::io;
 use std::path::Path;
@@ -9,8 +16,9 @@ use thiserror::Error;
 use wasmer_compiler::CompileError;
 #[cfg(feature = "wat")]
 use wasmer_compiler::WasmError;
-use wasmer_engine::{Artifact, DeserializeError, Resolver, SerializeError};
-use wasmer_vm::{ExportsIterator, ImportsIterator, InstanceHandle, ModuleInfo};
+use wasmer_engine::{Artifact, DeserializeError, LinkError, Resolver, SerializeError};
+use wasmer_types::FunctionIndex;
+use wasmer_vm::{ExportsIterator, Imports, ImportsIterator, InstanceHandle, ModuleInfo};
 
 #[derive(Error, Debug)]
 pub enum IoCompileError {
@@ -128,8 +136,9 @@ impl Module {
     /// the WebAssembly text format (if the "wat" feature is enabled for
     /// this crate).
     pub fn from_binary(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
-        Self::validate(store, binary)?;
-        unsafe { Self::from_binary_unchecked(store, binary) }
+        Self::check_limits(store, binary)?;
+        store.engine().validate(binary)?;
+        Self::compile(store, binary)
     }
 
     /// Creates a new WebAssembly module skipping any kind of validation.
@@ -139,10 +148,17 @@ impl Module {
     /// This can speed up compilation time a bit, but it should be only used
     /// in environments where the WebAssembly modules are trusted and validated
     /// beforehand.
+    ///
+    /// The module is still checked against the [`ModuleLimits`] of the
+    /// Store [`Tunables`].
+    ///
+    /// [`ModuleLimits`]: crate::ModuleLimits
+    /// [`Tunables`]: crate::Tunables
     pub unsafe fn from_binary_unchecked(
         store: &Store,
         binary: &[u8],
     ) -> Result<Self, CompileError> {
+        Self::check_limits(store, binary)?;
         let module = Self::compile(store, binary)?;
         Ok(module)
     }
@@ -153,10 +169,43 @@ impl Module {
     /// This validation is normally pretty fast and checks the enabled
     /// WebAssembly features in the Store Engine to assure deterministic
     /// validation of the Module.
+    ///
+    /// The module is first checked against the [`ModuleLimits`] of the
+    /// Store [`Tunables`], so that a module exceeding them is rejected
+    /// with [`CompileError::LimitExceeded`] before being validated.
+    ///
+    /// [`ModuleLimits`]: crate::ModuleLimits
+    /// [`Tunables`]: crate::Tunables
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let engine = Store::default().engine().clone();
+    /// let mut tunables = BaseTunables::for_target(engine.target());
+    /// tunables.module_limits.max_functions = Some(1);
+    /// let store = Store::new_with_tunables(&*engine, tunables);
+    ///
+    /// let wat = "(module (func) (func))";
+    /// let result = Module::validate(&store, &wat2wasm(wat.as_bytes())?);
+    /// assert!(matches!(result, Err(CompileError::LimitExceeded(_))));
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn validate(store: &Store, binary: &[u8]) -> Result<(), CompileError> {
+        Self::check_limits(store, binary)?;
         store.engine().validate(binary)
     }
 
+    #[allow(unused_variables)]
+    fn check_limits(store: &Store, binary: &[u8]) -> Result<(), CompileError> {
+        #[cfg(feature = "compiler")]
+        store.tunables().module_limits().check(binary)?;
+
+        Ok(())
+    }
+
     fn compile(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
         let artifact = store.engine().compile(binary, store.tunables())?;
         Ok(Self::from_artifact(store, artifact))
@@ -211,6 +260,13 @@ impl Module {
     ///
     /// And as such, the `deserialize` method is unsafe.
     ///
+    /// The [`ModuleLimits`] of the Store [`Tunables`] are not checked:
+    /// the module has been compiled already, and only the limits of the
+    /// Store it was compiled with applied.
+    ///
+    /// [`ModuleLimits`]: crate::ModuleLimits
+    /// [`Tunables`]: crate::Tunables
+    ///
     /// # Usage
     ///
     /// ```ignore
@@ -262,22 +318,81 @@ impl Module {
         &self,
         resolver: &dyn Resolver,
     ) -> Result<InstanceHandle, InstantiationError> {
+        let host_state = self.host_state()?;
+
         unsafe {
             let instance_handle =
                 self.artifact
-                    .instantiate(self.store.tunables(), resolver, Box::new(()))?;
+                    .instantiate(&self.store.limited_tunables(), resolver, host_state)?;
 
-            // After the instance handle is created, we need to initialize
-            // the data, call the start function and so. However, if any
-            // of this steps traps, we still need to keep the instance alive
-            // as some of the Instance elements may have placed in other
-            // instance tables.
-            self.artifact.finish_instantiation(&instance_handle)?;
+            Ok(instance_handle)
+        }
+    }
+
+    /// Resolves the imports of the module with `resolver`, to instantiate
+    /// it with [`Module::instantiate_with_imports`].
+    pub(crate) fn resolve_imports(
+        &self,
+        resolver: &dyn Resolver,
+    ) -> Result<Imports, InstantiationError> {
+        self.artifact
+            .resolve_imports(resolver)
+            .map_err(InstantiationError::Link)
+    }
+
+    /// Like [`Module::instantiate`], with the `imports` resolved by
+    /// [`Module::resolve_imports`].
+    pub(crate) fn instantiate_with_imports(
+        &self,
+        imports: Imports,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        let host_state = self.host_state()?;
+
+        unsafe {
+            let instance_handle = self.artifact.instantiate_with_imports(
+                &self.store.limited_tunables(),
+                imports,
+                host_state,
+            )?;
 
             Ok(instance_handle)
         }
     }
 
+    /// Returns the host state of a new instance, after checking that the
+    /// resource limiter allows its creation.
+    fn host_state(&self) -> Result<Box<dyn Any>, InstantiationError> {
+        // The instance is released from the resource limiter when its
+        // host state is dropped, along with the instance.
+        match self.store.resource_limiter() {
+            Some(limiter) if !limiter.instance_creating() => {
+                Err(InstantiationError::Link(LinkError::Resource(
+                    "the resource limiter denied the creation of an instance".to_string(),
+                )))
+            }
+            limiter => Ok(Box::new(InstanceHostState::new(
+                limiter.map(LimitedInstance),
+            ))),
+        }
+    }
+
+    /// Finishes the instantiation of `instance_handle`, created by
+    /// [`Module::instantiate`], once its host environments are
+    /// initialized.
+    pub(crate) fn finish_instantiation(
+        &self,
+        instance_handle: &InstanceHandle,
+    ) -> Result<(), InstantiationError> {
+        // After the instance handle is created, we need to initialize
+        // the data, call the start function and so. However, if any
+        // of this steps traps, we still need to keep the instance alive
+        // as some of the Instance elements may have placed in other
+        // instance tables.
+        unsafe { self.artifact.finish_instantiation(instance_handle) }?;
+
+        Ok(())
+    }
+
     /// Returns the name of the current module.
     ///
     /// This name is normally set in the WebAssembly bytecode by some
@@ -396,6 +511,92 @@ impl Module {
         self.artifact.module_ref().custom_sections(name)
     }
 
+    /// Returns the name of the function at `index`, from the `name`
+    /// section.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = "(module (func $add (param $lhs i32) (param $rhs i32)))";
+    /// let module = Module::new(&store, wat)?;
+    /// assert_eq!(module.function_name(FunctionIndex::from_u32(0)), Some("add"));
+    /// assert_eq!(module.local_name(FunctionIndex::from_u32(0), 1), Some("rhs"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn function_name(&self, index: FunctionIndex) -> Option<&str> {
+        self.artifact
+            .module_ref()
+            .function_names
+            .get(&index)
+            .map(String::as_str)
+    }
+
+    /// Returns the name of the local `local_index` of the function at
+    /// `index`, from the `name` section.
+    ///
+    /// The parameters are the first locals of a function.
+    pub fn local_name(&self, index: FunctionIndex, local_index: u32) -> Option<&str> {
+        self.artifact
+            .module_ref()
+            .local_names
+            .get(&index)?
+            .get(&local_index)
+            .map(String::as_str)
+    }
+
+    /// Returns the size in bytes of the body of the function at `index`,
+    /// or `None` if it is imported.
+    pub fn function_body_size(&self, index: FunctionIndex) -> Option<u32> {
+        let module_info = self.artifact.module_ref();
+        let local_index = module_info.local_func_index(index)?;
+        module_info.function_body_sizes.get(local_index).copied()
+    }
+
+    /// Returns the tools used to produce the module, from the `producers`
+    /// section, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the section is malformed.
+    pub fn producers(&self) -> Result<Option<Producers>, MetadataError> {
+        self.parse_custom_section("producers", parse_producers)
+    }
+
+    /// Returns the features used by the module, from the
+    /// `target_features` section, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the section is malformed.
+    pub fn target_features(&self) -> Result<Option<Vec<TargetFeature>>, MetadataError> {
+        self.parse_custom_section("target_features", parse_target_features)
+    }
+
+    /// Returns the URL of the source map of the module, from the
+    /// `sourceMappingURL` section, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the section is malformed.
+    pub fn source_mapping_url(&self) -> Result<Option<String>, MetadataError> {
+        self.parse_custom_section("sourceMappingURL", parse_source_mapping_url)
+    }
+
+    fn parse_custom_section<T>(
+        &self,
+        name: &str,
+        parse: fn(&[u8]) -> Result<T, MetadataError>,
+    ) -> Result<Option<T>, MetadataError> {
+        self.custom_sections(name)
+            .next()
+            .map(|data| parse(&data))
+            .transpose()
+    }
+
     /// Returns the [`Store`] where the `Instance` belongs.
     pub fn store(&self) -> &Store {
         &self.store
@@ -17,7 +17,10 @@ use crate::{FromToNativeWasmType, Function, RuntimeError, Store, WasmTypeList};
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use wasmer_engine::ExportFunction;
 use wasmer_types::NativeWasmType;
-use wasmer_vm::{VMDynamicFunctionContext, VMFunctionBody, VMFunctionEnvironment, VMFunctionKind};
+use wasmer_vm::{
+    with_externref_roots, VMDynamicFunctionContext, VMFunctionBody, VMFunctionEnvironment,
+    VMFunctionKind,
+};
 
 /// A WebAssembly function that can be called natively
 /// (using the Native ABI).
@@ -115,99 +118,103 @@ macro_rules! impl_native_traits {
         {
             /// Call the typed func and return results.
             pub fn call(&self, $( $x: $x, )* ) -> Result<Rets, RuntimeError> {
-                match self.definition {
-                    FunctionDefinition::Wasm(WasmFunctionDefinition {
-                        trampoline
-                    }) => {
-                        // TODO: when `const fn` related features mature more, we can declare a single array
-                        // of the correct size here.
-                        let mut params_list = [ $( $x.to_native().to_binary() ),* ];
-                        let mut rets_list_array = Rets::empty_array();
-                        let rets_list = rets_list_array.as_mut();
-                        let using_rets_array;
-                        let args_rets: &mut [i128] = if params_list.len() > rets_list.len() {
-                            using_rets_array = false;
-                            params_list.as_mut()
-                        } else {
-                            using_rets_array = true;
-                            for (i, &arg) in params_list.iter().enumerate() {
-                                rets_list[i] = arg;
-                            }
-                            rets_list.as_mut()
-                        };
-                        unsafe {
-                            wasmer_vm::wasmer_call_trampoline(
-                                self.vmctx(),
-                                trampoline,
-                                self.address(),
-                                args_rets.as_mut_ptr() as *mut u8,
-                            )
-                        }?;
-                        let num_rets = rets_list.len();
-                        if !using_rets_array && num_rets > 0 {
-                            let src_pointer = params_list.as_ptr();
-                            let rets_list = &mut rets_list_array.as_mut()[0] as *mut i128;
+                // The `externref` arguments and results are rooted until the
+                // results are converted.
+                with_externref_roots(|| {
+                    match self.definition {
+                        FunctionDefinition::Wasm(WasmFunctionDefinition {
+                            trampoline
+                        }) => {
+                            // TODO: when `const fn` related features mature more, we can declare a single array
+                            // of the correct size here.
+                            let mut params_list = [ $( $x.to_native().to_binary() ),* ];
+                            let mut rets_list_array = Rets::empty_array();
+                            let rets_list = rets_list_array.as_mut();
+                            let using_rets_array;
+                            let args_rets: &mut [i128] = if params_list.len() > rets_list.len() {
+                                using_rets_array = false;
+                                params_list.as_mut()
+                            } else {
+                                using_rets_array = true;
+                                for (i, &arg) in params_list.iter().enumerate() {
+                                    rets_list[i] = arg;
+                                }
+                                rets_list.as_mut()
+                            };
                             unsafe {
-                                // TODO: we can probably remove this copy by doing some clever `transmute`s.
-                                // we know it's not overlapping because `using_rets_array` is false
-                                std::ptr::copy_nonoverlapping(src_pointer,
-                                                              rets_list,
-                                                              num_rets);
+                                wasmer_vm::wasmer_call_trampoline(
+                                    self.vmctx(),
+                                    trampoline,
+                                    self.address(),
+                                    args_rets.as_mut_ptr() as *mut u8,
+                                )
+                            }?;
+                            let num_rets = rets_list.len();
+                            if !using_rets_array && num_rets > 0 {
+                                let src_pointer = params_list.as_ptr();
+                                let rets_list = &mut rets_list_array.as_mut()[0] as *mut i128;
+                                unsafe {
+                                    // TODO: we can probably remove this copy by doing some clever `transmute`s.
+                                    // we know it's not overlapping because `using_rets_array` is false
+                                    std::ptr::copy_nonoverlapping(src_pointer,
+                                                                  rets_list,
+                                                                  num_rets);
+                                }
                             }
-                        }
-                        Ok(Rets::from_array(rets_list_array))
-                        // TODO: When the Host ABI and Wasm ABI are the same, we could do this instead:
-                        // but we can't currently detect whether that's safe.
-                        //
-                        // let results = unsafe {
-                        //     wasmer_vm::catch_traps_with_result(self.vmctx, || {
-                        //         let f = std::mem::transmute::<_, unsafe extern "C" fn( *mut VMContext, $( $x, )*) -> Rets::CStruct>(self.address());
-                        //         // We always pass the vmctx
-                        //         f( self.vmctx, $( $x, )* )
-                        //     }).map_err(RuntimeError::from_trap)?
-                        // };
-                        // Ok(Rets::from_c_struct(results))
+                            Ok(Rets::from_array(rets_list_array))
+                            // TODO: When the Host ABI and Wasm ABI are the same, we could do this instead:
+                            // but we can't currently detect whether that's safe.
+                            //
+                            // let results = unsafe {
+                            //     wasmer_vm::catch_traps_with_result(self.vmctx, || {
+                            //         let f = std::mem::transmute::<_, unsafe extern "C" fn( *mut VMContext, $( $x, )*) -> Rets::CStruct>(self.address());
+                            //         // We always pass the vmctx
+                            //         f( self.vmctx, $( $x, )* )
+                            //     }).map_err(RuntimeError::from_trap)?
+                            // };
+                            // Ok(Rets::from_c_struct(results))
 
-                    }
-                    FunctionDefinition::Host(HostFunctionDefinition {
-                        has_env
-                    }) => {
-                        match self.arg_kind() {
-                            VMFunctionKind::Static => {
-                                let results = catch_unwind(AssertUnwindSafe(|| unsafe {
-                                    let f = std::mem::transmute::<_, unsafe extern "C" fn( VMFunctionEnvironment, $( $x, )*) -> Rets::CStruct>(self.address());
-                                    // We always pass the vmctx
-                                    f( self.vmctx(), $( $x, )* )
-                                })).map_err(|e| RuntimeError::new(format!("{:?}", e)))?;
-                                Ok(Rets::from_c_struct(results))
-                            },
-                            VMFunctionKind::Dynamic => {
-                                let params_list = [ $( $x.to_native().to_value() ),* ];
-                                let results = if !has_env {
-                                    type VMContextWithoutEnv = VMDynamicFunctionContext<DynamicFunctionWithoutEnv>;
-                                    unsafe {
-                                        let ctx = self.vmctx().host_env as *mut VMContextWithoutEnv;
-                                        (*ctx).ctx.call(&params_list)?
-                                    }
-                                } else {
-                                    type VMContextWithEnv = VMDynamicFunctionContext<DynamicFunctionWithEnv<std::ffi::c_void>>;
-                                    unsafe {
-                                        let ctx = self.vmctx().host_env as *mut VMContextWithEnv;
-                                        (*ctx).ctx.call(&params_list)?
-                                    }
-                                };
-                                let mut rets_list_array = Rets::empty_array();
-                                let mut_rets = rets_list_array.as_mut() as *mut [i128] as *mut i128;
-                                for (i, ret) in results.iter().enumerate() {
-                                    unsafe {
-                                        ret.write_value_to(mut_rets.add(i));
+                        }
+                        FunctionDefinition::Host(HostFunctionDefinition {
+                            has_env
+                        }) => {
+                            match self.arg_kind() {
+                                VMFunctionKind::Static => {
+                                    let results = catch_unwind(AssertUnwindSafe(|| unsafe {
+                                        let f = std::mem::transmute::<_, unsafe extern "C" fn( VMFunctionEnvironment, $( $x::Native, )*) -> Rets::CStruct>(self.address());
+                                        // We always pass the vmctx
+                                        f( self.vmctx(), $( $x.to_native(), )* )
+                                    })).map_err(|e| RuntimeError::new(format!("{:?}", e)))?;
+                                    Ok(Rets::from_c_struct(results))
+                                },
+                                VMFunctionKind::Dynamic => {
+                                    let params_list = [ $( $x.to_native().to_value() ),* ];
+                                    let results = if !has_env {
+                                        type VMContextWithoutEnv = VMDynamicFunctionContext<DynamicFunctionWithoutEnv>;
+                                        unsafe {
+                                            let ctx = self.vmctx().host_env as *mut VMContextWithoutEnv;
+                                            (*ctx).ctx.call(&params_list)?
+                                        }
+                                    } else {
+                                        type VMContextWithEnv = VMDynamicFunctionContext<DynamicFunctionWithEnv<std::ffi::c_void>>;
+                                        unsafe {
+                                            let ctx = self.vmctx().host_env as *mut VMContextWithEnv;
+                                            (*ctx).ctx.call(&params_list)?
+                                        }
+                                    };
+                                    let mut rets_list_array = Rets::empty_array();
+                                    let mut_rets = rets_list_array.as_mut() as *mut [i128] as *mut i128;
+                                    for (i, ret) in results.iter().enumerate() {
+                                        unsafe {
+                                            ret.write_value_to(mut_rets.add(i));
+                                        }
                                     }
+                                    Ok(Rets::from_array(rets_list_array))
                                 }
-                                Ok(Rets::from_array(rets_list_array))
                             }
-                        }
-                    },
-                }
+                        },
+                    }
+                })
 
             }
         }
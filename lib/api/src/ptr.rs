@@ -6,8 +6,13 @@
 //! Therefore, you should use this abstraction whenever possible to avoid memory
 //! related bugs when implementing an ABI.
 
-use crate::{externals::Memory, FromToNativeWasmType};
-use std::{cell::Cell, fmt, marker::PhantomData, mem};
+use crate::{externals::Memory, FromToNativeWasmType, MemoryAccessError};
+use std::{
+    cell::Cell,
+    fmt,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+};
 use wasmer_types::ValueType;
 
 /// The `Array` marker type. This type can be used like `WasmPtr<T, Array>`
@@ -67,6 +72,23 @@ fn align_pointer(ptr: usize, align: usize) -> usize {
 /// that implement [`ValueType`], meaning that they're valid for all possible
 /// bit patterns.
 impl<T: Copy + ValueType> WasmPtr<T, Item> {
+    /// Reads the value pointed to by this `WasmPtr`, with
+    /// [`Memory::read_struct`].
+    ///
+    /// Unlike [`WasmPtr::deref`], the access is sound with shared
+    /// memories and doesn't hold a reference to the memory.
+    #[inline]
+    pub fn read(self, memory: &Memory) -> Result<T, MemoryAccessError> {
+        memory.read_struct(self.offset as u64)
+    }
+
+    /// Writes `value` where this `WasmPtr` points to, with
+    /// [`Memory::write_struct`].
+    #[inline]
+    pub fn write(self, memory: &Memory, value: T) -> Result<(), MemoryAccessError> {
+        memory.write_struct(self.offset as u64, &value)
+    }
+
     /// Dereference the `WasmPtr` getting access to a `&Cell<T>` allowing for
     /// reading and mutating of the inner value.
     ///
@@ -277,7 +299,10 @@ unsafe impl<T: Copy, Ty> FromToNativeWasmType for WasmPtr<T, Ty> {
     }
 }
 
-unsafe impl<T: Copy, Ty> ValueType for WasmPtr<T, Ty> {}
+unsafe impl<T: Copy, Ty> ValueType for WasmPtr<T, Ty> {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
 
 impl<T: Copy, Ty> Clone for WasmPtr<T, Ty> {
     fn clone(&self) -> Self {
@@ -1,6 +1,7 @@
+use crate::limiter::{LimitedTunables, ResourceLimiter};
 use crate::tunables::BaseTunables;
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 #[cfg(all(feature = "compiler", feature = "engine"))]
 use wasmer_compiler::CompilerConfig;
 use wasmer_engine::{Engine, Tunables};
@@ -14,11 +15,16 @@ use wasmer_engine::{Engine, Tunables};
 /// the Wasm bytes into a valid module artifact), in addition to the
 /// [`Tunables`] (that are used to create the memories, tables and globals).
 ///
+/// A [`ResourceLimiter`] can account for the memories, tables and
+/// instances created with the `Store`, see
+/// [`Store::set_resource_limiter`].
+///
 /// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#store>
 #[derive(Clone)]
 pub struct Store {
     engine: Arc<dyn Engine + Send + Sync>,
     tunables: Arc<dyn Tunables + Send + Sync>,
+    resource_limiter: Arc<Mutex<Option<Arc<dyn ResourceLimiter>>>>,
 }
 
 impl Store {
@@ -30,6 +36,7 @@ impl Store {
         Self {
             engine: engine.cloned(),
             tunables: Arc::new(BaseTunables::for_target(engine.target())),
+            resource_limiter: Arc::default(),
         }
     }
 
@@ -41,6 +48,7 @@ impl Store {
         Self {
             engine: engine.cloned(),
             tunables: Arc::new(tunables),
+            resource_limiter: Arc::default(),
         }
     }
 
@@ -49,6 +57,30 @@ impl Store {
         self.tunables.as_ref()
     }
 
+    /// Sets the [`ResourceLimiter`] of the memories, tables and
+    /// instances created with this store and its clones, replacing the
+    /// previous one.
+    ///
+    /// The memories, tables and instances already created keep reporting
+    /// to the previous limiter, if any.
+    pub fn set_resource_limiter(&self, limiter: impl ResourceLimiter + 'static) {
+        *self.resource_limiter.lock().unwrap() = Some(Arc::new(limiter));
+    }
+
+    /// Returns the [`ResourceLimiter`], if any.
+    pub fn resource_limiter(&self) -> Option<Arc<dyn ResourceLimiter>> {
+        self.resource_limiter.lock().unwrap().clone()
+    }
+
+    /// Returns the [`Tunables`], wrapped to report the memories and
+    /// tables they create to the [`ResourceLimiter`].
+    pub(crate) fn limited_tunables(&self) -> LimitedTunables<'_> {
+        LimitedTunables {
+            base: self.tunables(),
+            limiter: self.resource_limiter(),
+        }
+    }
+
     /// Returns the [`Engine`].
     pub fn engine(&self) -> &Arc<dyn Engine + Send + Sync> {
         &self.engine
@@ -111,6 +143,7 @@ impl Default for Store {
         Store {
             engine: Arc::new(engine),
             tunables: Arc::new(tunables),
+            resource_limiter: Arc::default(),
         }
     }
 }
@@ -0,0 +1,134 @@
+//! The stub resolver module contains the [`StubResolver`], which
+//! resolves the imports that another resolver can't resolve to stubs.
+use crate::exports::Exportable;
+use crate::externals::{Extern, Function, Global, Memory, Table};
+use crate::module::Module;
+use crate::store::Store;
+use crate::types::{Val, ValType};
+use crate::{ExternRef, RuntimeError};
+use wasmer_engine::{Export, Resolver};
+use wasmer_types::{ExternType, Mutability};
+
+/// A `StubResolver` resolves the imports of a module with another
+/// resolver, and the imports that this resolver doesn't provide to
+/// stubs:
+///
+/// - functions trap when called, with a [`RuntimeError`] naming the
+///   missing import;
+/// - globals are initialized to zero, or to the null `externref`;
+///   `funcref` globals aren't stubbed, as they have no null value;
+/// - memories and tables are created with their minimum size, and
+///   tables are filled with null references.
+///
+/// It allows to run modules whose missing imports are never used. The
+/// imports that the resolver provides, but with an incompatible type,
+/// still fail the instantiation.
+///
+/// ```
+/// # use wasmer::{imports, Function, Instance, Module, Store, StubResolver};
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let module = Module::new(&store, r#"
+///     (module
+///       (import "host" "rarely_used" (func $rarely_used))
+///       (import "host" "answer" (func $answer (result i32)))
+///       (func (export "run") (result i32)
+///         call $answer)
+///       (func (export "fallback")
+///         call $rarely_used))
+/// "#)?;
+/// let import_object = imports! {
+///     "host" => { "answer" => Function::new_native(&store, || 42) },
+/// };
+/// let instance = Instance::new(&module, &StubResolver::new(&module, import_object))?;
+///
+/// let run = instance.exports.get_native_function::<(), i32>("run")?;
+/// assert_eq!(run.call()?, 42);
+///
+/// let fallback = instance.exports.get_native_function::<(), ()>("fallback")?;
+/// let error = fallback.call().unwrap_err();
+/// assert!(error.message().contains("\"host\".\"rarely_used\""));
+/// # Ok(())
+/// # }
+/// ```
+pub struct StubResolver<R: Resolver> {
+    store: Store,
+    imports: Vec<(String, String, ExternType)>,
+    resolver: R,
+}
+
+impl<R: Resolver> StubResolver<R> {
+    /// Creates a new `StubResolver` for the imports of `module`, resolved
+    /// with `resolver` first.
+    pub fn new(module: &Module, resolver: R) -> Self {
+        Self {
+            store: module.store().clone(),
+            imports: module
+                .imports()
+                .map(|import| {
+                    (
+                        import.module().to_string(),
+                        import.name().to_string(),
+                        import.ty().clone(),
+                    )
+                })
+                .collect(),
+            resolver,
+        }
+    }
+
+    /// Returns the resolver resolving the imports before the stubs.
+    pub fn resolver(&self) -> &R {
+        &self.resolver
+    }
+
+    /// Creates the stub of the import `module`.`name`, of type `ty`.
+    fn stub(&self, module: &str, name: &str, ty: &ExternType) -> Option<Extern> {
+        let stub = match ty {
+            ExternType::Function(ty) => {
+                let message = format!("called the unresolved import {:?}.{:?}", module, name);
+                Extern::Function(Function::new(&self.store, ty, move |_| {
+                    Err(RuntimeError::new(message.clone()))
+                }))
+            }
+            ExternType::Global(ty) => {
+                let value = default_value(ty.ty)?;
+                Extern::Global(match ty.mutability {
+                    Mutability::Const => Global::new(&self.store, value),
+                    Mutability::Var => Global::new_mut(&self.store, value),
+                })
+            }
+            ExternType::Memory(ty) => Extern::Memory(Memory::new(&self.store, *ty).ok()?),
+            ExternType::Table(ty) => {
+                // the null element of the tables of any reference type
+                let init = Val::ExternRef(ExternRef::null());
+                Extern::Table(Table::new(&self.store, *ty, init).ok()?)
+            }
+        };
+
+        Some(stub)
+    }
+}
+
+impl<R: Resolver> Resolver for StubResolver<R> {
+    fn resolve(&self, index: u32, module: &str, field: &str) -> Option<Export> {
+        self.resolver.resolve(index, module, field).or_else(|| {
+            let (module, name, ty) = self.imports.get(index as usize)?;
+            self.stub(module, name, ty).map(|stub| stub.to_export())
+        })
+    }
+}
+
+/// The zero value, or the null reference, of type `ty`, if it has one.
+fn default_value(ty: ValType) -> Option<Val> {
+    Some(match ty {
+        ValType::I32 => Val::I32(0),
+        ValType::I64 => Val::I64(0),
+        ValType::F32 => Val::F32(0.0),
+        ValType::F64 => Val::F64(0.0),
+        ValType::V128 => Val::V128(0),
+        ValType::ExternRef => Val::ExternRef(ExternRef::null()),
+        // a `Val::FuncRef` always holds a function
+        ValType::FuncRef => return None,
+    })
+}
@@ -3,7 +3,7 @@ use std::cmp::min;
 use std::ptr::NonNull;
 use std::sync::Arc;
 use target_lexicon::{OperatingSystem, PointerWidth};
-use wasmer_compiler::Target;
+use wasmer_compiler::{ModuleLimits, Target};
 use wasmer_engine::Tunables;
 use wasmer_vm::MemoryError;
 use wasmer_vm::{
@@ -29,6 +29,10 @@ pub struct BaseTunables {
 
     /// The size in bytes of the offset guard for dynamic heaps.
     pub dynamic_memory_offset_guard_size: u64,
+
+    /// The limits on the complexity of the modules to compile, see
+    /// [`Module::validate`](crate::Module::validate).
+    pub module_limits: ModuleLimits,
 }
 
 impl BaseTunables {
@@ -64,6 +68,7 @@ impl BaseTunables {
             static_memory_bound,
             static_memory_offset_guard_size,
             dynamic_memory_offset_guard_size,
+            module_limits: ModuleLimits::default(),
         }
     }
 }
@@ -94,6 +99,11 @@ impl Tunables for BaseTunables {
         TableStyle::CallerChecksSignature
     }
 
+    /// Get the [`ModuleLimits`] of the modules to compile.
+    fn module_limits(&self) -> ModuleLimits {
+        self.module_limits
+    }
+
     /// Create a memory owned by the host given a [`MemoryType`] and a [`MemoryStyle`].
     fn create_host_memory(
         &self,
@@ -159,6 +169,7 @@ mod tests {
             static_memory_bound: Pages(2048),
             static_memory_offset_guard_size: 128,
             dynamic_memory_offset_guard_size: 256,
+            module_limits: ModuleLimits::default(),
         };
 
         // No maximum
@@ -3,9 +3,10 @@ use crate::store::{Store, StoreObject};
 use crate::RuntimeError;
 use std::ptr;
 use wasmer_types::Value;
+#[allow(deprecated)]
 pub use wasmer_types::{
     ExportType, ExternRef, ExternType, FunctionType, GlobalType, HostInfo, HostRef, ImportType,
-    MemoryType, Mutability, TableType, Type as ValType,
+    MemoryType, Mutability, RawExternRef, TableType, Type as ValType,
 };
 
 /// WebAssembly computations manipulate values of basic value types:
@@ -20,8 +21,8 @@ impl StoreObject for Val {
     fn comes_from_same_store(&self, store: &Store) -> bool {
         match self {
             Self::FuncRef(f) => Store::same(store, f.store()),
-            Self::ExternRef(ExternRef::Ref(_)) | Self::ExternRef(ExternRef::Other(_)) => false,
-            Self::ExternRef(ExternRef::Null) => true,
+            // `externref`s point to host data, which doesn't belong to a store.
+            Self::ExternRef(_) => true,
             Self::I32(_) | Self::I64(_) | Self::F32(_) | Self::F64(_) | Self::V128(_) => true,
         }
     }
@@ -53,7 +54,7 @@ impl ValFuncRef for Val {
             return Err(RuntimeError::new("cross-`Store` values are not supported"));
         }
         Ok(match self {
-            Self::ExternRef(ExternRef::Null) => wasmer_vm::VMCallerCheckedAnyfunc {
+            Self::ExternRef(r) if r.is_null() => wasmer_vm::VMCallerCheckedAnyfunc {
                 func_ptr: ptr::null(),
                 type_index: wasmer_vm::VMSharedSignatureIndex::default(),
                 vmctx: wasmer_vm::VMFunctionEnvironment {
@@ -67,7 +68,7 @@ impl ValFuncRef for Val {
 
     fn from_checked_anyfunc(item: wasmer_vm::VMCallerCheckedAnyfunc, store: &Store) -> Self {
         if item.type_index == wasmer_vm::VMSharedSignatureIndex::default() {
-            return Self::ExternRef(ExternRef::Null);
+            return Self::ExternRef(ExternRef::null());
         }
         let signature = store
             .engine()
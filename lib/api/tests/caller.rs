@@ -0,0 +1,144 @@
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use wasmer::*;
+
+#[test]
+fn caller_is_the_calling_instance() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+    (module
+      (import "host" "read_id" (func $read_id (result i32)))
+      (global (export "id") (mut i32) (i32.const 0))
+      (func (export "run") (result i32)
+        call $read_id))
+"#,
+    )?;
+
+    // The same host function is shared by both instances.
+    let read_id = Function::new_native_with_caller(&store, |caller: &Caller| {
+        caller
+            .get_global("id")
+            .expect("the caller exports `id`")
+            .get()
+            .unwrap_i32()
+    });
+    let import_object = imports! {
+        "host" => {
+            "read_id" => read_id,
+        },
+    };
+
+    let first = Instance::new(&module, &import_object)?;
+    first.exports.get_global("id")?.set(Value::I32(1))?;
+    let second = Instance::new(&module, &import_object)?;
+    second.exports.get_global("id")?.set(Value::I32(2))?;
+
+    let run = first.exports.get_native_function::<(), i32>("run")?;
+    assert_eq!(run.call()?, 1);
+    let run = second.exports.get_function("run")?;
+    assert_eq!(run.call(&[])?.to_vec(), vec![Value::I32(2)]);
+
+    Ok(())
+}
+
+#[test]
+fn caller_is_the_instance_importing_the_function() -> Result<()> {
+    let store = Store::default();
+    let callee = Module::new(
+        &store,
+        r#"
+    (module
+      (import "host" "read_id" (func $read_id (result i32)))
+      (global (export "id") i32 (i32.const 2))
+      (func (export "run") (result i32)
+        call $read_id))
+"#,
+    )?;
+    let caller = Module::new(
+        &store,
+        r#"
+    (module
+      (import "callee" "run" (func $run (result i32)))
+      (import "host" "read_id" (func $read_id (result i32)))
+      (global (export "id") i32 (i32.const 1))
+      (func (export "run") (result i32)
+        call $run)
+      (func (export "read_id") (result i32)
+        call $read_id))
+"#,
+    )?;
+
+    let read_id = Function::new_native_with_caller(&store, |caller: &Caller| {
+        caller
+            .get_global("id")
+            .expect("the caller exports `id`")
+            .get()
+            .unwrap_i32()
+    });
+    let callee = Instance::new(
+        &callee,
+        &imports! { "host" => { "read_id" => read_id.clone() } },
+    )?;
+    let caller = Instance::new(
+        &caller,
+        &imports! {
+            "callee" => { "run" => callee.exports.get_function("run")?.clone() },
+            "host" => { "read_id" => read_id },
+        },
+    )?;
+
+    // The host function is called by the callee, through the caller.
+    let run = caller.exports.get_native_function::<(), i32>("run")?;
+    assert_eq!(run.call()?, 2);
+    let read_id = caller.exports.get_native_function::<(), i32>("read_id")?;
+    assert_eq!(read_id.call()?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn caller_during_start_and_outside_of_calls() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+    (module
+      (import "host" "check" (func $check))
+      (memory (export "memory") 1)
+      (start $check))
+"#,
+    )?;
+
+    let seen = Arc::new(Mutex::new(None));
+    let check = {
+        let seen = seen.clone();
+        Function::new_with_caller(
+            &store,
+            FunctionType::new(vec![], vec![]),
+            move |caller, _| {
+                let exports = caller.exports().expect("the start function has a caller");
+                assert!(exports.get_memory("memory").is_ok());
+                assert!(caller.get_table("memory").is_none());
+                *seen.lock().unwrap() = Some(caller.clone());
+
+                Ok(vec![])
+            },
+        )
+    };
+    let instance = Instance::new(&module, &imports! { "host" => { "check" => check } })?;
+
+    // The caller doesn't keep its instance alive.
+    let caller = seen.lock().unwrap().take().expect("the start function ran");
+    assert!(caller.is_active());
+    drop(instance);
+    assert!(!caller.is_active());
+    assert!(caller.get_memory("memory").is_none());
+
+    let caller = Caller::new();
+    assert!(!caller.is_active());
+    assert!(caller.get_memory("memory").is_none());
+
+    Ok(())
+}
@@ -70,14 +70,13 @@ fn table_new() -> Result<()> {
     let table = Table::new(&store, table_type, Value::FuncRef(f))?;
     assert_eq!(*table.ty(), table_type);
 
-    // Anyrefs not yet supported
-    // let table_type = TableType {
-    //     ty: Type::ExternRef,
-    //     minimum: 0,
-    //     maximum: None,
-    // };
-    // let table = Table::new(&store, table_type, Value::ExternRef(ExternRef::Null))?;
-    // assert_eq!(*table.ty(), table_type);
+    let table_type = TableType {
+        ty: Type::ExternRef,
+        minimum: 0,
+        maximum: None,
+    };
+    let table = Table::new(&store, table_type, Value::ExternRef(ExternRef::null()))?;
+    assert_eq!(*table.ty(), table_type);
 
     Ok(())
 }
@@ -134,6 +133,102 @@ fn table_copy() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn externref_global() -> Result<()> {
+    let store = Store::default();
+    let reference = ExternRef::new(String::from("hello"));
+    let global = Global::new_mut(&store, Value::ExternRef(reference.clone()));
+    assert_eq!(global.ty().ty, Type::ExternRef);
+
+    assert_eq!(reference.strong_count(), 2);
+
+    let value = global.get().unwrap_externref();
+    assert!(value.ptr_eq(&reference));
+    assert_eq!(
+        value.downcast::<String>().map(String::as_str),
+        Some("hello")
+    );
+    drop(value);
+
+    // Overwriting the value releases its reference.
+    global.set(Value::ExternRef(ExternRef::null()))?;
+    assert!(global.get().unwrap_externref().is_null());
+    assert_eq!(reference.strong_count(), 1);
+    assert!(global.set(Value::I32(1)).is_err());
+
+    // Dropping the global releases its reference.
+    global.set(Value::ExternRef(reference.clone()))?;
+    assert_eq!(reference.strong_count(), 2);
+    drop(global);
+    assert_eq!(reference.strong_count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn externref_table() -> Result<()> {
+    let store = Store::default();
+    let table_type = TableType {
+        ty: Type::ExternRef,
+        minimum: 2,
+        maximum: Some(4),
+    };
+    let reference = ExternRef::new(42u32);
+    let table = Table::new(&store, table_type, Value::ExternRef(reference.clone()))?;
+    assert_eq!(reference.strong_count(), 3);
+
+    let element = table.get(1).unwrap().unwrap_externref();
+    assert_eq!(element.downcast::<u32>(), Some(&42));
+    drop(element);
+
+    // Overwriting an element releases its reference.
+    table.set(0, Value::ExternRef(ExternRef::null()))?;
+    assert_eq!(reference.strong_count(), 2);
+    assert!(table.get(0).unwrap().unwrap_externref().is_null());
+
+    assert_eq!(table.grow(2, Value::ExternRef(reference.clone()))?, 2);
+    assert_eq!(reference.strong_count(), 4);
+
+    // Only `externref`s can be stored in the table.
+    let f = Function::new_native(&store, || {});
+    assert!(table.set(0, Value::FuncRef(f)).is_err());
+
+    // Dropping the table releases all its references.
+    drop(table);
+    assert_eq!(reference.strong_count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn externref_native_function() -> Result<()> {
+    let store = Store::default();
+    let unwrap = Function::new_native(&store, |reference: ExternRef| -> u32 {
+        reference.downcast::<u32>().copied().unwrap_or(0)
+    });
+    let unwrap = unwrap.native::<ExternRef, u32>()?;
+    assert_eq!(unwrap.call(ExternRef::new(42u32))?, 42);
+    assert_eq!(unwrap.call(ExternRef::null())?, 0);
+
+    let identity = Function::new(
+        &store,
+        &FunctionType::new(vec![Type::ExternRef], vec![Type::ExternRef]),
+        |args| Ok(vec![args[0].clone()]),
+    );
+    let identity = identity.native::<ExternRef, ExternRef>()?;
+    let native_identity = Function::new_native(&store, |reference: ExternRef| reference);
+    let native_identity = native_identity.native::<ExternRef, ExternRef>()?;
+    let reference = ExternRef::new(String::from("hello"));
+    assert!(identity.call(reference.clone())?.ptr_eq(&reference));
+    assert!(native_identity.call(reference.clone())?.ptr_eq(&reference));
+
+    // The references handed over during the calls are released when they
+    // return.
+    assert_eq!(reference.strong_count(), 1);
+
+    Ok(())
+}
+
 #[test]
 fn memory_new() -> Result<()> {
     let store = Store::default();
@@ -177,6 +272,85 @@ fn memory_grow() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn memory_read_write() -> Result<()> {
+    let store = Store::default();
+
+    for shared in [false, true].iter() {
+        let memory = Memory::new(&store, MemoryType::new(Pages(1), Some(Pages(2)), *shared))?;
+        let end = memory.data_size();
+
+        memory.write(0x10, b"hello")?;
+        let mut buf = [0u8; 5];
+        memory.read(0x10, &mut buf)?;
+        assert_eq!(&buf, b"hello");
+
+        // Unaligned typed accesses.
+        memory.write_struct(0x21, &0x0102_0304_0506_0708u64)?;
+        assert_eq!(memory.read_struct::<u64>(0x21)?, 0x0102_0304_0506_0708);
+        assert_eq!(memory.read_struct::<u8>(0x21)?, 0x08);
+
+        let ptr = WasmPtr::<u32>::new(0x30);
+        ptr.write(&memory, 42)?;
+        assert_eq!(ptr.read(&memory)?, 42);
+
+        // Out of bounds accesses are errors, and don't touch the memory.
+        memory.write(end - 2, b"ab")?;
+        assert_eq!(
+            memory.write(end - 2, b"abc"),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        );
+        assert_eq!(
+            memory.read_struct::<u32>(end - 2),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        );
+        assert_eq!(
+            memory.read(u64::MAX, &mut buf),
+            Err(MemoryAccessError::Overflow)
+        );
+        memory.read(end, &mut [])?;
+
+        // Accesses are checked against the current size.
+        memory.grow(Pages(1))?;
+        memory.write(end - 2, b"abc")?;
+        memory.read(end - 2, &mut buf[..3])?;
+        assert_eq!(&buf[..3], b"abc");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn memory_read_write_after_moving() -> Result<()> {
+    // With no static memory bound, the memories are dynamic, and move
+    // when they grow beyond their allocation.
+    let store = Store::default();
+    let mut tunables = BaseTunables::for_target(store.engine().target());
+    tunables.static_memory_bound = Pages(0);
+    let store = Store::new_with_tunables(&**store.engine(), tunables);
+
+    let memory = Memory::new(&store, MemoryType::new(Pages(1), None, false))?;
+    let end = memory.data_size();
+    memory.write(end - 5, b"hello")?;
+
+    let base = memory.data_ptr();
+    memory.grow(Pages(1))?;
+    assert_ne!(memory.data_ptr(), base);
+
+    // The accesses use the new base, and the new size.
+    let mut buf = [0u8; 8];
+    memory.read(end - 5, &mut buf[..5])?;
+    assert_eq!(&buf[..5], b"hello");
+    memory.write_struct(end - 4, &0x0102_0304_0506_0708u64)?;
+    assert_eq!(memory.read_struct::<u64>(end - 4)?, 0x0102_0304_0506_0708);
+    assert_eq!(
+        memory.read(memory.data_size() - 4, &mut buf),
+        Err(MemoryAccessError::HeapOutOfBounds)
+    );
+
+    Ok(())
+}
+
 #[test]
 fn function_new() -> Result<()> {
     let store = Store::default();
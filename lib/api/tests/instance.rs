@@ -1,4 +1,5 @@
 use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use wasmer::*;
 
 #[test]
@@ -37,3 +38,201 @@ fn exports_work_after_multiple_instances_have_been_freed() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn instance_pre_instantiates_many_times() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        "
+    (module
+      (import \"host\" \"double\" (func $double (param i32) (result i32)))
+      (global $counter (mut i32) (i32.const 0))
+      (func (export \"increment\") (result i32)
+        global.get $counter
+        i32.const 1
+        i32.add
+        call $double
+        global.set $counter
+        global.get $counter))
+",
+    )?;
+    let import_object = imports! {
+        "host" => {
+            "double" => Function::new_native(&store, |x: i32| x * 2),
+        },
+    };
+    let instance_pre = InstancePre::new(&module, &import_object)?;
+
+    // Every instance has its own state.
+    for _ in 0..3 {
+        let instance = instance_pre.instantiate()?;
+        let increment = instance
+            .exports
+            .get_native_function::<(), i32>("increment")?;
+        assert_eq!(increment.call()?, 2);
+        assert_eq!(increment.call()?, 6);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn instance_pre_resolves_imports_once() -> Result<()> {
+    /// Counts the imports it resolves.
+    struct CountingResolver(ImportObject, AtomicUsize);
+
+    impl Resolver for CountingResolver {
+        fn resolve(&self, index: u32, module: &str, field: &str) -> Option<Export> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.resolve(index, module, field)
+        }
+    }
+
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        "
+    (module
+      (import \"host\" \"read_id\" (func $read_id (result i32)))
+      (global (export \"id\") (mut i32) (i32.const 0))
+      (func (export \"run\") (result i32)
+        call $read_id))
+",
+    )?;
+    let read_id = Function::new_native_with_caller(&store, |caller: &Caller| {
+        caller.get_global("id").unwrap().get().unwrap_i32()
+    });
+    let resolver = CountingResolver(
+        imports! { "host" => { "read_id" => read_id } },
+        AtomicUsize::new(0),
+    );
+    let instance_pre = InstancePre::new(&module, &resolver)?;
+
+    // Every instance gets its own host function environment, bound to it.
+    let instances = (1..=3)
+        .map(|id| {
+            let instance = instance_pre.instantiate()?;
+            instance.exports.get_global("id")?.set(Value::I32(id))?;
+            Ok(instance)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for (id, instance) in (1..=3).zip(instances.iter()) {
+        let run = instance.exports.get_native_function::<(), i32>("run")?;
+        assert_eq!(run.call()?, id);
+    }
+    assert_eq!(resolver.1.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[test]
+fn instance_pre_checks_imports() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        "(module (import \"host\" \"double\" (func (param i32) (result i32))))",
+    )?;
+
+    let missing = ImportObject::new();
+    assert!(matches!(
+        InstancePre::new(&module, &missing),
+        Err(InstantiationError::Link(LinkError::Import(_, _, _)))
+    ));
+
+    let incompatible = imports! {
+        "host" => {
+            "double" => Function::new_native(&store, |x: i64| x * 2),
+        },
+    };
+    assert!(matches!(
+        InstancePre::new(&module, &incompatible),
+        Err(InstantiationError::Link(LinkError::Import(_, _, _)))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn stub_resolver_stubs_missing_imports() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+            (import "env" "answer" (func $answer (result i32)))
+            (import "env" "missing" (func $missing (param i32)))
+            (import "env" "counter" (global $counter (mut i64)))
+            (import "env" "memory" (memory 1))
+            (import "env" "table" (table 2 funcref))
+            (func (export "answer") (result i32)
+                call $answer)
+            (func (export "missing")
+                i32.const 1
+                call $missing)
+            (export "counter" (global $counter))
+            (export "memory" (memory 0))
+            (export "table" (table 0)))"#,
+    )?;
+    let import_object = imports! {
+        "env" => {
+            "answer" => Function::new_native(&store, || 42),
+        },
+    };
+    let instance = Instance::new(&module, &StubResolver::new(&module, import_object))?;
+
+    let answer: NativeFunc<(), i32> = instance.exports.get_native_function("answer")?;
+    assert_eq!(answer.call()?, 42);
+
+    let missing: NativeFunc<(), ()> = instance.exports.get_native_function("missing")?;
+    let error = missing.call().unwrap_err();
+    assert_eq!(
+        error.message(),
+        "called the unresolved import \"env\".\"missing\""
+    );
+
+    assert_eq!(instance.exports.get_global("counter")?.get(), Value::I64(0));
+    assert_eq!(instance.exports.get_memory("memory")?.size(), Pages(1));
+    assert_eq!(instance.exports.get_table("table")?.size(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn stub_resolver_checks_provided_imports() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        "(module (import \"host\" \"double\" (func (param i32) (result i32))))",
+    )?;
+
+    let incompatible = imports! {
+        "host" => {
+            "double" => Function::new_native(&store, |x: i64| x * 2),
+        },
+    };
+    assert!(matches!(
+        Instance::new(&module, &StubResolver::new(&module, incompatible)),
+        Err(InstantiationError::Link(LinkError::Import(_, _, _)))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn stub_resolver_skips_funcref_globals() -> Result<()> {
+    let mut features = Features::new();
+    features.reference_types(true);
+    let store = Store::new(&JIT::new(Cranelift::default()).features(features).engine());
+    let module = Module::new(
+        &store,
+        "(module (import \"host\" \"callback\" (global funcref)))",
+    )?;
+
+    // there is no null `funcref` value to stub the global with
+    assert!(matches!(
+        Instance::new(&module, &StubResolver::new(&module, imports! {})),
+        Err(InstantiationError::Link(LinkError::Import(_, _, _)))
+    ));
+
+    Ok(())
+}
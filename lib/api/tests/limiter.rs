@@ -0,0 +1,147 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use wasmer::*;
+
+/// Limits the combined size of the memories and tables, and the number
+/// of instances, of a store.
+#[derive(Default)]
+struct Budget {
+    max_pages: u32,
+    max_elements: u32,
+    max_instances: u32,
+    pages: AtomicU32,
+    elements: AtomicU32,
+    instances: AtomicU32,
+}
+
+impl Budget {
+    fn reserve(counter: &AtomicU32, delta: u32, max: u32) -> bool {
+        counter
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                used.checked_add(delta).filter(|&used| used <= max)
+            })
+            .is_ok()
+    }
+}
+
+/// A `Budget` shared with the store, to check its counters.
+struct SharedBudget(Arc<Budget>);
+
+impl ResourceLimiter for SharedBudget {
+    fn memory_growing(&self, current: Pages, desired: Pages, _maximum: Option<Pages>) -> bool {
+        Budget::reserve(&self.0.pages, desired.0 - current.0, self.0.max_pages)
+    }
+
+    fn memory_released(&self, size: Pages) {
+        self.0.pages.fetch_sub(size.0, Ordering::SeqCst);
+    }
+
+    fn table_growing(&self, current: u32, desired: u32, _maximum: Option<u32>) -> bool {
+        Budget::reserve(&self.0.elements, desired - current, self.0.max_elements)
+    }
+
+    fn table_released(&self, size: u32) {
+        self.0.elements.fetch_sub(size, Ordering::SeqCst);
+    }
+
+    fn instance_creating(&self) -> bool {
+        Budget::reserve(&self.0.instances, 1, self.0.max_instances)
+    }
+
+    fn instance_released(&self) {
+        self.0.instances.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn limited_store(budget: Budget) -> (Store, Arc<Budget>) {
+    let budget = Arc::new(budget);
+    let store = Store::default();
+    store.set_resource_limiter(SharedBudget(budget.clone()));
+    (store, budget)
+}
+
+#[test]
+fn limiter_memory_growth() -> Result<()> {
+    let (store, budget) = limited_store(Budget {
+        max_pages: 4,
+        max_instances: 10,
+        ..Budget::default()
+    });
+    let module = Module::new(
+        &store,
+        r#"(module
+            (memory 1)
+            (func (export "grow") (param i32) (result i32)
+                local.get 0
+                memory.grow))"#,
+    )?;
+
+    let instance = Instance::new(&module, &imports! {})?;
+    assert_eq!(budget.pages.load(Ordering::SeqCst), 1);
+
+    let grow = instance.exports.get_native_function::<i32, i32>("grow")?;
+    assert_eq!(grow.call(2)?, 1);
+    assert_eq!(budget.pages.load(Ordering::SeqCst), 3);
+    assert_eq!(grow.call(2)?, -1);
+    assert_eq!(budget.pages.load(Ordering::SeqCst), 3);
+
+    // The budget is shared with the other instances of the store.
+    let other = Instance::new(&module, &imports! {})?;
+    assert_eq!(budget.pages.load(Ordering::SeqCst), 4);
+    assert!(Instance::new(&module, &imports! {}).is_err());
+    assert!(Memory::new(&store, MemoryType::new(1, None, false)).is_err());
+
+    drop(other);
+    assert_eq!(budget.pages.load(Ordering::SeqCst), 3);
+    assert_eq!(grow.call(1)?, 3);
+    assert_eq!(budget.pages.load(Ordering::SeqCst), 4);
+
+    Ok(())
+}
+
+#[test]
+fn limiter_table_growth() -> Result<()> {
+    let (store, budget) = limited_store(Budget {
+        max_elements: 3,
+        ..Budget::default()
+    });
+
+    let table = Table::new(
+        &store,
+        TableType::new(Type::FuncRef, 1, None),
+        Value::ExternRef(ExternRef::null()),
+    )?;
+    assert_eq!(budget.elements.load(Ordering::SeqCst), 1);
+
+    assert_eq!(table.grow(2, Value::ExternRef(ExternRef::null()))?, 1);
+    assert_eq!(budget.elements.load(Ordering::SeqCst), 3);
+    assert!(table.grow(1, Value::ExternRef(ExternRef::null())).is_err());
+
+    drop(table);
+    assert_eq!(budget.elements.load(Ordering::SeqCst), 0);
+
+    Ok(())
+}
+
+#[test]
+fn limiter_instances() -> Result<()> {
+    let (store, budget) = limited_store(Budget {
+        max_instances: 1,
+        ..Budget::default()
+    });
+    let module = Module::new(&store, "(module)")?;
+
+    let instance = Instance::new(&module, &imports! {})?;
+    assert_eq!(budget.instances.load(Ordering::SeqCst), 1);
+    assert!(matches!(
+        Instance::new(&module, &imports! {}),
+        Err(InstantiationError::Link(LinkError::Resource(_)))
+    ));
+
+    drop(instance);
+    assert_eq!(budget.instances.load(Ordering::SeqCst), 0);
+    Instance::new(&module, &imports! {})?;
+
+    Ok(())
+}
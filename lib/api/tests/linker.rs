@@ -0,0 +1,148 @@
+use anyhow::Result;
+use wasmer::*;
+
+#[test]
+fn linker_links_instances_by_name() -> Result<()> {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    linker.define("host", "base", Global::new(&store, Value::I32(40)))?;
+
+    let library = Module::new(
+        &store,
+        r#"
+    (module
+      (import "host" "base" (global $base i32))
+      (func (export "add_base") (param i32) (result i32)
+        local.get 0
+        global.get $base
+        i32.add))
+"#,
+    )?;
+    linker.module("library", &library)?;
+
+    let program = Module::new(
+        &store,
+        r#"
+    (module
+      (import "library" "add_base" (func $add_base (param i32) (result i32)))
+      (func (export "run") (result i32)
+        i32.const 2
+        call $add_base))
+"#,
+    )?;
+    let instance = linker.instantiate(&program)?;
+
+    let run = instance.exports.get_native_function::<(), i32>("run")?;
+    assert_eq!(run.call()?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn linker_shadowing() -> Result<()> {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    linker.define("host", "value", Global::new(&store, Value::I32(1)))?;
+
+    match linker.define("host", "value", Global::new(&store, Value::I32(2))) {
+        Err(LinkerError::AlreadyDefined(module, name)) => {
+            assert_eq!(module, "host");
+            assert_eq!(name, "value");
+        }
+        _ => panic!("shadowing should be an error by default"),
+    }
+
+    linker.allow_shadowing(true);
+    linker.define("host", "value", Global::new(&store, Value::I32(2)))?;
+
+    let module = Module::new(
+        &store,
+        r#"
+    (module
+      (import "host" "value" (global $value i32))
+      (func (export "get") (result i32)
+        global.get $value))
+"#,
+    )?;
+    let instance = linker.instantiate(&module)?;
+
+    let get = instance.exports.get_native_function::<(), i32>("get")?;
+    assert_eq!(get.call()?, 2);
+
+    Ok(())
+}
+
+#[test]
+fn linker_lists_all_unresolved_imports() -> Result<()> {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    linker.define("env", "value", Global::new(&store, Value::I64(1)))?;
+
+    let module = Module::new(
+        &store,
+        r#"
+    (module
+      (import "env" "value" (global i32))
+      (import "env" "missing" (func))
+      (import "other" "memory" (memory 1)))
+"#,
+    )?;
+
+    let unresolved_imports = match linker.instantiate(&module) {
+        Err(LinkerError::UnresolvedImports(unresolved_imports)) => unresolved_imports,
+        _ => panic!("the instantiation should fail"),
+    };
+
+    let names: Vec<_> = unresolved_imports
+        .iter()
+        .map(|import| {
+            (
+                import.module.as_str(),
+                import.name.as_str(),
+                import.found.is_some(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("env", "value", true),
+            ("env", "missing", false),
+            ("other", "memory", false),
+        ]
+    );
+
+    let message = LinkerError::UnresolvedImports(unresolved_imports).to_string();
+    assert!(message.starts_with("3 import(s) can't be resolved:"));
+    assert!(message.contains("\"env\".\"missing\": unknown import"));
+
+    Ok(())
+}
+
+#[test]
+fn linker_rejects_other_stores() -> Result<()> {
+    let store = Store::default();
+    let other_store = Store::default();
+    let mut linker = Linker::new(&store);
+
+    match linker.define("host", "value", Global::new(&other_store, Value::I32(1))) {
+        Err(LinkerError::StoreMismatch(module, name)) => {
+            assert_eq!(module, "host");
+            assert_eq!(name, "value");
+        }
+        _ => panic!("the items of other stores should be rejected"),
+    }
+    assert!(linker.get("host", "value").is_none());
+
+    let module = Module::new(&other_store, "(module)")?;
+    assert!(matches!(
+        linker.instantiate(&module),
+        Err(LinkerError::ModuleStoreMismatch)
+    ));
+    assert!(matches!(
+        linker.instantiate_pre(&module),
+        Err(LinkerError::ModuleStoreMismatch)
+    ));
+
+    Ok(())
+}
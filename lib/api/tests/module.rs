@@ -246,3 +246,186 @@ fn calling_host_functions_with_negative_values_works() -> Result<()> {
 
     Ok(())
 }
+
+/// Appends a custom section `name` with `contents` to `wasm`.
+fn push_custom_section(wasm: &mut Vec<u8>, name: &str, contents: &[u8]) {
+    let mut section = vec![name.len() as u8];
+    section.extend_from_slice(name.as_bytes());
+    section.extend_from_slice(contents);
+
+    wasm.push(0);
+    wasm.push(section.len() as u8);
+    wasm.extend(section);
+}
+
+#[test]
+fn module_metadata() -> Result<()> {
+    let store = Store::default();
+    let mut wasm = wat2wasm(
+        br#"(module
+            (import "host" "log" (func $log (param i32)))
+            (func $add (param $lhs i32) (param $rhs i32) (result i32)
+                (local $sum i32)
+                local.get $lhs
+                local.get $rhs
+                i32.add))"#,
+    )?
+    .into_owned();
+
+    push_custom_section(
+        &mut wasm,
+        "producers",
+        b"\x02\x08language\x01\x04Rust\x00\x0cprocessed-by\x01\x05rustc\x061.50.0",
+    );
+    push_custom_section(
+        &mut wasm,
+        "target_features",
+        b"\x02+\x07simd128=\x07atomics",
+    );
+    push_custom_section(&mut wasm, "sourceMappingURL", b"\x0cadd.wasm.map");
+
+    let module = Module::new(&store, wasm)?;
+
+    let log = FunctionIndex::from_u32(0);
+    let add = FunctionIndex::from_u32(1);
+    assert_eq!(module.function_name(log), Some("log"));
+    assert_eq!(module.function_name(add), Some("add"));
+    assert_eq!(module.local_name(add, 0), Some("lhs"));
+    assert_eq!(module.local_name(add, 2), Some("sum"));
+    assert_eq!(module.local_name(add, 3), None);
+    assert_eq!(module.function_body_size(log), None);
+    assert!(module.function_body_size(add).unwrap() > 0);
+
+    assert_eq!(
+        module.producers()?,
+        Some(Producers {
+            language: vec![ProducerVersion {
+                name: "Rust".to_string(),
+                version: "".to_string(),
+            }],
+            processed_by: vec![ProducerVersion {
+                name: "rustc".to_string(),
+                version: "1.50.0".to_string(),
+            }],
+            sdk: vec![],
+        })
+    );
+    assert_eq!(
+        module.target_features()?,
+        Some(vec![
+            TargetFeature {
+                prefix: TargetFeaturePrefix::Used,
+                name: "simd128".to_string(),
+            },
+            TargetFeature {
+                prefix: TargetFeaturePrefix::Required,
+                name: "atomics".to_string(),
+            },
+        ])
+    );
+    assert_eq!(
+        module.source_mapping_url()?,
+        Some("add.wasm.map".to_string())
+    );
+
+    let module = Module::new(&store, "(module)")?;
+    assert_eq!(module.producers()?, None);
+    assert_eq!(module.target_features()?, None);
+    assert_eq!(module.source_mapping_url()?, None);
+
+    Ok(())
+}
+
+#[test]
+fn module_metadata_malformed() -> Result<()> {
+    let store = Store::default();
+    let mut wasm = wat2wasm(b"(module)")?.into_owned();
+    push_custom_section(&mut wasm, "target_features", b"\x01?\x07simd128");
+    push_custom_section(&mut wasm, "sourceMappingURL", b"\x0dadd.wasm");
+
+    let module = Module::new(&store, wasm)?;
+
+    let error = module.target_features().unwrap_err();
+    assert_eq!(error.section(), "target_features");
+    assert!(module.source_mapping_url().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn module_limits() -> Result<()> {
+    let wasm = wat2wasm(
+        br#"(module
+            (import "host" "log" (func $log (param i32)))
+            (memory 2)
+            (table 10 funcref)
+            (data (i32.const 0) "hello")
+            (func (local i32 i64))
+            (func))"#,
+    )?;
+
+    let exceeds = |limits: ModuleLimits| -> bool {
+        let store = Store::default();
+        let mut tunables = BaseTunables::for_target(store.engine().target());
+        tunables.module_limits = limits;
+        let store = Store::new_with_tunables(&**store.engine(), tunables);
+
+        match Module::new(&store, &wasm) {
+            Ok(_) => false,
+            Err(CompileError::LimitExceeded(_)) => true,
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    };
+
+    assert!(!exceeds(ModuleLimits::default()));
+    assert!(!exceeds(ModuleLimits {
+        max_functions: Some(2),
+        max_imports: Some(1),
+        max_locals_per_function: Some(2),
+        max_table_elements: Some(10),
+        max_memory_pages: Some(2),
+        max_data_segment_size: Some(5),
+        max_function_body_size: Some(1024),
+    }));
+
+    assert!(exceeds(ModuleLimits {
+        max_functions: Some(1),
+        ..ModuleLimits::default()
+    }));
+    assert!(exceeds(ModuleLimits {
+        max_imports: Some(0),
+        ..ModuleLimits::default()
+    }));
+    assert!(exceeds(ModuleLimits {
+        max_locals_per_function: Some(1),
+        ..ModuleLimits::default()
+    }));
+    assert!(exceeds(ModuleLimits {
+        max_table_elements: Some(9),
+        ..ModuleLimits::default()
+    }));
+    assert!(exceeds(ModuleLimits {
+        max_memory_pages: Some(1),
+        ..ModuleLimits::default()
+    }));
+    assert!(exceeds(ModuleLimits {
+        max_data_segment_size: Some(4),
+        ..ModuleLimits::default()
+    }));
+    assert!(exceeds(ModuleLimits {
+        max_function_body_size: Some(1),
+        ..ModuleLimits::default()
+    }));
+
+    // the limits are enforced without the validation too
+    let store = Store::default();
+    let mut tunables = BaseTunables::for_target(store.engine().target());
+    tunables.module_limits.max_functions = Some(1);
+    let store = Store::new_with_tunables(&**store.engine(), tunables);
+    assert!(matches!(
+        unsafe { Module::from_binary_unchecked(&store, &wasm) },
+        Err(CompileError::LimitExceeded(_))
+    ));
+
+    Ok(())
+}
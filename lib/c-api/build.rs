@@ -497,9 +497,15 @@ fn exclude_items_from_wasm_c_api(builder: Builder) -> Builder {
         .exclude_item("wasmer_features_t")
         .exclude_item("wasmer_features_tail_call")
         .exclude_item("wasmer_features_threads")
+        .exclude_item("wasmer_function_middleware_as_middleware")
+        .exclude_item("wasmer_function_middleware_delete")
+        .exclude_item("wasmer_function_middleware_feed_t")
+        .exclude_item("wasmer_function_middleware_new")
+        .exclude_item("wasmer_function_middleware_t")
         .exclude_item("wasmer_is_compiler_available")
         .exclude_item("wasmer_is_engine_available")
         .exclude_item("wasmer_is_headless")
+        .exclude_item("wasmer_memarg_t")
         .exclude_item("wasmer_metering_as_middleware")
         .exclude_item("wasmer_metering_delete")
         .exclude_item("wasmer_metering_get_remaining_points")
@@ -507,6 +513,14 @@ fn exclude_items_from_wasm_c_api(builder: Builder) -> Builder {
         .exclude_item("wasmer_metering_points_are_exhausted")
         .exclude_item("wasmer_metering_set_remaining_points")
         .exclude_item("wasmer_metering_t")
+        .exclude_item("wasmer_middleware_state_operator")
+        .exclude_item("wasmer_middleware_state_operator_index")
+        .exclude_item("wasmer_middleware_state_operator_memarg")
+        .exclude_item("wasmer_middleware_state_operator_offset")
+        .exclude_item("wasmer_middleware_state_operator_value")
+        .exclude_item("wasmer_middleware_state_push_operator")
+        .exclude_item("wasmer_middleware_state_push_operators")
+        .exclude_item("wasmer_middleware_state_t")
         .exclude_item("wasmer_middleware_t")
         .exclude_item("wasmer_module_name")
         .exclude_item("wasmer_module_set_name")
@@ -0,0 +1,793 @@
+//! Unstable non-standard Wasmer-specific API to implement custom
+//! function middlewares in C.
+//!
+//! A function middleware receives every operator of every function of
+//! a module before it is compiled. For each operator, it can push the
+//! operator itself, replacement operators, or both, which lets the
+//! embedder instrument the module.
+//!
+//! The `feed` callback may be called concurrently from several
+//! compilation threads, for different functions. Synchronization of
+//! `env`, if needed, must be done on the C side.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use inline_c::assert_c;
+//! # fn main() {
+//! #    (assert_c! {
+//! # #include "tests/wasmer_wasm.h"
+//! #
+//! // Our middleware replaces `i32.add` by `i32.sub`, and keeps the
+//! // other operators as they are.
+//! bool feed(void* env, uint32_t local_function_index, wasmer_middleware_state_t* state) {
+//!     // The module has a single function.
+//!     assert(local_function_index == 0);
+//!
+//!     // Count the operators we have seen.
+//!     uint32_t* counter = (uint32_t*) env;
+//!     *counter += 1;
+//!
+//!     if (wasmer_middleware_state_operator(state) == I32Add) {
+//!         // `i32.sub`, in the WebAssembly binary format.
+//!         wasm_byte_vec_t i32_sub;
+//!         wasm_byte_vec_new(&i32_sub, 1, "\x6b");
+//!
+//!         bool pushed = wasmer_middleware_state_push_operators(state, &i32_sub);
+//!         wasm_byte_vec_delete(&i32_sub);
+//!
+//!         return pushed;
+//!     }
+//!
+//!     return wasmer_middleware_state_push_operator(state);
+//! }
+//!
+//! int main() {
+//!     // Create a new function middleware, with our callback.
+//!     uint32_t counter = 0;
+//!     wasmer_function_middleware_t* function_middleware =
+//!         wasmer_function_middleware_new(feed, &counter, NULL);
+//!
+//!     // Consume `function_middleware` to produce a generic `wasmer_middleware_t` value.
+//!     wasmer_middleware_t* middleware = wasmer_function_middleware_as_middleware(function_middleware);
+//!
+//!     // Create a new configuration, and push the middleware in it.
+//!     wasm_config_t* config = wasm_config_new();
+//!     wasm_config_push_middleware(config, middleware);
+//!
+//!     // Create the engine and the store based on the configuration.
+//!     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+//!     wasm_store_t* store = wasm_store_new(engine);
+//!
+//!     // Create the new WebAssembly module.
+//!     wasm_byte_vec_t wat;
+//!     wasmer_byte_vec_new_from_string(
+//!         &wat,
+//!         "(module\n"
+//!         "  (type $add_t (func (param i32) (result i32)))\n"
+//!         "  (func $add_one_f (type $add_t) (param $value i32) (result i32)\n"
+//!         "    local.get $value\n"
+//!         "    i32.const 1\n"
+//!         "    i32.add)\n"
+//!         "  (export \"add_one\" (func $add_one_f)))"
+//!     );
+//!     wasm_byte_vec_t wasm;
+//!     wat2wasm(&wat, &wasm);
+//!
+//!     wasm_module_t* module = wasm_module_new(store, &wasm);
+//!     assert(module);
+//!
+//!     // `local.get`, `i32.const`, `i32.add` and `end` have been fed.
+//!     assert(counter == 4);
+//!
+//!     // Instantiate the module.
+//!     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+//!     wasm_trap_t* traps = NULL;
+//!     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &traps);
+//!     assert(instance);
+//!
+//!     // Get the `add_one` exported function.
+//!     wasm_extern_vec_t exports;
+//!     wasm_instance_exports(instance, &exports);
+//!     assert(exports.size >= 1);
+//!     assert(wasm_extern_kind(exports.data[0]) == WASM_EXTERN_FUNC);
+//!
+//!     const wasm_func_t* add_one = wasm_extern_as_func(exports.data[0]);
+//!     assert(add_one);
+//!
+//!     wasm_val_t arguments[1] = { WASM_I32_VAL(41) };
+//!     wasm_val_t results[1] = { WASM_INIT_VAL };
+//!
+//!     wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+//!     wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+//!
+//!     // `add_one` now subtracts one.
+//!     wasm_trap_t* trap = wasm_func_call(add_one, &arguments_as_array, &results_as_array);
+//!     assert(trap == NULL);
+//!     assert(results[0].of.i32 == 40);
+//!
+//!     wasm_extern_vec_delete(&exports);
+//!     wasm_instance_delete(instance);
+//!     wasm_module_delete(module);
+//!     wasm_store_delete(store);
+//!     wasm_engine_delete(engine);
+//!
+//!     return 0;
+//! }
+//! #    })
+//! #    .success();
+//! # }
+//! ```
+
+use super::super::super::externals::wasm_env_finalizer_t;
+use super::super::super::types::wasm_byte_vec_t;
+use super::super::super::value::wasm_val_t;
+use super::super::parser::operator::wasmer_parser_operator_t;
+use super::wasmer_middleware_t;
+use std::convert::TryFrom;
+use std::ffi::c_void;
+use std::fmt;
+use std::sync::Arc;
+use wasmer::wasmparser::{BinaryReader, Operator};
+use wasmer::{
+    FunctionMiddleware, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState,
+    ModuleMiddleware, Val,
+};
+
+/// Function type to represent the callback of a custom function
+/// middleware, implemented in C.
+///
+/// It is called for every operator of every local function of the
+/// module, with the `env` given to
+/// [`wasmer_function_middleware_new`]. The current operator can be
+/// inspected and pushed through `state`; operators which are not
+/// pushed are removed from the function.
+///
+/// Returning `false` aborts the compilation of the module.
+///
+/// # Example
+///
+/// See module's documentation.
+#[allow(non_camel_case_types)]
+pub type wasmer_function_middleware_feed_t = unsafe extern "C" fn(
+    env: *mut c_void,
+    local_function_index: u32,
+    state: &mut wasmer_middleware_state_t,
+) -> bool;
+
+/// The environment of a custom function middleware, shared by all the
+/// functions it instruments.
+struct FunctionMiddlewareEnv {
+    env: *mut c_void,
+    env_finalizer: Option<wasm_env_finalizer_t>,
+}
+
+// Only relevant when using multiple threads in the C API;
+// Synchronization will be done via the C API / on the C side.
+unsafe impl Send for FunctionMiddlewareEnv {}
+unsafe impl Sync for FunctionMiddlewareEnv {}
+
+impl Drop for FunctionMiddlewareEnv {
+    fn drop(&mut self) {
+        if let Some(env_finalizer) = self.env_finalizer {
+            if !self.env.is_null() {
+                unsafe { (env_finalizer)(self.env) }
+            }
+        }
+    }
+}
+
+/// The module middleware calling a C callback.
+struct CallbackMiddleware {
+    feed: wasmer_function_middleware_feed_t,
+    env: Arc<FunctionMiddlewareEnv>,
+}
+
+impl fmt::Debug for CallbackMiddleware {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("CallbackMiddleware").finish()
+    }
+}
+
+impl ModuleMiddleware for CallbackMiddleware {
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionCallbackMiddleware {
+            local_function_index,
+            feed: self.feed,
+            env: self.env.clone(),
+        })
+    }
+}
+
+/// The function middleware calling a C callback.
+struct FunctionCallbackMiddleware {
+    local_function_index: LocalFunctionIndex,
+    feed: wasmer_function_middleware_feed_t,
+    env: Arc<FunctionMiddlewareEnv>,
+}
+
+impl fmt::Debug for FunctionCallbackMiddleware {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("FunctionCallbackMiddleware")
+            .field("local_function_index", &self.local_function_index)
+            .finish()
+    }
+}
+
+impl FunctionMiddleware for FunctionCallbackMiddleware {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let offset = state.operator_offset();
+        let mut c_state = wasmer_middleware_state_t {
+            kind: (&operator).into(),
+            offset,
+            operator,
+            pushed: false,
+            inner: state,
+        };
+
+        if unsafe {
+            (self.feed)(
+                self.env.env,
+                self.local_function_index.as_u32(),
+                &mut c_state,
+            )
+        } {
+            Ok(())
+        } else {
+            Err(MiddlewareError::new(
+                "wasmer_function_middleware",
+                format!("the middleware rejected the operator at offset {}", offset),
+            ))
+        }
+    }
+}
+
+/// Opaque type representing a custom function middleware, implemented
+/// by a C callback.
+///
+/// To transform this specific middleware into a generic one, please
+/// see [`wasmer_function_middleware_as_middleware`].
+///
+/// # Example
+///
+/// See module's documentation.
+#[allow(non_camel_case_types)]
+pub struct wasmer_function_middleware_t {
+    inner: Arc<CallbackMiddleware>,
+}
+
+/// Opaque type representing the operator being fed to a custom
+/// function middleware, and the operators it pushes.
+///
+/// It is only valid during the call to the
+/// [`wasmer_function_middleware_feed_t`] callback.
+///
+/// # Example
+///
+/// See module's documentation.
+#[allow(non_camel_case_types)]
+pub struct wasmer_middleware_state_t<'a, 'b> {
+    kind: wasmer_parser_operator_t,
+    offset: usize,
+    operator: Operator<'a>,
+    /// Whether `operator` has been pushed, as it can only be pushed
+    /// once.
+    pushed: bool,
+    inner: &'b mut MiddlewareReaderState<'a>,
+}
+
+/// The memory immediate of a memory access, e.g. of `i32.load` or
+/// `i64.atomic.rmw.add`.
+///
+/// # Example
+///
+/// See [`wasmer_middleware_state_operator_memarg`].
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct wasmer_memarg_t {
+    /// The alignment of the access, as the exponent of a power of 2.
+    pub align: u8,
+    /// The offset added to the address of the access.
+    pub offset: u32,
+    /// The index of the accessed memory.
+    pub memory: u32,
+}
+
+/// Creates a new custom function middleware, calling `feed` for every
+/// operator of the compiled modules.
+///
+/// `env` is passed to every call of `feed`. If `env_finalizer` is not
+/// null, it is called with `env` when the middleware is no longer
+/// used, i.e. when it and the engines it has been pushed in are
+/// deleted.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_function_middleware_new(
+    feed: wasmer_function_middleware_feed_t,
+    env: *mut c_void,
+    env_finalizer: Option<wasm_env_finalizer_t>,
+) -> Box<wasmer_function_middleware_t> {
+    Box::new(wasmer_function_middleware_t {
+        inner: Arc::new(CallbackMiddleware {
+            feed,
+            env: Arc::new(FunctionMiddlewareEnv { env, env_finalizer }),
+        }),
+    })
+}
+
+/// Deletes a [`wasmer_function_middleware_t`].
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_function_middleware_delete(
+    _function_middleware: Option<Box<wasmer_function_middleware_t>>,
+) {
+}
+
+/// Transforms a [`wasmer_function_middleware_t`] into a generic
+/// [`wasmer_middleware_t`], to then be pushed in the configuration with
+/// [`wasm_config_push_middleware`][super::wasm_config_push_middleware].
+///
+/// This function takes ownership of `function_middleware`.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_function_middleware_as_middleware(
+    function_middleware: Option<Box<wasmer_function_middleware_t>>,
+) -> Option<Box<wasmer_middleware_t>> {
+    let function_middleware = function_middleware?;
+
+    Some(Box::new(wasmer_middleware_t {
+        inner: function_middleware.inner,
+    }))
+}
+
+/// Returns the kind of the operator being fed.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_operator(
+    state: &wasmer_middleware_state_t,
+) -> wasmer_parser_operator_t {
+    state.kind
+}
+
+/// Returns the offset of the operator being fed, in the bytes of the
+/// module.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_operator_offset(
+    state: &wasmer_middleware_state_t,
+) -> usize {
+    state.offset
+}
+
+/// Reads the `position`-th index immediate of the operator being fed,
+/// in the order of the WebAssembly binary format, into `out`.
+///
+/// The index immediates are the indices of locals, globals, functions,
+/// types, tables, memories, segments, and the relative depths of the
+/// branches. For example, the index immediates of `call_indirect` are
+/// its type index then its table index, and the ones of `br_table` are
+/// its targets then its default target.
+///
+/// Returns `false`, without writing `out`, if the operator has no such
+/// immediate.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer_wasm.h"
+/// #
+/// // Our middleware checks the immediates of the operators it is fed.
+/// bool feed(void* env, uint32_t local_function_index, wasmer_middleware_state_t* state) {
+///     assert(local_function_index == 0);
+///
+///     uint32_t index;
+///
+///     switch (wasmer_middleware_state_operator(state)) {
+///     case LocalGet:
+///         // The local index.
+///         assert(wasmer_middleware_state_operator_index(state, 0, &index));
+///         assert(index == 0);
+///         assert(!wasmer_middleware_state_operator_index(state, 1, &index));
+///         break;
+///
+///     case BrTable:
+///         // The targets, then the default target.
+///         assert(wasmer_middleware_state_operator_index(state, 0, &index));
+///         assert(index == 1);
+///         assert(wasmer_middleware_state_operator_index(state, 1, &index));
+///         assert(index == 0);
+///         assert(!wasmer_middleware_state_operator_index(state, 2, &index));
+///         break;
+///
+///     case I32Load8S: {
+///         wasmer_memarg_t memarg;
+///         assert(wasmer_middleware_state_operator_memarg(state, &memarg));
+///         assert(memarg.align == 0);
+///         assert(memarg.offset == 16);
+///         assert(memarg.memory == 0);
+///         assert(!wasmer_middleware_state_operator_index(state, 0, &index));
+///         break;
+///     }
+///
+///     case I32Const: {
+///         wasm_val_t value;
+///         assert(wasmer_middleware_state_operator_value(state, &value));
+///         assert(value.kind == WASM_I32);
+///         assert(value.of.i32 == -42);
+///         break;
+///     }
+///
+///     default:
+///         break;
+///     }
+///
+///     *((uint32_t*) env) += 1;
+///
+///     return wasmer_middleware_state_push_operator(state);
+/// }
+///
+/// int main() {
+///     uint32_t counter = 0;
+///     wasmer_function_middleware_t* function_middleware =
+///         wasmer_function_middleware_new(feed, &counter, NULL);
+///     wasmer_middleware_t* middleware = wasmer_function_middleware_as_middleware(function_middleware);
+///
+///     wasm_config_t* config = wasm_config_new();
+///     wasm_config_push_middleware(config, middleware);
+///
+///     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (memory 1)\n"
+///         "  (func (param i32) (result i32)\n"
+///         "    block\n"
+///         "      block\n"
+///         "        local.get 0\n"
+///         "        br_table 1 0\n"
+///         "      end\n"
+///         "    end\n"
+///         "    local.get 0\n"
+///         "    i32.load8_s offset=16\n"
+///         "    i32.const -42\n"
+///         "    i32.add))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     // All the operators have been fed, including the final `end`.
+///     assert(counter == 11);
+///
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_module_delete(module);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_operator_index(
+    state: &wasmer_middleware_state_t,
+    position: usize,
+    out: &mut u32,
+) -> bool {
+    match index_immediates(&state.operator).get(position) {
+        Some(index) => {
+            *out = *index;
+
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns the index immediates of `operator`, in the order of the
+/// WebAssembly binary format.
+fn index_immediates(operator: &Operator) -> Vec<u32> {
+    match *operator {
+        Operator::Catch { index } | Operator::Throw { index } => vec![index],
+        Operator::Rethrow { relative_depth }
+        | Operator::Br { relative_depth }
+        | Operator::BrIf { relative_depth } => vec![relative_depth],
+        Operator::BrTable { ref table } => table
+            .targets()
+            .filter_map(Result::ok)
+            .map(|(relative_depth, _)| relative_depth)
+            .collect(),
+        Operator::Call { function_index }
+        | Operator::ReturnCall { function_index }
+        | Operator::RefFunc { function_index } => vec![function_index],
+        Operator::CallIndirect { index, table_index }
+        | Operator::ReturnCallIndirect { index, table_index } => vec![index, table_index],
+        Operator::LocalGet { local_index }
+        | Operator::LocalSet { local_index }
+        | Operator::LocalTee { local_index } => vec![local_index],
+        Operator::GlobalGet { global_index } | Operator::GlobalSet { global_index } => {
+            vec![global_index]
+        }
+        Operator::MemorySize { mem, .. }
+        | Operator::MemoryGrow { mem, .. }
+        | Operator::MemoryFill { mem } => vec![mem],
+        Operator::MemoryInit { segment, mem } => vec![segment, mem],
+        Operator::MemoryCopy { src, dst } => vec![dst, src],
+        Operator::DataDrop { segment } | Operator::ElemDrop { segment } => vec![segment],
+        Operator::TableInit { segment, table } => vec![segment, table],
+        Operator::TableCopy {
+            dst_table,
+            src_table,
+        } => vec![dst_table, src_table],
+        Operator::TableFill { table }
+        | Operator::TableGet { table }
+        | Operator::TableSet { table }
+        | Operator::TableGrow { table }
+        | Operator::TableSize { table } => vec![table],
+        _ => vec![],
+    }
+}
+
+/// Reads the memory immediate of the operator being fed, a memory
+/// access, into `out`.
+///
+/// Returns `false`, without writing `out`, if the operator doesn't
+/// access the memory.
+///
+/// # Example
+///
+/// See [`wasmer_middleware_state_operator_index`].
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_operator_memarg(
+    state: &wasmer_middleware_state_t,
+    out: &mut wasmer_memarg_t,
+) -> bool {
+    let memarg = match state.operator {
+        Operator::I32Load { memarg }
+        | Operator::I64Load { memarg }
+        | Operator::F32Load { memarg }
+        | Operator::F64Load { memarg }
+        | Operator::I32Load8S { memarg }
+        | Operator::I32Load8U { memarg }
+        | Operator::I32Load16S { memarg }
+        | Operator::I32Load16U { memarg }
+        | Operator::I64Load8S { memarg }
+        | Operator::I64Load8U { memarg }
+        | Operator::I64Load16S { memarg }
+        | Operator::I64Load16U { memarg }
+        | Operator::I64Load32S { memarg }
+        | Operator::I64Load32U { memarg }
+        | Operator::I32Store { memarg }
+        | Operator::I64Store { memarg }
+        | Operator::F32Store { memarg }
+        | Operator::F64Store { memarg }
+        | Operator::I32Store8 { memarg }
+        | Operator::I32Store16 { memarg }
+        | Operator::I64Store8 { memarg }
+        | Operator::I64Store16 { memarg }
+        | Operator::I64Store32 { memarg }
+        | Operator::MemoryAtomicNotify { memarg }
+        | Operator::MemoryAtomicWait32 { memarg }
+        | Operator::MemoryAtomicWait64 { memarg }
+        | Operator::I32AtomicLoad { memarg }
+        | Operator::I64AtomicLoad { memarg }
+        | Operator::I32AtomicLoad8U { memarg }
+        | Operator::I32AtomicLoad16U { memarg }
+        | Operator::I64AtomicLoad8U { memarg }
+        | Operator::I64AtomicLoad16U { memarg }
+        | Operator::I64AtomicLoad32U { memarg }
+        | Operator::I32AtomicStore { memarg }
+        | Operator::I64AtomicStore { memarg }
+        | Operator::I32AtomicStore8 { memarg }
+        | Operator::I32AtomicStore16 { memarg }
+        | Operator::I64AtomicStore8 { memarg }
+        | Operator::I64AtomicStore16 { memarg }
+        | Operator::I64AtomicStore32 { memarg }
+        | Operator::I32AtomicRmwAdd { memarg }
+        | Operator::I64AtomicRmwAdd { memarg }
+        | Operator::I32AtomicRmw8AddU { memarg }
+        | Operator::I32AtomicRmw16AddU { memarg }
+        | Operator::I64AtomicRmw8AddU { memarg }
+        | Operator::I64AtomicRmw16AddU { memarg }
+        | Operator::I64AtomicRmw32AddU { memarg }
+        | Operator::I32AtomicRmwSub { memarg }
+        | Operator::I64AtomicRmwSub { memarg }
+        | Operator::I32AtomicRmw8SubU { memarg }
+        | Operator::I32AtomicRmw16SubU { memarg }
+        | Operator::I64AtomicRmw8SubU { memarg }
+        | Operator::I64AtomicRmw16SubU { memarg }
+        | Operator::I64AtomicRmw32SubU { memarg }
+        | Operator::I32AtomicRmwAnd { memarg }
+        | Operator::I64AtomicRmwAnd { memarg }
+        | Operator::I32AtomicRmw8AndU { memarg }
+        | Operator::I32AtomicRmw16AndU { memarg }
+        | Operator::I64AtomicRmw8AndU { memarg }
+        | Operator::I64AtomicRmw16AndU { memarg }
+        | Operator::I64AtomicRmw32AndU { memarg }
+        | Operator::I32AtomicRmwOr { memarg }
+        | Operator::I64AtomicRmwOr { memarg }
+        | Operator::I32AtomicRmw8OrU { memarg }
+        | Operator::I32AtomicRmw16OrU { memarg }
+        | Operator::I64AtomicRmw8OrU { memarg }
+        | Operator::I64AtomicRmw16OrU { memarg }
+        | Operator::I64AtomicRmw32OrU { memarg }
+        | Operator::I32AtomicRmwXor { memarg }
+        | Operator::I64AtomicRmwXor { memarg }
+        | Operator::I32AtomicRmw8XorU { memarg }
+        | Operator::I32AtomicRmw16XorU { memarg }
+        | Operator::I64AtomicRmw8XorU { memarg }
+        | Operator::I64AtomicRmw16XorU { memarg }
+        | Operator::I64AtomicRmw32XorU { memarg }
+        | Operator::I32AtomicRmwXchg { memarg }
+        | Operator::I64AtomicRmwXchg { memarg }
+        | Operator::I32AtomicRmw8XchgU { memarg }
+        | Operator::I32AtomicRmw16XchgU { memarg }
+        | Operator::I64AtomicRmw8XchgU { memarg }
+        | Operator::I64AtomicRmw16XchgU { memarg }
+        | Operator::I64AtomicRmw32XchgU { memarg }
+        | Operator::I32AtomicRmwCmpxchg { memarg }
+        | Operator::I64AtomicRmwCmpxchg { memarg }
+        | Operator::I32AtomicRmw8CmpxchgU { memarg }
+        | Operator::I32AtomicRmw16CmpxchgU { memarg }
+        | Operator::I64AtomicRmw8CmpxchgU { memarg }
+        | Operator::I64AtomicRmw16CmpxchgU { memarg }
+        | Operator::I64AtomicRmw32CmpxchgU { memarg }
+        | Operator::V128Load { memarg }
+        | Operator::V128Store { memarg }
+        | Operator::V128Load8Splat { memarg }
+        | Operator::V128Load16Splat { memarg }
+        | Operator::V128Load32Splat { memarg }
+        | Operator::V128Load32Zero { memarg }
+        | Operator::V128Load64Splat { memarg }
+        | Operator::V128Load64Zero { memarg }
+        | Operator::V128Load8x8S { memarg }
+        | Operator::V128Load8x8U { memarg }
+        | Operator::V128Load16x4S { memarg }
+        | Operator::V128Load16x4U { memarg }
+        | Operator::V128Load32x2S { memarg }
+        | Operator::V128Load32x2U { memarg }
+        | Operator::V128Load8Lane { memarg, .. }
+        | Operator::V128Load16Lane { memarg, .. }
+        | Operator::V128Load32Lane { memarg, .. }
+        | Operator::V128Load64Lane { memarg, .. }
+        | Operator::V128Store8Lane { memarg, .. }
+        | Operator::V128Store16Lane { memarg, .. }
+        | Operator::V128Store32Lane { memarg, .. }
+        | Operator::V128Store64Lane { memarg, .. } => memarg,
+        _ => return false,
+    };
+
+    *out = wasmer_memarg_t {
+        align: memarg.align,
+        offset: memarg.offset,
+        memory: memarg.memory,
+    };
+
+    true
+}
+
+/// Reads the value of the operator being fed, a constant (`i32.const`,
+/// `i64.const`, `f32.const` or `f64.const`), into `out`.
+///
+/// Returns `false`, without writing `out`, if the operator isn't one
+/// of these constants.
+///
+/// # Example
+///
+/// See [`wasmer_middleware_state_operator_index`].
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_operator_value(
+    state: &wasmer_middleware_state_t,
+    out: &mut wasm_val_t,
+) -> bool {
+    let value = match state.operator {
+        Operator::I32Const { value } => Val::I32(value),
+        Operator::I64Const { value } => Val::I64(value),
+        Operator::F32Const { value } => Val::F32(f32::from_bits(value.bits())),
+        Operator::F64Const { value } => Val::F64(f64::from_bits(value.bits())),
+        _ => return false,
+    };
+
+    match wasm_val_t::try_from(value) {
+        Ok(value) => {
+            *out = value;
+
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Pushes the operator being fed, unchanged.
+///
+/// Returns `false` if the operator has already been pushed.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_push_operator(
+    state: &mut wasmer_middleware_state_t,
+) -> bool {
+    if state.pushed {
+        return false;
+    }
+
+    state.pushed = true;
+    state.inner.push_operator(state.operator.clone());
+
+    true
+}
+
+/// Pushes operators encoded in the WebAssembly binary format, e.g.
+/// `"\x41\x2a"` for `i32.const 42`.
+///
+/// Returns `false`, without pushing anything, if `operators` can't be
+/// decoded or contains a `br_table`, which can't be pushed this way.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_middleware_state_push_operators(
+    state: &mut wasmer_middleware_state_t,
+    operators: &wasm_byte_vec_t,
+) -> bool {
+    let bytes = match operators.into_slice() {
+        Some(bytes) => bytes,
+        None => return true,
+    };
+    let mut reader = BinaryReader::new(bytes);
+    let mut decoded = vec![];
+
+    while !reader.eof() {
+        match reader.read_operator() {
+            Ok(Operator::BrTable { .. }) | Err(_) => return false,
+            Ok(operator) => decoded.push(operator),
+        }
+    }
+
+    for operator in decoded {
+        // SAFETY: only `br_table` borrows the bytes it has been decoded
+        // from, the other operators own their immediates.
+        let operator = std::mem::transmute::<Operator<'_>, Operator<'_>>(operator);
+        state.inner.push_operator(operator);
+    }
+
+    true
+}
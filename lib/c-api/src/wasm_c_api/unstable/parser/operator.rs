@@ -1,6 +1,7 @@
 use wasmer::wasmparser::Operator;
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 #[allow(non_camel_case_types)]
 pub enum wasmer_parser_operator_t {
     Unreachable,
@@ -645,8 +645,12 @@ typedef struct wasmer_cpu_features_t wasmer_cpu_features_t;
 
 typedef struct wasmer_features_t wasmer_features_t;
 
+typedef struct wasmer_function_middleware_t wasmer_function_middleware_t;
+
 typedef struct wasmer_metering_t wasmer_metering_t;
 
+typedef struct wasmer_middleware_state_t wasmer_middleware_state_t;
+
 typedef struct wasmer_middleware_t wasmer_middleware_t;
 
 #if defined(WASMER_WASI_ENABLED)
@@ -664,8 +668,18 @@ typedef struct wasmer_named_extern_vec_t {
 } wasmer_named_extern_vec_t;
 #endif
 
+typedef bool (*wasmer_function_middleware_feed_t)(void *env,
+                                                  uint32_t local_function_index,
+                                                  struct wasmer_middleware_state_t *state);
+
 typedef uint64_t (*wasmer_metering_cost_function_t)(enum wasmer_parser_operator_t wasm_operator);
 
+typedef struct wasmer_memarg_t {
+  uint8_t align;
+  uint32_t offset;
+  uint32_t memory;
+} wasmer_memarg_t;
+
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
@@ -801,6 +815,14 @@ bool wasmer_features_tail_call(struct wasmer_features_t *features, bool enable);
 
 bool wasmer_features_threads(struct wasmer_features_t *features, bool enable);
 
+struct wasmer_middleware_t *wasmer_function_middleware_as_middleware(struct wasmer_function_middleware_t *function_middleware);
+
+void wasmer_function_middleware_delete(struct wasmer_function_middleware_t *_function_middleware);
+
+struct wasmer_function_middleware_t *wasmer_function_middleware_new(wasmer_function_middleware_feed_t feed,
+                                                                    void *env,
+                                                                    void (*env_finalizer)(void*));
+
 bool wasmer_is_compiler_available(enum wasmer_compiler_t compiler);
 
 bool wasmer_is_engine_available(enum wasmer_engine_t engine);
@@ -824,6 +846,25 @@ bool wasmer_metering_points_are_exhausted(const wasm_instance_t *instance);
 
 void wasmer_metering_set_remaining_points(const wasm_instance_t *instance, uint64_t new_limit);
 
+enum wasmer_parser_operator_t wasmer_middleware_state_operator(const struct wasmer_middleware_state_t *state);
+
+bool wasmer_middleware_state_operator_index(const struct wasmer_middleware_state_t *state,
+                                            uintptr_t position,
+                                            uint32_t *out);
+
+bool wasmer_middleware_state_operator_memarg(const struct wasmer_middleware_state_t *state,
+                                             struct wasmer_memarg_t *out);
+
+uintptr_t wasmer_middleware_state_operator_offset(const struct wasmer_middleware_state_t *state);
+
+bool wasmer_middleware_state_operator_value(const struct wasmer_middleware_state_t *state,
+                                            wasm_val_t *out);
+
+bool wasmer_middleware_state_push_operator(struct wasmer_middleware_state_t *state);
+
+bool wasmer_middleware_state_push_operators(struct wasmer_middleware_state_t *state,
+                                            const wasm_byte_vec_t *operators);
+
 void wasmer_module_name(const wasm_module_t *module, wasm_name_t *out);
 
 bool wasmer_module_set_name(wasm_module_t *module, const wasm_name_t *name);
@@ -7,15 +7,25 @@ use crate::warning;
 use anyhow::{anyhow, Context, Result};
 use std::path::PathBuf;
 use std::str::FromStr;
+#[cfg(feature = "compiler")]
+use std::sync::Arc;
 use wasmer::*;
 #[cfg(feature = "cache")]
 use wasmer_cache::{Cache, FileSystemCache, Hash};
+#[cfg(feature = "compiler")]
+use wasmer_middlewares::CallTracing;
 
 use clap::Clap;
 
+#[cfg(feature = "compiler")]
+mod call_tracing;
+#[cfg(feature = "wasi")]
+mod strace;
 #[cfg(feature = "wasi")]
 mod wasi;
 
+#[cfg(feature = "compiler")]
+use call_tracing::WriterCallTracer;
 #[cfg(feature = "wasi")]
 use wasi::Wasi;
 
@@ -30,7 +40,8 @@ pub struct Run {
     #[clap(name = "FILE", parse(from_os_str))]
     path: PathBuf,
 
-    /// Invoke a specified function
+    /// Invoke a specified function. The WASI modules are initialized first,
+    /// so that the exports of the reactors can be called
     #[clap(long = "invoke", short = 'i')]
     invoke: Option<String>,
 
@@ -59,6 +70,35 @@ pub struct Run {
     #[clap(long = "enable-io-devices")]
     enable_experimental_io_devices: bool,
 
+    /// Trace the entry and exit of the functions, and the calls to
+    /// imported functions, on stderr
+    #[cfg(feature = "compiler")]
+    #[clap(long = "trace-calls")]
+    trace_calls: bool,
+
+    /// Write the call trace to the given file instead of stderr, where it
+    /// interleaves with the errors of the program
+    #[cfg(feature = "compiler")]
+    #[clap(
+        long = "trace-calls-output",
+        name = "CALL_TRACE_FILE",
+        parse(from_os_str),
+        requires = "trace-calls"
+    )]
+    trace_calls_output: Option<PathBuf>,
+
+    /// Only trace the functions whose name matches the pattern, where `*`
+    /// matches any sequence of characters. Imported functions are named
+    /// `module.field`
+    #[cfg(feature = "compiler")]
+    #[clap(
+        long = "trace-calls-filter",
+        name = "PATTERN",
+        multiple = true,
+        requires = "trace-calls"
+    )]
+    trace_calls_filters: Vec<String>,
+
     /// Enable debug output
     #[cfg(feature = "debug")]
     #[clap(long = "debug", short = 'd')]
@@ -93,8 +133,7 @@ impl Run {
         let module = self.get_module()?;
         // Do we want to invoke a function?
         if let Some(ref invoke) = self.invoke {
-            let imports = imports! {};
-            let instance = Instance::new(&module, &imports)?;
+            let instance = self.instantiate_for_invoke(&module)?;
             let result = self.invoke_function(&instance, &invoke, &self.args)?;
             println!(
                 "{}",
@@ -119,6 +158,8 @@ impl Run {
                 let mut em_env = EmEnv::new(&emscripten_globals.data, Default::default());
                 let import_object =
                     generate_emscripten_env(module.store(), &mut emscripten_globals, &mut em_env);
+                let import_object =
+                    import_object.chain_back(self.get_call_tracing_imports(&module)?);
                 let mut instance = match Instance::new(&module, &import_object) {
                     Ok(instance) => instance,
                     Err(e) => {
@@ -152,26 +193,22 @@ impl Run {
         // If WASI is enabled, try to execute it with it
         #[cfg(feature = "wasi")]
         {
-            let wasi_version = Wasi::get_version(&module);
-            if wasi_version.is_some() {
-                let program_name = self
-                    .command_name
-                    .clone()
-                    .or_else(|| {
-                        self.path
-                            .file_name()
-                            .map(|f| f.to_string_lossy().to_string())
-                    })
-                    .unwrap_or_default();
+            if self.has_wasi_imports(&module) {
+                let call_tracing_imports = self.get_call_tracing_imports(&module)?;
                 return self
                     .wasi
-                    .execute(module, program_name, self.args.clone())
+                    .execute(
+                        module,
+                        self.get_program_name(),
+                        self.args.clone(),
+                        call_tracing_imports,
+                    )
                     .with_context(|| "WASI execution failed");
             }
         }
 
         // Try to instantiate the wasm file, with no provided imports
-        let imports = imports! {};
+        let imports = self.get_call_tracing_imports(&module)?;
         let instance = Instance::new(&module, &imports)?;
         let start: Function = self.try_find_function(&instance, "_start", &[])?;
         start.call(&[])?;
@@ -179,6 +216,48 @@ impl Run {
         Ok(())
     }
 
+    #[cfg(feature = "wasi")]
+    fn has_wasi_imports(&self, module: &Module) -> bool {
+        // The call tracing hooks are imported besides the WASI functions.
+        if self.is_tracing_calls() {
+            Wasi::has_wasi_imports(module)
+        } else {
+            Wasi::get_version(module).is_some()
+        }
+    }
+
+    #[cfg(feature = "wasi")]
+    fn get_program_name(&self) -> String {
+        self.command_name
+            .clone()
+            .or_else(|| {
+                self.path
+                    .file_name()
+                    .map(|f| f.to_string_lossy().to_string())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Instantiates the module to invoke one of its exports. The WASI
+    /// modules, usually reactors, are instantiated with WASI and
+    /// initialized first.
+    fn instantiate_for_invoke(&self, module: &Module) -> Result<Instance> {
+        let imports = self.get_call_tracing_imports(module)?;
+        #[cfg(feature = "wasi")]
+        {
+            if self.has_wasi_imports(module) {
+                let (wasi_env, instance) =
+                    self.wasi
+                        .instantiate(module, self.get_program_name(), Vec::new(), imports)?;
+                wasi_env
+                    .initialize(&instance)
+                    .with_context(|| "failed to initialize the WASI instance")?;
+                return Ok(instance);
+            }
+        }
+        Ok(Instance::new(module, &imports)?)
+    }
+
     fn get_module(&self) -> Result<Module> {
         let contents = std::fs::read(self.path.clone())?;
         #[cfg(feature = "native")]
@@ -199,13 +278,15 @@ impl Run {
                 return Ok(module);
             }
         }
-        let (store, engine_type, compiler_type) = self.store.get_store()?;
+        let (store, engine_type, compiler_type) = self.get_store()?;
+        // Traced modules are not cached, since they are instrumented.
         #[cfg(feature = "cache")]
-        let module_result: Result<Module> = if !self.disable_cache && contents.len() > 0x1000 {
-            self.get_module_from_cache(&store, &contents, &engine_type, &compiler_type)
-        } else {
-            Module::new(&store, &contents).map_err(|e| e.into())
-        };
+        let module_result: Result<Module> =
+            if !self.disable_cache && !self.is_tracing_calls() && contents.len() > 0x1000 {
+                self.get_module_from_cache(&store, &contents, &engine_type, &compiler_type)
+            } else {
+                Module::new(&store, &contents).map_err(|e| e.into())
+            };
         #[cfg(not(feature = "cache"))]
         let module_result = Module::new(&store, &contents);
 
@@ -222,6 +303,46 @@ impl Run {
         Ok(module)
     }
 
+    fn get_store(&self) -> Result<(Store, EngineType, CompilerType)> {
+        #[cfg(feature = "compiler")]
+        if self.trace_calls {
+            let call_tracing = CallTracing::new(self.trace_calls_filters.clone());
+            return self
+                .store
+                .get_store_with_middlewares(vec![Arc::new(call_tracing)]);
+        }
+        self.store.get_store()
+    }
+
+    fn is_tracing_calls(&self) -> bool {
+        #[cfg(feature = "compiler")]
+        {
+            self.trace_calls
+        }
+        #[cfg(not(feature = "compiler"))]
+        {
+            false
+        }
+    }
+
+    /// Gets the imports of the call tracing hooks, which are empty if the
+    /// calls are not traced.
+    #[allow(unused_variables)]
+    fn get_call_tracing_imports(&self, module: &Module) -> Result<ImportObject> {
+        #[cfg(feature = "compiler")]
+        if self.trace_calls {
+            let tracer = match &self.trace_calls_output {
+                Some(trace_path) => WriterCallTracer::create(trace_path)?,
+                None => WriterCallTracer::stderr(),
+            };
+            return Ok(wasmer_middlewares::call_tracing::import_object(
+                module,
+                Arc::new(tracer),
+            ));
+        }
+        Ok(ImportObject::new())
+    }
+
     #[cfg(feature = "cache")]
     fn get_module_from_cache(
         &self,
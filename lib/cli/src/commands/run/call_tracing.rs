@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use wasmer::Val;
+use wasmer_middlewares::call_tracing::{CallTracer, TracedCall};
+
+/// A `CallTracer` writing the traced calls to stderr or to a file,
+/// indented by call depth.
+#[derive(Debug)]
+pub struct WriterCallTracer {
+    state: Mutex<WriterCallTracerState>,
+}
+
+struct WriterCallTracerState {
+    /// The trace output, flushed after every call.
+    out: LineWriter<Box<dyn Write + Send>>,
+
+    /// The indexes of the functions being called.
+    stack: Vec<u32>,
+}
+
+impl WriterCallTracer {
+    /// Creates a tracer writing to stderr.
+    pub fn stderr() -> Self {
+        Self::new(Box::new(io::stderr()))
+    }
+
+    /// Creates a tracer writing to the file at `path`, truncated first.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create the call trace `{}`", path.display()))?;
+        Ok(Self::new(Box::new(file)))
+    }
+
+    fn new(out: Box<dyn Write + Send>) -> Self {
+        Self {
+            state: Mutex::new(WriterCallTracerState {
+                out: LineWriter::new(out),
+                stack: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for WriterCallTracerState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WriterCallTracerState")
+            .field("stack", &self.stack)
+            .finish()
+    }
+}
+
+fn format_values(values: &[Val]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl CallTracer for WriterCallTracer {
+    fn on_enter(&self, call: &TracedCall, arguments: &[Val]) {
+        let mut state = self.state.lock().unwrap();
+        let indent = "  ".repeat(state.stack.len());
+        // A failing trace must not fail the traced program.
+        let _ = writeln!(
+            state.out,
+            "{}-> {}({})",
+            indent,
+            call.name,
+            format_values(arguments)
+        );
+        state.stack.push(call.function_index);
+    }
+
+    fn on_exit(&self, call: &TracedCall, results: &[Val]) {
+        let mut state = self.state.lock().unwrap();
+        // Unwind the calls whose exit hasn't been reported.
+        if let Some(position) = state
+            .stack
+            .iter()
+            .rposition(|index| *index == call.function_index)
+        {
+            state.stack.truncate(position);
+        }
+        let indent = "  ".repeat(state.stack.len());
+        let _ = writeln!(
+            state.out,
+            "{}<- {} = ({})",
+            indent,
+            call.name,
+            format_values(results)
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use wasmer_wasi::types::errno_to_str;
+use wasmer_wasi::{SyscallArg, SyscallEvent, SyscallTracer};
+
+/// A `SyscallTracer` writing the WASI syscalls to stderr or to a file, as
+/// one JSON object per line.
+pub struct JsonSyscallTracer {
+    /// The trace output, flushed after every syscall.
+    out: Mutex<LineWriter<Box<dyn Write + Send>>>,
+}
+
+impl JsonSyscallTracer {
+    /// Creates a tracer writing to stderr.
+    pub fn stderr() -> Self {
+        Self::new(Box::new(io::stderr()))
+    }
+
+    /// Creates a tracer writing to the file at `path`, truncated first.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create the syscall trace `{}`", path.display()))?;
+        Ok(Self::new(Box::new(file)))
+    }
+
+    fn new(out: Box<dyn Write + Send>) -> Self {
+        Self {
+            out: Mutex::new(LineWriter::new(out)),
+        }
+    }
+}
+
+impl fmt::Debug for JsonSyscallTracer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JsonSyscallTracer").finish()
+    }
+}
+
+fn event_to_json(event: &SyscallEvent) -> Value {
+    let args = event
+        .args
+        .iter()
+        .map(|(name, arg)| {
+            let value = match arg {
+                SyscallArg::Fd(fd) => json!(fd),
+                SyscallArg::Int(value) | SyscallArg::Flags(value) => json!(value),
+                SyscallArg::SignedInt(value) => json!(value),
+                SyscallArg::Path(path) => json!(path),
+            };
+            (name.to_string(), value)
+        })
+        .collect::<Map<String, Value>>();
+    json!({
+        "syscall": event.name,
+        "args": args,
+        "errno": event.errno.map(errno_to_str),
+        "duration_ns": event.duration.as_nanos() as u64,
+    })
+}
+
+impl SyscallTracer for JsonSyscallTracer {
+    fn on_syscall(&self, event: &SyscallEvent) {
+        let line = event_to_json(event).to_string();
+        // A failing trace must not fail the traced program.
+        let _ = writeln!(self.out.lock().unwrap(), "{}", line);
+    }
+}
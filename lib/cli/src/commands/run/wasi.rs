@@ -1,8 +1,13 @@
+use super::strace::JsonSyscallTracer;
 use crate::utils::{parse_envvar, parse_mapdir};
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::path::PathBuf;
-use wasmer::{Instance, Module};
-use wasmer_wasi::{get_wasi_version, WasiError, WasiState, WasiVersion};
+use std::sync::Arc;
+use wasmer::{ChainableNamedResolver, ImportObject, Instance, Module};
+use wasmer_wasi::{
+    get_wasi_version, SeededRandom, VirtualClock, WasiCapability, WasiEnv, WasiError,
+    WasiInstanceError, WasiPolicy, WasiState, WasiVersion,
+};
 
 use clap::Clap;
 
@@ -21,6 +26,37 @@ pub struct Wasi {
     #[clap(long = "env", name = "KEY=VALUE", multiple = true, parse(try_from_str = parse_envvar))]
     env_vars: Vec<(String, String)>,
 
+    /// Run deterministically: the clocks are virtual, starting at 0 and
+    /// advancing by 1ms at every read, and the random bytes come from a
+    /// generator with a fixed seed
+    #[clap(long = "deterministic")]
+    deterministic: bool,
+
+    /// Deny a category of WASI calls: `clock`, `random`, `exit` or
+    /// `fs-write`. The denied calls fail as not capable
+    #[clap(long = "deny", name = "CAPABILITY", multiple = true)]
+    denied_capabilities: Vec<WasiCapability>,
+
+    /// Limit the number of open file descriptors, including stdio and the
+    /// preopened directories
+    #[clap(long = "max-open-fds", name = "COUNT")]
+    max_open_fds: Option<u32>,
+
+    /// Trace the WASI syscalls to stderr, as one JSON object per line with
+    /// the syscall name, its arguments, its errno and its duration
+    #[clap(long = "strace")]
+    strace: bool,
+
+    /// Write the syscall trace to the given file instead of stderr, where
+    /// it interleaves with the errors of the program
+    #[clap(
+        long = "strace-output",
+        name = "TRACE_FILE",
+        parse(from_os_str),
+        requires = "strace"
+    )]
+    strace_output: Option<PathBuf>,
+
     /// Enable experimental IO devices
     #[cfg(feature = "experimental-io-devices")]
     #[clap(long = "enable-experimental-io-devices")]
@@ -43,8 +79,17 @@ impl Wasi {
         get_wasi_version(&module, false).is_some()
     }
 
-    /// Helper function for executing Wasi from the `Run` command.
-    pub fn execute(&self, module: Module, program_name: String, args: Vec<String>) -> Result<()> {
+    /// Helper function for instantiating a module with WASI, from the
+    /// `Run` command.
+    ///
+    /// The `extra_imports` are resolved after the WASI imports.
+    pub fn instantiate(
+        &self,
+        module: &Module,
+        program_name: String,
+        args: Vec<String>,
+        extra_imports: ImportObject,
+    ) -> Result<(WasiEnv, Instance)> {
         let args = args.iter().cloned().map(|arg| arg.into_bytes());
 
         let mut wasi_state_builder = WasiState::new(program_name);
@@ -54,6 +99,21 @@ impl Wasi {
             .preopen_dirs(self.pre_opened_directories.clone())?
             .map_dirs(self.mapped_dirs.clone())?;
 
+        if self.deterministic {
+            wasi_state_builder
+                .clock(Box::new(VirtualClock::stepping(0, 1_000_000)))
+                .random(Box::new(SeededRandom::new(0)));
+        }
+
+        let mut policy = WasiPolicy::new();
+        for capability in &self.denied_capabilities {
+            policy = policy.deny(*capability);
+        }
+        if let Some(max_open_fds) = self.max_open_fds {
+            policy = policy.max_open_fds(max_open_fds);
+        }
+        wasi_state_builder.policy(policy);
+
         #[cfg(feature = "experimental-io-devices")]
         {
             if self.enable_experimental_io_devices {
@@ -63,15 +123,37 @@ impl Wasi {
         }
 
         let mut wasi_env = wasi_state_builder.finalize()?;
-        let import_object = wasi_env.import_object(&module)?;
-        let instance = Instance::new(&module, &import_object)?;
+        if self.strace {
+            let tracer = match &self.strace_output {
+                Some(trace_path) => JsonSyscallTracer::create(trace_path)?,
+                None => JsonSyscallTracer::stderr(),
+            };
+            wasi_env.set_syscall_tracer(Arc::new(tracer));
+        }
+        let import_object = wasi_env.import_object(module)?.chain_back(extra_imports);
+        let instance = Instance::new(module, &import_object)?;
 
-        let start = instance.exports.get_function("_start")?;
-        let result = start.call(&[]);
+        Ok((wasi_env, instance))
+    }
+
+    /// Helper function for executing Wasi from the `Run` command.
+    ///
+    /// The `extra_imports` are resolved after the WASI imports.
+    pub fn execute(
+        &self,
+        module: Module,
+        program_name: String,
+        args: Vec<String>,
+        extra_imports: ImportObject,
+    ) -> Result<()> {
+        let (wasi_env, instance) = self.instantiate(&module, program_name, args, extra_imports)?;
 
-        match result {
+        match wasi_env.start(&instance) {
             Ok(_) => Ok(()),
-            Err(err) => {
+            Err(WasiInstanceError::Reactor) => {
+                bail!("the module is a WASI reactor, use `--invoke` to call one of its exports")
+            }
+            Err(WasiInstanceError::Runtime(err)) => {
                 let err: anyhow::Error = match err.downcast::<WasiError>() {
                     Ok(WasiError::Exit(exit_code)) => {
                         // We should exit with the provided exit code
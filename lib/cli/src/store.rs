@@ -11,7 +11,7 @@ use std::string::ToString;
 use std::sync::Arc;
 use wasmer::*;
 #[cfg(feature = "compiler")]
-use wasmer_compiler::CompilerConfig;
+use wasmer_compiler::{CompilerConfig, ModuleMiddleware};
 
 #[derive(Debug, Clone, Clap)]
 /// The compiler and engine options
@@ -394,6 +394,22 @@ impl StoreOptions {
         Ok((store, engine_type, compiler_type))
     }
 
+    /// Gets the store for the host target, compiling the modules with the
+    /// provided middlewares
+    pub fn get_store_with_middlewares(
+        &self,
+        middlewares: Vec<Arc<dyn ModuleMiddleware>>,
+    ) -> Result<(Store, EngineType, CompilerType)> {
+        let (mut compiler_config, compiler_type) = self.compiler.get_compiler_config()?;
+        for middleware in middlewares {
+            compiler_config.push_middleware(middleware);
+        }
+        let (engine, engine_type) =
+            self.get_engine_with_compiler(Target::default(), compiler_config)?;
+        let store = Store::new(&*engine);
+        Ok((store, engine_type, compiler_type))
+    }
+
     fn get_engine_with_compiler(
         &self,
         target: Target,
@@ -63,9 +63,10 @@ impl Compiler for CraneliftCompiler {
         let memory_styles = &compile_info.memory_styles;
         let table_styles = &compile_info.table_styles;
         let mut module = (*compile_info.module).clone();
-        self.config.middlewares.apply_on_module_info(&mut module);
+        let module_additions = self.config.middlewares.apply_on_module_info(&mut module);
         compile_info.module = Arc::new(module);
         let module = &compile_info.module;
+        let function_body_inputs = module_additions.function_body_inputs(&function_body_inputs);
         let signatures = module
             .signatures
             .iter()
@@ -125,6 +126,7 @@ impl Compiler for CraneliftCompiler {
                     &mut func_env,
                     *i,
                     &self.config,
+                    &module_additions,
                 )?;
 
                 let mut code_buf: Vec<u8> = Vec::new();
@@ -142,6 +142,13 @@ impl Cranelift {
             flags.enable("is_pic").expect("should be a valid flag");
         }
 
+        // The functions handling `externref`s can only be compiled with
+        // safepoints. Their stack maps aren't used though: the `externref`s
+        // are kept alive by the runtime while WebAssembly uses them.
+        flags
+            .enable("enable_safepoints")
+            .expect("should be valid flag");
+
         // Invert cranelift's default-on verification to instead default off.
         let enable_verifier = if self.enable_verifier {
             "true"
@@ -83,6 +83,14 @@ pub struct FuncEnvironment<'module_environment> {
     /// The external function signature for implementing wasm's `data.drop`.
     data_drop_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's `global.get`
+    /// on `externref` globals.
+    externref_global_get_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `global.set`
+    /// on `externref` globals.
+    externref_global_set_sig: Option<ir::SigRef>,
+
     /// Offsets to struct fields accessed by JIT code.
     offsets: VMOffsets,
 
@@ -115,6 +123,8 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             memory_fill_sig: None,
             memory_init_sig: None,
             data_drop_sig: None,
+            externref_global_get_sig: None,
+            externref_global_set_sig: None,
             offsets: VMOffsets::new(target_config.pointer_bytes(), module),
             memory_styles,
             table_styles,
@@ -445,6 +455,52 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         (sig, VMBuiltinFunctionIndex::get_data_drop_index())
     }
 
+    fn get_externref_global_get_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.externref_global_get_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Global index.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(self.reference_type())],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.externref_global_get_sig = Some(sig);
+        (
+            sig,
+            VMBuiltinFunctionIndex::get_externref_global_get_index(),
+        )
+    }
+
+    fn get_externref_global_set_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.externref_global_set_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Global index.
+                    AbiParam::new(I32),
+                    // Value.
+                    AbiParam::new(self.reference_type()),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.externref_global_set_sig = Some(sig);
+        (
+            sig,
+            VMBuiltinFunctionIndex::get_externref_global_set_index(),
+        )
+    }
+
     /// Translates load of builtin function and returns a pair of values `vmctx`
     /// and address of the loaded function.
     fn translate_load_builtin_function_address(
@@ -596,10 +652,10 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
     ) -> WasmResult<ir::Value> {
         Ok(match ty {
             Type::FuncRef => pos.ins().iconst(self.pointer_type(), 0),
-            // Type::ExternRef => pos.ins().null(self.reference_type(ty)),
+            Type::ExternRef => pos.ins().null(self.reference_type()),
             _ => {
                 return Err(WasmError::Unsupported(
-                    "`ref.null T` that is not a `funcref`".into(),
+                    "`ref.null T` that is not a `funcref` or an `externref`".into(),
                 ));
             }
         })
@@ -636,19 +692,31 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
     fn translate_custom_global_get(
         &mut self,
-        mut _pos: cranelift_codegen::cursor::FuncCursor<'_>,
-        _index: GlobalIndex,
+        mut pos: cranelift_codegen::cursor::FuncCursor<'_>,
+        index: GlobalIndex,
     ) -> WasmResult<ir::Value> {
-        unreachable!("we don't make any custom globals")
+        // Only the `externref` globals are custom, see `make_global`.
+        let (func_sig, func_idx) = self.get_externref_global_get_func(&mut pos.func);
+        let index_arg = pos.ins().iconst(I32, index.as_u32() as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst = pos
+            .ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, index_arg]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
     fn translate_custom_global_set(
         &mut self,
-        mut _pos: cranelift_codegen::cursor::FuncCursor<'_>,
-        _index: GlobalIndex,
-        _value: ir::Value,
+        mut pos: cranelift_codegen::cursor::FuncCursor<'_>,
+        index: GlobalIndex,
+        value: ir::Value,
     ) -> WasmResult<()> {
-        unreachable!("we don't make any custom globals")
+        let (func_sig, func_idx) = self.get_externref_global_set_func(&mut pos.func);
+        let index_arg = pos.ins().iconst(I32, index.as_u32() as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, index_arg, value]);
+        Ok(())
     }
 
     fn make_heap(&mut self, func: &mut ir::Function, index: MemoryIndex) -> WasmResult<ir::Heap> {
@@ -730,6 +798,12 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         func: &mut ir::Function,
         index: GlobalIndex,
     ) -> WasmResult<GlobalVariable> {
+        // The `externref` globals hold a reference count of their value,
+        // which is maintained by the runtime.
+        if self.module.globals[index].ty == wasmer_types::Type::ExternRef {
+            return Ok(GlobalVariable::Custom);
+        }
+
         let pointer_type = self.pointer_type();
 
         let (ptr, offset) = {
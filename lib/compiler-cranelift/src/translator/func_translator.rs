@@ -19,8 +19,7 @@ use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
 use tracing::info;
 use wasmer_compiler::wasmparser;
 use wasmer_compiler::{
-    wasm_unsupported, MiddlewareBinaryReader, ModuleMiddlewareChain, ModuleTranslationState,
-    WasmResult,
+    wasm_unsupported, MiddlewareBinaryReader, ModuleAdditions, ModuleTranslationState, WasmResult,
 };
 use wasmer_types::LocalFunctionIndex;
 
@@ -70,12 +69,12 @@ impl FuncTranslator {
         environ: &mut FE,
         local_function_index: LocalFunctionIndex,
         config: &Cranelift,
+        module_additions: &ModuleAdditions,
     ) -> WasmResult<()> {
         let mut reader = MiddlewareBinaryReader::new_with_offset(code, code_offset);
         reader.set_middleware_chain(
-            config
-                .middlewares
-                .generate_function_middleware_chain(local_function_index),
+            module_additions
+                .generate_function_middleware_chain(&*config.middlewares, local_function_index),
         );
         self.translate_from_reader(module_translation_state, reader, func, environ)
     }
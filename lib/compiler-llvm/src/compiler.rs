@@ -12,8 +12,8 @@ use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIter
 use std::sync::Arc;
 use wasmer_compiler::{
     Compilation, CompileError, CompileModuleInfo, Compiler, CustomSection, CustomSectionProtection,
-    Dwarf, FunctionBodyData, ModuleMiddlewareChain, ModuleTranslationState, RelocationTarget,
-    SectionBody, SectionIndex, Symbol, SymbolRegistry, Target,
+    Dwarf, FunctionBodyData, ModuleAdditions, ModuleMiddlewareChain, ModuleTranslationState,
+    RelocationTarget, SectionBody, SectionIndex, Symbol, SymbolRegistry, Target,
 };
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
@@ -79,6 +79,7 @@ impl LLVMCompiler {
         target: &Target,
         compile_info: &'module CompileModuleInfo,
         module_translation: &ModuleTranslationState,
+        module_additions: &ModuleAdditions,
         function_body_inputs: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
         symbol_registry: &dyn SymbolRegistry,
         wasmer_metadata: &[u8],
@@ -100,6 +101,7 @@ impl LLVMCompiler {
                     &i,
                     input,
                     self.config(),
+                    module_additions,
                     &compile_info.memory_styles,
                     &compile_info.table_styles,
                     symbol_registry,
@@ -201,14 +203,15 @@ impl Compiler for LLVMCompiler {
         wasmer_metadata: &[u8],
     ) -> Option<Result<Vec<u8>, CompileError>> {
         let mut module = (*compile_info.module).clone();
-        self.config.middlewares.apply_on_module_info(&mut module);
+        let module_additions = self.config.middlewares.apply_on_module_info(&mut module);
         compile_info.module = Arc::new(module);
 
         Some(self.compile_native_object(
             target,
             compile_info,
             module_translation,
-            function_body_inputs,
+            &module_additions,
+            &module_additions.function_body_inputs(function_body_inputs),
             symbol_registry,
             wasmer_metadata,
         ))
@@ -228,9 +231,10 @@ impl Compiler for LLVMCompiler {
         let table_styles = &compile_info.table_styles;
 
         let mut module = (*compile_info.module).clone();
-        self.config.middlewares.apply_on_module_info(&mut module);
+        let module_additions = self.config.middlewares.apply_on_module_info(&mut module);
         compile_info.module = Arc::new(module);
         let module = &compile_info.module;
+        let function_body_inputs = module_additions.function_body_inputs(&function_body_inputs);
 
         // TODO: merge constants in sections.
 
@@ -255,6 +259,7 @@ impl Compiler for LLVMCompiler {
                         i,
                         input,
                         self.config(),
+                        &module_additions,
                         memory_styles,
                         &table_styles,
                         &ShortNames {},
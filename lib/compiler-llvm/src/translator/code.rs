@@ -26,7 +26,7 @@ use crate::config::{CompiledKind, LLVM};
 use crate::object_file::{load_object_file, CompiledFunction};
 use wasmer_compiler::wasmparser::{MemoryImmediate, Operator};
 use wasmer_compiler::{
-    wptype_to_type, CompileError, FunctionBodyData, MiddlewareBinaryReader, ModuleMiddlewareChain,
+    wptype_to_type, CompileError, FunctionBodyData, MiddlewareBinaryReader, ModuleAdditions,
     ModuleTranslationState, RelocationTarget, Symbol, SymbolRegistry,
 };
 use wasmer_types::entity::PrimaryMap;
@@ -65,6 +65,7 @@ impl FuncTranslator {
         local_func_index: &LocalFunctionIndex,
         function_body: &FunctionBodyData,
         config: &LLVM,
+        module_additions: &ModuleAdditions,
         memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
         _table_styles: &PrimaryMap<TableIndex, TableStyle>,
         symbol_registry: &dyn SymbolRegistry,
@@ -135,9 +136,8 @@ impl FuncTranslator {
             function_body.module_offset,
         );
         reader.set_middleware_chain(
-            config
-                .middlewares
-                .generate_function_middleware_chain(*local_func_index),
+            module_additions
+                .generate_function_middleware_chain(&*config.middlewares, *local_func_index),
         );
 
         let mut params = vec![];
@@ -273,6 +273,7 @@ impl FuncTranslator {
         local_func_index: &LocalFunctionIndex,
         function_body: &FunctionBodyData,
         config: &LLVM,
+        module_additions: &ModuleAdditions,
         memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
         table_styles: &PrimaryMap<TableIndex, TableStyle>,
         symbol_registry: &dyn SymbolRegistry,
@@ -283,6 +284,7 @@ impl FuncTranslator {
             local_func_index,
             function_body,
             config,
+            module_additions,
             memory_styles,
             table_styles,
             symbol_registry,
@@ -17,7 +17,9 @@ use wasmer_compiler::{
 use wasmer_compiler::{Compilation, CompileError, CompiledFunction, Compiler, SectionIndex};
 use wasmer_compiler::{FunctionBody, FunctionBodyData};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
-use wasmer_types::{FunctionIndex, FunctionType, LocalFunctionIndex, MemoryIndex, TableIndex};
+use wasmer_types::{
+    FunctionIndex, FunctionType, LocalFunctionIndex, MemoryIndex, TableIndex, Type,
+};
 use wasmer_vm::{ModuleInfo, TrapCode, VMOffsets};
 
 /// A compiler that compiles a WebAssembly module with Singlepass.
@@ -62,10 +64,23 @@ impl Compiler for SinglepassCompiler {
         let memory_styles = &compile_info.memory_styles;
         let table_styles = &compile_info.table_styles;
         let mut module = (*compile_info.module).clone();
-        self.config.middlewares.apply_on_module_info(&mut module);
+        let module_additions = self.config.middlewares.apply_on_module_info(&mut module);
         compile_info.module = Arc::new(module);
+        if compile_info
+            .module
+            .globals
+            .values()
+            .any(|global| global.ty == Type::ExternRef)
+        {
+            // `global.get` and `global.set` don't count the references of
+            // the `externref` globals yet.
+            return Err(CompileError::UnsupportedFeature(
+                "externref globals".to_string(),
+            ));
+        }
         let vmoffsets = VMOffsets::new(8, &compile_info.module);
         let module = &compile_info.module;
+        let function_body_inputs = module_additions.function_body_inputs(&function_body_inputs);
         let import_trampolines: PrimaryMap<SectionIndex, _> = (0..module.num_imported_functions)
             .map(FunctionIndex::new)
             .collect::<Vec<_>>()
@@ -81,10 +96,8 @@ impl Compiler for SinglepassCompiler {
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .par_iter()
             .map(|(i, input)| {
-                let middleware_chain = self
-                    .config
-                    .middlewares
-                    .generate_function_middleware_chain(*i);
+                let middleware_chain = module_additions
+                    .generate_function_middleware_chain(&*self.config.middlewares, *i);
                 let mut reader =
                     MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
                 reader.set_middleware_chain(middleware_chain);
@@ -40,6 +40,11 @@ pub enum CompileError {
     /// Insufficient resources available for execution.
     #[cfg_attr(feature = "std", error("Insufficient resources: {0}"))]
     Resource(String),
+
+    /// The module exceeds one of the configured
+    /// [`ModuleLimits`](crate::ModuleLimits).
+    #[cfg_attr(feature = "std", error("Module limit exceeded: {0}"))]
+    LimitExceeded(String),
 }
 
 impl From<WasmError> for CompileError {
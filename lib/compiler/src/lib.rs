@@ -55,6 +55,7 @@ mod compiler;
 mod error;
 mod function;
 mod jump_table;
+mod limits;
 mod module;
 mod relocation;
 mod target;
@@ -77,6 +78,7 @@ pub use crate::function::{
     Functions,
 };
 pub use crate::jump_table::{JumpTable, JumpTableOffsets};
+pub use crate::limits::ModuleLimits;
 pub use crate::module::CompileModuleInfo;
 pub use crate::relocation::{Relocation, RelocationKind, RelocationTarget, Relocations};
 pub use crate::section::{CustomSection, CustomSectionProtection, SectionBody, SectionIndex};
@@ -88,8 +90,8 @@ pub use crate::target::{
 #[cfg(feature = "translator")]
 pub use crate::translator::{
     translate_module, wptype_to_type, FunctionBodyData, FunctionMiddleware, MiddlewareBinaryReader,
-    MiddlewareReaderState, ModuleEnvironment, ModuleInfoTranslation, ModuleMiddleware,
-    ModuleMiddlewareChain, ModuleTranslationState,
+    MiddlewareReaderState, ModuleAdditions, ModuleEnvironment, ModuleInfoTranslation,
+    ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState,
 };
 pub use crate::trap::TrapInformation;
 pub use crate::unwind::CompiledFunctionUnwindInfo;
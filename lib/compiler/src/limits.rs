@@ -0,0 +1,175 @@
+//! Complexity limits for the modules to compile, to guard against
+//! untrusted modules exhausting compilation time and memory.
+#[cfg(feature = "translator")]
+use crate::error::CompileError;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "translator")]
+use wasmparser::{
+    BinaryReaderError, ImportSectionEntryType, MemoryType as WPMemoryType, Parser, Payload,
+    ResizableLimits,
+};
+
+/// Limits on the complexity of the modules to compile.
+///
+/// Every limit is optional; the default is a module without any limit,
+/// other than the ones of the validator.
+///
+/// The limits are checked before the module is translated, so that a
+/// module exceeding them is rejected with
+/// [`CompileError::LimitExceeded`] before any code generation starts.
+///
+/// [`CompileError::LimitExceeded`]: crate::CompileError::LimitExceeded
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct ModuleLimits {
+    /// The maximum number of functions defined by the module, not
+    /// counting the imported ones.
+    pub max_functions: Option<u32>,
+    /// The maximum number of imports of the module.
+    pub max_imports: Option<u32>,
+    /// The maximum number of locals declared by a function, not counting
+    /// its parameters.
+    pub max_locals_per_function: Option<u32>,
+    /// The maximum size in bytes of the body of a function.
+    pub max_function_body_size: Option<u32>,
+    /// The maximum minimum number of elements of a table, defined or
+    /// imported.
+    pub max_table_elements: Option<u32>,
+    /// The maximum minimum number of pages of a memory, defined or
+    /// imported.
+    pub max_memory_pages: Option<u32>,
+    /// The maximum size in bytes of a data segment.
+    pub max_data_segment_size: Option<u32>,
+}
+
+impl ModuleLimits {
+    /// Creates limits allowing any module.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks that the module in `data` doesn't exceed these limits.
+    ///
+    /// It stops at the first limit exceeded, without reading the rest of
+    /// the module. The module isn't validated, but a malformed module is
+    /// reported with [`CompileError::Validate`].
+    #[cfg(feature = "translator")]
+    pub fn check(&self, data: &[u8]) -> Result<(), CompileError> {
+        if *self == Self::default() {
+            return Ok(());
+        }
+
+        for payload in Parser::new(0).parse_all(data) {
+            match payload.map_err(malformed)? {
+                Payload::ImportSection(imports) => {
+                    check_limit("imports", imports.get_count(), self.max_imports)?;
+
+                    for import in imports {
+                        let import = import.map_err(malformed)?;
+                        match import.ty {
+                            ImportSectionEntryType::Table(table) => {
+                                self.check_table(&table.limits)?
+                            }
+                            ImportSectionEntryType::Memory(memory) => self.check_memory(&memory)?,
+                            _ => {}
+                        }
+                    }
+                }
+
+                Payload::FunctionSection(functions) => {
+                    check_limit("functions", functions.get_count(), self.max_functions)?;
+                }
+
+                Payload::TableSection(tables) => {
+                    for table in tables {
+                        let table = table.map_err(malformed)?;
+                        self.check_table(&table.limits)?;
+                    }
+                }
+
+                Payload::MemorySection(memories) => {
+                    for memory in memories {
+                        let memory = memory.map_err(malformed)?;
+                        self.check_memory(&memory)?;
+                    }
+                }
+
+                Payload::CodeSectionEntry(body) => {
+                    let size = body.get_binary_reader().bytes_remaining();
+                    check_limit(
+                        "bytes in a function body",
+                        size as u64,
+                        self.max_function_body_size,
+                    )?;
+
+                    if self.max_locals_per_function.is_some() {
+                        let mut locals = body.get_locals_reader().map_err(malformed)?;
+                        let mut num_locals = 0u64;
+                        for _ in 0..locals.get_count() {
+                            let (count, _) = locals.read().map_err(malformed)?;
+                            num_locals += u64::from(count);
+                            check_limit(
+                                "locals in a function",
+                                num_locals,
+                                self.max_locals_per_function,
+                            )?;
+                        }
+                    }
+                }
+
+                Payload::DataSection(segments) => {
+                    for segment in segments {
+                        let segment = segment.map_err(malformed)?;
+                        check_limit(
+                            "bytes in a data segment",
+                            segment.data.len() as u64,
+                            self.max_data_segment_size,
+                        )?;
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "translator")]
+    fn check_table(&self, limits: &ResizableLimits) -> Result<(), CompileError> {
+        check_limit(
+            "initial elements in a table",
+            limits.initial,
+            self.max_table_elements,
+        )
+    }
+
+    #[cfg(feature = "translator")]
+    fn check_memory(&self, memory: &WPMemoryType) -> Result<(), CompileError> {
+        let initial = match memory {
+            WPMemoryType::M32 { limits, .. } => u64::from(limits.initial),
+            WPMemoryType::M64 { limits, .. } => limits.initial,
+        };
+        check_limit("initial pages in a memory", initial, self.max_memory_pages)
+    }
+}
+
+/// Reports a module that can't be parsed.
+#[cfg(feature = "translator")]
+fn malformed(error: BinaryReaderError) -> CompileError {
+    CompileError::Validate(format!("{}", error))
+}
+
+/// Checks that `value` doesn't exceed `limit`, if any.
+#[cfg(feature = "translator")]
+fn check_limit(what: &str, value: impl Into<u64>, limit: Option<u32>) -> Result<(), CompileError> {
+    let value = value.into();
+    match limit {
+        Some(limit) if value > u64::from(limit) => Err(CompileError::LimitExceeded(format!(
+            "{} {}, more than the limit of {}",
+            value, what, limit
+        ))),
+        _ => Ok(()),
+    }
+}
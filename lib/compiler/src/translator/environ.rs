@@ -378,6 +378,10 @@ impl<'data> ModuleEnvironment<'data> {
             data: body_bytes,
             module_offset: body_offset,
         });
+        self.result
+            .module
+            .function_body_sizes
+            .push(u32::try_from(body_bytes.len()).unwrap());
         Ok(())
     }
 
@@ -445,6 +449,21 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(())
     }
 
+    pub(crate) fn declare_local_name(
+        &mut self,
+        func_index: FunctionIndex,
+        local_index: u32,
+        name: &'data str,
+    ) -> WasmResult<()> {
+        self.result
+            .module
+            .local_names
+            .entry(func_index)
+            .or_default()
+            .insert(local_index, name.to_string());
+        Ok(())
+    }
+
     /// Provides the number of imports up front. By default this does nothing, but
     /// implementations can use this to preallocate memory if desired.
     pub(crate) fn reserve_imports(&mut self, _num: u32) -> WasmResult<()> {
@@ -3,13 +3,19 @@
 
 use smallvec::SmallVec;
 use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::ops::Deref;
-use wasmer_types::LocalFunctionIndex;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{
+    ExportIndex, FunctionIndex, FunctionType, GlobalIndex, GlobalInit, GlobalType, ImportIndex,
+    LocalFunctionIndex, SignatureIndex,
+};
 use wasmer_vm::ModuleInfo;
 use wasmparser::{BinaryReader, Operator, Type};
 
 use crate::error::{MiddlewareError, WasmResult};
+use crate::translator::environ::FunctionBodyData;
 
 /// A shared builder for function middlewares.
 pub trait ModuleMiddleware: Debug + Send + Sync {
@@ -23,6 +29,21 @@ pub trait ModuleMiddleware: Debug + Send + Sync {
         local_function_index: LocalFunctionIndex,
     ) -> Box<dyn FunctionMiddleware>;
 
+    /// Declares the functions this middleware imports from the host,
+    /// with [`ModuleAdditions::add_function_import`].
+    ///
+    /// This is called on every middleware of the chain, with the original
+    /// module, before `add_definitions` and `transform_module_info`.
+    fn add_imports(&self, _: &ModuleInfo, _: &mut ModuleAdditions) {}
+
+    /// Declares the globals and helper functions this middleware adds to
+    /// the module, with [`ModuleAdditions::add_global`] and
+    /// [`ModuleAdditions::add_function`].
+    ///
+    /// This is called on every middleware of the chain, with the original
+    /// module, after `add_imports` and before `transform_module_info`.
+    fn add_definitions(&self, _: &ModuleInfo, _: &mut ModuleAdditions) {}
+
     /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
     fn transform_module_info(&self, _: &mut ModuleInfo) {}
 }
@@ -58,6 +79,9 @@ pub struct MiddlewareReaderState<'a> {
 
     /// The pending operations added by the middleware.
     pending_operations: VecDeque<Operator<'a>>,
+
+    /// The original offset of the raw operator currently fed through the chain.
+    operator_offset: usize,
 }
 
 /// Trait for generating middleware chains from "prototype" (generator) chains.
@@ -69,7 +93,10 @@ pub trait ModuleMiddlewareChain {
     ) -> Vec<Box<dyn FunctionMiddleware>>;
 
     /// Applies the chain on a `ModuleInfo` struct.
-    fn apply_on_module_info(&self, module_info: &mut ModuleInfo);
+    ///
+    /// The returned `ModuleAdditions` must be used to get the function
+    /// bodies to compile and to generate their middleware chains.
+    fn apply_on_module_info(&self, module_info: &mut ModuleInfo) -> ModuleAdditions;
 }
 
 impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
@@ -84,10 +111,366 @@ impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
     }
 
     /// Applies the chain on a `ModuleInfo` struct.
-    fn apply_on_module_info(&self, module_info: &mut ModuleInfo) {
+    fn apply_on_module_info(&self, module_info: &mut ModuleInfo) -> ModuleAdditions {
+        let mut additions = ModuleAdditions::new(module_info);
+
+        for item in self {
+            item.add_imports(module_info, &mut additions);
+        }
+
+        additions.imports_sealed = true;
+
+        for item in self {
+            item.add_definitions(module_info, &mut additions);
+        }
+
+        additions.apply_on_module_info(module_info);
+
         for item in self {
             item.transform_module_info(module_info);
         }
+
+        additions
+    }
+}
+
+/// The imports, globals and helper functions added to a module by a
+/// middleware chain.
+///
+/// Middlewares declare them in [`ModuleMiddleware::add_imports`] and
+/// [`ModuleMiddleware::add_definitions`]. The returned indexes are the
+/// indexes of the new items in the transformed module, so they can be
+/// referenced by the operators that function middlewares push (e.g. a
+/// `call` to an imported host function).
+///
+/// Function imports are inserted after the imported functions of the
+/// original module, which shifts the indexes of its local functions.
+/// This is taken care of by the middleware chains generated with
+/// [`ModuleAdditions::generate_function_middleware_chain`].
+#[derive(Debug)]
+pub struct ModuleAdditions {
+    /// Number of imported functions in the original module.
+    num_imported_functions: usize,
+
+    /// Number of functions (imported and local) in the original module.
+    num_functions: usize,
+
+    /// Number of globals (imported and local) in the original module.
+    num_globals: usize,
+
+    /// Whether function imports can no longer be added.
+    imports_sealed: bool,
+
+    /// The function imports added, as `(module, field, signature)`.
+    function_imports: Vec<(String, String, FunctionType)>,
+
+    /// The globals added, with their initializers.
+    globals: Vec<(GlobalType, GlobalInit)>,
+
+    /// The helper functions added.
+    functions: Vec<AddedFunction>,
+}
+
+/// A helper function added by a middleware.
+#[derive(Debug)]
+struct AddedFunction {
+    /// The name of the function.
+    name: String,
+
+    /// The signature of the function.
+    signature: FunctionType,
+
+    /// The body of the function, in the binary format.
+    body: Vec<u8>,
+}
+
+impl ModuleAdditions {
+    /// Creates empty additions for the given original module.
+    fn new(module_info: &ModuleInfo) -> Self {
+        Self {
+            num_imported_functions: module_info.num_imported_functions,
+            num_functions: module_info.functions.len(),
+            num_globals: module_info.globals.len(),
+            imports_sealed: false,
+            function_imports: vec![],
+            globals: vec![],
+            functions: vec![],
+        }
+    }
+
+    /// Adds a function imported from `module` and `field`, and returns its
+    /// index in the transformed module.
+    ///
+    /// The import must be satisfied at instantiation time, like any other
+    /// import of the module.
+    ///
+    /// # Panic
+    ///
+    /// Function imports can only be added from [`ModuleMiddleware::add_imports`],
+    /// otherwise this will panic.
+    pub fn add_function_import(
+        &mut self,
+        module: &str,
+        field: &str,
+        signature: FunctionType,
+    ) -> FunctionIndex {
+        if self.imports_sealed {
+            panic!("ModuleAdditions::add_function_import: imports must be added from `ModuleMiddleware::add_imports`.");
+        }
+
+        self.function_imports
+            .push((module.to_string(), field.to_string(), signature));
+
+        FunctionIndex::new(self.num_imported_functions + self.function_imports.len() - 1)
+    }
+
+    /// Adds a global, and returns its index in the transformed module.
+    pub fn add_global(&mut self, global_type: GlobalType, init: GlobalInit) -> GlobalIndex {
+        self.globals.push((global_type, init));
+
+        GlobalIndex::new(self.num_globals + self.globals.len() - 1)
+    }
+
+    /// Adds a helper function, and returns its index in the transformed
+    /// module.
+    ///
+    /// `body` is the function body in the WebAssembly binary format, as
+    /// found in the code section without its size prefix: the local
+    /// declarations followed by the instructions and the final `end`.
+    /// Helper functions are not instrumented by the middleware chain.
+    pub fn add_function(
+        &mut self,
+        name: &str,
+        signature: FunctionType,
+        body: Vec<u8>,
+    ) -> FunctionIndex {
+        self.functions.push(AddedFunction {
+            name: name.to_string(),
+            signature,
+            body,
+        });
+
+        FunctionIndex::new(
+            self.num_functions + self.function_imports.len() + self.functions.len() - 1,
+        )
+    }
+
+    /// Whether the middlewares added anything to the module.
+    pub fn is_empty(&self) -> bool {
+        self.function_imports.is_empty() && self.globals.is_empty() && self.functions.is_empty()
+    }
+
+    /// Returns the function bodies to compile: the ones of the original
+    /// module, followed by the helper functions.
+    pub fn function_body_inputs<'a>(
+        &'a self,
+        function_body_inputs: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'a>>,
+    ) -> PrimaryMap<LocalFunctionIndex, FunctionBodyData<'a>> {
+        function_body_inputs
+            .values()
+            .map(|input| FunctionBodyData {
+                data: input.data,
+                module_offset: input.module_offset,
+            })
+            .chain(self.functions.iter().map(|function| FunctionBodyData {
+                data: &function.body,
+                module_offset: 0,
+            }))
+            .collect()
+    }
+
+    /// Generates the middleware chain of a local function of the
+    /// transformed module.
+    ///
+    /// Helper functions get an empty chain. Functions of the original
+    /// module get the chain generated by `middlewares`, preceded by a
+    /// middleware shifting their function indexes if function imports
+    /// have been added.
+    pub fn generate_function_middleware_chain<C: ModuleMiddlewareChain + ?Sized>(
+        &self,
+        middlewares: &C,
+        local_function_index: LocalFunctionIndex,
+    ) -> Vec<Box<dyn FunctionMiddleware>> {
+        if local_function_index.index() >= self.num_functions - self.num_imported_functions {
+            return vec![];
+        }
+
+        let mut chain = middlewares.generate_function_middleware_chain(local_function_index);
+
+        if !self.function_imports.is_empty() {
+            chain.insert(
+                0,
+                Box::new(FunctionIndexShift {
+                    num_imported_functions: self.num_imported_functions as u32,
+                    shift: self.function_imports.len() as u32,
+                }),
+            );
+        }
+
+        chain
+    }
+
+    /// Returns the index in the transformed module of a function of the
+    /// original module.
+    fn shift_function_index(&self, function_index: FunctionIndex) -> FunctionIndex {
+        if function_index.index() < self.num_imported_functions {
+            function_index
+        } else {
+            FunctionIndex::new(function_index.index() + self.function_imports.len())
+        }
+    }
+
+    /// Returns the index of a signature, adding it to the module if needed.
+    fn signature_index(module_info: &mut ModuleInfo, signature: &FunctionType) -> SignatureIndex {
+        if let Some((signature_index, _)) = module_info
+            .signatures
+            .iter()
+            .find(|(_, existing)| *existing == signature)
+        {
+            return signature_index;
+        }
+
+        module_info.signatures.push(signature.clone())
+    }
+
+    /// Adds the new items to the original module.
+    fn apply_on_module_info(&self, module_info: &mut ModuleInfo) {
+        if !self.function_imports.is_empty() {
+            // Shift the indexes of the local functions referenced by the
+            // original module.
+            for export in module_info.exports.values_mut() {
+                if let ExportIndex::Function(function_index) = export {
+                    *function_index = self.shift_function_index(*function_index);
+                }
+            }
+
+            if let Some(start_function) = module_info.start_function.as_mut() {
+                *start_function = self.shift_function_index(*start_function);
+            }
+
+            for table_initializer in module_info.table_initializers.iter_mut() {
+                for element in table_initializer.elements.iter_mut() {
+                    *element = self.shift_function_index(*element);
+                }
+            }
+
+            for elements in module_info.passive_elements.values_mut() {
+                for element in elements.iter_mut() {
+                    *element = self.shift_function_index(*element);
+                }
+            }
+
+            for initializer in module_info.global_initializers.values_mut() {
+                if let GlobalInit::RefFunc(function_index) = initializer {
+                    *function_index = self.shift_function_index(*function_index);
+                }
+            }
+
+            module_info.function_names = module_info
+                .function_names
+                .drain()
+                .map(|(function_index, name)| (self.shift_function_index(function_index), name))
+                .collect();
+
+            module_info.local_names = module_info
+                .local_names
+                .drain()
+                .map(|(function_index, names)| (self.shift_function_index(function_index), names))
+                .collect();
+
+            // Insert the function imports after the original ones.
+            let functions = std::mem::replace(&mut module_info.functions, PrimaryMap::new())
+                .values()
+                .cloned()
+                .collect::<Vec<SignatureIndex>>();
+            let (imported_functions, local_functions) =
+                functions.split_at(self.num_imported_functions);
+
+            for signature_index in imported_functions {
+                module_info.functions.push(*signature_index);
+            }
+
+            for (module, field, signature) in &self.function_imports {
+                let signature_index = Self::signature_index(module_info, signature);
+                let function_index = module_info.functions.push(signature_index);
+                let import_index = module_info.imports.len() as u32;
+
+                module_info.imports.insert(
+                    (module.clone(), field.clone(), import_index),
+                    ImportIndex::Function(function_index),
+                );
+            }
+
+            for signature_index in local_functions {
+                module_info.functions.push(*signature_index);
+            }
+
+            module_info.num_imported_functions += self.function_imports.len();
+        }
+
+        for (global_type, init) in &self.globals {
+            module_info.globals.push(*global_type);
+            module_info.global_initializers.push(*init);
+        }
+
+        for function in &self.functions {
+            let signature_index = Self::signature_index(module_info, &function.signature);
+            let function_index = module_info.functions.push(signature_index);
+
+            module_info
+                .function_names
+                .insert(function_index, function.name.clone());
+            module_info
+                .function_body_sizes
+                .push(u32::try_from(function.body.len()).unwrap());
+        }
+    }
+}
+
+/// A function middleware shifting the indexes of the local functions of
+/// the original module, to make room for the function imports added by
+/// middlewares.
+#[derive(Debug)]
+struct FunctionIndexShift {
+    /// Number of imported functions in the original module.
+    num_imported_functions: u32,
+
+    /// Number of function imports added by middlewares.
+    shift: u32,
+}
+
+impl FunctionIndexShift {
+    fn shift(&self, function_index: u32) -> u32 {
+        if function_index < self.num_imported_functions {
+            function_index
+        } else {
+            function_index + self.shift
+        }
+    }
+}
+
+impl FunctionMiddleware for FunctionIndexShift {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let operator = match operator {
+            Operator::Call { function_index } => Operator::Call {
+                function_index: self.shift(function_index),
+            },
+            Operator::ReturnCall { function_index } => Operator::ReturnCall {
+                function_index: self.shift(function_index),
+            },
+            Operator::RefFunc { function_index } => Operator::RefFunc {
+                function_index: self.shift(function_index),
+            },
+            operator => operator,
+        };
+
+        state.push_operator(operator);
+
+        Ok(())
     }
 }
 
@@ -96,6 +479,15 @@ impl<'a> MiddlewareReaderState<'a> {
     pub fn push_operator(&mut self, operator: Operator<'a>) {
         self.pending_operations.push_back(operator);
     }
+
+    /// Returns the original offset (in the module bytes) of the raw operator
+    /// that is currently being fed through the middleware chain.
+    ///
+    /// Operators pushed by previous stages of the chain report the offset
+    /// of the raw operator they were generated from.
+    pub fn operator_offset(&self) -> usize {
+        self.operator_offset
+    }
 }
 
 impl<'a> Extend<Operator<'a>> for MiddlewareReaderState<'a> {
@@ -118,6 +510,7 @@ impl<'a> MiddlewareBinaryReader<'a> {
             state: MiddlewareReaderState {
                 inner,
                 pending_operations: VecDeque::new(),
+                operator_offset: original_offset,
             },
             chain: vec![],
         }
@@ -149,6 +542,7 @@ impl<'a> MiddlewareBinaryReader<'a> {
 
         // Try to fill the `self.pending_operations` buffer, until it is non-empty.
         while self.state.pending_operations.is_empty() {
+            self.state.operator_offset = self.state.inner.original_position();
             let raw_op = self.state.inner.read_operator()?;
 
             // Fill the initial raw operator into pending buffer.
@@ -15,8 +15,8 @@ mod sections;
 
 pub use self::environ::{FunctionBodyData, ModuleEnvironment, ModuleInfoTranslation};
 pub use self::middleware::{
-    FunctionMiddleware, MiddlewareBinaryReader, MiddlewareReaderState, ModuleMiddleware,
-    ModuleMiddlewareChain,
+    FunctionMiddleware, MiddlewareBinaryReader, MiddlewareReaderState, ModuleAdditions,
+    ModuleMiddleware, ModuleMiddlewareChain,
 };
 pub use self::module::translate_module;
 pub use self::sections::wptype_to_type;
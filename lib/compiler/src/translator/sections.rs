@@ -27,9 +27,10 @@ use wasmer_types::{
 use wasmparser::{
     self, Data, DataKind, DataSectionReader, Element, ElementItem, ElementItems, ElementKind,
     ElementSectionReader, Export, ExportSectionReader, ExternalKind, FuncType as WPFunctionType,
-    FunctionSectionReader, GlobalSectionReader, GlobalType as WPGlobalType, ImportSectionEntryType,
-    ImportSectionReader, MemorySectionReader, MemoryType as WPMemoryType, NameSectionReader,
-    Naming, NamingReader, Operator, TableSectionReader, TypeDef, TypeSectionReader,
+    FunctionLocalReader, FunctionSectionReader, GlobalSectionReader, GlobalType as WPGlobalType,
+    ImportSectionEntryType, ImportSectionReader, MemorySectionReader, MemoryType as WPMemoryType,
+    NameSectionReader, Naming, NamingReader, Operator, TableSectionReader, TypeDef,
+    TypeSectionReader,
 };
 
 /// Helper function translating wasmparser types to Wasm Type.
@@ -444,7 +445,17 @@ pub fn parse_name_section<'data>(
                     environ.declare_module_name(name)?;
                 }
             }
-            wasmparser::Name::Local(_) => {}
+            wasmparser::Name::Local(local_subsection) => {
+                if let Some(local_names) = local_subsection
+                    .get_function_local_reader()
+                    .ok()
+                    .and_then(parse_local_name_subsection)
+                {
+                    for (func_index, local_index, name) in local_names {
+                        environ.declare_local_name(func_index, local_index, name)?;
+                    }
+                }
+            }
         };
     }
     Ok(())
@@ -473,3 +484,19 @@ fn parse_function_name_subsection(
     }
     Some(function_names)
 }
+
+fn parse_local_name_subsection(
+    mut function_local_reader: FunctionLocalReader<'_>,
+) -> Option<Vec<(FunctionIndex, u32, &str)>> {
+    let mut local_names = Vec::new();
+    for _ in 0..function_local_reader.get_count() {
+        let function_local_name = function_local_reader.read().ok()?;
+        let func_index = FunctionIndex::from_u32(function_local_name.func_index);
+        let mut naming_reader = function_local_name.get_map().ok()?;
+        for _ in 0..naming_reader.get_count() {
+            let Naming { index, name } = naming_reader.read().ok()?;
+            local_names.push((func_index, index, name));
+        }
+    }
+    Some(local_names)
+}
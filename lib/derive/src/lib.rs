@@ -6,6 +6,7 @@ use quote::{quote, quote_spanned, ToTokens};
 use syn::{spanned::Spanned, *};
 
 mod parse;
+mod value_type;
 
 use crate::parse::WasmerAttr;
 
@@ -17,6 +18,14 @@ pub fn derive_wasmer_env(input: proc_macro::TokenStream) -> proc_macro::TokenStr
     gen.into()
 }
 
+#[proc_macro_error]
+#[proc_macro_derive(ValueType, attributes(value_type))]
+pub fn derive_value_type(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+    let gen = value_type::impl_value_type(&input);
+    gen.into()
+}
+
 fn impl_wasmer_env_for_struct(
     name: &Ident,
     data: &DataStruct,
@@ -0,0 +1,213 @@
+//! The `ValueType` derive.
+//!
+//! The padding and layout checks are generated as constant array
+//! lengths, so that a mismatch is a compile-time error.
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, *};
+
+/// The layout assertions of a `#[value_type(...)]` attribute.
+#[derive(Default)]
+struct LayoutAttr {
+    size: Option<LitInt>,
+    align: Option<LitInt>,
+    offset: Option<LitInt>,
+}
+
+impl LayoutAttr {
+    /// Parses the `#[value_type(...)]` attributes in `attrs`.
+    fn from_attributes(attrs: &[Attribute]) -> Self {
+        let mut layout = Self::default();
+
+        for attr in attrs {
+            if !attr.path.is_ident("value_type") {
+                continue;
+            }
+
+            let list = match attr.parse_meta() {
+                Ok(Meta::List(list)) => list,
+                Ok(meta) => abort!(meta, "Expected `#[value_type(...)]`"),
+                Err(e) => abort!(attr, "Failed to parse `value_type` attribute: {}", e),
+            };
+
+            for nested in list.nested {
+                let (name, value) = match nested {
+                    NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                        path,
+                        lit: Lit::Int(value),
+                        ..
+                    })) => (path, value),
+                    otherwise => abort!(
+                        otherwise,
+                        "Expected `size = int`, `align = int` or `offset = int` in `value_type` attribute"
+                    ),
+                };
+
+                let slot = if name.is_ident("size") {
+                    &mut layout.size
+                } else if name.is_ident("align") {
+                    &mut layout.align
+                } else if name.is_ident("offset") {
+                    &mut layout.offset
+                } else {
+                    abort!(
+                        name,
+                        "Unrecognized argument in `value_type` attribute: expected `size`, `align` or `offset`"
+                    );
+                };
+                *slot = Some(value);
+            }
+        }
+
+        layout
+    }
+}
+
+/// Aborts unless the struct has a `#[repr(C)]` or `#[repr(transparent)]`
+/// attribute, as its layout must not depend on the compiler.
+fn check_repr(input: &DeriveInput) {
+    let has_stable_layout = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("repr"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .any(|nested| match nested {
+            NestedMeta::Meta(Meta::Path(path)) => {
+                path.is_ident("C") || path.is_ident("transparent")
+            }
+            _ => false,
+        });
+
+    if !has_stable_layout {
+        abort!(
+            input.ident,
+            "ValueType can only be derived for `#[repr(C)]` or `#[repr(transparent)]` structs"
+        );
+    }
+}
+
+/// A constant assertion, failing to compile if `condition` is false.
+///
+/// `name` appears in the compiler error, to tell what's wrong.
+fn const_assert(name: &str, span: Span, condition: TokenStream) -> TokenStream {
+    let name = Ident::new(name, span);
+
+    quote_spanned! {span=>
+        let #name: [(); 0] = [(); !(#condition) as usize];
+    }
+}
+
+pub fn impl_value_type(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+
+    let data = match &input.data {
+        Data::Struct(data) => data,
+        _ => abort!(name, "ValueType can only be derived for structs"),
+    };
+
+    if !input.generics.params.is_empty() {
+        abort!(
+            input.generics,
+            "ValueType can't be derived for generic structs, as their padding can't be checked"
+        );
+    }
+
+    check_repr(input);
+
+    let mut assertions = vec![];
+    // Without padding, the offset of a field is the size of the fields
+    // before it.
+    let mut offset = quote! { 0 };
+
+    let mut zero_padding_bytes = vec![];
+
+    for (index, field) in data.fields.iter().enumerate() {
+        let ty = &field.ty;
+        let span = ty.span();
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(index)),
+        };
+
+        // The fields may have padding of their own.
+        zero_padding_bytes.push(quote_spanned! {span=>
+            ::wasmer::ValueType::zero_padding_bytes(
+                // Copied, as the fields of packed structs can't be borrowed.
+                &{ self.#member },
+                &mut bytes[#offset..#offset + ::core::mem::size_of::<#ty>()],
+            );
+        });
+
+        assertions.push(quote_spanned! {span=>
+            assert_value_type::<#ty>();
+        });
+
+        let field_layout = LayoutAttr::from_attributes(&field.attrs);
+
+        if field_layout.size.is_some() || field_layout.align.is_some() {
+            abort!(field, "`size` and `align` can only be given on structs");
+        }
+
+        if let Some(expected) = field_layout.offset {
+            assertions.push(const_assert(
+                "field_offset_mismatch",
+                expected.span(),
+                quote! { #offset == #expected },
+            ));
+        }
+
+        offset = quote! { #offset + ::core::mem::size_of::<#ty>() };
+    }
+
+    assertions.push(const_assert(
+        "struct_has_padding_bytes",
+        name.span(),
+        quote! { ::core::mem::size_of::<#name>() == #offset },
+    ));
+
+    let layout = LayoutAttr::from_attributes(&input.attrs);
+
+    if layout.offset.is_some() {
+        abort!(name, "`offset` can only be given on fields");
+    }
+
+    if let Some(expected) = layout.size {
+        assertions.push(const_assert(
+            "struct_size_mismatch",
+            expected.span(),
+            quote! { ::core::mem::size_of::<#name>() == #expected },
+        ));
+    }
+
+    if let Some(expected) = layout.align {
+        assertions.push(const_assert(
+            "struct_align_mismatch",
+            expected.span(),
+            quote! { ::core::mem::align_of::<#name>() == #expected },
+        ));
+    }
+
+    quote! {
+        unsafe impl ::wasmer::ValueType for #name {
+            #[allow(unused_variables)]
+            fn zero_padding_bytes(&self, bytes: &mut [::core::mem::MaybeUninit<u8>]) {
+                #(#zero_padding_bytes)*
+            }
+        }
+
+        const _: () = {
+            #[allow(dead_code)]
+            fn assert_value_type<T: ::wasmer::ValueType>() {}
+
+            #[allow(dead_code, unused_variables)]
+            fn assert_layout() {
+                #(#assertions)*
+            }
+        };
+    }
+}
@@ -0,0 +1,12 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(ValueType, Clone, Copy)]
+#[repr(C)]
+struct NotPod {
+    flag: bool, //~ ERROR the trait bound `bool: ValueType` is not satisfied
+    _padding: [u8; 3],
+}
+
+fn main() {}
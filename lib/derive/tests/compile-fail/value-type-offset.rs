@@ -0,0 +1,13 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(ValueType, Clone, Copy)]
+#[repr(C)]
+struct WrongOffset {
+    first: u32,
+    #[value_type(offset = 8)] //~ ERROR mismatched types
+    second: u32,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(ValueType, Clone, Copy)]
+#[repr(C)]
+struct Padded { //~ ERROR mismatched types
+    small: u8,
+    big: u32,
+}
+
+fn main() {}
@@ -0,0 +1,62 @@
+#![allow(dead_code)]
+
+use wasmer::{Memory, MemoryType, Store, ValueType, WasmPtr};
+
+#[derive(ValueType, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+#[value_type(size = 16, align = 8)]
+struct Timestamp {
+    #[value_type(offset = 0)]
+    seconds: u64,
+    #[value_type(offset = 8)]
+    nanoseconds: u32,
+    #[value_type(offset = 12)]
+    flags: u32,
+}
+
+#[derive(ValueType, Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+struct Event {
+    kind: u32,
+    id: u32,
+    timestamp: Timestamp,
+}
+
+#[derive(ValueType, Clone, Copy, Debug, PartialEq)]
+#[repr(transparent)]
+struct Fd(u32);
+
+fn impls_value_type<T: ValueType>() -> bool {
+    true
+}
+
+#[test]
+fn test_derive() {
+    assert!(impls_value_type::<Timestamp>());
+    assert!(impls_value_type::<Event>());
+    assert!(impls_value_type::<Fd>());
+}
+
+#[test]
+fn test_read_write_derived() {
+    let store = Store::default();
+    let memory = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    let event = Event {
+        kind: 1,
+        id: 2,
+        timestamp: Timestamp {
+            seconds: 3,
+            nanoseconds: 4,
+            flags: 5,
+        },
+    };
+
+    let ptr = WasmPtr::<Event>::new(0x10);
+    ptr.write(&memory, event).unwrap();
+    assert_eq!(ptr.read(&memory).unwrap(), event);
+
+    // The fields are laid out as in the guest.
+    assert_eq!(memory.read_struct::<u32>(0x14).unwrap(), 2);
+    assert_eq!(memory.read_struct::<u64>(0x18).unwrap(), 3);
+    assert_eq!(memory.read_struct::<u32>(0x24).unwrap(), 5);
+}
@@ -18,6 +18,7 @@ use crate::{
     EmscriptenData,
 };
 
+use std::mem::MaybeUninit;
 use std::os::raw::c_int;
 use std::sync::MutexGuard;
 
@@ -187,7 +188,10 @@ pub struct EmAddrInfo {
     pub ai_next: WasmPtr<EmAddrInfo>,
 }
 
-unsafe impl ValueType for EmAddrInfo {}
+unsafe impl ValueType for EmAddrInfo {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
 
 // NOTE: from looking at emscripten JS, this should be a union
 // TODO: review this, highly likely to have bugs
@@ -198,4 +202,7 @@ pub struct EmSockAddr {
     pub sa_data: [c_char; 14],
 }
 
-unsafe impl ValueType for EmSockAddr {}
+unsafe impl ValueType for EmSockAddr {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
@@ -5,13 +5,16 @@
 // don't want to warn about unusued code here
 #![allow(dead_code)]
 
-use std::{cell::Cell, fmt};
+use std::{cell::Cell, fmt, mem::MaybeUninit};
 pub use wasmer::{Array, FromToNativeWasmType, Memory, ValueType};
 
 #[repr(transparent)]
 pub struct WasmPtr<T: Copy, Ty = wasmer::Item>(wasmer::WasmPtr<T, Ty>);
 
-unsafe impl<T: Copy, Ty> ValueType for WasmPtr<T, Ty> {}
+unsafe impl<T: Copy, Ty> ValueType for WasmPtr<T, Ty> {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
 impl<T: Copy, Ty> Copy for WasmPtr<T, Ty> {}
 
 impl<T: Copy, Ty> Clone for WasmPtr<T, Ty> {
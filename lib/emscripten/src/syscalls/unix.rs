@@ -118,7 +118,7 @@ use crate::utils::{self, get_cstr_path};
 use crate::EmEnv;
 #[allow(unused_imports)]
 use std::io::Error;
-use std::mem;
+use std::mem::{self, MaybeUninit};
 
 // Linking to functions that are not provided by rust libc
 #[cfg(target_os = "macos")]
@@ -850,7 +850,10 @@ pub struct EmPollFd {
     pub revents: i16,
 }
 
-unsafe impl wasmer::ValueType for EmPollFd {}
+unsafe impl wasmer::ValueType for EmPollFd {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
 
 /// poll
 pub fn ___syscall168(ctx: &EmEnv, _which: i32, mut varargs: VarArgs) -> i32 {
@@ -11,8 +11,8 @@ use wasmer_compiler::{CompileError, Features, Triple};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::{CompileModuleInfo, ModuleEnvironment};
 use wasmer_engine::{
-    register_frame_info, Artifact, DeserializeError, FunctionExtent, GlobalFrameInfoRegistration,
-    SerializeError,
+    check_serialized_artifact_version, register_frame_info, Artifact, DeserializeError,
+    FunctionExtent, GlobalFrameInfoRegistration, SerializeError, SERIALIZED_ARTIFACT_VERSION,
 };
 #[cfg(feature = "compiler")]
 use wasmer_engine::{Engine, SerializableFunctionFrameInfo, Tunables};
@@ -140,7 +140,16 @@ impl JITArtifact {
             ));
         }
 
-        let inner_bytes = &bytes[Self::MAGIC_HEADER.len()..];
+        let bytes = &bytes[Self::MAGIC_HEADER.len()..];
+        if bytes.len() < 4 {
+            return Err(DeserializeError::CorruptedBinary(
+                "Can't read the format version".to_string(),
+            ));
+        }
+        let (version, inner_bytes) = bytes.split_at(4);
+        let mut version_bytes = [0; 4];
+        version_bytes.copy_from_slice(version);
+        check_serialized_artifact_version(u32::from_le_bytes(version_bytes))?;
 
         // let r = flexbuffers::Reader::get_root(bytes).map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
         // let serializable = SerializableModule::deserialize(r).map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
@@ -318,8 +327,9 @@ impl Artifact for JITArtifact {
         let bytes = bincode::serialize(&self.serializable)
             .map_err(|e| SerializeError::Generic(format!("{:?}", e)))?;
 
-        // Prepend the header.
+        // Prepend the header, and the format version.
         let mut serialized = Self::MAGIC_HEADER.to_vec();
+        serialized.extend(&SERIALIZED_ARTIFACT_VERSION.to_le_bytes());
         serialized.extend(bytes);
         Ok(serialized)
     }
@@ -19,9 +19,12 @@ use wasmer_compiler::{CompileError, Features, OperatingSystem, Symbol, SymbolReg
 use wasmer_compiler::{
     CompileModuleInfo, FunctionBodyData, ModuleEnvironment, ModuleTranslationState,
 };
-use wasmer_engine::{Artifact, DeserializeError, InstantiationError, SerializeError};
+use wasmer_engine::{
+    check_serialized_artifact_version, Artifact, DeserializeError, InstantiationError,
+    SerializeError,
+};
 #[cfg(feature = "compiler")]
-use wasmer_engine::{Engine, Tunables};
+use wasmer_engine::{Engine, Tunables, SERIALIZED_ARTIFACT_VERSION};
 #[cfg(feature = "compiler")]
 use wasmer_object::{emit_compilation, emit_data, get_object_for_target};
 use wasmer_types::entity::{BoxedSlice, PrimaryMap};
@@ -177,6 +180,7 @@ impl NativeArtifact {
             .collect::<PrimaryMap<LocalFunctionIndex, u64>>();
 
         let mut metadata = ModuleMetadata {
+            version: SERIALIZED_ARTIFACT_VERSION,
             compile_info,
             prefix: engine_inner.get_prefix(&data),
             data_initializers,
@@ -545,6 +549,9 @@ impl NativeArtifact {
         })?;
         let metadata_slice: &'static [u8] =
             slice::from_raw_parts(&size[10] as *const u8, metadata_len as usize);
+        let version: u32 = bincode::deserialize(metadata_slice)
+            .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
+        check_serialized_artifact_version(version)?;
         let metadata: ModuleMetadata = bincode::deserialize(metadata_slice)
             .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
         let mut engine_inner = engine.inner_mut();
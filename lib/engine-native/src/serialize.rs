@@ -6,6 +6,9 @@ use wasmer_types::{FunctionIndex, LocalFunctionIndex, OwnedDataInitializer, Sign
 /// Serializable struct that represents the compiled metadata.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ModuleMetadata {
+    // The format version, first so that it's checked before the rest is
+    // deserialized
+    pub version: u32,
     pub compile_info: CompileModuleInfo,
     pub prefix: String,
     pub data_initializers: Box<[OwnedDataInitializer]>,
@@ -12,9 +12,12 @@ use wasmer_compiler::{CompileError, Features, OperatingSystem, SymbolRegistry, T
 use wasmer_compiler::{
     CompileModuleInfo, FunctionBodyData, ModuleEnvironment, ModuleTranslationState,
 };
-use wasmer_engine::{Artifact, DeserializeError, InstantiationError, SerializeError};
+use wasmer_engine::{
+    check_serialized_artifact_version, Artifact, DeserializeError, InstantiationError,
+    SerializeError,
+};
 #[cfg(feature = "compiler")]
-use wasmer_engine::{Engine, Tunables};
+use wasmer_engine::{Engine, Tunables, SERIALIZED_ARTIFACT_VERSION};
 #[cfg(feature = "compiler")]
 use wasmer_object::{emit_compilation, emit_data, get_object_for_target};
 use wasmer_types::entity::EntityRef;
@@ -166,6 +169,7 @@ impl ObjectFileArtifact {
             .collect::<PrimaryMap<LocalFunctionIndex, u64>>();
 
         let mut metadata = ModuleMetadata {
+            version: SERIALIZED_ARTIFACT_VERSION,
             compile_info,
             prefix: engine_inner.get_prefix(&data),
             data_initializers,
@@ -300,7 +304,11 @@ impl ObjectFileArtifact {
         let mut reader = bytes;
         let data_len = leb128::read::unsigned(&mut reader).unwrap() as usize;
 
-        let metadata: ModuleMetadata = bincode::deserialize(&bytes[10..(data_len + 10)]).unwrap();
+        let metadata_slice = &bytes[10..(data_len + 10)];
+        let version: u32 = bincode::deserialize(metadata_slice)
+            .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
+        check_serialized_artifact_version(version)?;
+        let metadata: ModuleMetadata = bincode::deserialize(metadata_slice).unwrap();
 
         const WORD_SIZE: usize = mem::size_of::<usize>();
         let mut byte_buffer = [0u8; WORD_SIZE];
@@ -1,5 +1,6 @@
 use crate::{
-    resolve_imports, InstantiationError, Resolver, RuntimeError, SerializeError, Tunables,
+    resolve_imports, InstantiationError, LinkError, Resolver, RuntimeError, SerializeError,
+    Tunables,
 };
 use std::any::Any;
 use std::fs;
@@ -12,8 +13,8 @@ use wasmer_types::{
     SignatureIndex, TableIndex,
 };
 use wasmer_vm::{
-    FunctionBodyPtr, InstanceAllocator, InstanceHandle, MemoryStyle, ModuleInfo, TableStyle,
-    VMSharedSignatureIndex, VMTrampoline,
+    FunctionBodyPtr, Imports, InstanceAllocator, InstanceHandle, MemoryStyle, ModuleInfo,
+    TableStyle, VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// An `Artifact` is the product that the `Engine`
@@ -91,26 +92,49 @@ pub trait Artifact: Send + Sync + Upcastable {
         tunables: &dyn Tunables,
         resolver: &dyn Resolver,
         host_state: Box<dyn Any>,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        let imports = self
+            .resolve_imports(resolver)
+            .map_err(InstantiationError::Link)?;
+
+        self.instantiate_with_imports(tunables, imports, host_state)
+    }
+
+    /// Resolves the imports of this `Artifact` with `resolver`, and
+    /// checks their types.
+    ///
+    /// The resolved imports can be given to
+    /// [`Artifact::instantiate_with_imports`] as many times as needed,
+    /// by cloning them.
+    fn resolve_imports(&self, resolver: &dyn Resolver) -> Result<Imports, LinkError> {
+        resolve_imports(
+            self.module_ref(),
+            resolver,
+            self.finished_dynamic_function_trampolines(),
+            self.memory_styles(),
+            self.table_styles(),
+        )
+    }
+
+    /// Crate an `Instance` from this `Artifact`, with the `imports`
+    /// resolved by [`Artifact::resolve_imports`].
+    ///
+    /// # Safety
+    ///
+    /// See [`InstanceHandle::new`].
+    unsafe fn instantiate_with_imports(
+        &self,
+        tunables: &dyn Tunables,
+        mut imports: Imports,
+        host_state: Box<dyn Any>,
     ) -> Result<InstanceHandle, InstantiationError> {
         self.preinstantiate()?;
 
         let module = self.module();
-        let (imports, import_function_envs) = {
-            let mut imports = resolve_imports(
-                &module,
-                resolver,
-                &self.finished_dynamic_function_trampolines(),
-                self.memory_styles(),
-                self.table_styles(),
-            )
-            .map_err(InstantiationError::Link)?;
-
-            // Get the `WasmerEnv::init_with_instance` function pointers and the pointers
-            // to the envs to call it on.
-            let import_function_envs = imports.get_imported_function_envs();
 
-            (imports, import_function_envs)
-        };
+        // Get the `WasmerEnv::init_with_instance` function pointers and the pointers
+        // to the envs to call it on.
+        let import_function_envs = imports.get_imported_function_envs();
 
         // Get pointers to where metadata about local memories should live in VM memory.
         // Get pointers to where metadata about local tables should live in VM memory.
@@ -41,7 +41,9 @@ pub use crate::resolver::{
     resolve_imports, ChainableNamedResolver, NamedResolver, NamedResolverChain, NullResolver,
     Resolver,
 };
-pub use crate::serialize::SerializableFunctionFrameInfo;
+pub use crate::serialize::{
+    check_serialized_artifact_version, SerializableFunctionFrameInfo, SERIALIZED_ARTIFACT_VERSION,
+};
 pub use crate::trap::*;
 pub use crate::tunables::Tunables;
 
@@ -1,9 +1,32 @@
+use crate::DeserializeError;
 use serde::de::{Deserializer, Visitor};
 use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use wasmer_compiler::CompiledFunctionFrameInfo;
 
+/// The version of the format of the serialized artifacts, written in
+/// their header.
+///
+/// It must be bumped whenever the serialized layout changes: the layout
+/// of the `ModuleInfo` and of the compilation metadata, but also the
+/// `VMContext` layout (like the builtin functions), which the compiled
+/// code depends on.
+pub const SERIALIZED_ARTIFACT_VERSION: u32 = 1;
+
+/// Checks that an artifact has been serialized with the current format
+/// version, [`SERIALIZED_ARTIFACT_VERSION`].
+pub fn check_serialized_artifact_version(version: u32) -> Result<(), DeserializeError> {
+    if version != SERIALIZED_ARTIFACT_VERSION {
+        return Err(DeserializeError::Incompatible(format!(
+            "the artifact has been serialized with the format version {}, \
+             but this version of Wasmer only supports the version {}",
+            version, SERIALIZED_ARTIFACT_VERSION
+        )));
+    }
+    Ok(())
+}
+
 /// This is the unserialized verison of `CompiledFunctionFrameInfo`.
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(transparent)]
@@ -1,6 +1,7 @@
 use crate::error::LinkError;
 use std::ptr::NonNull;
 use std::sync::Arc;
+use wasmer_compiler::ModuleLimits;
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     GlobalType, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType,
@@ -20,6 +21,13 @@ pub trait Tunables {
     /// Construct a `TableStyle` for the provided `TableType`
     fn table_style(&self, table: &TableType) -> TableStyle;
 
+    /// The limits on the complexity of the modules to compile.
+    ///
+    /// By default, any valid module is accepted.
+    fn module_limits(&self) -> ModuleLimits {
+        ModuleLimits::default()
+    }
+
     /// Create a memory owned by the host given a [`MemoryType`] and a [`MemoryStyle`].
     fn create_host_memory(
         &self,
@@ -0,0 +1,820 @@
+//! `call_tracing` is a middleware for tracing the entry and the exit
+//! of functions, and the calls to imported (host) functions.
+//!
+//! Every traced function reports its arguments when it is entered and
+//! its results when it returns, by calling host hooks imported from the
+//! `wasmer_call_tracing` namespace. Those imports are satisfied by
+//! [`import_object`], which forwards the calls to a [`CallTracer`].
+//! The default [`TracingCallTracer`] opens a `tracing` span for every
+//! traced call.
+//!
+//! Only functions whose parameters and results are all numbers
+//! (`i32`, `i64`, `f32` or `f64`) are traced.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::{
+    Exports, ExternType, Function, FunctionMiddleware, FunctionType, GlobalInit, GlobalType,
+    ImportObject, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, Module,
+    ModuleAdditions, ModuleMiddleware, Mutability, Type, Value,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{FunctionIndex, GlobalIndex, ImportIndex};
+use wasmer_vm::ModuleInfo;
+
+/// The namespace of the host hooks imported by the traced modules.
+pub const CALL_TRACING_NAMESPACE: &str = "wasmer_call_tracing";
+
+/// Module-level information gathered while the module is transformed.
+#[derive(Debug, Clone, Default)]
+struct CallTracingModuleInfo {
+    /// Number of imported functions in the original module.
+    num_imported_functions: usize,
+
+    /// Number of function imports added to the module by the middleware
+    /// chain, which shifts the index of the local functions.
+    index_shift: usize,
+
+    /// The signatures of the functions of the original module.
+    function_types: Vec<FunctionType>,
+
+    /// The functions of the original module which are traced.
+    traced_functions: HashSet<FunctionIndex>,
+
+    /// The hooks reporting the entry of a function, by parameter types.
+    enter_hooks: HashMap<Vec<Type>, FunctionIndex>,
+
+    /// The hooks reporting the exit of a function, by result types.
+    exit_hooks: HashMap<Vec<Type>, FunctionIndex>,
+
+    /// The globals used to temporarily hold values of the stack, by type.
+    scratch_globals: HashMap<Type, Vec<GlobalIndex>>,
+
+    /// The global used to temporarily hold the condition of a `br_if`.
+    condition_global: Option<GlobalIndex>,
+}
+
+/// The module-level call tracing middleware.
+///
+/// # Panic
+///
+/// An instance of `CallTracing` should not be shared among different modules, since it tracks
+/// module-specific information like the indexes of the tracing hooks. Attempts to use
+/// a `CallTracing` instance from multiple modules will result in a panic.
+#[derive(Debug)]
+pub struct CallTracing {
+    /// The name patterns of the traced functions.
+    filters: Vec<String>,
+
+    /// Module-level information, set while the module is transformed.
+    module_info: Mutex<Option<Arc<CallTracingModuleInfo>>>,
+}
+
+/// The function-level call tracing middleware.
+#[derive(Debug)]
+pub struct FunctionCallTracing {
+    /// Module-level information.
+    module_info: Arc<CallTracingModuleInfo>,
+
+    /// The index of the function in the original module.
+    function_index: FunctionIndex,
+
+    /// Whether the function itself is traced.
+    traced: bool,
+
+    /// Whether the function entry has been instrumented.
+    entered: bool,
+
+    /// Current nesting depth of control blocks, including the function body.
+    depth: u32,
+}
+
+impl CallTracing {
+    /// Creates a `CallTracing` middleware tracing the functions whose
+    /// name matches one of `filters`.
+    ///
+    /// Filters are patterns where `*` matches any sequence of
+    /// characters. Local functions are matched against their name in
+    /// the name section, imported functions against `module.field`. An
+    /// empty list of filters traces every function.
+    pub fn new(filters: Vec<String>) -> Self {
+        Self {
+            filters,
+            module_info: Mutex::new(None),
+        }
+    }
+
+    /// Whether a function named `name` is selected by the filters.
+    fn is_selected(&self, name: &str) -> bool {
+        self.filters.is_empty()
+            || self
+                .filters
+                .iter()
+                .any(|pattern| matches_pattern(pattern, name))
+    }
+}
+
+impl ModuleMiddleware for CallTracing {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let module_info = self.module_info.lock().unwrap().clone().unwrap();
+        let function_index =
+            FunctionIndex::new(module_info.num_imported_functions + local_function_index.index());
+
+        Box::new(FunctionCallTracing {
+            traced: module_info.traced_functions.contains(&function_index),
+            module_info,
+            function_index,
+            entered: false,
+            depth: 1,
+        })
+    }
+
+    /// Imports one host hook per distinct list of traced types.
+    fn add_imports(&self, module_info: &ModuleInfo, additions: &mut ModuleAdditions) {
+        let mut call_tracing_module_info = self.module_info.lock().unwrap();
+
+        if call_tracing_module_info.is_some() {
+            panic!("CallTracing::add_imports: Attempting to use a `CallTracing` middleware from multiple modules.");
+        }
+
+        let names = function_names(module_info);
+        let function_types: Vec<FunctionType> = module_info
+            .functions
+            .values()
+            .map(|signature_index| module_info.signatures[*signature_index].clone())
+            .collect();
+
+        let traced_functions: HashSet<FunctionIndex> = function_types
+            .iter()
+            .enumerate()
+            .filter(|(index, function_type)| {
+                is_traceable(function_type.params())
+                    && is_traceable(function_type.results())
+                    && self.is_selected(&names[*index])
+            })
+            .map(|(index, _)| FunctionIndex::new(index))
+            .collect();
+
+        // Sort the hooks by name, so that the imports of the module don't
+        // depend on the iteration order of `traced_functions`.
+        let mut enter_types = BTreeMap::new();
+        let mut exit_types = BTreeMap::new();
+
+        for function_index in &traced_functions {
+            let function_type = &function_types[function_index.index()];
+            let params = function_type.params().to_vec();
+            let results = function_type.results().to_vec();
+
+            enter_types.insert(hook_name("enter", &params), params);
+            exit_types.insert(hook_name("exit", &results), results);
+        }
+
+        let mut add_hooks = |hooks: BTreeMap<String, Vec<Type>>| {
+            hooks
+                .into_iter()
+                .map(|(name, types)| {
+                    let mut params = types.clone();
+                    params.push(Type::I32);
+
+                    let function_index = additions.add_function_import(
+                        CALL_TRACING_NAMESPACE,
+                        &name,
+                        FunctionType::new(params, vec![]),
+                    );
+
+                    (types, function_index)
+                })
+                .collect::<HashMap<_, _>>()
+        };
+        let enter_hooks = add_hooks(enter_types);
+        let exit_hooks = add_hooks(exit_types);
+
+        *call_tracing_module_info = Some(Arc::new(CallTracingModuleInfo {
+            num_imported_functions: module_info.num_imported_functions,
+            index_shift: 0,
+            function_types,
+            traced_functions,
+            enter_hooks,
+            exit_hooks,
+            scratch_globals: HashMap::new(),
+            condition_global: None,
+        }));
+    }
+
+    /// Adds the scratch globals used to report values of the stack.
+    fn add_definitions(&self, _: &ModuleInfo, additions: &mut ModuleAdditions) {
+        let mut call_tracing_module_info = self.module_info.lock().unwrap();
+        let call_tracing_module_info = Arc::get_mut(call_tracing_module_info.as_mut().unwrap())
+            .expect("CallTracing::add_definitions: the middleware is already in use");
+
+        // Values are stashed on function exits (the results) and around
+        // calls to imported functions (the arguments and the results).
+        let mut stashed_types: Vec<&[Type]> = vec![];
+
+        for function_index in &call_tracing_module_info.traced_functions {
+            let function_type = &call_tracing_module_info.function_types[function_index.index()];
+
+            stashed_types.push(function_type.results());
+
+            if function_index.index() < call_tracing_module_info.num_imported_functions {
+                stashed_types.push(function_type.params());
+            }
+        }
+
+        let mut scratch_counts: HashMap<Type, usize> = HashMap::new();
+
+        for types in stashed_types {
+            let mut counts: HashMap<Type, usize> = HashMap::new();
+
+            for ty in types {
+                *counts.entry(*ty).or_default() += 1;
+            }
+
+            for (ty, count) in counts {
+                let scratch_count = scratch_counts.entry(ty).or_default();
+                *scratch_count = (*scratch_count).max(count);
+            }
+        }
+
+        // Add the globals in a fixed order, for the same reason as the hooks.
+        let mut scratch_globals = HashMap::new();
+
+        for ty in &[Type::I32, Type::I64, Type::F32, Type::F64] {
+            let count = scratch_counts.get(ty).copied().unwrap_or(0);
+            let init = match ty {
+                Type::I32 => GlobalInit::I32Const(0),
+                Type::I64 => GlobalInit::I64Const(0),
+                Type::F32 => GlobalInit::F32Const(0.0),
+                _ => GlobalInit::F64Const(0.0),
+            };
+            let globals = (0..count)
+                .map(|_| additions.add_global(GlobalType::new(*ty, Mutability::Var), init))
+                .collect();
+
+            scratch_globals.insert(*ty, globals);
+        }
+
+        call_tracing_module_info.scratch_globals = scratch_globals;
+
+        if !call_tracing_module_info.traced_functions.is_empty() {
+            call_tracing_module_info.condition_global = Some(additions.add_global(
+                GlobalType::new(Type::I32, Mutability::Var),
+                GlobalInit::I32Const(0),
+            ));
+        }
+    }
+
+    /// Records how much the local functions have been shifted by the
+    /// function imports of the middleware chain.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut call_tracing_module_info = self.module_info.lock().unwrap();
+        let call_tracing_module_info = Arc::get_mut(call_tracing_module_info.as_mut().unwrap())
+            .expect("CallTracing::transform_module_info: the middleware is already in use");
+
+        call_tracing_module_info.index_shift =
+            module_info.num_imported_functions - call_tracing_module_info.num_imported_functions;
+    }
+}
+
+impl FunctionCallTracing {
+    /// The index of a function of the original module in the transformed module.
+    fn final_index(&self, function_index: FunctionIndex) -> i32 {
+        if function_index.index() < self.module_info.num_imported_functions {
+            function_index.as_u32() as i32
+        } else {
+            (function_index.index() + self.module_info.index_shift) as i32
+        }
+    }
+
+    /// The scratch globals holding values of `types`.
+    fn scratch_globals(&self, types: &[Type]) -> Vec<u32> {
+        let mut used: HashMap<Type, usize> = HashMap::new();
+
+        types
+            .iter()
+            .map(|ty| {
+                let index = used.entry(*ty).or_default();
+                *index += 1;
+                self.module_info.scratch_globals[ty][*index - 1].as_u32()
+            })
+            .collect()
+    }
+
+    /// Moves the values of `types` from the top of the stack to the scratch globals.
+    fn stash(&self, types: &[Type], state: &mut MiddlewareReaderState<'_>) {
+        for global_index in self.scratch_globals(types).into_iter().rev() {
+            state.push_operator(Operator::GlobalSet { global_index });
+        }
+    }
+
+    /// Pushes the values of `types` from the scratch globals to the stack.
+    fn unstash(&self, types: &[Type], state: &mut MiddlewareReaderState<'_>) {
+        for global_index in self.scratch_globals(types) {
+            state.push_operator(Operator::GlobalGet { global_index });
+        }
+    }
+
+    /// Calls `hook` with the values of `types` at the top of the stack,
+    /// leaving the stack unchanged.
+    fn report(
+        &self,
+        hook: FunctionIndex,
+        function_index: FunctionIndex,
+        types: &[Type],
+        state: &mut MiddlewareReaderState<'_>,
+    ) {
+        self.stash(types, state);
+        self.unstash(types, state);
+        self.call_hook(hook, function_index, state);
+        self.unstash(types, state);
+    }
+
+    /// Calls `hook` with the values already on the stack.
+    fn call_hook(
+        &self,
+        hook: FunctionIndex,
+        function_index: FunctionIndex,
+        state: &mut MiddlewareReaderState<'_>,
+    ) {
+        state.extend(&[
+            Operator::I32Const {
+                value: self.final_index(function_index),
+            },
+            Operator::Call {
+                function_index: hook.as_u32(),
+            },
+        ]);
+    }
+
+    /// Reports the entry of the current function with its arguments.
+    fn trace_entry(&self, state: &mut MiddlewareReaderState<'_>) {
+        let params = self.module_info.function_types[self.function_index.index()].params();
+
+        for local_index in 0..params.len() as u32 {
+            state.push_operator(Operator::LocalGet { local_index });
+        }
+
+        self.call_hook(
+            self.module_info.enter_hooks[params],
+            self.function_index,
+            state,
+        );
+    }
+
+    /// Reports the exit of the current function with the results at the
+    /// top of the stack.
+    fn trace_exit(&self, state: &mut MiddlewareReaderState<'_>) {
+        let results = self.module_info.function_types[self.function_index.index()].results();
+
+        self.report(
+            self.module_info.exit_hooks[results],
+            self.function_index,
+            results,
+            state,
+        );
+    }
+
+    /// Reports the exit of the current function if the condition at the
+    /// top of the stack, below which are the results, is non-zero.
+    fn trace_conditional_exit(&self, state: &mut MiddlewareReaderState<'_>) {
+        let results = self.module_info.function_types[self.function_index.index()].results();
+        let condition = self.module_info.condition_global.unwrap().as_u32();
+
+        state.push_operator(Operator::GlobalSet {
+            global_index: condition,
+        });
+        self.stash(results, state);
+        state.extend(&[
+            Operator::GlobalGet {
+                global_index: condition,
+            },
+            Operator::If {
+                ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            },
+        ]);
+        self.unstash(results, state);
+        self.call_hook(
+            self.module_info.exit_hooks[results],
+            self.function_index,
+            state,
+        );
+        state.push_operator(Operator::End);
+        self.unstash(results, state);
+        state.push_operator(Operator::GlobalGet {
+            global_index: condition,
+        });
+    }
+}
+
+impl FunctionMiddleware for FunctionCallTracing {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.entered {
+            self.entered = true;
+
+            if self.traced {
+                self.trace_entry(state);
+            }
+        }
+
+        match operator {
+            Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => self.depth += 1,
+            Operator::End => {
+                self.depth -= 1;
+
+                if self.depth == 0 && self.traced {
+                    self.trace_exit(state);
+                }
+            }
+            Operator::Return if self.traced => self.trace_exit(state),
+            // A branch to the outermost label returns from the function.
+            // Exits through `br_table` are not reported: tracers unwind to
+            // the next reported exit instead.
+            Operator::Br { relative_depth } if self.traced && relative_depth == self.depth - 1 => {
+                self.trace_exit(state)
+            }
+            Operator::BrIf { relative_depth }
+                if self.traced && relative_depth == self.depth - 1 =>
+            {
+                self.trace_conditional_exit(state)
+            }
+            Operator::Call { function_index }
+                if (function_index as usize) < self.module_info.num_imported_functions
+                    && self
+                        .module_info
+                        .traced_functions
+                        .contains(&FunctionIndex::from_u32(function_index)) =>
+            {
+                let callee = FunctionIndex::from_u32(function_index);
+                let function_type = &self.module_info.function_types[callee.index()];
+
+                self.report(
+                    self.module_info.enter_hooks[function_type.params()],
+                    callee,
+                    function_type.params(),
+                    state,
+                );
+                state.push_operator(operator);
+                self.report(
+                    self.module_info.exit_hooks[function_type.results()],
+                    callee,
+                    function_type.results(),
+                    state,
+                );
+
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// A traced function.
+#[derive(Debug, Clone)]
+pub struct TracedCall {
+    /// The index of the function in the instrumented module.
+    pub function_index: u32,
+
+    /// The name of the function: `module.field` for imported functions,
+    /// the name from the name section or `<wasm function N>` otherwise.
+    pub name: String,
+
+    /// Whether the function is imported.
+    pub imported: bool,
+}
+
+/// Receives the entries and exits of the traced functions.
+///
+/// Exits are not reported when a function traps, or when it returns
+/// with a `br_table`. An implementation keeping a stack of calls should
+/// unwind it up to the call matching a reported exit.
+pub trait CallTracer: Send + Sync {
+    /// Called when a traced function is entered.
+    fn on_enter(&self, call: &TracedCall, arguments: &[Value]);
+
+    /// Called when a traced function returns.
+    fn on_exit(&self, call: &TracedCall, results: &[Value]);
+}
+
+thread_local! {
+    /// The spans of the traced calls of the current thread.
+    static CALL_SPANS: RefCell<Vec<(u32, tracing::span::EnteredSpan)>> = RefCell::new(Vec::new());
+}
+
+/// A `CallTracer` entering a `tracing` span for every traced call.
+///
+/// Spans are named `call` and emitted at the `TRACE` level, with the
+/// function name, whether it is imported and its arguments as fields.
+/// The results are recorded in a `return` event inside the span.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TracingCallTracer;
+
+impl CallTracer for TracingCallTracer {
+    fn on_enter(&self, call: &TracedCall, arguments: &[Value]) {
+        let span = tracing::trace_span!(
+            "call",
+            function = %call.name,
+            imported = call.imported,
+            arguments = ?arguments,
+        )
+        .entered();
+
+        CALL_SPANS.with(|spans| spans.borrow_mut().push((call.function_index, span)));
+    }
+
+    fn on_exit(&self, call: &TracedCall, results: &[Value]) {
+        CALL_SPANS.with(|spans| {
+            let mut spans = spans.borrow_mut();
+
+            if !spans.iter().any(|(index, _)| *index == call.function_index) {
+                return;
+            }
+
+            // Exit the spans of the calls which haven't been reported, up
+            // to the call that is returning.
+            while let Some((index, _span)) = spans.pop() {
+                if index == call.function_index {
+                    tracing::trace!(results = ?results, "return");
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Creates the imports satisfying the tracing hooks of `module`, which
+/// must have been compiled with the `CallTracing` middleware. The
+/// traced calls are forwarded to `tracer`.
+///
+/// The returned `ImportObject` can be chained with the other imports of
+/// the module.
+pub fn import_object(module: &Module, tracer: Arc<dyn CallTracer>) -> ImportObject {
+    let module_info = module.info();
+    let calls: Arc<Vec<TracedCall>> = Arc::new(
+        function_names(module_info)
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| TracedCall {
+                function_index: index as u32,
+                name,
+                imported: index < module_info.num_imported_functions,
+            })
+            .collect(),
+    );
+    let mut namespace = Exports::new();
+
+    for import in module.imports() {
+        if import.module() != CALL_TRACING_NAMESPACE {
+            continue;
+        }
+
+        let function_type = match import.ty() {
+            ExternType::Function(function_type) => function_type.clone(),
+            _ => continue,
+        };
+        let is_enter = import.name().starts_with("enter");
+        let calls = calls.clone();
+        let tracer = tracer.clone();
+
+        let hook = Function::new(module.store(), function_type, move |values| {
+            // The last argument is the index of the traced function.
+            if let Some((Value::I32(index), values)) = values.split_last() {
+                if let Some(call) = calls.get(*index as usize) {
+                    if is_enter {
+                        tracer.on_enter(call, values);
+                    } else {
+                        tracer.on_exit(call, values);
+                    }
+                }
+            }
+
+            Ok(vec![])
+        });
+
+        namespace.insert(import.name(), hook);
+    }
+
+    let mut import_object = ImportObject::new();
+    import_object.register(CALL_TRACING_NAMESPACE, namespace);
+
+    import_object
+}
+
+/// The names of the functions of a module, by function index.
+fn function_names(module_info: &ModuleInfo) -> Vec<String> {
+    let mut names: Vec<String> = (0..module_info.functions.len())
+        .map(|index| {
+            module_info
+                .function_names
+                .get(&FunctionIndex::new(index))
+                .cloned()
+                .unwrap_or_else(|| format!("<wasm function {}>", index))
+        })
+        .collect();
+
+    for ((module, field, _), import_index) in module_info.imports.iter() {
+        if let ImportIndex::Function(function_index) = import_index {
+            names[function_index.index()] = format!("{}.{}", module, field);
+        }
+    }
+
+    names
+}
+
+/// Whether values of `types` can be reported to the host.
+fn is_traceable(types: &[Type]) -> bool {
+    types
+        .iter()
+        .all(|ty| matches!(ty, Type::I32 | Type::I64 | Type::F32 | Type::F64))
+}
+
+/// The name of the hook of `kind` reporting values of `types`, e.g.
+/// `enter_i32_f64`.
+fn hook_name(kind: &str, types: &[Type]) -> String {
+    let mut name = kind.to_string();
+
+    for ty in types {
+        name.push('_');
+        name.push_str(&ty.to_string().to_lowercase());
+    }
+
+    name
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any sequence of
+/// characters.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+
+    if !name.starts_with(first) {
+        return false;
+    }
+
+    let parts: Vec<&str> = parts.collect();
+    let mut rest = &name[first.len()..];
+
+    let (last, middle) = match parts.split_last() {
+        Some(split) => split,
+        None => return rest.is_empty(),
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasmer::{
+        imports, wat2wasm, ChainableNamedResolver, CompilerConfig, Cranelift, Instance, Store, JIT,
+    };
+
+    #[derive(Debug, Default)]
+    struct RecordingCallTracer {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl CallTracer for RecordingCallTracer {
+        fn on_enter(&self, call: &TracedCall, arguments: &[Value]) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("enter {} {:?}", call.name, arguments));
+        }
+
+        fn on_exit(&self, call: &TracedCall, results: &[Value]) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("exit {} {:?}", call.name, results));
+        }
+    }
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (import "host" "double" (func $double (param i32) (result i32)))
+            (func $add_one (export "add_one") (param $value i32) (result i32)
+                local.get $value
+                i32.const 1
+                i32.add)
+            (func $compute (export "compute") (param $value i32) (result i32)
+                local.get $value
+                i32.eqz
+                if
+                    i32.const -1
+                    return
+                end
+                local.get $value
+                call $double
+                call $add_one))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn run(filters: Vec<&str>, value: i32) -> (i32, Vec<String>) {
+        let call_tracing = Arc::new(CallTracing::new(
+            filters.into_iter().map(String::from).collect(),
+        ));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(call_tracing);
+        let store = Store::new(&JIT::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+
+        let tracer = Arc::new(RecordingCallTracer::default());
+        let double = Function::new_native(&store, |value: i32| value * 2);
+        let imports = imports! {
+            "host" => {
+                "double" => double,
+            },
+        }
+        .chain_back(import_object(&module, tracer.clone()));
+        let instance = Instance::new(&module, &imports).unwrap();
+
+        let compute = instance
+            .exports
+            .get_native_function::<i32, i32>("compute")
+            .unwrap();
+        let result = compute.call(value).unwrap();
+        let events = tracer.events.lock().unwrap().clone();
+
+        (result, events)
+    }
+
+    #[test]
+    fn traces_calls() {
+        let (result, events) = run(vec![], 5);
+
+        assert_eq!(result, 11);
+        assert_eq!(
+            events,
+            vec![
+                "enter compute [I32(5)]",
+                "enter host.double [I32(5)]",
+                "exit host.double [I32(10)]",
+                "enter add_one [I32(10)]",
+                "exit add_one [I32(11)]",
+                "exit compute [I32(11)]",
+            ]
+        );
+
+        let (result, events) = run(vec![], 0);
+
+        assert_eq!(result, -1);
+        assert_eq!(
+            events,
+            vec!["enter compute [I32(0)]", "exit compute [I32(-1)]"]
+        );
+    }
+
+    #[test]
+    fn filters_calls() {
+        let (result, events) = run(vec!["host.*", "add_*"], 5);
+
+        assert_eq!(result, 11);
+        assert_eq!(
+            events,
+            vec![
+                "enter host.double [I32(5)]",
+                "exit host.double [I32(10)]",
+                "enter add_one [I32(10)]",
+                "exit add_one [I32(11)]",
+            ]
+        );
+    }
+
+    #[test]
+    fn pattern_matching() {
+        assert!(matches_pattern("add_one", "add_one"));
+        assert!(!matches_pattern("add_one", "add_one_more"));
+        assert!(matches_pattern("add_*", "add_one"));
+        assert!(matches_pattern("*_one", "add_one"));
+        assert!(matches_pattern("a*d*e", "add_one"));
+        assert!(!matches_pattern("a*x*e", "add_one"));
+        assert!(matches_pattern("*", ""));
+    }
+}
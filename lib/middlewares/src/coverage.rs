@@ -0,0 +1,640 @@
+//! `coverage` is a middleware for recording which basic blocks of a
+//! module have been executed, and reporting them in the lcov or JSON
+//! formats.
+//!
+//! Every local function gets a bitmap of `words_per_function` 64-bit
+//! globals. Each time a basic block is entered, its bit is set in the
+//! bitmap. Basic blocks are identified by the function they belong to
+//! and the offset of their first operator in the module bytes.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::Operator;
+use wasmer::{
+    ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance, LocalFunctionIndex,
+    MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type, Value,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{FunctionIndex, GlobalIndex};
+use wasmer_vm::ModuleInfo;
+
+/// The default number of 64-bit words used to record the basic blocks
+/// of a single function.
+const DEFAULT_WORDS_PER_FUNCTION: u32 = 4;
+
+/// Module-level information gathered in `transform_module_info`.
+#[derive(Debug, Clone)]
+struct CoverageModuleInfo {
+    /// The name of the module, if any.
+    module_name: Option<String>,
+
+    /// The names of the local functions, if any.
+    function_names: HashMap<LocalFunctionIndex, String>,
+
+    /// Number of imported functions in the module.
+    num_imported_functions: usize,
+
+    /// Number of local functions in the module.
+    num_local_functions: usize,
+
+    /// The index of the first global of the coverage bitmap.
+    first_global_index: GlobalIndex,
+}
+
+/// The basic blocks found in each local function, described by the
+/// offset of their first operator.
+type CoverageBlocks = Arc<Mutex<HashMap<LocalFunctionIndex, Vec<usize>>>>;
+
+/// The module-level coverage middleware.
+///
+/// # Panic
+///
+/// An instance of `Coverage` should not be shared among different modules, since it tracks
+/// module-specific information like the global indexes of the coverage bitmap. Attempts to use
+/// a `Coverage` instance from multiple modules will result in a panic.
+///
+/// The basic blocks are collected while the module is compiled, so a
+/// report can only be produced for instances of a module compiled
+/// with this middleware in the current process (i.e. not for
+/// deserialized modules).
+#[derive(Debug)]
+pub struct Coverage {
+    /// Number of 64-bit words of the bitmap of each function.
+    words_per_function: u32,
+
+    /// Module-level information, set by `transform_module_info`.
+    module_info: Mutex<Option<CoverageModuleInfo>>,
+
+    /// The basic blocks collected by the function middlewares.
+    blocks: CoverageBlocks,
+}
+
+/// The function-level coverage middleware.
+#[derive(Debug)]
+pub struct FunctionCoverage {
+    /// The index of the function being instrumented.
+    local_function_index: LocalFunctionIndex,
+
+    /// The global index of the first word of the function bitmap.
+    first_global_index: GlobalIndex,
+
+    /// Number of 64-bit words of the function bitmap.
+    words: u32,
+
+    /// Current nesting depth of control blocks, including the function body.
+    depth: u32,
+
+    /// Whether the next operator starts a new basic block.
+    block_start_pending: bool,
+
+    /// The offsets of the basic blocks instrumented so far.
+    block_offsets: Vec<usize>,
+
+    /// The shared basic blocks, filled when this middleware is dropped.
+    blocks: CoverageBlocks,
+}
+
+impl Coverage {
+    /// Creates a `Coverage` middleware.
+    pub fn new() -> Self {
+        Self::with_words_per_function(DEFAULT_WORDS_PER_FUNCTION)
+    }
+
+    /// Creates a `Coverage` middleware, using `words` 64-bit globals
+    /// per function to record basic blocks hits.
+    ///
+    /// Each function can track up to `64 * words` basic blocks, the
+    /// following ones are neither instrumented nor reported.
+    pub fn with_words_per_function(words: u32) -> Self {
+        Self {
+            words_per_function: words,
+            module_info: Mutex::new(None),
+            blocks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Collects the coverage of an `Instance`.
+    ///
+    /// # Panic
+    ///
+    /// The instance Module must have been compiled with this middleware,
+    /// otherwise this will panic.
+    pub fn report(&self, instance: &Instance) -> CoverageReport {
+        let module_info = self
+            .module_info
+            .lock()
+            .unwrap()
+            .clone()
+            .expect("Coverage::report: the middleware has not been applied to any module");
+        let blocks = self.blocks.lock().unwrap();
+
+        let functions = (0..module_info.num_local_functions)
+            .map(LocalFunctionIndex::new)
+            .map(|local_function_index| {
+                let bitmap = (0..self.words_per_function)
+                    .map(|word| {
+                        let value = instance
+                            .exports
+                            .get_global(&coverage_global_name(local_function_index, word))
+                            .expect("Can't get the coverage bitmap from Instance")
+                            .get();
+
+                        match value {
+                            Value::I64(bits) => bits as u64,
+                            _ => panic!("The coverage bitmap from Instance has wrong type"),
+                        }
+                    })
+                    .collect::<Vec<u64>>();
+
+                let blocks = blocks
+                    .get(&local_function_index)
+                    .map(|offsets| {
+                        offsets
+                            .iter()
+                            .enumerate()
+                            .map(|(block, offset)| BlockCoverage {
+                                offset: *offset,
+                                hit: bitmap[block / 64] & (1 << (block % 64)) != 0,
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                FunctionCoverageReport {
+                    function_index: FunctionIndex::new(
+                        module_info.num_imported_functions + local_function_index.index(),
+                    ),
+                    name: module_info
+                        .function_names
+                        .get(&local_function_index)
+                        .cloned(),
+                    blocks,
+                }
+            })
+            .collect();
+
+        CoverageReport {
+            module_name: module_info.module_name,
+            functions,
+        }
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleMiddleware for Coverage {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let module_info = self.module_info.lock().unwrap();
+        let first_global_index = module_info.as_ref().unwrap().first_global_index;
+
+        Box::new(FunctionCoverage {
+            local_function_index,
+            first_global_index: GlobalIndex::from_u32(
+                first_global_index.as_u32()
+                    + local_function_index.as_u32() * self.words_per_function,
+            ),
+            words: self.words_per_function,
+            depth: 1,
+            block_start_pending: true,
+            block_offsets: vec![],
+            blocks: self.blocks.clone(),
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut coverage_module_info = self.module_info.lock().unwrap();
+
+        if coverage_module_info.is_some() {
+            panic!("Coverage::transform_module_info: Attempting to use a `Coverage` middleware from multiple modules.");
+        }
+
+        let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+        let first_global_index = module_info.globals.next_key();
+
+        // Append the bitmap globals of every local function, and export them.
+        for local_function_index in (0..num_local_functions).map(LocalFunctionIndex::new) {
+            for word in 0..self.words_per_function {
+                let global_index = module_info
+                    .globals
+                    .push(GlobalType::new(Type::I64, Mutability::Var));
+
+                module_info
+                    .global_initializers
+                    .push(GlobalInit::I64Const(0));
+
+                module_info.exports.insert(
+                    coverage_global_name(local_function_index, word),
+                    ExportIndex::Global(global_index),
+                );
+            }
+        }
+
+        let function_names = module_info
+            .function_names
+            .iter()
+            .filter_map(|(function_index, name)| {
+                module_info
+                    .local_func_index(*function_index)
+                    .map(|local_function_index| (local_function_index, name.clone()))
+            })
+            .collect();
+
+        *coverage_module_info = Some(CoverageModuleInfo {
+            module_name: module_info.name.clone(),
+            function_names,
+            num_imported_functions: module_info.num_imported_functions,
+            num_local_functions,
+            first_global_index,
+        });
+    }
+}
+
+impl FunctionCoverage {
+    /// Records a new basic block starting at `offset`, and sets its
+    /// bit in the function bitmap when it is entered.
+    fn instrument_block(&mut self, offset: usize, state: &mut MiddlewareReaderState<'_>) {
+        let block = self.block_offsets.len() as u32;
+
+        if block >= self.words * 64 {
+            return;
+        }
+
+        self.block_offsets.push(offset);
+
+        let global_index = self.first_global_index.as_u32() + block / 64;
+
+        state.extend(&[
+            // globals[word] |= 1 << bit;
+            Operator::GlobalGet { global_index },
+            Operator::I64Const {
+                value: (1u64 << (block % 64)) as i64,
+            },
+            Operator::I64Or,
+            Operator::GlobalSet { global_index },
+        ]);
+    }
+}
+
+impl FunctionMiddleware for FunctionCoverage {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if self.block_start_pending {
+            self.block_start_pending = false;
+            self.instrument_block(state.operator_offset(), state);
+        }
+
+        match operator {
+            Operator::Block { .. } => self.depth += 1,
+            Operator::Loop { .. } // loop headers are branch targets
+            | Operator::If { .. } => { // the "then" branch
+                self.depth += 1;
+                self.block_start_pending = true;
+            }
+            Operator::Else // the "else" branch
+            | Operator::BrIf { .. } // the fall-through of a conditional branch
+            => self.block_start_pending = true,
+            Operator::End => {
+                self.depth -= 1;
+
+                // Block ends are branch targets, except the end of the function.
+                self.block_start_pending = self.depth > 0;
+            }
+            _ => {}
+        }
+
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+impl Drop for FunctionCoverage {
+    fn drop(&mut self) {
+        if let Ok(mut blocks) = self.blocks.lock() {
+            blocks.insert(
+                self.local_function_index,
+                std::mem::take(&mut self.block_offsets),
+            );
+        }
+    }
+}
+
+/// The name of the exported global holding the `word`-th word of the
+/// bitmap of a function.
+fn coverage_global_name(local_function_index: LocalFunctionIndex, word: u32) -> String {
+    format!("wasmer_coverage_{}_{}", local_function_index.as_u32(), word)
+}
+
+/// The coverage of a basic block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockCoverage {
+    /// The offset of the first operator of the block in the module bytes.
+    pub offset: usize,
+
+    /// Whether the block has been executed.
+    pub hit: bool,
+}
+
+/// The coverage of a local function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCoverageReport {
+    /// The index of the function in the module.
+    pub function_index: FunctionIndex,
+
+    /// The name of the function, from the `name` section.
+    pub name: Option<String>,
+
+    /// The basic blocks of the function, in order.
+    pub blocks: Vec<BlockCoverage>,
+}
+
+impl FunctionCoverageReport {
+    /// The name of the function, or a name derived from its index if
+    /// the module has no `name` section.
+    pub fn display_name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("<wasm function {}>", self.function_index.as_u32()))
+    }
+
+    /// Whether the function has been called at least once.
+    pub fn is_hit(&self) -> bool {
+        self.blocks.first().map_or(false, |block| block.hit)
+    }
+}
+
+/// The coverage of a module instance, as returned by [`Coverage::report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageReport {
+    /// The name of the module, from the `name` section.
+    pub module_name: Option<String>,
+
+    /// The coverage of every local function.
+    pub functions: Vec<FunctionCoverageReport>,
+}
+
+impl CoverageReport {
+    /// Returns the number of basic blocks, and the number of basic
+    /// blocks that have been executed.
+    pub fn block_counts(&self) -> (usize, usize) {
+        self.functions
+            .iter()
+            .flat_map(|function| function.blocks.iter())
+            .fold((0, 0), |(found, hit), block| {
+                (found + 1, hit + block.hit as usize)
+            })
+    }
+
+    /// Formats the report as a lcov tracefile.
+    ///
+    /// As there is no source information, the module is used as the
+    /// source file, and the offsets of basic blocks in the module bytes
+    /// are used as line numbers.
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        let mut lines = BTreeMap::new();
+
+        writeln!(lcov, "TN:").unwrap();
+        writeln!(
+            lcov,
+            "SF:{}",
+            self.module_name.as_deref().unwrap_or("module.wasm")
+        )
+        .unwrap();
+
+        for function in self.functions.iter().filter(|f| !f.blocks.is_empty()) {
+            writeln!(
+                lcov,
+                "FN:{},{}",
+                function.blocks[0].offset,
+                function.display_name()
+            )
+            .unwrap();
+        }
+
+        for function in self.functions.iter().filter(|f| !f.blocks.is_empty()) {
+            writeln!(
+                lcov,
+                "FNDA:{},{}",
+                function.is_hit() as u8,
+                function.display_name()
+            )
+            .unwrap();
+
+            for block in &function.blocks {
+                *lines.entry(block.offset).or_insert(false) |= block.hit;
+            }
+        }
+
+        let functions_found = self.functions.iter().filter(|f| !f.blocks.is_empty());
+        writeln!(lcov, "FNF:{}", functions_found.clone().count()).unwrap();
+        writeln!(
+            lcov,
+            "FNH:{}",
+            functions_found.filter(|f| f.is_hit()).count()
+        )
+        .unwrap();
+
+        for (offset, hit) in &lines {
+            writeln!(lcov, "DA:{},{}", offset, *hit as u8).unwrap();
+        }
+
+        writeln!(lcov, "LF:{}", lines.len()).unwrap();
+        writeln!(lcov, "LH:{}", lines.values().filter(|hit| **hit).count()).unwrap();
+        writeln!(lcov, "end_of_record").unwrap();
+
+        lcov
+    }
+
+    /// Formats the report as a JSON document.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+
+        write!(json, "{{\"module\":").unwrap();
+        write_json_option_string(&mut json, self.module_name.as_deref());
+        write!(json, ",\"functions\":[").unwrap();
+
+        for (nth, function) in self.functions.iter().enumerate() {
+            if nth > 0 {
+                json.push(',');
+            }
+
+            write!(
+                json,
+                "{{\"index\":{},\"name\":",
+                function.function_index.as_u32()
+            )
+            .unwrap();
+            write_json_option_string(&mut json, function.name.as_deref());
+            write!(json, ",\"blocks\":[").unwrap();
+
+            for (nth, block) in function.blocks.iter().enumerate() {
+                if nth > 0 {
+                    json.push(',');
+                }
+
+                write!(
+                    json,
+                    "{{\"offset\":{},\"hit\":{}}}",
+                    block.offset, block.hit
+                )
+                .unwrap();
+            }
+
+            write!(json, "]}}").unwrap();
+        }
+
+        write!(json, "]}}").unwrap();
+
+        json
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (found, hit) = self.block_counts();
+
+        write!(f, "{}/{} basic blocks covered", hit, found)
+    }
+}
+
+/// Writes an optional string as a JSON string or `null`.
+fn write_json_option_string(json: &mut String, string: Option<&str>) {
+    let string = match string {
+        Some(string) => string,
+        None => {
+            json.push_str("null");
+            return;
+        }
+    };
+
+    json.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, JIT};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (func $abs (export "abs") (param $value i32) (result i32)
+                local.get $value
+                i32.const 0
+                i32.lt_s
+                if (result i32)
+                    i32.const 0
+                    local.get $value
+                    i32.sub
+                else
+                    local.get $value
+                end)
+            (func $unused (export "unused") (result i32)
+                i32.const 42))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn instance(coverage: Arc<Coverage>) -> Instance {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(coverage);
+        let store = Store::new(&JIT::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+
+        Instance::new(&module, &imports! {}).unwrap()
+    }
+
+    #[test]
+    fn report_works() {
+        let coverage = Arc::new(Coverage::new());
+        let instance = instance(coverage.clone());
+
+        // Nothing has been executed yet.
+        let report = coverage.report(&instance);
+        assert_eq!(report.functions.len(), 2);
+        // `abs` has 4 blocks: the entry, the "then" and "else" branches,
+        // and the continuation after `end`. `unused` only has its entry.
+        assert_eq!(report.block_counts(), (5, 0));
+
+        let abs = instance
+            .exports
+            .get_function("abs")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+
+        assert_eq!(abs.call(3).unwrap(), 3);
+
+        let report = coverage.report(&instance);
+        let hits = report.functions[0]
+            .blocks
+            .iter()
+            .map(|block| block.hit)
+            .collect::<Vec<_>>();
+        assert_eq!(hits, vec![true, false, true, true]);
+        assert!(!report.functions[1].is_hit());
+
+        assert_eq!(abs.call(-3).unwrap(), 3);
+        assert_eq!(coverage.report(&instance).block_counts(), (5, 4));
+    }
+
+    #[test]
+    fn lcov_and_json_outputs() {
+        let coverage = Arc::new(Coverage::new());
+        let instance = instance(coverage.clone());
+
+        let abs = instance
+            .exports
+            .get_function("abs")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+        abs.call(-1).unwrap();
+
+        let report = coverage.report(&instance);
+
+        let lcov = report.to_lcov();
+        assert!(lcov.starts_with("TN:\nSF:module.wasm\n"));
+        assert!(lcov.contains("FNDA:1,abs\n"));
+        assert!(lcov.contains("FNDA:0,unused\n"));
+        assert!(lcov.contains("FNF:2\nFNH:1\n"));
+        assert!(lcov.ends_with("end_of_record\n"));
+
+        let json = report.to_json();
+        assert!(json.starts_with("{\"module\":null,\"functions\":[{\"index\":0,\"name\":\"abs\""));
+        assert!(json.contains("\"name\":\"unused\",\"blocks\":[{\"offset\":"));
+    }
+}
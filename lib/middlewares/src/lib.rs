@@ -1,5 +1,11 @@
+pub mod call_tracing;
+pub mod coverage;
+pub mod memory_tracing;
 pub mod metering;
 
 // The most commonly used symbol are exported at top level of the module. Others are available
 // via modules, e.g. `wasmer_middlewares::metering::get_remaining_points`
+pub use call_tracing::CallTracing;
+pub use coverage::Coverage;
+pub use memory_tracing::MemoryTracing;
 pub use metering::Metering;
@@ -0,0 +1,947 @@
+//! `memory_tracing` is a middleware for tracing the memory accesses of
+//! a module: loads, stores and, optionally, bulk memory operations.
+//!
+//! Every traced access calls a host hook imported from the
+//! `wasmer_memory_tracing` namespace, with the kind of the access, its
+//! effective address, its size and its value. Those imports are
+//! satisfied by [`import_object`], which forwards the accesses to a
+//! [`MemoryAccessTracer`], e.g. a [`RingBuffer`] keeping the latest
+//! accesses.
+//!
+//! Address ranges can be given to only trace the accesses overlapping
+//! them; the check is done in WebAssembly, so that other accesses don't
+//! call the host.
+//!
+//! SIMD and atomic accesses are not traced.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::{
+    Exports, ExternType, Function, FunctionMiddleware, FunctionType, GlobalInit, GlobalType,
+    ImportObject, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, Module,
+    ModuleAdditions, ModuleMiddleware, Mutability, Type, Value,
+};
+use wasmer_types::FunctionIndex;
+use wasmer_vm::ModuleInfo;
+
+/// The namespace of the host hook imported by the traced modules.
+pub const MEMORY_TRACING_NAMESPACE: &str = "wasmer_memory_tracing";
+
+/// The name of the host hook imported by the traced modules.
+const HOOK_NAME: &str = "access";
+
+/// The kind of a memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccessKind {
+    /// A load, whose value is the loaded bytes, before the sign
+    /// extension of `i32.load8_s` and the like.
+    Load,
+
+    /// A store, whose value is the stored bytes, i.e. the value wrapped
+    /// by `i32.store8` and the like.
+    Store,
+
+    /// A `memory.fill`, whose value is the byte written.
+    Fill,
+
+    /// A `memory.copy`, whose address is the destination and value is
+    /// the source address.
+    Copy,
+
+    /// A `memory.init`, whose value is the index of the data segment.
+    Init,
+}
+
+impl MemoryAccessKind {
+    fn as_i32(self) -> i32 {
+        match self {
+            Self::Load => 0,
+            Self::Store => 1,
+            Self::Fill => 2,
+            Self::Copy => 3,
+            Self::Init => 4,
+        }
+    }
+
+    fn from_i32(kind: i32) -> Option<Self> {
+        Some(match kind {
+            0 => Self::Load,
+            1 => Self::Store,
+            2 => Self::Fill,
+            3 => Self::Copy,
+            4 => Self::Init,
+            _ => return None,
+        })
+    }
+}
+
+/// A memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    /// The kind of the access.
+    pub kind: MemoryAccessKind,
+
+    /// The effective address of the access, i.e. including the static
+    /// offset of loads and stores.
+    pub address: u64,
+
+    /// The number of bytes accessed.
+    pub size: u32,
+
+    /// The value of the access, see [`MemoryAccessKind`]. Floats are
+    /// given by their bits; the loaded and stored bytes are
+    /// zero-extended, even for the sign-extending loads.
+    pub value: u64,
+}
+
+/// Receives the memory accesses of the traced modules.
+pub trait MemoryAccessTracer: Send + Sync {
+    /// Called for every traced memory access. Loads are reported after
+    /// they happen, stores and bulk operations before.
+    fn on_access(&self, access: &MemoryAccess);
+}
+
+impl<F> MemoryAccessTracer for F
+where
+    F: Fn(&MemoryAccess) + Send + Sync,
+{
+    fn on_access(&self, access: &MemoryAccess) {
+        self(access)
+    }
+}
+
+/// A `MemoryAccessTracer` keeping the latest `capacity` accesses.
+#[derive(Debug)]
+pub struct RingBuffer {
+    capacity: usize,
+    accesses: Mutex<VecDeque<MemoryAccess>>,
+}
+
+impl RingBuffer {
+    /// Creates a `RingBuffer` keeping the latest `capacity` accesses.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            accesses: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// The recorded accesses, from the oldest to the latest.
+    pub fn accesses(&self) -> Vec<MemoryAccess> {
+        self.accesses.lock().unwrap().iter().copied().collect()
+    }
+
+    /// Forgets the recorded accesses.
+    pub fn clear(&self) {
+        self.accesses.lock().unwrap().clear();
+    }
+}
+
+impl MemoryAccessTracer for RingBuffer {
+    fn on_access(&self, access: &MemoryAccess) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut accesses = self.accesses.lock().unwrap();
+
+        if accesses.len() == self.capacity {
+            accesses.pop_front();
+        }
+
+        accesses.push_back(*access);
+    }
+}
+
+/// Module-level information gathered while the module is transformed.
+#[derive(Debug, Clone)]
+struct MemoryTracingModuleInfo {
+    /// The host hook, if the module has memories.
+    hook: Option<FunctionIndex>,
+
+    /// Scratch globals holding the address and the other operands of
+    /// the accesses.
+    address_global: u32,
+    operand_globals: [u32; 2],
+
+    /// Scratch global holding the effective address.
+    effective_address_global: u32,
+
+    /// Scratch globals holding the values, for `i32`, `i64`, `f32` and `f64`.
+    value_globals: [u32; 4],
+}
+
+/// The module-level memory tracing middleware.
+///
+/// # Panic
+///
+/// An instance of `MemoryTracing` should not be shared among different modules, since it tracks
+/// module-specific information like the index of the tracing hook. Attempts to use
+/// a `MemoryTracing` instance from multiple modules will result in a panic.
+#[derive(Debug, Default)]
+pub struct MemoryTracing {
+    /// Only the accesses overlapping these ranges are traced, if any.
+    address_ranges: Vec<Range<u64>>,
+
+    /// Whether bulk memory operations are traced.
+    bulk_memory: bool,
+
+    /// Module-level information, set while the module is transformed.
+    module_info: Mutex<Option<Arc<MemoryTracingModuleInfo>>>,
+}
+
+/// The function-level memory tracing middleware.
+#[derive(Debug)]
+pub struct FunctionMemoryTracing {
+    /// Module-level information.
+    module_info: Arc<MemoryTracingModuleInfo>,
+
+    /// Only the accesses overlapping these ranges are traced, if any.
+    address_ranges: Vec<Range<u64>>,
+
+    /// Whether bulk memory operations are traced.
+    bulk_memory: bool,
+}
+
+impl MemoryTracing {
+    /// Creates a `MemoryTracing` middleware tracing all the loads and
+    /// stores.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only traces the accesses overlapping `range`, and those
+    /// overlapping the other ranges given with this method.
+    pub fn with_address_range(mut self, range: Range<u64>) -> Self {
+        self.address_ranges.push(range);
+        self
+    }
+
+    /// Whether to also trace `memory.fill`, `memory.copy` and
+    /// `memory.init`.
+    pub fn with_bulk_memory(mut self, bulk_memory: bool) -> Self {
+        self.bulk_memory = bulk_memory;
+        self
+    }
+}
+
+impl ModuleMiddleware for MemoryTracing {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionMemoryTracing {
+            module_info: self.module_info.lock().unwrap().clone().unwrap(),
+            address_ranges: self.address_ranges.clone(),
+            bulk_memory: self.bulk_memory,
+        })
+    }
+
+    /// Imports the host hook, if the module has memories.
+    fn add_imports(&self, module_info: &ModuleInfo, additions: &mut ModuleAdditions) {
+        let mut memory_tracing_module_info = self.module_info.lock().unwrap();
+
+        if memory_tracing_module_info.is_some() {
+            panic!("MemoryTracing::add_imports: Attempting to use a `MemoryTracing` middleware from multiple modules.");
+        }
+
+        let hook = if module_info.memories.is_empty() {
+            None
+        } else {
+            Some(additions.add_function_import(
+                MEMORY_TRACING_NAMESPACE,
+                HOOK_NAME,
+                FunctionType::new(vec![Type::I32, Type::I64, Type::I32, Type::I64], vec![]),
+            ))
+        };
+
+        *memory_tracing_module_info = Some(Arc::new(MemoryTracingModuleInfo {
+            hook,
+            address_global: 0,
+            operand_globals: [0; 2],
+            effective_address_global: 0,
+            value_globals: [0; 4],
+        }));
+    }
+
+    /// Adds the scratch globals used to report the accesses.
+    fn add_definitions(&self, _: &ModuleInfo, additions: &mut ModuleAdditions) {
+        let mut memory_tracing_module_info = self.module_info.lock().unwrap();
+        let memory_tracing_module_info = Arc::get_mut(memory_tracing_module_info.as_mut().unwrap())
+            .expect("MemoryTracing::add_definitions: the middleware is already in use");
+
+        if memory_tracing_module_info.hook.is_none() {
+            return;
+        }
+
+        let mut add_global = |ty: Type, init: GlobalInit| -> u32 {
+            additions
+                .add_global(GlobalType::new(ty, Mutability::Var), init)
+                .as_u32()
+        };
+
+        memory_tracing_module_info.address_global = add_global(Type::I32, GlobalInit::I32Const(0));
+        memory_tracing_module_info.operand_globals = [
+            add_global(Type::I32, GlobalInit::I32Const(0)),
+            add_global(Type::I32, GlobalInit::I32Const(0)),
+        ];
+        memory_tracing_module_info.effective_address_global =
+            add_global(Type::I64, GlobalInit::I64Const(0));
+        memory_tracing_module_info.value_globals = [
+            add_global(Type::I32, GlobalInit::I32Const(0)),
+            add_global(Type::I64, GlobalInit::I64Const(0)),
+            add_global(Type::F32, GlobalInit::F32Const(0.0)),
+            add_global(Type::F64, GlobalInit::F64Const(0.0)),
+        ];
+    }
+}
+
+/// The size of an access.
+#[derive(Clone, Copy)]
+enum AccessSize {
+    /// A static size, for loads and stores.
+    Static(u32),
+
+    /// A size held by an `i32` global, for bulk memory operations.
+    Dynamic(u32),
+}
+
+/// The value of an access.
+#[derive(Clone, Copy)]
+enum AccessValue {
+    /// A value held by a global of the given type.
+    Global(u32, Type),
+
+    /// A static value.
+    Static(i64),
+}
+
+impl FunctionMemoryTracing {
+    /// The scratch global holding values of `ty`.
+    fn value_global(&self, ty: Type) -> u32 {
+        let globals = &self.module_info.value_globals;
+
+        match ty {
+            Type::I32 => globals[0],
+            Type::I64 => globals[1],
+            Type::F32 => globals[2],
+            _ => globals[3],
+        }
+    }
+
+    /// Reports an access at the address held by `address_global`, plus
+    /// `offset`, if it overlaps one of the address ranges.
+    fn report(
+        &self,
+        kind: MemoryAccessKind,
+        address_global: u32,
+        offset: u32,
+        size: AccessSize,
+        value: AccessValue,
+        state: &mut MiddlewareReaderState<'_>,
+    ) {
+        let hook = self.module_info.hook.unwrap().as_u32();
+        let effective_address = self.module_info.effective_address_global;
+
+        let push_size = |state: &mut MiddlewareReaderState<'_>| match size {
+            AccessSize::Static(size) => {
+                state.push_operator(Operator::I32Const { value: size as i32 })
+            }
+            AccessSize::Dynamic(global_index) => {
+                state.push_operator(Operator::GlobalGet { global_index })
+            }
+        };
+
+        // effective_address = (address as u64) + offset;
+        state.extend(&[
+            Operator::GlobalGet {
+                global_index: address_global,
+            },
+            Operator::I64ExtendI32U,
+            Operator::I64Const {
+                value: offset as i64,
+            },
+            Operator::I64Add,
+            Operator::GlobalSet {
+                global_index: effective_address,
+            },
+        ]);
+
+        if !self.address_ranges.is_empty() {
+            // Check if [effective_address, effective_address + size)
+            // overlaps one of the ranges.
+            for (index, range) in self.address_ranges.iter().enumerate() {
+                state.extend(&[
+                    Operator::GlobalGet {
+                        global_index: effective_address,
+                    },
+                    Operator::I64Const {
+                        value: range.end as i64,
+                    },
+                    Operator::I64LtU,
+                    Operator::GlobalGet {
+                        global_index: effective_address,
+                    },
+                ]);
+                push_size(state);
+                state.extend(&[
+                    Operator::I64ExtendI32U,
+                    Operator::I64Add,
+                    Operator::I64Const {
+                        value: range.start as i64,
+                    },
+                    Operator::I64GtU,
+                    Operator::I32And,
+                ]);
+
+                if index > 0 {
+                    state.push_operator(Operator::I32Or);
+                }
+            }
+
+            state.push_operator(Operator::If {
+                ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            });
+        }
+
+        state.extend(&[
+            Operator::I32Const {
+                value: kind.as_i32(),
+            },
+            Operator::GlobalGet {
+                global_index: effective_address,
+            },
+        ]);
+        push_size(state);
+
+        match value {
+            AccessValue::Global(global_index, ty) => {
+                state.push_operator(Operator::GlobalGet { global_index });
+
+                match ty {
+                    Type::I32 => state.push_operator(Operator::I64ExtendI32U),
+                    Type::F32 => {
+                        state.extend(&[Operator::I32ReinterpretF32, Operator::I64ExtendI32U])
+                    }
+                    Type::F64 => state.push_operator(Operator::I64ReinterpretF64),
+                    _ => {}
+                }
+
+                // keep the accessed bytes only, e.g. before the sign
+                // extension of the narrow loads
+                if let AccessSize::Static(size) = size {
+                    if size < 8 {
+                        state.extend(&[
+                            Operator::I64Const {
+                                value: (1 << (size * 8)) - 1,
+                            },
+                            Operator::I64And,
+                        ]);
+                    }
+                }
+            }
+            AccessValue::Static(value) => state.push_operator(Operator::I64Const { value }),
+        }
+
+        state.push_operator(Operator::Call {
+            function_index: hook,
+        });
+
+        if !self.address_ranges.is_empty() {
+            state.push_operator(Operator::End);
+        }
+    }
+
+    /// Traces a load of `size` bytes producing a value of `ty`.
+    fn trace_load<'a>(
+        &self,
+        operator: Operator<'a>,
+        offset: u32,
+        size: u32,
+        ty: Type,
+        state: &mut MiddlewareReaderState<'a>,
+    ) {
+        let address = self.module_info.address_global;
+        let value = self.value_global(ty);
+
+        state.extend(&[
+            Operator::GlobalSet {
+                global_index: address,
+            },
+            Operator::GlobalGet {
+                global_index: address,
+            },
+        ]);
+        state.push_operator(operator);
+        state.push_operator(Operator::GlobalSet {
+            global_index: value,
+        });
+        self.report(
+            MemoryAccessKind::Load,
+            address,
+            offset,
+            AccessSize::Static(size),
+            AccessValue::Global(value, ty),
+            state,
+        );
+        state.push_operator(Operator::GlobalGet {
+            global_index: value,
+        });
+    }
+
+    /// Traces a store of `size` bytes of a value of `ty`.
+    fn trace_store<'a>(
+        &self,
+        operator: Operator<'a>,
+        offset: u32,
+        size: u32,
+        ty: Type,
+        state: &mut MiddlewareReaderState<'a>,
+    ) {
+        let address = self.module_info.address_global;
+        let value = self.value_global(ty);
+
+        state.extend(&[
+            Operator::GlobalSet {
+                global_index: value,
+            },
+            Operator::GlobalSet {
+                global_index: address,
+            },
+        ]);
+        self.report(
+            MemoryAccessKind::Store,
+            address,
+            offset,
+            AccessSize::Static(size),
+            AccessValue::Global(value, ty),
+            state,
+        );
+        state.extend(&[
+            Operator::GlobalGet {
+                global_index: address,
+            },
+            Operator::GlobalGet {
+                global_index: value,
+            },
+        ]);
+        state.push_operator(operator);
+    }
+
+    /// Traces a bulk memory operation taking an `i32` destination
+    /// address, another `i32` operand and an `i32` length.
+    fn trace_bulk<'a>(
+        &self,
+        operator: Operator<'a>,
+        kind: MemoryAccessKind,
+        value: Option<i64>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) {
+        let address = self.module_info.address_global;
+        let [operand, length] = self.module_info.operand_globals;
+
+        state.extend(&[
+            Operator::GlobalSet {
+                global_index: length,
+            },
+            Operator::GlobalSet {
+                global_index: operand,
+            },
+            Operator::GlobalSet {
+                global_index: address,
+            },
+        ]);
+        self.report(
+            kind,
+            address,
+            0,
+            AccessSize::Dynamic(length),
+            match value {
+                Some(value) => AccessValue::Static(value),
+                None => AccessValue::Global(operand, Type::I32),
+            },
+            state,
+        );
+        state.extend(&[
+            Operator::GlobalGet {
+                global_index: address,
+            },
+            Operator::GlobalGet {
+                global_index: operand,
+            },
+            Operator::GlobalGet {
+                global_index: length,
+            },
+        ]);
+        state.push_operator(operator);
+    }
+}
+
+impl FunctionMiddleware for FunctionMemoryTracing {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if self.module_info.hook.is_none() {
+            state.push_operator(operator);
+            return Ok(());
+        }
+
+        match operator {
+            Operator::I32Load { memarg } => {
+                self.trace_load(operator, memarg.offset, 4, Type::I32, state)
+            }
+            Operator::I64Load { memarg } => {
+                self.trace_load(operator, memarg.offset, 8, Type::I64, state)
+            }
+            Operator::F32Load { memarg } => {
+                self.trace_load(operator, memarg.offset, 4, Type::F32, state)
+            }
+            Operator::F64Load { memarg } => {
+                self.trace_load(operator, memarg.offset, 8, Type::F64, state)
+            }
+            Operator::I32Load8S { memarg } | Operator::I32Load8U { memarg } => {
+                self.trace_load(operator, memarg.offset, 1, Type::I32, state)
+            }
+            Operator::I32Load16S { memarg } | Operator::I32Load16U { memarg } => {
+                self.trace_load(operator, memarg.offset, 2, Type::I32, state)
+            }
+            Operator::I64Load8S { memarg } | Operator::I64Load8U { memarg } => {
+                self.trace_load(operator, memarg.offset, 1, Type::I64, state)
+            }
+            Operator::I64Load16S { memarg } | Operator::I64Load16U { memarg } => {
+                self.trace_load(operator, memarg.offset, 2, Type::I64, state)
+            }
+            Operator::I64Load32S { memarg } | Operator::I64Load32U { memarg } => {
+                self.trace_load(operator, memarg.offset, 4, Type::I64, state)
+            }
+            Operator::I32Store { memarg } => {
+                self.trace_store(operator, memarg.offset, 4, Type::I32, state)
+            }
+            Operator::I64Store { memarg } => {
+                self.trace_store(operator, memarg.offset, 8, Type::I64, state)
+            }
+            Operator::F32Store { memarg } => {
+                self.trace_store(operator, memarg.offset, 4, Type::F32, state)
+            }
+            Operator::F64Store { memarg } => {
+                self.trace_store(operator, memarg.offset, 8, Type::F64, state)
+            }
+            Operator::I32Store8 { memarg } => {
+                self.trace_store(operator, memarg.offset, 1, Type::I32, state)
+            }
+            Operator::I32Store16 { memarg } => {
+                self.trace_store(operator, memarg.offset, 2, Type::I32, state)
+            }
+            Operator::I64Store8 { memarg } => {
+                self.trace_store(operator, memarg.offset, 1, Type::I64, state)
+            }
+            Operator::I64Store16 { memarg } => {
+                self.trace_store(operator, memarg.offset, 2, Type::I64, state)
+            }
+            Operator::I64Store32 { memarg } => {
+                self.trace_store(operator, memarg.offset, 4, Type::I64, state)
+            }
+            Operator::MemoryFill { .. } if self.bulk_memory => {
+                self.trace_bulk(operator, MemoryAccessKind::Fill, None, state)
+            }
+            Operator::MemoryCopy { .. } if self.bulk_memory => {
+                self.trace_bulk(operator, MemoryAccessKind::Copy, None, state)
+            }
+            Operator::MemoryInit { segment, .. } if self.bulk_memory => self.trace_bulk(
+                operator,
+                MemoryAccessKind::Init,
+                Some(segment as i64),
+                state,
+            ),
+            _ => state.push_operator(operator),
+        }
+
+        Ok(())
+    }
+}
+
+/// Creates the imports satisfying the tracing hook of `module`, which
+/// must have been compiled with the `MemoryTracing` middleware. The
+/// traced accesses are forwarded to `tracer`.
+///
+/// The returned `ImportObject` can be chained with the other imports of
+/// the module.
+pub fn import_object(module: &Module, tracer: Arc<dyn MemoryAccessTracer>) -> ImportObject {
+    let mut namespace = Exports::new();
+
+    for import in module.imports() {
+        if import.module() != MEMORY_TRACING_NAMESPACE || import.name() != HOOK_NAME {
+            continue;
+        }
+
+        let function_type = match import.ty() {
+            ExternType::Function(function_type) => function_type.clone(),
+            _ => continue,
+        };
+        let tracer = tracer.clone();
+
+        let hook = Function::new(module.store(), function_type, move |values| {
+            if let [Value::I32(kind), Value::I64(address), Value::I32(size), Value::I64(value)] =
+                values
+            {
+                if let Some(kind) = MemoryAccessKind::from_i32(*kind) {
+                    tracer.on_access(&MemoryAccess {
+                        kind,
+                        address: *address as u64,
+                        size: *size as u32,
+                        value: *value as u64,
+                    });
+                }
+            }
+
+            Ok(vec![])
+        });
+
+        namespace.insert(HOOK_NAME, hook);
+    }
+
+    let mut import_object = ImportObject::new();
+    import_object.register(MEMORY_TRACING_NAMESPACE, namespace);
+
+    import_object
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasmer::{
+        imports, wat2wasm, ChainableNamedResolver, CompilerConfig, Cranelift, Instance, Store, JIT,
+    };
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (memory 1)
+            (func (export "swap") (param $a i32) (param $b i32)
+                (local $value i64)
+                local.get $a
+                i64.load
+                local.set $value
+                local.get $a
+                local.get $b
+                i64.load offset=0
+                i64.store
+                local.get $b
+                local.get $value
+                i64.store)
+            (func (export "store_byte") (param $address i32) (param $value i32)
+                local.get $address
+                local.get $value
+                i32.store8 offset=2)
+            (func (export "load_byte") (param $address i32) (result i32)
+                local.get $address
+                i32.load8_s)
+            (func (export "load_i64_half") (param $address i32) (result i64)
+                local.get $address
+                i64.load16_s)
+            (func (export "fill") (param $address i32) (param $value i32) (param $length i32)
+                local.get $address
+                local.get $value
+                local.get $length
+                memory.fill))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn instance(memory_tracing: MemoryTracing, tracer: Arc<RingBuffer>) -> Instance {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(memory_tracing));
+        let store = Store::new(&JIT::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let imports = imports! {}.chain_back(import_object(&module, tracer));
+
+        Instance::new(&module, &imports).unwrap()
+    }
+
+    #[test]
+    fn traces_loads_and_stores() {
+        let tracer = Arc::new(RingBuffer::new(16));
+        let instance = instance(MemoryTracing::new(), tracer.clone());
+
+        let store_byte = instance
+            .exports
+            .get_native_function::<(i32, i32), ()>("store_byte")
+            .unwrap();
+        store_byte.call(8, 0x2a).unwrap();
+
+        let swap = instance
+            .exports
+            .get_native_function::<(i32, i32), ()>("swap")
+            .unwrap();
+        swap.call(8, 16).unwrap();
+
+        assert_eq!(
+            tracer.accesses(),
+            vec![
+                MemoryAccess {
+                    kind: MemoryAccessKind::Store,
+                    address: 10,
+                    size: 1,
+                    value: 0x2a,
+                },
+                MemoryAccess {
+                    kind: MemoryAccessKind::Load,
+                    address: 8,
+                    size: 8,
+                    value: 0x2a_0000,
+                },
+                MemoryAccess {
+                    kind: MemoryAccessKind::Load,
+                    address: 16,
+                    size: 8,
+                    value: 0,
+                },
+                MemoryAccess {
+                    kind: MemoryAccessKind::Store,
+                    address: 8,
+                    size: 8,
+                    value: 0,
+                },
+                MemoryAccess {
+                    kind: MemoryAccessKind::Store,
+                    address: 16,
+                    size: 8,
+                    value: 0x2a_0000,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn traces_the_bytes_of_narrow_accesses() {
+        let tracer = Arc::new(RingBuffer::new(16));
+        let instance = instance(MemoryTracing::new(), tracer.clone());
+
+        let store_byte = instance
+            .exports
+            .get_native_function::<(i32, i32), ()>("store_byte")
+            .unwrap();
+        store_byte.call(0, -1).unwrap();
+        store_byte.call(1, 0x180).unwrap();
+
+        let load_byte = instance
+            .exports
+            .get_native_function::<i32, i32>("load_byte")
+            .unwrap();
+        assert_eq!(load_byte.call(2).unwrap(), -1);
+
+        let load_i64_half = instance
+            .exports
+            .get_native_function::<i32, i64>("load_i64_half")
+            .unwrap();
+        assert_eq!(load_i64_half.call(2).unwrap(), -0x7f01);
+
+        assert_eq!(
+            tracer.accesses(),
+            vec![
+                MemoryAccess {
+                    kind: MemoryAccessKind::Store,
+                    address: 2,
+                    size: 1,
+                    value: 0xff,
+                },
+                MemoryAccess {
+                    kind: MemoryAccessKind::Store,
+                    address: 3,
+                    size: 1,
+                    value: 0x80,
+                },
+                MemoryAccess {
+                    kind: MemoryAccessKind::Load,
+                    address: 2,
+                    size: 1,
+                    value: 0xff,
+                },
+                MemoryAccess {
+                    kind: MemoryAccessKind::Load,
+                    address: 2,
+                    size: 2,
+                    value: 0x80ff,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_addresses() {
+        let tracer = Arc::new(RingBuffer::new(16));
+        let instance = instance(
+            MemoryTracing::new()
+                .with_address_range(0..4)
+                .with_address_range(20..24)
+                .with_bulk_memory(true),
+            tracer.clone(),
+        );
+
+        let store_byte = instance
+            .exports
+            .get_native_function::<(i32, i32), ()>("store_byte")
+            .unwrap();
+        store_byte.call(0, 1).unwrap();
+        store_byte.call(8, 2).unwrap();
+        store_byte.call(18, 3).unwrap();
+
+        let fill = instance
+            .exports
+            .get_native_function::<(i32, i32, i32), ()>("fill")
+            .unwrap();
+        fill.call(10, 0xff, 11).unwrap();
+        fill.call(4, 0xff, 4).unwrap();
+
+        assert_eq!(
+            tracer.accesses(),
+            vec![
+                MemoryAccess {
+                    kind: MemoryAccessKind::Store,
+                    address: 2,
+                    size: 1,
+                    value: 1,
+                },
+                MemoryAccess {
+                    kind: MemoryAccessKind::Store,
+                    address: 20,
+                    size: 1,
+                    value: 3,
+                },
+                MemoryAccess {
+                    kind: MemoryAccessKind::Fill,
+                    address: 10,
+                    size: 11,
+                    value: 0xff,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ring_buffer_keeps_the_latest_accesses() {
+        let ring_buffer = RingBuffer::new(2);
+
+        for address in 0..3 {
+            ring_buffer.on_access(&MemoryAccess {
+                kind: MemoryAccessKind::Load,
+                address,
+                size: 4,
+                value: 0,
+            });
+        }
+
+        let addresses: Vec<u64> = ring_buffer
+            .accesses()
+            .iter()
+            .map(|access| access.address)
+            .collect();
+        assert_eq!(addresses, vec![1, 2]);
+    }
+}
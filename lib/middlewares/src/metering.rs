@@ -6,7 +6,7 @@ use std::fmt;
 use std::sync::{Arc, Mutex};
 use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
 use wasmer::{
-    ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance, LocalFunctionIndex,
+    Caller, ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance, LocalFunctionIndex,
     MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
 };
 use wasmer_types::GlobalIndex;
@@ -274,12 +274,67 @@ pub fn set_remaining_points(instance: &Instance, points: u64) {
         .expect("Can't set `wasmer_metering_points_exhausted` in Instance");
 }
 
+/// Get the remaining points in the instance calling a host function.
+///
+/// The points consumed by the caller so far are accounted for, so a host
+/// function can check them or charge its own cost with
+/// [`set_caller_remaining_points`].
+///
+/// # Panic
+///
+/// This must be called from a host function, and the calling instance
+/// Module must have been processed with the [`Metering`] middleware at
+/// compile time, otherwise this will panic.
+pub fn get_caller_remaining_points(caller: &Caller) -> MeteringPoints {
+    let exhausted: i32 = caller
+        .get_global("wasmer_metering_points_exhausted")
+        .expect("Can't get `wasmer_metering_points_exhausted` from Caller")
+        .get()
+        .try_into()
+        .expect("`wasmer_metering_points_exhausted` from Caller has wrong type");
+
+    if exhausted > 0 {
+        return MeteringPoints::Exhausted;
+    }
+
+    let points = caller
+        .get_global("wasmer_metering_remaining_points")
+        .expect("Can't get `wasmer_metering_remaining_points` from Caller")
+        .get()
+        .try_into()
+        .expect("`wasmer_metering_remaining_points` from Caller has wrong type");
+
+    MeteringPoints::Remaining(points)
+}
+
+/// Set the provided remaining points in the instance calling a host
+/// function.
+///
+/// # Panic
+///
+/// This must be called from a host function, and the calling instance
+/// Module must have been processed with the [`Metering`] middleware at
+/// compile time, otherwise this will panic.
+pub fn set_caller_remaining_points(caller: &Caller, points: u64) {
+    caller
+        .get_global("wasmer_metering_remaining_points")
+        .expect("Can't get `wasmer_metering_remaining_points` from Caller")
+        .set(points.into())
+        .expect("Can't set `wasmer_metering_remaining_points` in Caller");
+
+    caller
+        .get_global("wasmer_metering_points_exhausted")
+        .expect("Can't get `wasmer_metering_points_exhausted` from Caller")
+        .set(0i32.into())
+        .expect("Can't set `wasmer_metering_points_exhausted` in Caller");
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::sync::Arc;
-    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, JIT};
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Function, Module, Store, JIT};
 
     fn cost_function(operator: &Operator) -> u64 {
         match operator {
@@ -403,4 +458,51 @@ mod tests {
             MeteringPoints::Remaining(4)
         );
     }
+
+    #[test]
+    fn caller_remaining_points_works() {
+        let metering = Arc::new(Metering::new(10, cost_function));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let store = Store::new(&JIT::new(compiler_config).engine());
+        let module = Module::new(
+            &store,
+            wat2wasm(
+                br#"
+                (module
+                (import "host" "charge" (func $charge (param i32) (result i64)))
+                (func (export "run") (result i64)
+                    i32.const 3
+                    call $charge))
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        // The host function charges its argument, and returns the points
+        // left before charging.
+        let charge = Function::new_native_with_caller(&store, |caller: &Caller, cost: i32| {
+            match get_caller_remaining_points(caller) {
+                MeteringPoints::Remaining(points) => {
+                    set_caller_remaining_points(caller, points - cost as u64);
+                    points as i64
+                }
+                MeteringPoints::Exhausted => -1,
+            }
+        });
+        let instance =
+            Instance::new(&module, &imports! { "host" => { "charge" => charge } }).unwrap();
+        let run = instance
+            .exports
+            .get_native_function::<(), i64>("run")
+            .unwrap();
+
+        // `i32.const 3` costs 1 point.
+        assert_eq!(run.call().unwrap(), 9);
+        assert_eq!(
+            get_remaining_points(&instance),
+            MeteringPoints::Remaining(6)
+        );
+    }
 }
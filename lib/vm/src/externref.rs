@@ -0,0 +1,73 @@
+//! The roots of the `externref`s handed over to WebAssembly.
+//!
+//! WebAssembly code handles `externref`s as raw pointers, which don't hold
+//! a reference count. The `externref`s it gets from the host, or reads
+//! from a global, are rooted until the call from the host returns, so
+//! that they stay alive even if their other references are released
+//! meanwhile.
+
+use std::cell::RefCell;
+use wasmer_types::ExternRef;
+
+/// The `externref`s handed over to WebAssembly by the calls running on
+/// this thread.
+#[derive(Default)]
+struct ExternRefRoots {
+    /// The number of calls running.
+    depth: usize,
+    roots: Vec<ExternRef>,
+}
+
+thread_local! {
+    static EXTERNREF_ROOTS: RefCell<ExternRefRoots> = RefCell::new(ExternRefRoots::default());
+}
+
+/// Calls `f`, keeping the `externref`s rooted during the call alive until
+/// it returns.
+///
+/// It must wrap every call of WebAssembly code from the host, so that the
+/// `externref`s passed as raw pointers, e.g. as the results of host
+/// functions or the values of globals read by WebAssembly, live until
+/// they are not used anymore.
+pub fn with_externref_roots<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    /// Releases the roots of the call when dropped, even if `f` panics.
+    struct Release(usize);
+
+    impl Drop for Release {
+        fn drop(&mut self) {
+            // The roots are taken out first, as dropping them may run
+            // arbitrary code.
+            let released = EXTERNREF_ROOTS.with(|roots| {
+                let mut roots = roots.borrow_mut();
+                roots.depth -= 1;
+                // The roots taken outside of a call are released by the
+                // outermost one.
+                let len = if roots.depth == 0 { 0 } else { self.0 };
+                roots.roots.split_off(len)
+            });
+            drop(released);
+        }
+    }
+
+    let len = EXTERNREF_ROOTS.with(|roots| {
+        let mut roots = roots.borrow_mut();
+        roots.depth += 1;
+        roots.roots.len()
+    });
+    let _release = Release(len);
+
+    f()
+}
+
+/// Keeps `extern_ref` alive until the innermost call running on this
+/// thread returns, before handing it over to WebAssembly as a raw pointer.
+pub fn root_externref(extern_ref: &ExternRef) {
+    if extern_ref.is_null() {
+        return;
+    }
+
+    EXTERNREF_ROOTS.with(|roots| roots.borrow_mut().roots.push(extern_ref.clone()));
+}
@@ -1,12 +1,16 @@
 use crate::vmcontext::VMGlobalDefinition;
 use std::cell::UnsafeCell;
+use std::mem;
 use std::ptr::NonNull;
 use std::sync::Mutex;
 use thiserror::Error;
-use wasmer_types::{GlobalType, Mutability, Type, Value};
+use wasmer_types::{ExternRef, GlobalType, Mutability, Type, Value};
 
 #[derive(Debug)]
 /// A Global instance
+///
+/// An `externref` global holds a reference count of its value, released
+/// when it's overwritten or when the global is dropped.
 pub struct Global {
     ty: GlobalType,
     // TODO: this box may be unnecessary
@@ -74,6 +78,9 @@ impl Global {
                 Type::F32 => Value::F32(definition.to_f32()),
                 Type::F64 => Value::F64(definition.to_f64()),
                 Type::V128 => Value::V128(definition.to_u128()),
+                Type::ExternRef => {
+                    Value::ExternRef(ExternRef::clone_from_raw(definition.to_externref()))
+                }
                 _ => unimplemented!("Global::get for {:?}", self.ty),
             }
         }
@@ -112,8 +119,24 @@ impl Global {
             Value::F32(f) => *definition.as_f32_mut() = f,
             Value::F64(f) => *definition.as_f64_mut() = f,
             Value::V128(x) => *definition.as_bytes_mut() = x.to_ne_bytes(),
+            Value::ExternRef(r) => {
+                let previous = mem::replace(definition.as_externref_mut(), r.into_raw());
+                drop(ExternRef::from_raw(previous));
+            }
             _ => unimplemented!("Global::set for {:?}", val.ty()),
         }
         Ok(())
     }
 }
+
+impl Drop for Global {
+    fn drop(&mut self) {
+        if self.ty.ty == Type::ExternRef {
+            // Release the reference held by the global.
+            unsafe {
+                let definition = &*self.vm_global_definition.get();
+                drop(ExternRef::from_raw(definition.to_externref()));
+            }
+        }
+    }
+}
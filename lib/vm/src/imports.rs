@@ -2,12 +2,13 @@
 // Attributions: https://github.com/wasmerio/wasmer/blob/master/ATTRIBUTIONS.md
 
 use crate::instance::ImportFunctionEnv;
-use crate::vmcontext::{VMFunctionImport, VMGlobalImport, VMMemoryImport, VMTableImport};
+use crate::vmcontext::{
+    VMFunctionEnvironment, VMFunctionImport, VMGlobalImport, VMMemoryImport, VMTableImport,
+};
 use wasmer_types::entity::{BoxedSlice, PrimaryMap};
 use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, TableIndex};
 
 /// Resolved import pointers.
-#[derive(Clone)]
 pub struct Imports {
     /// Resolved addresses for imported functions.
     pub functions: BoxedSlice<FunctionIndex, VMFunctionImport>,
@@ -74,3 +75,30 @@ impl Imports {
             .unwrap_or_else(|| PrimaryMap::new().into_boxed_slice())
     }
 }
+
+impl Clone for Imports {
+    /// Clones the imports, along with the host function environments, so
+    /// that the clone can be given to another instance.
+    fn clone(&self) -> Self {
+        let mut functions = self.functions.clone();
+        let host_function_env_initializers = self.host_function_env_initializers.clone();
+
+        // The imported functions must be called with the cloned
+        // environments.
+        if let Some(import_function_envs) = &host_function_env_initializers {
+            for (index, import_function_env) in import_function_envs.iter() {
+                if let ImportFunctionEnv::Env { env, .. } = import_function_env {
+                    functions[index].environment = VMFunctionEnvironment { host_env: *env };
+                }
+            }
+        }
+
+        Self {
+            functions,
+            host_function_env_initializers,
+            tables: self.tables.clone(),
+            memories: self.memories.clone(),
+            globals: self.globals.clone(),
+        }
+    }
+}
@@ -11,13 +11,14 @@ mod allocator;
 mod r#ref;
 
 pub use allocator::InstanceAllocator;
-pub use r#ref::InstanceRef;
+pub use r#ref::{InstanceRef, WeakInstanceRef};
 
 use crate::export::VMExport;
+use crate::externref::root_externref;
 use crate::global::Global;
 use crate::imports::Imports;
 use crate::memory::{Memory, MemoryError};
-use crate::table::Table;
+use crate::table::{Table, TableElement};
 use crate::trap::{catch_traps, init_traps, Trap, TrapCode};
 use crate::vmcontext::{
     VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionBody,
@@ -40,9 +41,9 @@ use std::sync::Arc;
 use std::{mem, ptr, slice};
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
-    DataIndex, DataInitializer, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, GlobalInit,
-    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, Pages,
-    SignatureIndex, TableIndex, TableInitializer,
+    DataIndex, DataInitializer, ElemIndex, ExportIndex, ExternRef, FunctionIndex, GlobalIndex,
+    GlobalInit, LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex,
+    MemoryIndex, Pages, RawExternRef, SignatureIndex, TableIndex, TableInitializer, Type,
 };
 
 /// The function pointer to call with data and an [`Instance`] pointer to
@@ -108,6 +109,24 @@ pub(crate) struct Instance {
     vmctx: VMContext,
 }
 
+impl Drop for Instance {
+    fn drop(&mut self) {
+        // Release the imports written in the `VMContext` by
+        // `InstanceHandle::new`.
+        unsafe {
+            for i in 0..self.module.num_imported_tables {
+                ptr::drop_in_place(self.imported_tables_ptr().add(i));
+            }
+            for i in 0..self.module.num_imported_memories {
+                ptr::drop_in_place(self.imported_memories_ptr().add(i));
+            }
+            for i in 0..self.module.num_imported_globals {
+                ptr::drop_in_place(self.imported_globals_ptr().add(i));
+            }
+        }
+    }
+}
+
 /// A collection of data about host envs used by imported functions.
 #[derive(Debug)]
 pub enum ImportFunctionEnv {
@@ -513,26 +532,28 @@ impl Instance {
         from.size()
     }
 
-    /// Grow table by the specified amount of elements.
+    /// Grow table by the specified amount of elements, initialized to
+    /// `init`.
     ///
     /// Returns `None` if table can't be grown by the specified amount
     /// of elements.
-    pub(crate) fn table_grow(&self, table_index: LocalTableIndex, delta: u32) -> Option<u32> {
+    pub(crate) fn table_grow(
+        &self,
+        table_index: LocalTableIndex,
+        delta: u32,
+        init: TableElement,
+    ) -> Option<u32> {
         let result = self
             .tables
             .get(table_index)
             .unwrap_or_else(|| panic!("no table for index {}", table_index.index()))
-            .grow(delta);
+            .grow(delta, init);
 
         result
     }
 
     /// Get table element by index.
-    fn table_get(
-        &self,
-        table_index: LocalTableIndex,
-        index: u32,
-    ) -> Option<VMCallerCheckedAnyfunc> {
+    fn table_get(&self, table_index: LocalTableIndex, index: u32) -> Option<TableElement> {
         self.tables
             .get(table_index)
             .unwrap_or_else(|| panic!("no table for index {}", table_index.index()))
@@ -544,7 +565,7 @@ impl Instance {
         &self,
         table_index: LocalTableIndex,
         index: u32,
-        val: VMCallerCheckedAnyfunc,
+        val: TableElement,
     ) -> Result<(), Trap> {
         self.tables
             .get(table_index)
@@ -613,7 +634,7 @@ impl Instance {
 
         for (dst, src) in (dst..dst + len).zip(src..src + len) {
             table
-                .set(dst, elem[src as usize].clone())
+                .set(dst, elem[src as usize].clone().into())
                 .expect("should never panic because we already did the bounds check above");
         }
 
@@ -749,6 +770,40 @@ impl Instance {
         passive_data.remove(&data_index);
     }
 
+    /// Get a global definition by index regardless of whether it is
+    /// locally-defined or an imported, foreign global.
+    fn get_global_definition(&self, global_index: GlobalIndex) -> NonNull<VMGlobalDefinition> {
+        if let Some(local_global_index) = self.module.local_global_index(global_index) {
+            self.global_ptr(local_global_index)
+        } else {
+            self.imported_global(global_index).definition
+        }
+    }
+
+    /// Reads the `externref` global `global_index`.
+    ///
+    /// The returned `externref` is rooted, so that it stays alive while it
+    /// is used by WebAssembly, even if the global is set again.
+    pub(crate) fn externref_global_get(&self, global_index: GlobalIndex) -> RawExternRef {
+        let raw = unsafe { self.get_global_definition(global_index).as_ref() }.to_externref();
+        root_externref(&unsafe { ExternRef::clone_from_raw(raw) });
+
+        raw
+    }
+
+    /// Sets the `externref` global `global_index` to `raw`, taking a
+    /// reference count of it and releasing the one of the previous value.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be a live `externref`.
+    pub(crate) unsafe fn externref_global_set(&self, global_index: GlobalIndex, raw: RawExternRef) {
+        let value = ExternRef::clone_from_raw(raw);
+        let definition = self.get_global_definition(global_index).as_mut();
+        let previous = mem::replace(definition.as_externref_mut(), value.into_raw());
+        drop(ExternRef::from_raw(previous));
+    }
+
     /// Get a table by index regardless of whether it is locally-defined or an
     /// imported, foreign table.
     pub(crate) fn get_table(&self, table_index: TableIndex) -> &dyn Table {
@@ -776,12 +831,19 @@ impl Instance {
 ///
 /// This is more or less a public facade of the private `Instance`,
 /// providing useful higher-level API.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct InstanceHandle {
     /// The [`InstanceRef`]. See its documentation to learn more.
     instance: InstanceRef,
 }
 
+impl From<InstanceRef> for InstanceHandle {
+    /// Creates a handle sharing the `Instance` held by `instance`.
+    fn from(instance: InstanceRef) -> Self {
+        Self { instance }
+    }
+}
+
 impl InstanceHandle {
     /// Create a new `InstanceHandle` pointing at a new [`InstanceRef`].
     ///
@@ -862,21 +924,17 @@ impl InstanceHandle {
             instance.imported_functions_ptr() as *mut VMFunctionImport,
             imports.functions.len(),
         );
-        ptr::copy(
-            imports.tables.values().as_slice().as_ptr(),
-            instance.imported_tables_ptr() as *mut VMTableImport,
-            imports.tables.len(),
-        );
-        ptr::copy(
-            imports.memories.values().as_slice().as_ptr(),
-            instance.imported_memories_ptr() as *mut VMMemoryImport,
-            imports.memories.len(),
-        );
-        ptr::copy(
-            imports.globals.values().as_slice().as_ptr(),
-            instance.imported_globals_ptr() as *mut VMGlobalImport,
-            imports.globals.len(),
-        );
+        // The `VMContext` holds its own references to the imported tables,
+        // memories and globals, released when the instance is dropped.
+        for (i, import) in imports.tables.values().enumerate() {
+            ptr::write(instance.imported_tables_ptr().add(i), import.clone());
+        }
+        for (i, import) in imports.memories.values().enumerate() {
+            ptr::write(instance.imported_memories_ptr().add(i), import.clone());
+        }
+        for (i, import) in imports.globals.values().enumerate() {
+            ptr::write(instance.imported_globals_ptr().add(i), import.clone());
+        }
         // these should already be set, add asserts here? for:
         // - instance.tables_ptr() as *mut VMTableDefinition
         // - instance.memories_ptr() as *mut VMMemoryDefinition
@@ -906,6 +964,12 @@ impl InstanceHandle {
         &self.instance
     }
 
+    /// Creates a [`WeakInstanceRef`] to the contained `Instance`. See
+    /// [`InstanceRef::downgrade`].
+    pub fn downgrade(&self) -> WeakInstanceRef {
+        self.instance.downgrade()
+    }
+
     /// Finishes the instantiation process started by `Instance::new`.
     ///
     /// # Safety
@@ -1086,22 +1150,26 @@ impl InstanceHandle {
         self.instance().as_ref().table_index(table)
     }
 
-    /// Grow table in this instance by the specified amount of pages.
+    /// Grow table in this instance by the specified amount of elements,
+    /// initialized to `init`.
     ///
-    /// Returns `None` if memory can't be grown by the specified amount
-    /// of pages.
-    pub fn table_grow(&self, table_index: LocalTableIndex, delta: u32) -> Option<u32> {
-        self.instance().as_ref().table_grow(table_index, delta)
+    /// Returns `None` if the table can't be grown by the specified amount
+    /// of elements.
+    pub fn table_grow(
+        &self,
+        table_index: LocalTableIndex,
+        delta: u32,
+        init: TableElement,
+    ) -> Option<u32> {
+        self.instance()
+            .as_ref()
+            .table_grow(table_index, delta, init)
     }
 
     /// Get table element reference.
     ///
     /// Returns `None` if index is out of bounds.
-    pub fn table_get(
-        &self,
-        table_index: LocalTableIndex,
-        index: u32,
-    ) -> Option<VMCallerCheckedAnyfunc> {
+    pub fn table_get(&self, table_index: LocalTableIndex, index: u32) -> Option<TableElement> {
         self.instance().as_ref().table_get(table_index, index)
     }
 
@@ -1112,7 +1180,7 @@ impl InstanceHandle {
         &self,
         table_index: LocalTableIndex,
         index: u32,
-        val: VMCallerCheckedAnyfunc,
+        val: TableElement,
     ) -> Result<(), Trap> {
         self.instance().as_ref().table_set(table_index, index, val)
     }
@@ -1290,7 +1358,7 @@ fn initialize_tables(instance: &Instance) -> Result<(), Trap> {
         for (i, func_idx) in init.elements.iter().enumerate() {
             let anyfunc = instance.get_caller_checked_anyfunc(*func_idx);
             table
-                .set(u32::try_from(start + i).unwrap(), anyfunc)
+                .set(u32::try_from(start + i).unwrap(), anyfunc.into())
                 .unwrap();
         }
     }
@@ -1372,8 +1440,13 @@ fn initialize_globals(instance: &Instance) {
                             instance.imported_global(*x).definition.as_ref().clone()
                         };
                     *to = from;
+                    // The globals hold a reference count of their `externref`s.
+                    if module.globals[module.global_index(index)].ty == Type::ExternRef {
+                        mem::forget(ExternRef::clone_from_raw((*to).to_externref()));
+                    }
                 }
-                GlobalInit::RefNullConst | GlobalInit::RefFunc(_) => unimplemented!(),
+                GlobalInit::RefNullConst => *(*to).as_externref_mut() = RawExternRef::null(),
+                GlobalInit::RefFunc(_) => unimplemented!(),
             }
         }
     }
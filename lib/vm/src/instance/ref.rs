@@ -110,6 +110,16 @@ impl InstanceRef {
     pub(super) unsafe fn as_mut(&mut self) -> &mut Instance {
         self.instance.as_mut()
     }
+
+    /// Creates a [`WeakInstanceRef`] to the `Instance`, which doesn't
+    /// keep it alive.
+    pub fn downgrade(&self) -> WeakInstanceRef {
+        WeakInstanceRef {
+            strong: self.strong.clone(),
+            instance_layout: self.instance_layout,
+            instance: self.instance,
+        }
+    }
 }
 
 /// TODO: Review this super carefully.
@@ -208,3 +218,63 @@ impl Drop for InstanceRef {
         unsafe { Self::deallocate_instance(self) };
     }
 }
+
+/// A `WeakInstanceRef` is a reference to an `Instance` which doesn't
+/// keep it alive, like `std::sync::Weak` is to `std::sync::Arc`.
+///
+/// It is used where a strong reference would create a cycle, e.g. by
+/// the host environments owned by the `Instance` itself.
+#[derive(Debug, Clone)]
+pub struct WeakInstanceRef {
+    /// The strong reference count of the `InstanceRef`s. It is zero
+    /// once the `Instance` has been deallocated.
+    strong: Arc<atomic::AtomicUsize>,
+
+    /// The layout of `Instance`.
+    instance_layout: Layout,
+
+    /// The `Instance`. It must not be dereferenced unless `Self.strong`
+    /// is non-zero.
+    instance: NonNull<Instance>,
+}
+
+impl WeakInstanceRef {
+    /// Creates an [`InstanceRef`] to the `Instance`, if it is still
+    /// alive.
+    pub fn upgrade(&self) -> Option<InstanceRef> {
+        let mut count = self.strong.load(atomic::Ordering::Relaxed);
+
+        // The count must not be increased once it has reached zero, as
+        // the `Instance` is being, or has been, deallocated.
+        loop {
+            if count == 0 {
+                return None;
+            }
+
+            if count > InstanceRef::MAX_REFCOUNT {
+                panic!("Too many references of `InstanceRef`");
+            }
+
+            match self.strong.compare_exchange_weak(
+                count,
+                count + 1,
+                atomic::Ordering::Acquire,
+                atomic::Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(current) => count = current,
+            }
+        }
+
+        Some(InstanceRef {
+            strong: self.strong.clone(),
+            instance_layout: self.instance_layout,
+            instance: self.instance,
+        })
+    }
+}
+
+/// The `Instance` is only accessed through the [`InstanceRef`] returned
+/// by [`WeakInstanceRef::upgrade`].
+unsafe impl Send for WeakInstanceRef {}
+unsafe impl Sync for WeakInstanceRef {}
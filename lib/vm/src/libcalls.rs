@@ -40,7 +40,9 @@ use crate::trap::{raise_lib_trap, Trap, TrapCode};
 use crate::vmcontext::VMContext;
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use wasmer_types::{DataIndex, ElemIndex, LocalMemoryIndex, MemoryIndex, TableIndex};
+use wasmer_types::{
+    DataIndex, ElemIndex, GlobalIndex, LocalMemoryIndex, MemoryIndex, RawExternRef, TableIndex,
+};
 
 /// Implementation of f32.ceil
 #[no_mangle]
@@ -381,6 +383,38 @@ pub unsafe extern "C" fn wasmer_data_drop(vmctx: *mut VMContext, data_index: u32
     instance.data_drop(data_index)
 }
 
+/// Implementation of `global.get` for `externref` globals.
+///
+/// The `externref` is rooted until the call from the host returns.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_externref_global_get(
+    vmctx: *mut VMContext,
+    global_index: u32,
+) -> RawExternRef {
+    let global_index = GlobalIndex::from_u32(global_index);
+    let instance = (&*vmctx).instance();
+    instance.externref_global_get(global_index)
+}
+
+/// Implementation of `global.set` for `externref` globals.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null, and `value` must be a live
+/// `externref`.
+pub unsafe extern "C" fn wasmer_externref_global_set(
+    vmctx: *mut VMContext,
+    global_index: u32,
+    value: RawExternRef,
+) {
+    let global_index = GlobalIndex::from_u32(global_index);
+    let instance = (&*vmctx).instance();
+    instance.externref_global_set(global_index, value)
+}
+
 /// Implementation for raising a trap
 ///
 /// # Safety
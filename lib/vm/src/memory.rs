@@ -113,6 +113,15 @@ pub trait Memory: fmt::Debug + Send + Sync {
     ///
     /// The pointer returned in [`VMMemoryDefinition`] must be valid for the lifetime of this memory.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
+
+    /// Returns a copy of the [`VMMemoryDefinition`] of this memory, whose
+    /// base and length are read together.
+    ///
+    /// The default implementation reads them without synchronization: a
+    /// memory which may grow while it is read must override it.
+    fn vmmemory_definition(&self) -> VMMemoryDefinition {
+        unsafe { *self.vmmemory().as_ref() }
+    }
 }
 
 /// A linear memory instance.
@@ -411,4 +420,11 @@ impl Memory for LinearMemory {
         let _mmap_guard = self.mmap.lock().unwrap();
         unsafe { self.get_vm_memory_definition() }
     }
+
+    /// Returns a copy of the `VMMemoryDefinition`, read under the lock
+    /// held while the memory grows.
+    fn vmmemory_definition(&self) -> VMMemoryDefinition {
+        let _mmap_guard = self.mmap.lock().unwrap();
+        unsafe { *self.get_vm_memory_definition().as_ref() }
+    }
 }
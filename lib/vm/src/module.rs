@@ -81,6 +81,12 @@ pub struct ModuleInfo {
     /// WebAssembly function names.
     pub function_names: HashMap<FunctionIndex, String>,
 
+    /// WebAssembly local names, by function and local index.
+    pub local_names: HashMap<FunctionIndex, HashMap<u32, String>>,
+
+    /// The size in bytes of the body of the local functions.
+    pub function_body_sizes: PrimaryMap<LocalFunctionIndex, u32>,
+
     /// WebAssembly function signatures.
     pub signatures: PrimaryMap<SignatureIndex, FunctionType>,
 
@@ -129,6 +135,8 @@ impl ModuleInfo {
             passive_data: HashMap::new(),
             global_initializers: PrimaryMap::new(),
             function_names: HashMap::new(),
+            local_names: HashMap::new(),
+            function_body_sizes: PrimaryMap::new(),
             signatures: PrimaryMap::new(),
             functions: PrimaryMap::new(),
             tables: PrimaryMap::new(),
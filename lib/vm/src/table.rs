@@ -14,7 +14,7 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::ptr::NonNull;
 use std::sync::Mutex;
-use wasmer_types::{TableType, Type as ValType};
+use wasmer_types::{ExternRef, TableType, Type as ValType};
 
 /// Implementation styles for WebAssembly tables.
 #[derive(Debug, Clone, Hash, Serialize, Deserialize)]
@@ -23,7 +23,41 @@ pub enum TableStyle {
     CallerChecksSignature,
 }
 
+/// The value of a table element.
+#[derive(Debug, Clone)]
+pub enum TableElement {
+    /// A reference to a function, in a `funcref` table.
+    FuncRef(VMCallerCheckedAnyfunc),
+    /// An opaque reference to host data, in an `externref` table.
+    ExternRef(ExternRef),
+}
+
+impl TableElement {
+    /// The type of the tables this element can be stored in.
+    pub fn ty(&self) -> ValType {
+        match self {
+            Self::FuncRef(_) => ValType::FuncRef,
+            Self::ExternRef(_) => ValType::ExternRef,
+        }
+    }
+}
+
+impl From<VMCallerCheckedAnyfunc> for TableElement {
+    fn from(anyfunc: VMCallerCheckedAnyfunc) -> Self {
+        Self::FuncRef(anyfunc)
+    }
+}
+
+impl From<ExternRef> for TableElement {
+    fn from(extern_ref: ExternRef) -> Self {
+        Self::ExternRef(extern_ref)
+    }
+}
+
 /// Trait for implementing the interface of a Wasm table.
+///
+/// The `externref` elements of a table hold a reference count, released
+/// when they are overwritten or when the table is dropped.
 pub trait Table: fmt::Debug + Send + Sync {
     /// Returns the style for this Table.
     fn style(&self) -> &TableStyle;
@@ -34,23 +68,26 @@ pub trait Table: fmt::Debug + Send + Sync {
     /// Returns the number of allocated elements.
     fn size(&self) -> u32;
 
-    /// Grow table by the specified amount of elements.
+    /// Grow table by the specified amount of elements, initialized to
+    /// `init`.
     ///
     /// Returns `None` if table can't be grown by the specified amount
-    /// of elements, otherwise returns the previous size of the table.
-    fn grow(&self, delta: u32) -> Option<u32>;
+    /// of elements, or if `init` has the wrong type, otherwise returns
+    /// the previous size of the table.
+    fn grow(&self, delta: u32, init: TableElement) -> Option<u32>;
 
     /// Get reference to the specified element.
     ///
     /// Returns `None` if the index is out of bounds.
-    fn get(&self, index: u32) -> Option<VMCallerCheckedAnyfunc>;
+    fn get(&self, index: u32) -> Option<TableElement>;
 
     /// Set reference to the specified element.
     ///
     /// # Errors
     ///
-    /// Returns an error if the index is out of bounds.
-    fn set(&self, index: u32, func: VMCallerCheckedAnyfunc) -> Result<(), Trap>;
+    /// Returns an error if the index is out of bounds, or if the
+    /// element has the wrong type.
+    fn set(&self, index: u32, element: TableElement) -> Result<(), Trap>;
 
     /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
     fn vmtable(&self) -> NonNull<VMTableDefinition>;
@@ -102,11 +139,82 @@ pub trait Table: fmt::Debug + Send + Sync {
     }
 }
 
+/// The elements of a `LinearTable`.
+///
+/// The vectors are the backing storage exposed to compiled code.
+#[derive(Debug)]
+enum TableElements {
+    FuncRefs(Vec<VMCallerCheckedAnyfunc>),
+    ExternRefs(Vec<ExternRef>),
+}
+
+impl TableElements {
+    fn get(&self, index: usize) -> Option<TableElement> {
+        match self {
+            Self::FuncRefs(vec) => vec.get(index).cloned().map(TableElement::FuncRef),
+            Self::ExternRefs(vec) => vec.get(index).cloned().map(TableElement::ExternRef),
+        }
+    }
+
+    fn set(&mut self, index: usize, element: TableElement) -> Result<(), Trap> {
+        let table_ty = self.ty();
+        let out_of_bounds = || Trap::new_from_runtime(TrapCode::TableAccessOutOfBounds);
+
+        match (self, element) {
+            (Self::FuncRefs(vec), TableElement::FuncRef(anyfunc)) => {
+                *vec.get_mut(index).ok_or_else(out_of_bounds)? = anyfunc;
+            }
+            (Self::ExternRefs(vec), TableElement::ExternRef(extern_ref)) => {
+                // The previous element is dropped, releasing its reference.
+                *vec.get_mut(index).ok_or_else(out_of_bounds)? = extern_ref;
+            }
+            (_, element) => {
+                return Err(Trap::new_from_user(
+                    format!(
+                        "cannot store a `{}` in a `{}` table",
+                        element.ty(),
+                        table_ty
+                    )
+                    .into(),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resize(&mut self, new_len: usize, init: TableElement) -> Option<()> {
+        match (self, init) {
+            (Self::FuncRefs(vec), TableElement::FuncRef(anyfunc)) => vec.resize(new_len, anyfunc),
+            (Self::ExternRefs(vec), TableElement::ExternRef(extern_ref)) => {
+                vec.resize(new_len, extern_ref)
+            }
+            _ => return None,
+        }
+
+        Some(())
+    }
+
+    fn ty(&self) -> ValType {
+        match self {
+            Self::FuncRefs(_) => ValType::FuncRef,
+            Self::ExternRefs(_) => ValType::ExternRef,
+        }
+    }
+
+    fn base(&mut self) -> *mut u8 {
+        match self {
+            Self::FuncRefs(vec) => vec.as_mut_ptr() as _,
+            Self::ExternRefs(vec) => vec.as_mut_ptr() as _,
+        }
+    }
+}
+
 /// A table instance.
 #[derive(Debug)]
 pub struct LinearTable {
     // TODO: we can remove the mutex by using atomic swaps and preallocating the max table size
-    vec: Mutex<Vec<VMCallerCheckedAnyfunc>>,
+    vec: Mutex<TableElements>,
     maximum: Option<u32>,
     /// The WebAssembly table description.
     table: TableType,
@@ -163,10 +271,6 @@ impl LinearTable {
         style: &TableStyle,
         vm_table_location: Option<NonNull<VMTableDefinition>>,
     ) -> Result<Self, String> {
-        match table.ty {
-            ValType::FuncRef => (),
-            ty => return Err(format!("tables of types other than anyfunc ({})", ty)),
-        };
         if let Some(max) = table.maximum {
             if max < table.minimum {
                 return Err(format!(
@@ -177,8 +281,19 @@ impl LinearTable {
         }
         let table_minimum = usize::try_from(table.minimum)
             .map_err(|_| "Table minimum is bigger than usize".to_string())?;
-        let mut vec = vec![VMCallerCheckedAnyfunc::default(); table_minimum];
-        let base = vec.as_mut_ptr();
+        let mut vec = match table.ty {
+            ValType::FuncRef => {
+                TableElements::FuncRefs(vec![VMCallerCheckedAnyfunc::default(); table_minimum])
+            }
+            ValType::ExternRef => TableElements::ExternRefs(vec![ExternRef::null(); table_minimum]),
+            ty => {
+                return Err(format!(
+                    "tables of types other than funcref or externref ({})",
+                    ty
+                ))
+            }
+        };
+        let base = vec.base();
         match style {
             TableStyle::CallerChecksSignature => Ok(Self {
                 vec: Mutex::new(vec),
@@ -241,11 +356,13 @@ impl Table for LinearTable {
         }
     }
 
-    /// Grow table by the specified amount of elements.
+    /// Grow table by the specified amount of elements, initialized to
+    /// `init`.
     ///
     /// Returns `None` if table can't be grown by the specified amount
-    /// of elements, otherwise returns the previous size of the table.
-    fn grow(&self, delta: u32) -> Option<u32> {
+    /// of elements, or if `init` has the wrong type, otherwise returns
+    /// the previous size of the table.
+    fn grow(&self, delta: u32, init: TableElement) -> Option<u32> {
         let mut vec_guard = self.vec.lock().unwrap();
         let vec = vec_guard.borrow_mut();
         let size = self.size();
@@ -253,17 +370,14 @@ impl Table for LinearTable {
         if self.maximum.map_or(false, |max| new_len > max) {
             return None;
         }
-        vec.resize(
-            usize::try_from(new_len).unwrap(),
-            VMCallerCheckedAnyfunc::default(),
-        );
+        vec.resize(usize::try_from(new_len).unwrap(), init)?;
 
         // update table definition
         unsafe {
             let mut td_ptr = self.get_vm_table_definition();
             let td = td_ptr.as_mut();
             td.current_elements = new_len;
-            td.base = vec.as_mut_ptr() as _;
+            td.base = vec.base();
         }
         Some(size)
     }
@@ -271,26 +385,21 @@ impl Table for LinearTable {
     /// Get reference to the specified element.
     ///
     /// Returns `None` if the index is out of bounds.
-    fn get(&self, index: u32) -> Option<VMCallerCheckedAnyfunc> {
+    fn get(&self, index: u32) -> Option<TableElement> {
         let vec_guard = self.vec.lock().unwrap();
-        vec_guard.borrow().get(index as usize).cloned()
+        vec_guard.borrow().get(index as usize)
     }
 
     /// Set reference to the specified element.
     ///
     /// # Errors
     ///
-    /// Returns an error if the index is out of bounds.
-    fn set(&self, index: u32, func: VMCallerCheckedAnyfunc) -> Result<(), Trap> {
+    /// Returns an error if the index is out of bounds, or if the
+    /// element has the wrong type.
+    fn set(&self, index: u32, element: TableElement) -> Result<(), Trap> {
         let mut vec_guard = self.vec.lock().unwrap();
         let vec = vec_guard.borrow_mut();
-        match vec.get_mut(index as usize) {
-            Some(slot) => {
-                *slot = func;
-                Ok(())
-            }
-            None => Err(Trap::new_from_runtime(TrapCode::TableAccessOutOfBounds)),
-        }
+        vec.set(index as usize, element)
     }
 
     /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
@@ -15,6 +15,7 @@ use std::fmt;
 use std::ptr::{self, NonNull};
 use std::sync::Arc;
 use std::u32;
+use wasmer_types::RawExternRef;
 
 /// Union representing the first parameter passed when calling a function.
 ///
@@ -488,6 +489,7 @@ pub union VMGlobalDefinitionStorage {
     as_u64: u64,
     as_f64: f64,
     as_u128: u128,
+    as_externref: RawExternRef,
     bytes: [u8; 16],
 }
 
@@ -685,6 +687,25 @@ impl VMGlobalDefinition {
         &mut self.storage.as_u128
     }
 
+    /// Return the value as a raw `externref`.
+    ///
+    /// If this is not an ExternRef typed global it is unspecified what value is returned.
+    pub fn to_externref(&self) -> RawExternRef {
+        unsafe { self.storage.as_externref }
+    }
+
+    /// Return a mutable reference to the value as a raw `externref`.
+    ///
+    /// # Safety
+    ///
+    /// It is the callers responsibility to make sure the global has ExternRef type.
+    /// Until the returned borrow is dropped, reads and writes of this global
+    /// must be done exclusively through this borrow. That includes reads and
+    /// writes of globals inside wasm functions.
+    pub unsafe fn as_externref_mut(&mut self) -> &mut RawExternRef {
+        &mut self.storage.as_externref
+    }
+
     /// Return a reference to the value as bytes.
     pub fn to_bytes(&self) -> [u8; 16] {
         unsafe { self.storage.bytes }
@@ -866,9 +887,17 @@ impl VMBuiltinFunctionIndex {
     pub const fn get_raise_trap_index() -> Self {
         Self(13)
     }
+    /// Returns an index for wasm's `global.get` on `externref` globals.
+    pub const fn get_externref_global_get_index() -> Self {
+        Self(14)
+    }
+    /// Returns an index for wasm's `global.set` on `externref` globals.
+    pub const fn get_externref_global_set_index() -> Self {
+        Self(15)
+    }
     /// Returns the total number of builtin functions.
     pub const fn builtin_functions_total_number() -> u32 {
-        14
+        16
     }
 
     /// Return the index as an u32 number.
@@ -926,6 +955,10 @@ impl VMBuiltinFunctionsArray {
             wasmer_data_drop as usize;
         ptrs[VMBuiltinFunctionIndex::get_raise_trap_index().index() as usize] =
             wasmer_raise_trap as usize;
+        ptrs[VMBuiltinFunctionIndex::get_externref_global_get_index().index() as usize] =
+            wasmer_externref_global_get as usize;
+        ptrs[VMBuiltinFunctionIndex::get_externref_global_set_index().index() as usize] =
+            wasmer_externref_global_set as usize;
 
         debug_assert!(ptrs.iter().cloned().all(|p| p != 0));
 
@@ -17,23 +17,34 @@ mod macros;
 mod ptr;
 mod state;
 mod syscalls;
+#[cfg(test)]
+mod test_utils;
+mod trace;
 mod utils;
 
-use crate::syscalls::*;
+// The syscalls are registered through their traced wrappers.
+use crate::trace::*;
 
 pub use crate::state::{
-    Fd, Pipe, Stderr, Stdin, Stdout, WasiFile, WasiFs, WasiFsError, WasiState, WasiStateBuilder,
-    WasiStateCreationError, ALL_RIGHTS, VIRTUAL_ROOT_FD,
+    bounded_pipe, Fd, HostClock, HostRandom, Pipe, PipeReader, PipeWriter, SeededRandom, Stderr,
+    Stdin, Stdout, VirtualClock, WasiCapability, WasiClock, WasiFile, WasiFs, WasiFsError,
+    WasiPolicy, WasiQuotas, WasiRandom, WasiState, WasiStateBuilder, WasiStateCreationError,
+    ALL_RIGHTS, VIRTUAL_ROOT_FD,
 };
 pub use crate::syscalls::types;
-pub use crate::utils::{get_wasi_version, is_wasi_module, WasiVersion};
+pub use crate::trace::{SyscallArg, SyscallEvent, SyscallTracer};
+pub use crate::utils::{get_wasi_version, is_wasi_module, is_wasi_reactor, WasiVersion};
 
 use thiserror::Error;
-use wasmer::{imports, Function, ImportObject, LazyInit, Memory, Module, Store, WasmerEnv};
+use wasmer::{
+    imports, Function, ImportObject, Instance, LazyInit, Linker, LinkerError, Memory, Module,
+    RuntimeError, Store, WasmerEnv,
+};
 #[cfg(all(target_os = "macos", target_arch = "aarch64",))]
 use wasmer::{FunctionType, ValType};
 
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
 
 /// This is returned in `RuntimeError`.
 /// Use `downcast` or `downcast_ref` to retrieve the `ExitCode`.
@@ -43,6 +54,19 @@ pub enum WasiError {
     Exit(syscalls::types::__wasi_exitcode_t),
     #[error("The WASI version could not be determined")]
     UnknownWasiVersion,
+    #[error("WASI call `{0}` was denied by the policy")]
+    Denied(&'static str),
+}
+
+/// An error while initializing or starting a WASI instance.
+#[derive(Error, Debug)]
+pub enum WasiInstanceError {
+    /// The module is a reactor: it exports `_initialize`, or no `_start`.
+    #[error("The WASI module is a reactor: it can't be started, its exports must be called")]
+    Reactor,
+    /// The call failed; use `downcast` to retrieve a [`WasiError`].
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
 }
 
 /// The environment provided to the WASI imports.
@@ -57,6 +81,8 @@ pub struct WasiEnv {
     pub state: Arc<Mutex<WasiState>>,
     #[wasmer(export)]
     memory: LazyInit<Memory>,
+    /// The sink of the syscall trace, if any.
+    syscall_tracer: Option<Arc<dyn SyscallTracer>>,
 }
 
 impl WasiEnv {
@@ -64,9 +90,18 @@ impl WasiEnv {
         Self {
             state: Arc::new(Mutex::new(state)),
             memory: LazyInit::new(),
+            syscall_tracer: None,
         }
     }
 
+    /// Reports the syscalls made by the program to `tracer`.
+    ///
+    /// The import objects hold clones of the environment: the tracer must
+    /// be set before generating them.
+    pub fn set_syscall_tracer(&mut self, tracer: Arc<dyn SyscallTracer>) {
+        self.syscall_tracer = Some(tracer);
+    }
+
     pub fn import_object(&mut self, module: &Module) -> Result<ImportObject, WasiError> {
         let wasi_version = get_wasi_version(module, false).ok_or(WasiError::UnknownWasiVersion)?;
         Ok(generate_import_object_from_env(
@@ -76,6 +111,79 @@ impl WasiEnv {
         ))
     }
 
+    /// Defines the WASI imports of all the WASI versions in `linker`, so
+    /// that modules of any version can be instantiated with it.
+    ///
+    /// The environment is shared by all the definitions: it should be
+    /// used by a single instance.
+    pub fn add_to_linker<'a>(&self, linker: &'a mut Linker) -> Result<&'a mut Linker, LinkerError> {
+        for version in &[WasiVersion::Snapshot0, WasiVersion::Snapshot1] {
+            let import_object =
+                generate_import_object_from_env(linker.store(), self.clone(), *version);
+            linker.define_import_object(&import_object)?;
+        }
+
+        Ok(linker)
+    }
+
+    /// Initializes a reactor `instance` by calling its `_initialize`
+    /// function, if it exports one. Once it succeeds, the next calls for
+    /// the same instance do nothing; after a failure, it can be retried.
+    /// The concurrent calls wait for the ongoing initialization to end.
+    ///
+    /// Reactors must be initialized before calling their other exports.
+    pub fn initialize(&self, instance: &Instance) -> Result<(), WasiInstanceError> {
+        let initialization = instance.host_data::<ReactorInitialization>();
+        {
+            let mut state = initialization.state.lock().unwrap();
+            loop {
+                match *state {
+                    InitializationState::Initialized => return Ok(()),
+                    // Called again by `_initialize` itself.
+                    InitializationState::Initializing(thread)
+                        if thread == thread::current().id() =>
+                    {
+                        return Ok(())
+                    }
+                    InitializationState::Initializing(_) => {
+                        state = initialization.done.wait(state).unwrap();
+                    }
+                    InitializationState::Uninitialized => break,
+                }
+            }
+            *state = InitializationState::Initializing(thread::current().id());
+        }
+
+        // The lock isn't held while running the guest code: the concurrent
+        // callers wait for the outcome on the condition variable instead.
+        let result = match instance.exports.get_function("_initialize") {
+            Ok(initialize) => initialize.call(&[]).map(drop),
+            Err(_) => Ok(()),
+        };
+        *initialization.state.lock().unwrap() = if result.is_ok() {
+            InitializationState::Initialized
+        } else {
+            InitializationState::Uninitialized
+        };
+        initialization.done.notify_all();
+
+        Ok(result?)
+    }
+
+    /// Runs a command `instance` by calling its `_start` function.
+    ///
+    /// Reactors, exporting `_initialize` or no `_start` function, can't be
+    /// started: see [`is_wasi_reactor`].
+    pub fn start(&self, instance: &Instance) -> Result<(), WasiInstanceError> {
+        let start = match instance.exports.get_function("_start") {
+            Ok(start) if !instance.exports.contains("_initialize") => start,
+            _ => return Err(WasiInstanceError::Reactor),
+        };
+        start.call(&[])?;
+
+        Ok(())
+    }
+
     /// Get the WASI state
     ///
     /// Be careful when using this in host functions that call into Wasm:
@@ -108,6 +216,29 @@ impl WasiEnv {
     }
 }
 
+/// The initialization of a reactor instance, attached to it.
+struct ReactorInitialization {
+    state: Mutex<InitializationState>,
+    /// Notified when an initialization attempt ends.
+    done: Condvar,
+}
+
+impl Default for ReactorInitialization {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(InitializationState::Uninitialized),
+            done: Condvar::new(),
+        }
+    }
+}
+
+enum InitializationState {
+    Uninitialized,
+    /// `_initialize` is running on the thread.
+    Initializing(ThreadId),
+    Initialized,
+}
+
 /// Create an [`ImportObject`] with an existing [`WasiEnv`]. `WasiEnv`
 /// needs a [`WasiState`], that can be constructed from a
 /// [`WasiStateBuilder`](state::WasiStateBuilder).
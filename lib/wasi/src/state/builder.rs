@@ -1,6 +1,9 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
-use crate::state::{WasiFile, WasiFs, WasiFsError, WasiState};
+use crate::state::{
+    HostClock, HostRandom, WasiCapability, WasiClock, WasiFile, WasiFs, WasiFsError, WasiPolicy,
+    WasiQuotas, WasiRandom, WasiState, FD_WRITE_RIGHTS,
+};
 use crate::syscalls::types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO};
 use crate::WasiEnv;
 use std::path::{Path, PathBuf};
@@ -37,11 +40,16 @@ pub struct WasiStateBuilder {
     args: Vec<Vec<u8>>,
     envs: Vec<(Vec<u8>, Vec<u8>)>,
     preopens: Vec<PreopenedDir>,
+    preopened_files: Vec<PreopenedFile>,
     #[allow(clippy::type_complexity)]
     setup_fs_fn: Option<Box<dyn Fn(&mut WasiFs) -> Result<(), String> + Send>>,
     stdout_override: Option<Box<dyn WasiFile>>,
     stderr_override: Option<Box<dyn WasiFile>>,
     stdin_override: Option<Box<dyn WasiFile>>,
+    clock: Option<Box<dyn WasiClock>>,
+    random: Option<Box<dyn WasiRandom>>,
+    policy: WasiPolicy,
+    quotas: WasiQuotas,
 }
 
 impl std::fmt::Debug for WasiStateBuilder {
@@ -50,10 +58,15 @@ impl std::fmt::Debug for WasiStateBuilder {
             .field("args", &self.args)
             .field("envs", &self.envs)
             .field("preopens", &self.preopens)
+            .field("preopened_files", &self.preopened_files)
             .field("setup_fs_fn exists", &self.setup_fs_fn.is_some())
             .field("stdout_override exists", &self.stdout_override.is_some())
             .field("stderr_override exists", &self.stderr_override.is_some())
             .field("stdin_override exists", &self.stdin_override.is_some())
+            .field("clock", &self.clock)
+            .field("random", &self.random)
+            .field("policy", &self.policy)
+            .field("quotas", &self.quotas)
             .finish()
     }
 }
@@ -69,6 +82,10 @@ pub enum WasiStateCreationError {
     PreopenedDirectoryNotFound(PathBuf),
     #[error("preopened directory error: `{0}`")]
     PreopenedDirectoryError(String),
+    #[error("preopened file not found: `{0}`")]
+    PreopenedFileNotFound(PathBuf),
+    #[error("preopened file error: `{0}`")]
+    PreopenedFileError(String),
     #[error("mapped dir alias has wrong format: `{0}`")]
     MappedDirAliasFormattingError(String),
     #[error("wasi filesystem creation error: `{0}`")]
@@ -258,6 +275,47 @@ impl WasiStateBuilder {
         Ok(self)
     }
 
+    /// Preopen a host file, or an in-memory buffer, at a path of the WASI
+    /// namespace and configure it, without exposing the directory of the
+    /// host file.
+    ///
+    /// Usage:
+    ///
+    /// ```no_run
+    /// # use wasmer_wasi::{WasiState, WasiStateCreationError};
+    /// # fn main() -> Result<(), WasiStateCreationError> {
+    /// WasiState::new("program_name")
+    ///    .preopen_file(|p| p.file("config.toml").alias("/etc/config.toml").read(true))?
+    ///    .preopen_file(|p| p.buffer(Vec::new()).alias("/out/result.json").write(true))?
+    ///    .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preopen_file<F>(&mut self, inner: F) -> Result<&mut Self, WasiStateCreationError>
+    where
+        F: FnOnce(&mut PreopenFileBuilder) -> &mut PreopenFileBuilder,
+    {
+        let mut pfb = PreopenFileBuilder::new();
+        let po_file = inner(&mut pfb).build()?;
+
+        self.preopened_files.push(po_file);
+
+        Ok(self)
+    }
+
+    /// Preopen a host file with a different name exposed to the WASI, and
+    /// allow the WASI module to read and write it.
+    pub fn map_file<FilePath>(
+        &mut self,
+        alias: &str,
+        po_file: FilePath,
+    ) -> Result<&mut Self, WasiStateCreationError>
+    where
+        FilePath: AsRef<Path>,
+    {
+        self.preopen_file(|p| p.file(po_file).alias(alias).read(true).write(true))
+    }
+
     /// Overwrite the default WASI `stdout`, if you want to hold on to the
     /// original `stdout` use [`WasiFs::swap_file`] after building.
     pub fn stdout(&mut self, new_file: Box<dyn WasiFile>) -> &mut Self {
@@ -282,6 +340,89 @@ impl WasiStateBuilder {
         self
     }
 
+    /// Use `clock` as the source of time of the WASI clocks, instead of the
+    /// host clocks.
+    ///
+    /// Usage:
+    ///
+    /// ```no_run
+    /// # use wasmer_wasi::{VirtualClock, WasiState, WasiStateCreationError};
+    /// # fn main() -> Result<(), WasiStateCreationError> {
+    /// // the time starts at the epoch, and advances by 1ms every time it is read
+    /// WasiState::new("program_name")
+    ///    .clock(Box::new(VirtualClock::stepping(0, 1_000_000)))
+    ///    .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn clock(&mut self, clock: Box<dyn WasiClock>) -> &mut Self {
+        self.clock = Some(clock);
+
+        self
+    }
+
+    /// Use `random` as the source of the bytes of `random_get`, instead of
+    /// the random number generator of the host.
+    ///
+    /// Usage:
+    ///
+    /// ```no_run
+    /// # use wasmer_wasi::{SeededRandom, WasiState, WasiStateCreationError};
+    /// # fn main() -> Result<(), WasiStateCreationError> {
+    /// WasiState::new("program_name")
+    ///    .random(Box::new(SeededRandom::new(42)))
+    ///    .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn random(&mut self, random: Box<dyn WasiRandom>) -> &mut Self {
+        self.random = Some(random);
+
+        self
+    }
+
+    /// Restrict the calls allowed to the program with `policy`; the denied
+    /// calls return `__WASI_ENOTCAPABLE`.
+    ///
+    /// Usage:
+    ///
+    /// ```no_run
+    /// # use wasmer_wasi::{WasiCapability, WasiPolicy, WasiState, WasiStateCreationError};
+    /// # fn main() -> Result<(), WasiStateCreationError> {
+    /// WasiState::new("program_name")
+    ///    .preopen_dir("data")?
+    ///    .policy(WasiPolicy::new().deny(WasiCapability::FsWrite))
+    ///    .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn policy(&mut self, policy: WasiPolicy) -> &mut Self {
+        self.policy = policy;
+
+        self
+    }
+
+    /// Limit the resources the program can use through the filesystem with
+    /// `quotas`.
+    ///
+    /// Usage:
+    ///
+    /// ```no_run
+    /// # use wasmer_wasi::{WasiQuotas, WasiState, WasiStateCreationError};
+    /// # fn main() -> Result<(), WasiStateCreationError> {
+    /// WasiState::new("program_name")
+    ///    .preopen_dir("out")?
+    ///    .quotas(WasiQuotas::new().max_bytes_written(1024 * 1024))
+    ///    .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn quotas(&mut self, quotas: WasiQuotas) -> &mut Self {
+        self.quotas = quotas;
+
+        self
+    }
+
     /// Setup the WASI filesystem before running
     // TODO: improve ergonomics on this function
     pub fn setup_fs(
@@ -381,9 +522,26 @@ impl WasiStateBuilder {
                 .swap_file(__WASI_STDERR_FILENO, stderr_override)
                 .map_err(WasiStateCreationError::WasiFsError)?;
         }
+        // the preopened directories and files lose their write rights if the
+        // policy denies writing
+        let write_allowed = self.policy.is_allowed(WasiCapability::FsWrite);
+        if !write_allowed {
+            for fd in &wasi_fs.preopen_fds {
+                if let Some(fd_entry) = wasi_fs.fd_map.get_mut(fd) {
+                    fd_entry.rights &= !FD_WRITE_RIGHTS;
+                    fd_entry.rights_inheriting &= !FD_WRITE_RIGHTS;
+                }
+            }
+        }
+        for po_file in &self.preopened_files {
+            wasi_fs
+                .preopen_file(po_file, write_allowed)
+                .map_err(WasiStateCreationError::WasiFsCreationError)?;
+        }
         if let Some(f) = &self.setup_fs_fn {
             f(&mut wasi_fs).map_err(WasiStateCreationError::WasiFsSetupError)?;
         }
+        wasi_fs.set_quotas(self.quotas.clone());
         Ok(WasiState {
             fs: wasi_fs,
             args: self.args.clone(),
@@ -399,6 +557,9 @@ impl WasiStateBuilder {
                     env
                 })
                 .collect(),
+            policy: self.policy.clone(),
+            clock: self.clock.take().unwrap_or_else(|| Box::new(HostClock)),
+            random: self.random.take().unwrap_or_else(|| Box::new(HostRandom)),
         })
     }
 
@@ -514,9 +675,165 @@ impl PreopenDirBuilder {
     }
 }
 
+/// Builder for preopened files.
+#[derive(Debug, Default)]
+pub struct PreopenFileBuilder {
+    source: Option<PreopenedFileSource>,
+    alias: Option<String>,
+    read: bool,
+    write: bool,
+    create: bool,
+}
+
+/// The content of a preopened file.
+#[derive(Debug)]
+pub(crate) enum PreopenedFileSource {
+    /// A file on the host system
+    Host(PathBuf),
+    /// An in-memory file, initially holding the bytes of the buffer
+    Buffer(Vec<u8>),
+}
+
+/// The built version of `PreopenFileBuilder`
+#[derive(Debug)]
+pub(crate) struct PreopenedFile {
+    pub(crate) source: PreopenedFileSource,
+    pub(crate) alias: String,
+    pub(crate) read: bool,
+    pub(crate) write: bool,
+    pub(crate) create: bool,
+}
+
+impl PreopenFileBuilder {
+    /// Create an empty builder
+    pub(crate) fn new() -> Self {
+        PreopenFileBuilder::default()
+    }
+
+    /// Point the preopened file to the host file given by `po_file`
+    pub fn file<FilePath>(&mut self, po_file: FilePath) -> &mut Self
+    where
+        FilePath: AsRef<Path>,
+    {
+        let path = po_file.as_ref();
+        self.source = Some(PreopenedFileSource::Host(path.to_path_buf()));
+
+        self
+    }
+
+    /// Make the preopened file an in-memory file, initially holding
+    /// `buffer`
+    pub fn buffer(&mut self, buffer: Vec<u8>) -> &mut Self {
+        self.source = Some(PreopenedFileSource::Buffer(buffer));
+
+        self
+    }
+
+    /// Make this preopened file appear to the WASI program as `alias`,
+    /// e.g. `/etc/config.toml`
+    ///
+    /// The alias is required for the buffers; the host files appear as
+    /// their path by default. The alias is relative to the root, and must
+    /// not contain `.` or `..` components.
+    pub fn alias(&mut self, alias: &str) -> &mut Self {
+        // The preopened files are mounted at `/`, like the preopened dirs.
+        let alias = alias.trim_start_matches('/');
+        self.alias = Some(alias.to_string());
+
+        self
+    }
+
+    /// Set read permissions on the file
+    pub fn read(&mut self, toggle: bool) -> &mut Self {
+        self.read = toggle;
+
+        self
+    }
+
+    /// Set write permissions on the file
+    pub fn write(&mut self, toggle: bool) -> &mut Self {
+        self.write = toggle;
+
+        self
+    }
+
+    /// Create the host file when the state is built, if it doesn't exist
+    ///
+    /// Create implies `write` permissions; the file isn't created if the
+    /// policy denies writing
+    pub fn create(&mut self, toggle: bool) -> &mut Self {
+        self.create = toggle;
+        if toggle {
+            self.write = true;
+        }
+
+        self
+    }
+
+    pub(crate) fn build(&mut self) -> Result<PreopenedFile, WasiStateCreationError> {
+        // ensure at least one is set
+        if !(self.read || self.write) {
+            return Err(WasiStateCreationError::PreopenedFileError(
+                "Preopened files must have at least one of read, write permissions set".to_string(),
+            ));
+        }
+
+        let source = self.source.take().ok_or_else(|| {
+            WasiStateCreationError::PreopenedFileError(
+                "Preopened files must point to a host file or a buffer".to_string(),
+            )
+        })?;
+        let alias = match (&source, self.alias.take()) {
+            (_, Some(alias)) => alias,
+            (PreopenedFileSource::Host(path), None) => {
+                path.to_string_lossy().trim_start_matches('/').to_string()
+            }
+            (PreopenedFileSource::Buffer(_), None) => {
+                return Err(WasiStateCreationError::PreopenedFileError(
+                    "Preopened buffers must have an alias".to_string(),
+                ))
+            }
+        };
+        validate_mapped_dir_alias(&alias)?;
+        if alias
+            .split('/')
+            .any(|component| component.is_empty() || component == "." || component == "..")
+        {
+            return Err(WasiStateCreationError::PreopenedFileError(format!(
+                "The alias `{}` must be a path to a file, without `.` or `..` components",
+                alias
+            )));
+        }
+
+        // the file is only created when the state is built, if the policy
+        // allows writing
+        if let PreopenedFileSource::Host(path) = &source {
+            let found = if path.exists() {
+                path.is_file()
+            } else {
+                self.create
+            };
+            if !found {
+                return Err(WasiStateCreationError::PreopenedFileNotFound(path.clone()));
+            }
+        }
+
+        Ok(PreopenedFile {
+            source,
+            alias,
+            read: self.read,
+            write: self.write,
+            create: self.create,
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::state::VIRTUAL_ROOT_FD;
+    use crate::syscalls::types::*;
+    use crate::test_utils::SyscallTester;
 
     #[test]
     fn env_var_errors() {
@@ -572,4 +889,85 @@ mod test {
             _ => assert!(false),
         }
     }
+
+    #[test]
+    fn preopened_host_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.toml");
+        let result = dir.path().join("result.json");
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&config, "act = 1").unwrap();
+        std::fs::write(&notes, "to be").unwrap();
+
+        let mut builder = create_wasi_state("test_prog");
+        builder
+            .preopen_file(|p| p.file(&config).alias("/etc/config.toml").read(true))
+            .unwrap()
+            .preopen_file(|p| p.file(&result).alias("/out/result.json").create(true))
+            .unwrap()
+            .map_file("notes.txt", &notes)
+            .unwrap();
+        // the files are only created when the state is built
+        assert!(!result.exists());
+        let tester = SyscallTester::new(builder.build().unwrap());
+        assert!(result.exists());
+        let (config_fd, result_fd, notes_fd) = {
+            let fs = &tester.env.state().fs;
+            (fs.preopen_fds[1], fs.preopen_fds[2], fs.preopen_fds[3])
+        };
+        let open = |dirfd, path, rights| tester.path_open(dirfd, 0, path, 0, rights, 0);
+
+        // the files are opened as `.` through their preopened fd, with its
+        // rights
+        let fd = open(config_fd, ".", __WASI_RIGHT_FD_READ).unwrap();
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"act = 1".to_vec()));
+        assert_eq!(tester.fd_write(fd, b"act = 2"), Err(__WASI_EACCES));
+        let fd = open(result_fd, ".", __WASI_RIGHT_FD_WRITE).unwrap();
+        assert_eq!(tester.fd_write(fd, b"{}"), Ok(2));
+        assert_eq!(std::fs::read_to_string(&result).unwrap(), "{}");
+        let fd = open(notes_fd, ".", __WASI_RIGHT_FD_READ).unwrap();
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"to be".to_vec()));
+        assert_eq!(tester.fd_write(fd, b" or not"), Ok(7));
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "to be or not");
+
+        // they are also found by their alias from the root, without more
+        // rights
+        let fd = open(VIRTUAL_ROOT_FD, "etc/config.toml", __WASI_RIGHT_FD_READ).unwrap();
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"act = 1".to_vec()));
+        assert_eq!(
+            open(VIRTUAL_ROOT_FD, "etc/config.toml", __WASI_RIGHT_FD_WRITE),
+            Err(__WASI_ENOTCAPABLE)
+        );
+        assert_eq!(
+            open(VIRTUAL_ROOT_FD, "etc/passwd", __WASI_RIGHT_FD_READ),
+            Err(__WASI_ENOENT)
+        );
+    }
+
+    #[test]
+    fn preopened_file_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.json");
+
+        // the files aren't created if they can't be written
+        assert!(create_wasi_state("test_prog")
+            .preopen_file(|p| p.file(&missing).alias("result.json").create(true))
+            .unwrap()
+            .policy(WasiPolicy::new().deny(WasiCapability::FsWrite))
+            .build()
+            .is_err());
+        assert!(!missing.exists());
+        assert!(matches!(
+            create_wasi_state("test_prog").preopen_file(|p| p.file(&missing).read(true)),
+            Err(WasiStateCreationError::PreopenedFileNotFound(_))
+        ));
+
+        for alias in &["", "etc/", "../etc/passwd", "etc/./config.toml"] {
+            assert!(matches!(
+                create_wasi_state("test_prog")
+                    .preopen_file(|p| p.buffer(Vec::new()).alias(alias).read(true)),
+                Err(WasiStateCreationError::PreopenedFileError(_))
+            ));
+        }
+    }
 }
@@ -0,0 +1,160 @@
+//! The sources of time of the WASI clocks: the host clocks, or a virtual
+//! clock for reproducible runs.
+
+use crate::syscalls::types::*;
+use crate::syscalls::{platform_clock_res_get, platform_clock_time_get};
+use std::cell::Cell;
+use std::fmt;
+use std::time::Duration;
+
+/// The source of time of the WASI clocks, read by `clock_time_get`,
+/// `clock_res_get` and the clock subscriptions of `poll_oneoff`.
+///
+/// Implement it to control the time seen by a program, for example from
+/// a host callback, and install it with [`WasiStateBuilder::clock`].
+///
+/// [`WasiStateBuilder::clock`]: crate::WasiStateBuilder::clock
+pub trait WasiClock: fmt::Debug + Send {
+    /// Returns the time of the clock `clock_id`, in nanoseconds.
+    fn time_get(
+        &mut self,
+        clock_id: __wasi_clockid_t,
+        precision: __wasi_timestamp_t,
+    ) -> Result<__wasi_timestamp_t, __wasi_errno_t>;
+
+    /// Returns the resolution of the clock `clock_id`, in nanoseconds.
+    fn res_get(&mut self, clock_id: __wasi_clockid_t)
+        -> Result<__wasi_timestamp_t, __wasi_errno_t>;
+
+    /// Called when `poll_oneoff` waits for `duration` nanoseconds of the
+    /// clock `clock_id` to elapse, and returns how long the thread must
+    /// sleep for them.
+    ///
+    /// The default implementation returns `duration`, for a clock following
+    /// the host time.
+    fn sleep(
+        &mut self,
+        _clock_id: __wasi_clockid_t,
+        duration: __wasi_timestamp_t,
+    ) -> Result<Duration, __wasi_errno_t> {
+        Ok(Duration::from_nanos(duration))
+    }
+}
+
+/// The clocks of the host, used by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HostClock;
+
+impl WasiClock for HostClock {
+    fn time_get(
+        &mut self,
+        clock_id: __wasi_clockid_t,
+        precision: __wasi_timestamp_t,
+    ) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+        let time = Cell::new(0);
+        match platform_clock_time_get(clock_id, precision, &time) {
+            __WASI_ESUCCESS => Ok(time.get()),
+            errno => Err(errno),
+        }
+    }
+
+    fn res_get(
+        &mut self,
+        clock_id: __wasi_clockid_t,
+    ) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+        let resolution = Cell::new(0);
+        match platform_clock_res_get(clock_id, &resolution) {
+            __WASI_ESUCCESS => Ok(resolution.get()),
+            errno => Err(errno),
+        }
+    }
+}
+
+/// A virtual clock, whose time advances by a fixed step every time it is
+/// read, independently of the host clocks. Waiting for it in `poll_oneoff`
+/// advances it to the end of the wait at once.
+///
+/// All the WASI clocks read the same virtual time.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    time: __wasi_timestamp_t,
+    step: __wasi_timestamp_t,
+}
+
+impl VirtualClock {
+    /// Creates a clock frozen at `time`.
+    pub fn frozen(time: __wasi_timestamp_t) -> Self {
+        Self::stepping(time, 0)
+    }
+
+    /// Creates a clock starting at `start`, and advancing by `step`
+    /// nanoseconds every time it is read.
+    pub fn stepping(start: __wasi_timestamp_t, step: __wasi_timestamp_t) -> Self {
+        Self { time: start, step }
+    }
+}
+
+impl WasiClock for VirtualClock {
+    fn time_get(
+        &mut self,
+        clock_id: __wasi_clockid_t,
+        _precision: __wasi_timestamp_t,
+    ) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+        check_clock_id(clock_id)?;
+        let time = self.time;
+        self.time = self.time.saturating_add(self.step);
+        Ok(time)
+    }
+
+    fn res_get(
+        &mut self,
+        clock_id: __wasi_clockid_t,
+    ) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+        check_clock_id(clock_id)?;
+        Ok(self.step.max(1))
+    }
+
+    /// The clock advances by `duration` at once, without sleeping.
+    fn sleep(
+        &mut self,
+        clock_id: __wasi_clockid_t,
+        duration: __wasi_timestamp_t,
+    ) -> Result<Duration, __wasi_errno_t> {
+        check_clock_id(clock_id)?;
+        self.time = self.time.saturating_add(duration);
+        Ok(Duration::from_secs(0))
+    }
+}
+
+fn check_clock_id(clock_id: __wasi_clockid_t) -> Result<(), __wasi_errno_t> {
+    match clock_id {
+        __WASI_CLOCK_REALTIME
+        | __WASI_CLOCK_MONOTONIC
+        | __WASI_CLOCK_PROCESS_CPUTIME_ID
+        | __WASI_CLOCK_THREAD_CPUTIME_ID => Ok(()),
+        _ => Err(__WASI_EINVAL),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn virtual_clocks() {
+        let mut clock = VirtualClock::stepping(10, 5);
+        assert_eq!(clock.time_get(__WASI_CLOCK_REALTIME, 0), Ok(10));
+        assert_eq!(clock.time_get(__WASI_CLOCK_MONOTONIC, 0), Ok(15));
+        assert_eq!(clock.res_get(__WASI_CLOCK_MONOTONIC), Ok(5));
+        assert_eq!(clock.time_get(42, 0), Err(__WASI_EINVAL));
+
+        let mut frozen = VirtualClock::frozen(7);
+        assert_eq!(frozen.time_get(__WASI_CLOCK_REALTIME, 0), Ok(7));
+        assert_eq!(frozen.time_get(__WASI_CLOCK_REALTIME, 0), Ok(7));
+        assert_eq!(
+            frozen.sleep(__WASI_CLOCK_MONOTONIC, 100),
+            Ok(Duration::from_secs(0))
+        );
+        assert_eq!(frozen.time_get(__WASI_CLOCK_REALTIME, 0), Ok(107));
+    }
+}
@@ -16,21 +16,32 @@
 #![allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 
 mod builder;
+mod clock;
+mod pipe;
+mod policy;
+mod quota;
+mod random;
 mod types;
+mod waker;
 
 pub use self::builder::*;
+pub use self::clock::*;
+pub use self::pipe::*;
+pub use self::policy::*;
+pub use self::quota::*;
+pub use self::random::*;
 pub use self::types::*;
+pub use self::waker::*;
 use crate::syscalls::types::*;
 use generational_arena::Arena;
 pub use generational_arena::Index as Inode;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::{
-    borrow::Borrow,
     cell::Cell,
     fs,
     io::Write,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     time::SystemTime,
 };
 use tracing::debug;
@@ -163,6 +174,11 @@ pub struct WasiFs {
     inode_counter: Cell<u64>,
     /// for fds still open after the file has been deleted
     pub orphan_fds: HashMap<Inode, InodeVal>,
+    quotas: WasiQuotas,
+    quota_usage: QuotaUsage,
+    /// The rights of the preopened files, which limit the rights of the fds
+    /// opening them from any directory
+    preopened_file_rights: HashMap<Inode, __wasi_rights_t>,
 }
 
 impl WasiFs {
@@ -415,6 +431,157 @@ impl WasiFs {
         Ok(wasi_fs)
     }
 
+    /// Preopens the file `po_file`, with its write rights only if
+    /// `write_allowed`. A host file is only created if it can be written.
+    ///
+    /// The file gets its own fd, named after its alias like a preopened
+    /// directory: WASI only knows preopened directories, so `fd_prestat_get`
+    /// reports it as one, and the program opens it through this fd as `.`.
+    /// The file can also be opened by its alias from the root, where the
+    /// missing parent directories of the alias are created as read-only
+    /// virtual directories.
+    pub(crate) fn preopen_file(
+        &mut self,
+        po_file: &PreopenedFile,
+        write_allowed: bool,
+    ) -> Result<(), String> {
+        let PreopenedFile {
+            source,
+            alias,
+            read,
+            write,
+            create,
+        } = po_file;
+        debug!("Attempting to preopen file {:?} at {}", source, alias);
+        let write = *write && write_allowed;
+
+        let inode = match source {
+            PreopenedFileSource::Host(path) => {
+                let file = std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(write)
+                    .create(*create && write)
+                    .open(path)
+                    .map_err(|e| format!("Could not open file {:?}: {}", path, e))?;
+                let kind = Kind::File {
+                    handle: Some(Box::new(HostFile::new(
+                        file,
+                        path.clone(),
+                        true,
+                        write,
+                        false,
+                    ))),
+                    path: path.clone(),
+                    fd: None,
+                };
+                self.create_inode(kind, true, alias.clone()).map_err(|e| {
+                    format!(
+                        "Failed to create inode for preopened file: WASI error code: {}",
+                        e
+                    )
+                })?
+            }
+            PreopenedFileSource::Buffer(buffer) => {
+                let stat = __wasi_filestat_t {
+                    st_filetype: __WASI_FILETYPE_REGULAR_FILE,
+                    st_size: buffer.len() as u64,
+                    ..__wasi_filestat_t::default()
+                };
+                let kind = Kind::Buffer {
+                    buffer: buffer.clone(),
+                };
+                self.create_inode_with_stat(kind, true, alias.clone(), stat)
+            }
+        };
+
+        // the file is reopened as `.` through its fd
+        let mut rights = __WASI_RIGHT_PATH_OPEN
+            | __WASI_RIGHT_PATH_FILESTAT_GET
+            | __WASI_RIGHT_FD_FILESTAT_GET
+            | __WASI_RIGHT_FD_FDSTAT_SET_FLAGS
+            | __WASI_RIGHT_FD_ADVISE
+            | __WASI_RIGHT_FD_SEEK
+            | __WASI_RIGHT_FD_TELL
+            | __WASI_RIGHT_POLL_FD_READWRITE;
+        let mut fd_flags = 0;
+        if *read {
+            rights |= __WASI_RIGHT_FD_READ;
+            fd_flags |= Fd::READ;
+        }
+        if write {
+            rights |= __WASI_RIGHT_FD_WRITE
+                | __WASI_RIGHT_FD_DATASYNC
+                | __WASI_RIGHT_FD_SYNC
+                | __WASI_RIGHT_FD_ALLOCATE
+                | __WASI_RIGHT_FD_FILESTAT_SET_SIZE
+                | __WASI_RIGHT_FD_FILESTAT_SET_TIMES
+                | __WASI_RIGHT_PATH_FILESTAT_SET_SIZE
+                | __WASI_RIGHT_PATH_FILESTAT_SET_TIMES;
+            fd_flags |= Fd::WRITE;
+        }
+        let fd = self
+            .create_fd(rights, rights, 0, fd_flags, inode)
+            .map_err(|e| format!("Could not open fd for file {}: {}", alias, e))?;
+        self.preopened_file_rights.insert(inode, rights);
+
+        let mut parent_inode = self.fd_map[&VIRTUAL_ROOT_FD].inode;
+        let mut components = alias.split('/').collect::<Vec<_>>();
+        let name = components.pop().unwrap_or_default();
+        for component in components {
+            let entry = match &self.inodes[parent_inode].kind {
+                Kind::Dir { entries, .. } | Kind::Root { entries } => {
+                    entries.get(component).cloned()
+                }
+                _ => return Err(format!("Can not preopen `{}`: not a directory", alias)),
+            };
+            parent_inode = match entry {
+                Some(inode) => inode,
+                None => {
+                    let stat = __wasi_filestat_t {
+                        st_filetype: __WASI_FILETYPE_DIRECTORY,
+                        ..__wasi_filestat_t::default()
+                    };
+                    let kind = Kind::Root {
+                        entries: HashMap::new(),
+                    };
+                    let inode =
+                        self.create_inode_with_stat(kind, false, component.to_string(), stat);
+                    self.insert_entry(parent_inode, component.to_string(), inode);
+                    inode
+                }
+            };
+        }
+        match &mut self.inodes[parent_inode].kind {
+            Kind::Dir { entries, .. } | Kind::Root { entries } => {
+                if entries.insert(name.to_string(), inode).is_some() {
+                    return Err(format!("Found duplicate entry for alias `{}`", alias));
+                }
+            }
+            _ => return Err(format!("Can not preopen `{}`: not a directory", alias)),
+        }
+        self.preopen_fds.push(fd);
+
+        Ok(())
+    }
+
+    /// Returns the rights of `inode` if it's a preopened file.
+    pub(crate) fn preopened_file_rights(&self, inode: Inode) -> Option<__wasi_rights_t> {
+        self.preopened_file_rights.get(&inode).cloned()
+    }
+
+    /// Returns the content of the preopened buffer at `alias`, e.g. to read
+    /// the output of the program.
+    pub fn preopened_buffer(&self, alias: &str) -> Option<&[u8]> {
+        let alias = alias.trim_start_matches('/');
+        self.preopen_fds.iter().find_map(|fd| {
+            let inode = &self.inodes[self.fd_map.get(fd)?.inode];
+            match &inode.kind {
+                Kind::Buffer { buffer } if inode.name == alias => Some(&buffer[..]),
+                _ => None,
+            }
+        })
+    }
+
     /// Private helper function to init the filesystem, called in `new` and
     /// `new_with_preopen`
     fn new_init() -> Result<(Self, Inode), String> {
@@ -428,6 +595,9 @@ impl WasiFs {
             next_fd: Cell::new(3),
             inode_counter: Cell::new(1024),
             orphan_fds: HashMap::new(),
+            quotas: WasiQuotas::default(),
+            quota_usage: QuotaUsage::default(),
+            preopened_file_rights: HashMap::new(),
         };
         wasi_fs.create_stdin();
         wasi_fs.create_stdout();
@@ -498,8 +668,8 @@ impl WasiFs {
             if let Kind::File { ref handle, .. } = self.inodes[fd.inode].kind {
                 Ok(handle)
             } else {
-                // Our public API should ensure that this is not possible
-                unreachable!("Non-file found in standard device location")
+                // a directory has been renumbered to the standard device
+                Err(WasiFsError::NotAFile)
             }
         } else {
             // this should only trigger if we made a mistake in this crate
@@ -516,8 +686,8 @@ impl WasiFs {
             if let Kind::File { ref mut handle, .. } = self.inodes[fd.inode].kind {
                 Ok(handle)
             } else {
-                // Our public API should ensure that this is not possible
-                unreachable!("Non-file found in standard device location")
+                // a directory has been renumbered to the standard device
+                Err(WasiFsError::NotAFile)
             }
         } else {
             // this should only trigger if we made a mistake in this crate
@@ -525,6 +695,18 @@ impl WasiFs {
         }
     }
 
+    /// Returns another handle on the pipe end opened as `fd`, if any.
+    pub(crate) fn get_pipe_end(&self, fd: __wasi_fd_t) -> Option<PipeEnd> {
+        let fd = self.fd_map.get(&fd)?;
+        match &self.inodes[fd.inode].kind {
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => PipeEnd::of(handle.as_ref()),
+            _ => None,
+        }
+    }
+
     /// Returns the next available inode index for creating a new inode.
     fn get_next_inode_index(&mut self) -> u64 {
         let next = self.inode_counter.get();
@@ -691,6 +873,49 @@ impl WasiFs {
         Ok(ret)
     }
 
+    /// Whether `inode` is the directory `dir` or is within it.
+    pub(crate) fn is_inode_within(&self, mut inode: Inode, dir: Inode) -> bool {
+        loop {
+            if inode == dir {
+                return true;
+            }
+            match &self.inodes[inode].kind {
+                Kind::Dir {
+                    parent: Some(parent),
+                    ..
+                } => inode = *parent,
+                _ => return false,
+            }
+        }
+    }
+
+    /// Updates the host paths of `inode`, and of all the inodes within it if
+    /// it's a directory, after it was moved to `new_path` on the host.
+    pub(crate) fn update_host_paths(&mut self, inode: Inode, new_path: PathBuf) {
+        let mut stack = vec![(inode, new_path)];
+        while let Some((inode, new_path)) = stack.pop() {
+            match &mut self.inodes[inode].kind {
+                Kind::Dir { path, entries, .. } => {
+                    for (name, entry) in entries.iter() {
+                        stack.push((*entry, new_path.join(name)));
+                    }
+                    *path = new_path;
+                }
+                Kind::File { path, handle, .. } => {
+                    if let Some(host_file) = handle
+                        .as_mut()
+                        .and_then(|handle| handle.downcast_mut::<HostFile>())
+                    {
+                        host_file.host_path = new_path.clone();
+                    }
+                    *path = new_path;
+                }
+                // the virtual entries don't exist on the host
+                Kind::Buffer { .. } | Kind::Symlink { .. } | Kind::Root { .. } => {}
+            }
+        }
+    }
+
     /// refresh size from filesystem
     pub(crate) fn filestat_resync_size(
         &mut self,
@@ -707,6 +932,11 @@ impl WasiFs {
                     Err(__WASI_EBADF)
                 }
             }
+            Kind::Buffer { buffer } => {
+                let new_size = buffer.len() as u64;
+                self.inodes[fd.inode].stat.st_size = new_size;
+                Ok(new_size as __wasi_filesize_t)
+            }
             Kind::Dir { .. } | Kind::Root { .. } => Err(__WASI_EISDIR),
             _ => Err(__WASI_EINVAL),
         }
@@ -717,246 +947,285 @@ impl WasiFs {
     /// `.` and `..`) and resolving symlinks (while preventing infinite
     /// loops/stack overflows).
     ///
-    /// TODO: expand upon exactly what the state of the returned value is,
-    /// explaining lazy-loading from the real file system and synchronizing
-    /// between them.
+    /// Every component but the last one must be a directory: the symlinks
+    /// found there are always followed. The last component is followed only
+    /// if `follow_symlinks` is set, otherwise the symlink itself is returned.
+    /// `symlink_count` counts the symlinks followed so far, across the
+    /// recursive resolutions of their targets; more than [`MAX_SYMLINKS`]
+    /// of them is reported as a loop, with `__WASI_ELOOP`.
+    ///
+    /// The files, directories and symlinks missing from the inode tree are
+    /// lazily loaded from the host file system. The files and directories
+    /// are inserted in their parent directory, but not the symlinks, which
+    /// are loaded again on every lookup, as they can be changed on the host.
     ///
     /// This is where a lot of the magic happens, be very careful when editing
     /// this code.
-    ///
-    /// TODO: write more tests for this code
     fn get_inode_at_path_inner(
         &mut self,
         base: __wasi_fd_t,
         path: &str,
-        mut symlink_count: u32,
+        symlink_count: &mut u32,
         follow_symlinks: bool,
     ) -> Result<Inode, __wasi_errno_t> {
-        if symlink_count > MAX_SYMLINKS {
-            return Err(__WASI_EMLINK);
-        }
-
         let base_dir = self.get_fd(base)?;
         let path: &Path = Path::new(path);
+        // the lookups from the virtual root can already reach all the
+        // preopened directories
+        let from_root = matches!(self.inodes[base_dir.inode].kind, Kind::Root { .. });
 
         let mut cur_inode = base_dir.inode;
-        let n_components = path.components().count();
         // TODO: rights checks
-        'path_iter: for (i, component) in path.components().enumerate() {
-            // used to terminate symlink resolution properly
-            let last_component = i + 1 == n_components;
-            // for each component traverse file structure
-            // loading inodes as necessary
-            'symlink_resolution: while symlink_count < MAX_SYMLINKS {
-                match &mut self.inodes[cur_inode].kind {
-                    Kind::Buffer { .. } => unimplemented!("state::get_inode_at_path for buffers"),
-                    Kind::Dir {
-                        ref mut entries,
-                        ref path,
-                        ref parent,
-                        ..
-                    } => {
-                        match component.as_os_str().to_string_lossy().borrow() {
-                            ".." => {
-                                if let Some(p) = parent {
-                                    cur_inode = *p;
-                                    continue 'path_iter;
-                                } else {
-                                    return Err(__WASI_EACCES);
-                                }
+        for component in path.components() {
+            // the previous component is a directory we traverse, so it's
+            // resolved even if it is a symlink
+            cur_inode = self.follow_symlink(cur_inode, symlink_count)?;
+            let name = component.as_os_str().to_string_lossy().to_string();
+
+            match &self.inodes[cur_inode].kind {
+                Kind::Dir {
+                    entries,
+                    path,
+                    parent,
+                    ..
+                } => {
+                    match component {
+                        Component::ParentDir => {
+                            // `..` doesn't escape a preopened directory to
+                            // the virtual root, and to the other preopened
+                            // directories
+                            if self.inodes[cur_inode].is_preopened && !from_root {
+                                return Err(__WASI_ENOTCAPABLE);
                             }
-                            "." => continue 'path_iter,
-                            _ => (),
-                        }
-                        // used for full resolution of symlinks
-                        let mut loop_for_symlink = false;
-                        if let Some(entry) =
-                            entries.get(component.as_os_str().to_string_lossy().as_ref())
-                        {
-                            cur_inode = *entry;
-                        } else {
-                            let file = {
-                                let mut cd = path.clone();
-                                cd.push(component);
-                                cd
-                            };
-                            let metadata = file.symlink_metadata().ok().ok_or(__WASI_EINVAL)?;
-                            let file_type = metadata.file_type();
-                            // we want to insert newly opened dirs and files, but not transient symlinks
-                            // TODO: explain why (think about this deeply when well rested)
-                            let mut should_insert = false;
-
-                            let kind = if file_type.is_dir() {
-                                should_insert = true;
-                                // load DIR
-                                Kind::Dir {
-                                    parent: Some(cur_inode),
-                                    path: file.clone(),
-                                    entries: Default::default(),
-                                }
-                            } else if file_type.is_file() {
-                                should_insert = true;
-                                // load file
-                                Kind::File {
-                                    handle: None,
-                                    path: file.clone(),
-                                    fd: None,
-                                }
-                            } else if file_type.is_symlink() {
-                                let link_value = file.read_link().ok().ok_or(__WASI_EIO)?;
-                                debug!("attempting to decompose path {:?}", link_value);
-
-                                let (pre_open_dir_fd, relative_path) = if link_value.is_relative() {
-                                    self.path_into_pre_open_and_relative_path(&file)?
-                                } else {
-                                    unimplemented!("Absolute symlinks are not yet supported");
-                                };
-                                loop_for_symlink = true;
-                                symlink_count += 1;
-                                Kind::Symlink {
-                                    base_po_dir: pre_open_dir_fd,
-                                    path_to_symlink: relative_path,
-                                    relative_path: link_value,
-                                }
+                            if let Some(p) = parent {
+                                cur_inode = *p;
+                                continue;
                             } else {
-                                #[cfg(unix)]
-                                {
-                                    use std::os::unix::fs::FileTypeExt;
-                                    let file_type: __wasi_filetype_t = if file_type.is_char_device()
-                                    {
-                                        __WASI_FILETYPE_CHARACTER_DEVICE
-                                    } else if file_type.is_block_device() {
-                                        __WASI_FILETYPE_BLOCK_DEVICE
-                                    } else if file_type.is_fifo() {
-                                        // FIFO doesn't seem to fit any other type, so unknown
-                                        __WASI_FILETYPE_UNKNOWN
-                                    } else if file_type.is_socket() {
-                                        // TODO: how do we know if it's a `__WASI_FILETYPE_SOCKET_STREAM` or
-                                        // a `__WASI_FILETYPE_SOCKET_DGRAM`?
-                                        __WASI_FILETYPE_SOCKET_STREAM
-                                    } else {
-                                        unimplemented!("state::get_inode_at_path unknown file type: not file, directory, symlink, char device, block device, fifo, or socket");
-                                    };
-
-                                    let kind = Kind::File {
-                                        handle: None,
-                                        path: file.clone(),
-                                        fd: None,
-                                    };
-                                    let new_inode = self.create_inode_with_stat(
-                                        kind,
-                                        false,
-                                        file.to_string_lossy().to_string(),
-                                        __wasi_filestat_t {
-                                            st_filetype: file_type,
-                                            ..__wasi_filestat_t::default()
-                                        },
-                                    );
-                                    if let Kind::Dir {
-                                        ref mut entries, ..
-                                    } = &mut self.inodes[cur_inode].kind
-                                    {
-                                        entries.insert(
-                                            component.as_os_str().to_string_lossy().to_string(),
-                                            new_inode,
-                                        );
-                                    } else {
-                                        unreachable!(
-                                            "Attempted to insert special device into non-directory"
-                                        );
-                                    }
-                                    // perhaps just continue with symlink resolution and return at the end
-                                    return Ok(new_inode);
-                                }
-                                #[cfg(not(unix))]
-                                unimplemented!("state::get_inode_at_path unknown file type: not file, directory, or symlink");
-                            };
-
-                            let new_inode =
-                                self.create_inode(kind, false, file.to_string_lossy().to_string())?;
-                            if should_insert {
-                                if let Kind::Dir {
-                                    ref mut entries, ..
-                                } = &mut self.inodes[cur_inode].kind
-                                {
-                                    entries.insert(
-                                        component.as_os_str().to_string_lossy().to_string(),
-                                        new_inode,
-                                    );
-                                }
-                            }
-                            cur_inode = new_inode;
-
-                            if loop_for_symlink && follow_symlinks {
-                                debug!("Following symlink to {:?}", cur_inode);
-                                continue 'symlink_resolution;
+                                return Err(__WASI_EACCES);
                             }
                         }
-                    }
-                    Kind::Root { entries } => {
-                        match component.as_os_str().to_string_lossy().borrow() {
-                            // the root's parent is the root
-                            ".." => continue 'path_iter,
-                            // the root's current directory is the root
-                            "." => continue 'path_iter,
-                            _ => (),
+                        Component::CurDir => continue,
+                        // an absolute path would escape the sandbox
+                        Component::RootDir | Component::Prefix(_) => {
+                            return Err(__WASI_ENOTCAPABLE)
                         }
+                        Component::Normal(_) => (),
+                    }
 
-                        if let Some(entry) =
-                            entries.get(component.as_os_str().to_string_lossy().as_ref())
-                        {
-                            cur_inode = *entry;
-                        } else {
-                            return Err(__WASI_EINVAL);
-                        }
+                    if let Some(entry) = entries.get(&name) {
+                        cur_inode = *entry;
+                        continue;
                     }
-                    Kind::File { .. } => {
-                        return Err(__WASI_ENOTDIR);
+
+                    let file = path.join(component);
+                    cur_inode = self.load_inode_from_host(cur_inode, name, file)?;
+                }
+                Kind::Root { entries } => {
+                    match component {
+                        // the root's parent is the root
+                        Component::ParentDir => continue,
+                        // the root's current directory is the root
+                        Component::CurDir => continue,
+                        _ => (),
                     }
-                    Kind::Symlink {
-                        base_po_dir,
-                        path_to_symlink,
-                        relative_path,
-                    } => {
-                        let new_base_dir = *base_po_dir;
-                        // allocate to reborrow mutabily to recur
-                        let new_path = {
-                            /*if let Kind::Root { .. } = self.inodes[base_po_dir].kind {
-                                assert!(false, "symlinks should never be relative to the root");
-                            }*/
-                            let mut base = path_to_symlink.clone();
-                            // remove the symlink file itself from the path, leaving just the path from the base
-                            // to the dir containing the symlink
-                            base.pop();
-                            base.push(relative_path);
-                            base.to_string_lossy().to_string()
-                        };
-                        debug!("Following symlink recursively");
-                        let symlink_inode = self.get_inode_at_path_inner(
-                            new_base_dir,
-                            &new_path,
-                            symlink_count + 1,
-                            follow_symlinks,
-                        )?;
-                        cur_inode = symlink_inode;
-                        // if we're at the very end and we found a file, then we're done
-                        // TODO: figure out if this should also happen for directories?
-                        if let Kind::File { .. } = &self.inodes[cur_inode].kind {
-                            // check if on last step
-                            if last_component {
-                                break 'symlink_resolution;
-                            }
-                        }
-                        continue 'symlink_resolution;
+
+                    cur_inode = *entries.get(&name).ok_or(__WASI_ENOENT)?;
+                }
+                Kind::File { .. } | Kind::Buffer { .. } => {
+                    // a preopened file is opened as `.` through its own fd
+                    if component == Component::CurDir && self.inodes[cur_inode].is_preopened {
+                        continue;
                     }
+                    return Err(__WASI_ENOTDIR);
                 }
-                break 'symlink_resolution;
+                Kind::Symlink { .. } => unreachable!("symlinks are followed before traversal"),
             }
         }
 
+        if follow_symlinks {
+            cur_inode = self.follow_symlink(cur_inode, symlink_count)?;
+        }
+
         Ok(cur_inode)
     }
 
+    /// Loads the host file `file`, named `name` in the directory
+    /// `parent_inode`, into a new inode.
+    fn load_inode_from_host(
+        &mut self,
+        parent_inode: Inode,
+        name: String,
+        file: PathBuf,
+    ) -> Result<Inode, __wasi_errno_t> {
+        let metadata = file
+            .symlink_metadata()
+            .map_err(|e| WasiFsError::from(e).into_wasi_err())?;
+        let file_type = metadata.file_type();
+
+        let kind = if file_type.is_dir() {
+            Kind::Dir {
+                parent: Some(parent_inode),
+                path: file.clone(),
+                entries: Default::default(),
+            }
+        } else if file_type.is_file() {
+            Kind::File {
+                handle: None,
+                path: file.clone(),
+                fd: None,
+            }
+        } else if file_type.is_symlink() {
+            let link_value = file.read_link().map_err(|_| __WASI_EIO)?;
+            debug!("attempting to decompose path {:?}", link_value);
+
+            let (pre_open_dir_fd, path_to_symlink) =
+                self.path_into_pre_open_and_relative_path(&file)?;
+            let relative_path = if link_value.is_relative() {
+                link_value
+            } else {
+                self.absolute_link_into_relative_path(
+                    pre_open_dir_fd,
+                    &path_to_symlink,
+                    &link_value,
+                )?
+            };
+            // symlinks are transient: they are not inserted in their parent
+            // directory, so that they are loaded again on the next lookup
+            return self.create_inode(
+                Kind::Symlink {
+                    base_po_dir: pre_open_dir_fd,
+                    path_to_symlink,
+                    relative_path,
+                },
+                false,
+                file.to_string_lossy().to_string(),
+            );
+        } else {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::FileTypeExt;
+                let file_type: __wasi_filetype_t = if file_type.is_char_device() {
+                    __WASI_FILETYPE_CHARACTER_DEVICE
+                } else if file_type.is_block_device() {
+                    __WASI_FILETYPE_BLOCK_DEVICE
+                } else if file_type.is_fifo() {
+                    // FIFO doesn't seem to fit any other type, so unknown
+                    __WASI_FILETYPE_UNKNOWN
+                } else if file_type.is_socket() {
+                    // TODO: how do we know if it's a `__WASI_FILETYPE_SOCKET_STREAM` or
+                    // a `__WASI_FILETYPE_SOCKET_DGRAM`?
+                    __WASI_FILETYPE_SOCKET_STREAM
+                } else {
+                    return Err(__WASI_EINVAL);
+                };
+
+                let kind = Kind::File {
+                    handle: None,
+                    path: file.clone(),
+                    fd: None,
+                };
+                let new_inode = self.create_inode_with_stat(
+                    kind,
+                    false,
+                    file.to_string_lossy().to_string(),
+                    __wasi_filestat_t {
+                        st_filetype: file_type,
+                        ..__wasi_filestat_t::default()
+                    },
+                );
+                self.insert_entry(parent_inode, name, new_inode);
+                return Ok(new_inode);
+            }
+            #[cfg(not(unix))]
+            return Err(__WASI_EINVAL);
+        };
+
+        let new_inode = self.create_inode(kind, false, file.to_string_lossy().to_string())?;
+        self.insert_entry(parent_inode, name, new_inode);
+        Ok(new_inode)
+    }
+
+    /// Inserts `inode`, named `name`, in the directory `parent_inode`.
+    fn insert_entry(&mut self, parent_inode: Inode, name: String, inode: Inode) {
+        match &mut self.inodes[parent_inode].kind {
+            Kind::Dir { entries, .. } | Kind::Root { entries } => {
+                entries.insert(name, inode);
+            }
+            _ => unreachable!("Attempted to insert an entry into a non-directory"),
+        }
+    }
+
+    /// Resolves `inode` while it is a symlink, counting the symlinks
+    /// followed in `symlink_count`.
+    fn follow_symlink(
+        &mut self,
+        mut inode: Inode,
+        symlink_count: &mut u32,
+    ) -> Result<Inode, __wasi_errno_t> {
+        while let Kind::Symlink {
+            base_po_dir,
+            path_to_symlink,
+            relative_path,
+        } = &self.inodes[inode].kind
+        {
+            *symlink_count += 1;
+            if *symlink_count > MAX_SYMLINKS {
+                return Err(__WASI_ELOOP);
+            }
+
+            let base_po_dir = *base_po_dir;
+            let target = symlink_target(path_to_symlink, relative_path)?;
+            debug!("Following symlink to {:?}", target);
+            inode = self.get_inode_at_path_inner(
+                base_po_dir,
+                &target.to_string_lossy(),
+                symlink_count,
+                true,
+            )?;
+        }
+
+        Ok(inode)
+    }
+
+    /// Converts the absolute value `link_value` of the symlink at
+    /// `path_to_symlink` in the preopened directory `pre_open_dir_fd` into
+    /// a path relative to the symlink.
+    ///
+    /// The symlinks pointing outside of their preopened directory are
+    /// refused with `__WASI_ENOTCAPABLE`.
+    fn absolute_link_into_relative_path(
+        &self,
+        pre_open_dir_fd: __wasi_fd_t,
+        path_to_symlink: &Path,
+        link_value: &Path,
+    ) -> Result<PathBuf, __wasi_errno_t> {
+        let po_path = match &self.inodes[self.get_fd(pre_open_dir_fd)?.inode].kind {
+            Kind::Dir { path, .. } => path.clone(),
+            _ => return Err(__WASI_ENOTCAPABLE),
+        };
+        // the preopened directory may have been given as a relative path
+        let rest = link_value
+            .strip_prefix(&po_path)
+            .ok()
+            .map(Path::to_path_buf)
+            .or_else(|| {
+                let po_path = po_path.canonicalize().ok()?;
+                link_value.strip_prefix(po_path).ok().map(Path::to_path_buf)
+            })
+            .ok_or(__WASI_ENOTCAPABLE)?;
+
+        // go up from the directory of the symlink to the preopened
+        // directory, then down to the target
+        let mut relative_path = PathBuf::new();
+        if let Some(symlink_dir) = path_to_symlink.parent() {
+            for _ in symlink_dir.components() {
+                relative_path.push("..");
+            }
+        }
+        relative_path.push(rest);
+        Ok(relative_path)
+    }
+
     /// Splits a path into the first preopened directory that is a parent of it,
     /// if such a preopened directory exists, and the rest of the path.
     ///
@@ -969,24 +1238,28 @@ impl WasiFs {
         &self,
         path: &Path,
     ) -> Result<(__wasi_fd_t, PathBuf), __wasi_errno_t> {
-        // for each preopened directory
+        // the innermost preopened directory holding the path, when the
+        // preopened directories are nested
+        let mut pre_open = None;
         for po_fd in &self.preopen_fds {
             let po_inode = self.fd_map[po_fd].inode;
             let po_path = match &self.inodes[po_inode].kind {
                 Kind::Dir { path, .. } => &**path,
-                Kind::Root { .. } => Path::new("/"),
-                _ => unreachable!("Preopened FD that's not a directory or the root"),
+                // the virtual root and the preopened files aren't host
+                // directories
+                _ => continue,
             };
-            // stem path based on it
             if let Ok(rest) = path.strip_prefix(po_path) {
-                // if any path meets this criteria
-                // (verify that all remaining components are not symlinks except for maybe last? (or do the more complex logic of resolving intermediary symlinks))
-                // return preopened dir and the rest of the path
-
-                return Ok((*po_fd, rest.to_owned()));
+                let depth = po_path.components().count();
+                if pre_open.map_or(true, |(_, _, best_depth)| depth > best_depth) {
+                    pre_open = Some((*po_fd, rest, depth));
+                }
             }
         }
-        Err(__WASI_EINVAL) // this may not make sense
+
+        pre_open
+            .map(|(po_fd, rest, _)| (po_fd, rest.to_owned()))
+            .ok_or(__WASI_EINVAL) // this may not make sense
     }
 
     // if this is still dead code and the year is 2020 or later, please delete this function
@@ -1020,55 +1293,28 @@ impl WasiFs {
         Ok(out)
     }
 
-    /// finds the number of directories between the fd and the inode if they're connected
-    /// expects inode to point to a directory
-    pub(crate) fn path_depth_from_fd(
-        &self,
-        fd: __wasi_fd_t,
-        inode: Inode,
-    ) -> Result<usize, __wasi_errno_t> {
-        let mut counter = 0;
-        let base_fd = self.get_fd(fd)?;
-        let base_inode = base_fd.inode;
-        let mut cur_inode = inode;
-
-        while cur_inode != base_inode {
-            counter += 1;
-            match &self.inodes[cur_inode].kind {
-                Kind::Dir { parent, .. } => {
-                    if let Some(p) = parent {
-                        cur_inode = *p;
-                    }
-                }
-                _ => return Err(__WASI_EINVAL),
-            }
-        }
-
-        Ok(counter)
-    }
-
     /// gets a host file from a base directory and a path
     /// this function ensures the fs remains sandboxed
-    // NOTE: follow symlinks is super weird right now
-    // even if it's false, it still follows symlinks, just not the last
-    // symlink so
-    // This will be resolved when we have tests asserting the correct behavior
+    ///
+    /// The symlinks in the middle of the path are always followed; the last
+    /// one is followed only if `follow_symlinks` is set.
     pub(crate) fn get_inode_at_path(
         &mut self,
         base: __wasi_fd_t,
         path: &str,
         follow_symlinks: bool,
     ) -> Result<Inode, __wasi_errno_t> {
-        self.get_inode_at_path_inner(base, path, 0, follow_symlinks)
+        self.get_inode_at_path_inner(base, path, &mut 0, follow_symlinks)
     }
 
     /// Returns the parent Dir or Root that the file at a given path is in and the file name
     /// stripped off
+    ///
+    /// The parent is always resolved, even if it is a symlink.
     pub(crate) fn get_parent_inode_at_path(
         &mut self,
         base: __wasi_fd_t,
         path: &Path,
-        follow_symlinks: bool,
     ) -> Result<(Inode, String), __wasi_errno_t> {
         let mut parent_dir = std::path::PathBuf::new();
         let mut components = path.components().rev();
@@ -1081,7 +1327,7 @@ impl WasiFs {
         for comp in components.rev() {
             parent_dir.push(comp);
         }
-        self.get_inode_at_path(base, &parent_dir.to_string_lossy(), follow_symlinks)
+        self.get_inode_at_path(base, &parent_dir.to_string_lossy(), true)
             .map(|v| (v, new_entity_name))
     }
 
@@ -1269,6 +1515,7 @@ impl WasiFs {
         open_flags: u16,
         inode: Inode,
     ) -> Result<__wasi_fd_t, __wasi_errno_t> {
+        self.check_open_fds_quota()?;
         let idx = self.next_fd.get();
         self.next_fd.set(idx + 1);
         self.fd_map.insert(
@@ -1285,6 +1532,77 @@ impl WasiFs {
         Ok(idx)
     }
 
+    /// Get the quotas limiting the resources used through the filesystem.
+    pub fn quotas(&self) -> &WasiQuotas {
+        &self.quotas
+    }
+
+    /// Set the quotas limiting the resources used through the filesystem.
+    ///
+    /// The resources already used still count.
+    pub fn set_quotas(&mut self, quotas: WasiQuotas) {
+        self.quotas = quotas;
+    }
+
+    /// Checks that writing `bytes` at `offset` in the file of `inode` stays
+    /// within the quotas.
+    pub(crate) fn check_write_quotas(
+        &self,
+        inode: Inode,
+        offset: u64,
+        bytes: u64,
+    ) -> Result<(), __wasi_errno_t> {
+        match &self.inodes[inode].kind {
+            // stdin, stdout and stderr only count when they are pipes
+            Kind::File {
+                fd: Some(_),
+                handle,
+                ..
+            } => {
+                if let Some(pipe) = handle.as_ref().and_then(|h| h.downcast_ref::<Pipe>()) {
+                    self.quotas
+                        .check_buffer_size(pipe.size().saturating_add(bytes))?;
+                }
+                return Ok(());
+            }
+            Kind::File { .. } => (),
+            Kind::Buffer { buffer } => {
+                let new_size = std::cmp::max(buffer.len() as u64, offset.saturating_add(bytes));
+                self.quotas.check_buffer_size(new_size)?;
+            }
+            Kind::Dir { .. } | Kind::Root { .. } | Kind::Symlink { .. } => return Ok(()),
+        }
+        self.quotas
+            .check_bytes_written(&self.quota_usage, inode, bytes)
+    }
+
+    /// Counts `bytes` written to the file of `inode` against the quotas.
+    pub(crate) fn record_write(&mut self, inode: Inode, bytes: u64) {
+        if let Kind::File { fd: None, .. } | Kind::Buffer { .. } = &self.inodes[inode].kind {
+            self.quota_usage.bytes_written += bytes;
+            *self
+                .quota_usage
+                .bytes_written_per_file
+                .entry(inode)
+                .or_insert(0) += bytes;
+        }
+    }
+
+    /// Checks that opening a file descriptor stays within the quotas.
+    pub(crate) fn check_open_fds_quota(&self) -> Result<(), __wasi_errno_t> {
+        self.quotas.check_open_fds(self.fd_map.len())
+    }
+
+    /// Checks that creating a directory entry stays within the quotas.
+    pub(crate) fn check_created_entries_quota(&self) -> Result<(), __wasi_errno_t> {
+        self.quotas.check_created_entries(&self.quota_usage)
+    }
+
+    /// Counts a created directory entry against the quotas.
+    pub(crate) fn record_created_entry(&mut self) {
+        self.quota_usage.created_entries += 1;
+    }
+
     /// Low level function to remove an inode, that is it deletes the WASI FS's
     /// knowledge of a file.
     ///
@@ -1540,6 +1858,24 @@ pub struct WasiState {
     pub fs: WasiFs,
     pub args: Vec<Vec<u8>>,
     pub envs: Vec<Vec<u8>>,
+    /// The categories of calls allowed to the program
+    pub policy: WasiPolicy,
+    /// The source of time of the clocks; it isn't saved by
+    /// [`WasiState::freeze`], the host clocks are used once unfrozen
+    #[serde(skip, default = "default_clock")]
+    pub clock: Box<dyn WasiClock>,
+    /// The source of random bytes; it isn't saved by [`WasiState::freeze`],
+    /// the host generator is used once unfrozen
+    #[serde(skip, default = "default_random")]
+    pub random: Box<dyn WasiRandom>,
+}
+
+fn default_clock() -> Box<dyn WasiClock> {
+    Box::new(HostClock)
+}
+
+fn default_random() -> Box<dyn WasiRandom> {
+    Box::new(HostRandom)
 }
 
 impl WasiState {
@@ -1573,3 +1909,117 @@ pub fn host_file_type_to_wasi_file_type(file_type: fs::FileType) -> __wasi_filet
         __WASI_FILETYPE_UNKNOWN
     }
 }
+
+/// Computes the target of the symlink at `path_to_symlink`, whose value is
+/// `relative_path`, as a path relative to the preopened directory of the
+/// symlink.
+///
+/// The path is normalized lexically, and a target outside of the preopened
+/// directory is refused with `__WASI_ENOTCAPABLE`.
+fn symlink_target(path_to_symlink: &Path, relative_path: &Path) -> Result<PathBuf, __wasi_errno_t> {
+    let symlink_dir = path_to_symlink.parent().unwrap_or_else(|| Path::new(""));
+    let mut target = PathBuf::new();
+    for component in symlink_dir.components().chain(relative_path.components()) {
+        match component {
+            Component::Normal(name) => target.push(name),
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !target.pop() {
+                    return Err(__WASI_ENOTCAPABLE);
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return Err(__WASI_ENOTCAPABLE),
+        }
+    }
+    if target.as_os_str().is_empty() {
+        target.push(".");
+    }
+    Ok(target)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn symlink_targets() {
+        let target = |path_to_symlink: &str, relative_path: &str| {
+            symlink_target(Path::new(path_to_symlink), Path::new(relative_path))
+        };
+
+        assert_eq!(
+            target("bookmarks/2019-07-16", "../act1/scene2.txt"),
+            Ok(PathBuf::from("act1/scene2.txt"))
+        );
+        assert_eq!(
+            target("temp/act3", "./../hamlet/act3"),
+            Ok(PathBuf::from("hamlet/act3"))
+        );
+        assert_eq!(target("a/link", ".."), Ok(PathBuf::from(".")));
+
+        // the symlinks can't point outside of their preopened directory
+        assert_eq!(target("link", "../secret"), Err(__WASI_ENOTCAPABLE));
+        assert_eq!(
+            target("a/link", "b/../../../secret"),
+            Err(__WASI_ENOTCAPABLE)
+        );
+        assert_eq!(target("a/link", "/etc/passwd"), Err(__WASI_ENOTCAPABLE));
+    }
+
+    #[test]
+    fn preopened_files() {
+        let mut state = WasiState::new("test")
+            .preopen_file(|p| {
+                p.buffer(b"act = 1".to_vec())
+                    .alias("/etc/config.toml")
+                    .read(true)
+            })
+            .unwrap()
+            .preopen_file(|p| p.buffer(Vec::new()).alias("/out/result.json").write(true))
+            .unwrap()
+            .build()
+            .unwrap();
+        let fs = &mut state.fs;
+        let (config_fd, result_fd) = (fs.preopen_fds[1], fs.preopen_fds[2]);
+
+        // the files are opened as `.` through their preopened fd
+        assert!(fs.prestat_fd(config_fd).is_ok());
+        let config = fs.get_inode_at_path(config_fd, ".", false).unwrap();
+        assert_eq!(fs.inodes[config].name, "etc/config.toml");
+        assert_eq!(
+            fs.get_inode_at_path(config_fd, "../hamlet", false),
+            Err(__WASI_ENOTDIR)
+        );
+
+        // the rights of each file are the rights it's preopened with
+        let config_rights = fs.get_fd(config_fd).unwrap().rights_inheriting;
+        assert_ne!(config_rights & __WASI_RIGHT_FD_READ, 0);
+        assert_eq!(config_rights & __WASI_RIGHT_FD_WRITE, 0);
+        let result_rights = fs.get_fd(result_fd).unwrap().rights_inheriting;
+        assert_eq!(result_rights & __WASI_RIGHT_FD_READ, 0);
+        assert_ne!(result_rights & __WASI_RIGHT_FD_WRITE, 0);
+
+        let result = fs.get_inode_at_path(result_fd, ".", false).unwrap();
+        if let Kind::Buffer { buffer } = &mut fs.inodes[result].kind {
+            buffer.extend_from_slice(b"{}");
+        }
+        assert_eq!(fs.preopened_buffer("/out/result.json"), Some(&b"{}"[..]));
+        assert_eq!(fs.preopened_buffer("/etc/hamlet"), None);
+
+        // the policy removes the write rights
+        let state = WasiState::new("test")
+            .preopen_file(|p| p.buffer(Vec::new()).alias("/out/result.json").write(true))
+            .unwrap()
+            .policy(WasiPolicy::new().deny(WasiCapability::FsWrite))
+            .build()
+            .unwrap();
+        let result_fd = state.fs.preopen_fds[1];
+        let result_rights = state.fs.get_fd(result_fd).unwrap().rights_inheriting;
+        assert_eq!(result_rights & __WASI_RIGHT_FD_WRITE, 0);
+
+        assert!(matches!(
+            WasiState::new("test").preopen_file(|p| p.buffer(Vec::new()).read(true)),
+            Err(WasiStateCreationError::PreopenedFileError(_))
+        ));
+    }
+}
@@ -0,0 +1,480 @@
+//! Bounded, blocking pipes connecting host threads to the stdio of a WASI
+//! program.
+
+use crate::state::{PollEvent, PollEventBuilder, PollEventSet, PollWaker, WasiFile, WasiFsError};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, Write};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+/// Creates a pipe buffering up to `capacity` bytes, and returns its read
+/// and write ends.
+///
+/// Reading blocks until some bytes are written, or until the write end is
+/// closed: reading then returns the end of file. Writing blocks while the
+/// buffer is full, and fails with a broken pipe once the read end is
+/// closed. An end is closed when it's dropped, or with `close`.
+///
+/// The ends can be used as the stdio of a program, to stream its input
+/// and output from other threads.
+///
+/// Usage:
+///
+/// ```no_run
+/// # use wasmer_wasi::{bounded_pipe, WasiState, WasiStateCreationError};
+/// # use std::io::{Read, Write};
+/// # fn main() -> Result<(), WasiStateCreationError> {
+/// let (stdin_reader, mut stdin_writer) = bounded_pipe(4096);
+/// let (mut stdout_reader, stdout_writer) = bounded_pipe(4096);
+///
+/// let wasi_env = WasiState::new("program_name")
+///     .stdin(Box::new(stdin_reader))
+///     .stdout(Box::new(stdout_writer))
+///     .finalize()?;
+///
+/// std::thread::spawn(move || {
+///     stdin_writer.write_all(b"hello\n").unwrap();
+///     // dropping the writer closes the stdin of the program
+/// });
+/// std::thread::spawn(move || {
+///     let mut output = String::new();
+///     stdout_reader.read_to_string(&mut output).unwrap();
+/// });
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics if `capacity` is 0.
+pub fn bounded_pipe(capacity: usize) -> (PipeReader, PipeWriter) {
+    assert!(capacity > 0, "the capacity of a pipe must not be 0");
+    let shared = Arc::new(PipeShared {
+        state: Mutex::new(PipeState {
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+            reader_closed: false,
+            writer_closed: false,
+            wakers: Vec::new(),
+        }),
+        changed: Condvar::new(),
+    });
+    (
+        PipeReader {
+            shared: shared.clone(),
+        },
+        PipeWriter { shared },
+    )
+}
+
+#[derive(Debug)]
+struct PipeState {
+    buffer: VecDeque<u8>,
+    capacity: usize,
+    reader_closed: bool,
+    writer_closed: bool,
+    /// The polls blocked on the pipe.
+    wakers: Vec<PollWaker>,
+}
+
+#[derive(Debug)]
+struct PipeShared {
+    state: Mutex<PipeState>,
+    /// Notified when bytes are read or written, and when an end is closed.
+    changed: Condvar,
+}
+
+impl PipeShared {
+    /// The pipes aren't saved by [`WasiState::freeze`]: the unfrozen ends
+    /// are closed on the other side.
+    ///
+    /// [`WasiState::freeze`]: crate::WasiState::freeze
+    fn disconnected() -> Arc<Self> {
+        let (reader, writer) = bounded_pipe(1);
+        reader.close();
+        writer.close();
+        reader.shared.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<PipeState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Wakes up the threads blocked on the pipe, after a change of `state`.
+    fn notify(&self, state: &mut PipeState) {
+        self.changed.notify_all();
+        PollWaker::wake_all(&mut state.wakers);
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, PipeState>) -> MutexGuard<'a, PipeState> {
+        self.changed.wait(state).unwrap()
+    }
+
+    fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut state = self.lock();
+        loop {
+            if !state.buffer.is_empty() {
+                let amt = std::cmp::min(buf.len(), state.buffer.len());
+                for (i, byte) in state.buffer.drain(..amt).enumerate() {
+                    buf[i] = byte;
+                }
+                self.notify(&mut state);
+                return Ok(amt);
+            }
+            if state.writer_closed {
+                return Ok(0);
+            }
+            state = self.wait(state);
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut state = self.lock();
+        loop {
+            if state.reader_closed {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            let space = state.capacity - state.buffer.len();
+            if space > 0 {
+                let amt = std::cmp::min(buf.len(), space);
+                state.buffer.extend(&buf[..amt]);
+                self.notify(&mut state);
+                return Ok(amt);
+            }
+            state = self.wait(state);
+        }
+    }
+}
+
+/// The read end of a [`bounded_pipe`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PipeReader {
+    #[serde(skip, default = "PipeShared::disconnected")]
+    shared: Arc<PipeShared>,
+}
+
+/// The write end of a [`bounded_pipe`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PipeWriter {
+    #[serde(skip, default = "PipeShared::disconnected")]
+    shared: Arc<PipeShared>,
+}
+
+impl PipeReader {
+    /// Closes the read end: the writes fail from now on, and the buffered
+    /// bytes are discarded.
+    pub fn close(&self) {
+        let mut state = self.shared.lock();
+        state.reader_closed = true;
+        state.buffer.clear();
+        self.shared.notify(&mut state);
+    }
+}
+
+impl PipeWriter {
+    /// Closes the write end: the reads return the end of file once the
+    /// buffered bytes are read.
+    pub fn close(&self) {
+        let mut state = self.shared.lock();
+        state.writer_closed = true;
+        self.shared.notify(&mut state);
+    }
+}
+
+/// Another handle on the pipe end of a file, used by the syscalls to block
+/// on the pipe without holding the lock of the [`WasiState`]. Dropping it
+/// doesn't close the pipe.
+///
+/// [`WasiState`]: crate::WasiState
+pub(crate) struct PipeEnd {
+    shared: Arc<PipeShared>,
+    /// Whether it's the read end, or the write end.
+    is_reader: bool,
+}
+
+impl PipeEnd {
+    /// Returns a handle on `file` if it's a pipe end.
+    pub(crate) fn of(file: &dyn WasiFile) -> Option<Self> {
+        if let Some(reader) = file.downcast_ref::<PipeReader>() {
+            Some(Self {
+                shared: reader.shared.clone(),
+                is_reader: true,
+            })
+        } else {
+            file.downcast_ref::<PipeWriter>().map(|writer| Self {
+                shared: writer.shared.clone(),
+                is_reader: false,
+            })
+        }
+    }
+}
+
+impl Read for PipeEnd {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.is_reader {
+            self.shared.read(buf)
+        } else {
+            read_from_write_end()
+        }
+    }
+}
+
+impl Write for PipeEnd {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.is_reader {
+            write_to_read_end()
+        } else {
+            self.shared.write(buf)
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn write_to_read_end() -> io::Result<usize> {
+    Err(io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "can not write to the read end of a pipe",
+    ))
+}
+
+fn read_from_write_end() -> io::Result<usize> {
+    Err(io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "can not read from the write end of a pipe",
+    ))
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.shared.read(buf)
+    }
+}
+
+impl Write for PipeReader {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        write_to_read_end()
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.shared.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for PipeWriter {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        read_from_write_end()
+    }
+}
+
+impl Seek for PipeReader {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not seek in a pipe",
+        ))
+    }
+}
+
+impl Seek for PipeWriter {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not seek in a pipe",
+        ))
+    }
+}
+
+#[typetag::serde]
+impl WasiFile for PipeReader {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        self.shared.lock().buffer.len() as u64
+    }
+    fn set_len(&mut self, _len: u64) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        Ok(self.shared.lock().buffer.len())
+    }
+    fn poll_ready(&self, events: PollEventSet) -> Result<PollEventSet, WasiFsError> {
+        let state = self.shared.lock();
+        let mut ready = PollEventBuilder::new();
+        // the end of file is readable
+        if events & PollEvent::PollIn as PollEventSet != 0
+            && (!state.buffer.is_empty() || state.writer_closed)
+        {
+            ready = ready.add(PollEvent::PollIn);
+        }
+        if state.writer_closed {
+            ready = ready.add(PollEvent::PollHangUp);
+        }
+        Ok(ready.build())
+    }
+    fn register_poll_waker(&mut self, waker: &PollWaker) -> bool {
+        waker.register(&mut self.shared.lock().wakers);
+        true
+    }
+}
+
+#[typetag::serde]
+impl WasiFile for PipeWriter {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        self.shared.lock().buffer.len() as u64
+    }
+    fn set_len(&mut self, _len: u64) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    /// Returns the free space in the buffer.
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        let state = self.shared.lock();
+        Ok(state.capacity - state.buffer.len())
+    }
+    fn poll_ready(&self, events: PollEventSet) -> Result<PollEventSet, WasiFsError> {
+        let state = self.shared.lock();
+        let mut ready = PollEventBuilder::new();
+        if state.reader_closed {
+            ready = ready.add(PollEvent::PollError);
+        } else if events & PollEvent::PollOut as PollEventSet != 0
+            && state.buffer.len() < state.capacity
+        {
+            ready = ready.add(PollEvent::PollOut);
+        }
+        Ok(ready.build())
+    }
+    fn register_poll_waker(&mut self, waker: &PollWaker) -> bool {
+        waker.register(&mut self.shared.lock().wakers);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syscalls::types::*;
+    use crate::test_utils::SyscallTester;
+    use crate::WasiState;
+
+    #[test]
+    fn bounded_pipes() {
+        let (mut reader, mut writer) = bounded_pipe(4);
+        assert_eq!(writer.poll_ready(PollEvent::PollOut as PollEventSet), Ok(2));
+        assert_eq!(reader.poll_ready(PollEvent::PollIn as PollEventSet), Ok(0));
+
+        // the writes are bounded by the capacity of the pipe
+        assert_eq!(writer.write(b"hamlet").unwrap(), 4);
+        assert_eq!(writer.bytes_available(), Ok(0));
+        assert_eq!(writer.poll_ready(PollEvent::PollOut as PollEventSet), Ok(0));
+        assert_eq!(reader.bytes_available(), Ok(4));
+        assert_eq!(reader.poll_ready(PollEvent::PollIn as PollEventSet), Ok(1));
+
+        // the writer blocks until the reader makes some space
+        let writer_thread = std::thread::spawn(move || {
+            writer.write_all(b"et\nact1").unwrap();
+        });
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        writer_thread.join().unwrap();
+        assert_eq!(output, b"hamlet\nact1");
+
+        // the writer has been dropped: the reader is at the end of file
+        assert_eq!(reader.read(&mut [0; 4]).unwrap(), 0);
+        assert_eq!(
+            reader.poll_ready(PollEvent::PollIn as PollEventSet),
+            Ok(PollEvent::PollIn as PollEventSet | PollEvent::PollHangUp as PollEventSet)
+        );
+
+        let (reader, mut writer) = bounded_pipe(4);
+        drop(reader);
+        assert_eq!(
+            writer.write(b"act2").unwrap_err().kind(),
+            std::io::ErrorKind::BrokenPipe
+        );
+        assert_eq!(writer.poll_ready(PollEvent::PollOut as PollEventSet), Ok(4));
+    }
+
+    #[test]
+    fn pipes_dont_lock_the_state() {
+        let (stdin_reader, mut stdin_writer) = bounded_pipe(16);
+        let (mut stdout_reader, stdout_writer) = bounded_pipe(4);
+        let tester = SyscallTester::new(
+            WasiState::new("test")
+                .stdin(Box::new(stdin_reader))
+                .stdout(Box::new(stdout_writer))
+                .build()
+                .unwrap(),
+        );
+        let env = tester.env.clone();
+
+        let program = std::thread::spawn(move || {
+            let input = tester.fd_read(__WASI_STDIN_FILENO, 16).unwrap();
+            // the write blocks until the output is read, 4 bytes at a time
+            tester
+                .fd_write(__WASI_STDOUT_FILENO, b"to be or not")
+                .unwrap();
+            input
+        });
+        // the state is available while the program is blocked on its pipes
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert_eq!(env.state().fs.fd_map.len(), 4);
+        stdin_writer.write_all(b"hamlet").unwrap();
+        let mut output = [0; 12];
+        stdout_reader.read_exact(&mut output[..4]).unwrap();
+        assert_eq!(env.state().fs.fd_map.len(), 4);
+        stdout_reader.read_exact(&mut output[4..]).unwrap();
+        assert_eq!(&output, b"to be or not");
+
+        // a short read returns what's available instead of blocking
+        assert_eq!(program.join().unwrap(), b"hamlet");
+    }
+}
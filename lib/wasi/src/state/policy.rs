@@ -0,0 +1,185 @@
+//! Policies denying whole categories of WASI calls to a program, on top
+//! of the rights of its file descriptors.
+
+use super::quota::check_open_fds;
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// The rights of a file descriptor allowing to modify its file, removed
+/// when [`WasiCapability::FsWrite`] is denied.
+pub(crate) const FD_WRITE_RIGHTS: __wasi_rights_t =
+    __WASI_RIGHT_FD_WRITE | __WASI_RIGHT_FD_ALLOCATE | __WASI_RIGHT_FD_FILESTAT_SET_SIZE;
+
+/// A category of WASI calls that can be denied by a [`WasiPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WasiCapability {
+    /// Reading the clocks with `clock_time_get` and `clock_res_get`. The
+    /// clock subscriptions of `poll_oneoff` are still allowed, so that the
+    /// program can sleep.
+    Clock,
+    /// Getting random bytes with `random_get`.
+    Random,
+    /// Exiting with `proc_exit`. A denied exit traps with
+    /// [`WasiError::Denied`](crate::WasiError::Denied).
+    Exit,
+    /// Modifying the filesystem: creating, truncating, renaming, linking
+    /// or removing files and directories, opening files for writing and
+    /// changing their size or times. Writing to stdout and stderr is still
+    /// allowed.
+    FsWrite,
+}
+
+impl FromStr for WasiCapability {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clock" => Ok(Self::Clock),
+            "random" => Ok(Self::Random),
+            "exit" => Ok(Self::Exit),
+            "fs-write" => Ok(Self::FsWrite),
+            _ => Err(format!(
+                "unknown capability `{}`, expected `clock`, `random`, `exit` or `fs-write`",
+                s
+            )),
+        }
+    }
+}
+
+/// The categories of WASI calls allowed to a program, enforced by the
+/// syscalls. The denied calls return `__WASI_ENOTCAPABLE`.
+///
+/// Everything is allowed by default.
+///
+/// Usage:
+///
+/// ```
+/// # use wasmer_wasi::{WasiCapability, WasiPolicy};
+/// let policy = WasiPolicy::new()
+///     .deny(WasiCapability::Clock)
+///     .deny(WasiCapability::FsWrite)
+///     .max_open_fds(16);
+/// assert!(!policy.is_allowed(WasiCapability::Clock));
+/// assert!(policy.is_allowed(WasiCapability::Random));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WasiPolicy {
+    denied: HashSet<WasiCapability>,
+    max_open_fds: Option<u32>,
+}
+
+impl WasiPolicy {
+    /// Creates a policy allowing everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Denies the calls of `capability`.
+    pub fn deny(mut self, capability: WasiCapability) -> Self {
+        self.denied.insert(capability);
+        self
+    }
+
+    /// Limits the number of open file descriptors, including stdin, stdout,
+    /// stderr and the preopened directories: `path_open` fails with
+    /// `__WASI_EMFILE` past it.
+    pub fn max_open_fds(mut self, max_open_fds: u32) -> Self {
+        self.max_open_fds = Some(max_open_fds);
+        self
+    }
+
+    /// Returns whether the calls of `capability` are allowed.
+    pub fn is_allowed(&self, capability: WasiCapability) -> bool {
+        !self.denied.contains(&capability)
+    }
+
+    pub(crate) fn check(&self, capability: WasiCapability) -> Result<(), __wasi_errno_t> {
+        if self.is_allowed(capability) {
+            Ok(())
+        } else {
+            Err(__WASI_ENOTCAPABLE)
+        }
+    }
+
+    pub(crate) fn check_open_fds(&self, open_fds: usize) -> Result<(), __wasi_errno_t> {
+        check_open_fds(self.max_open_fds, open_fds)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::{Pipe, WasiState};
+    use crate::test_utils::SyscallTester;
+
+    #[test]
+    fn policies() {
+        let policy = WasiPolicy::new()
+            .deny(WasiCapability::Clock)
+            .max_open_fds(4);
+        assert_eq!(policy.check(WasiCapability::Clock), Err(__WASI_ENOTCAPABLE));
+        assert_eq!(policy.check(WasiCapability::FsWrite), Ok(()));
+        assert_eq!(policy.check_open_fds(3), Ok(()));
+        assert_eq!(policy.check_open_fds(4), Err(__WASI_EMFILE));
+
+        assert_eq!("fs-write".parse(), Ok(WasiCapability::FsWrite));
+        assert!("network".parse::<WasiCapability>().is_err());
+    }
+
+    #[test]
+    fn denied_fs_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("data.txt"), "hamlet").unwrap();
+        let tester = SyscallTester::new(
+            WasiState::new("test")
+                .preopen_dir(dir.path())
+                .unwrap()
+                .stdout(Box::new(Pipe::new()))
+                .policy(
+                    WasiPolicy::new()
+                        .deny(WasiCapability::FsWrite)
+                        .max_open_fds(7),
+                )
+                .build()
+                .unwrap(),
+        );
+        // the root is preopened first
+        let dirfd = tester.env.state().fs.preopen_fds[1];
+        let open = |o_flags, rights, fd_flags| {
+            tester.path_open(dirfd, 0, "data.txt", o_flags, rights, fd_flags)
+        };
+
+        // the files can't be opened for writing through the preopened
+        // directory
+        assert_eq!(open(0, __WASI_RIGHT_FD_WRITE, 0), Err(__WASI_ENOTCAPABLE));
+        assert_eq!(open(__WASI_O_TRUNC, 0, 0), Err(__WASI_ENOTCAPABLE));
+        assert_eq!(open(__WASI_O_CREAT, 0, 0), Err(__WASI_ENOTCAPABLE));
+        assert_eq!(open(0, 0, __WASI_FDFLAG_APPEND), Err(__WASI_ENOTCAPABLE));
+        assert_eq!(tester.fd_write(dirfd, b"ophelia"), Err(__WASI_EACCES));
+
+        // the inherited write rights are removed
+        let fd = open(0, __WASI_RIGHT_FD_READ, 0).unwrap();
+        assert_eq!(tester.fd_write(fd, b"ophelia"), Err(__WASI_EACCES));
+
+        // the overridden stdout is still writable, but not the files
+        // renumbered to it
+        assert_eq!(tester.fd_write(__WASI_STDOUT_FILENO, b"to be"), Ok(5));
+        tester.fd_renumber(fd, __WASI_STDOUT_FILENO).unwrap();
+        assert_eq!(
+            tester.fd_write(__WASI_STDOUT_FILENO, b"ophelia"),
+            Err(__WASI_EACCES)
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("data.txt")).unwrap(),
+            "hamlet"
+        );
+
+        // stdio, the root, the preopened directory and two more files
+        assert_eq!(tester.env.state().fs.fd_map.len(), 5);
+        open(0, __WASI_RIGHT_FD_READ, 0).unwrap();
+        open(0, __WASI_RIGHT_FD_READ, 0).unwrap();
+        assert_eq!(open(0, __WASI_RIGHT_FD_READ, 0), Err(__WASI_EMFILE));
+    }
+}
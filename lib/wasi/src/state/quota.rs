@@ -0,0 +1,213 @@
+//! Quotas limiting the resources a program can use through the WASI
+//! filesystem.
+
+use super::Inode;
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The limits on the resources used by a program through [`WasiFs`],
+/// enforced by the syscalls. Nothing is limited by default.
+///
+/// Usage:
+///
+/// ```
+/// # use wasmer_wasi::WasiQuotas;
+/// // at most 64 open fds, and 10MiB written in at most 100 new files
+/// let quotas = WasiQuotas::new()
+///     .max_open_fds(64)
+///     .max_bytes_written(10 * 1024 * 1024)
+///     .max_created_entries(100);
+/// ```
+///
+/// [`WasiFs`]: crate::WasiFs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WasiQuotas {
+    max_open_fds: Option<u32>,
+    max_bytes_written_per_file: Option<u64>,
+    max_bytes_written: Option<u64>,
+    max_created_entries: Option<u64>,
+    max_buffer_size: Option<u64>,
+}
+
+impl WasiQuotas {
+    /// Creates quotas limiting nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of open file descriptors, including stdin, stdout,
+    /// stderr and the preopened directories. Opening more fails with
+    /// `__WASI_EMFILE`.
+    ///
+    /// Unlike [`WasiPolicy::max_open_fds`], this also limits the files
+    /// opened by the host through [`WasiFs`].
+    ///
+    /// [`WasiPolicy::max_open_fds`]: crate::WasiPolicy::max_open_fds
+    /// [`WasiFs`]: crate::WasiFs
+    pub fn max_open_fds(mut self, max_open_fds: u32) -> Self {
+        self.max_open_fds = Some(max_open_fds);
+        self
+    }
+
+    /// Limits the number of bytes written to each file. Writing more fails
+    /// with `__WASI_EFBIG`.
+    ///
+    /// Growing a file with `fd_allocate` or `fd_filestat_set_size` counts as
+    /// writing. Writing to stdout and stderr doesn't count.
+    pub fn max_bytes_written_per_file(mut self, max_bytes: u64) -> Self {
+        self.max_bytes_written_per_file = Some(max_bytes);
+        self
+    }
+
+    /// Limits the number of bytes written to all the files. Writing more
+    /// fails with `__WASI_EDQUOT`.
+    ///
+    /// Growing a file with `fd_allocate` or `fd_filestat_set_size` counts as
+    /// writing. Writing to stdout and stderr doesn't count.
+    pub fn max_bytes_written(mut self, max_bytes: u64) -> Self {
+        self.max_bytes_written = Some(max_bytes);
+        self
+    }
+
+    /// Limits the number of files, directories and links created. Creating
+    /// more fails with `__WASI_EDQUOT`.
+    pub fn max_created_entries(mut self, max_entries: u64) -> Self {
+        self.max_created_entries = Some(max_entries);
+        self
+    }
+
+    /// Limits the size of the in-memory files and of the [`Pipe`]s used as
+    /// stdio. Growing them more fails with `__WASI_EFBIG`.
+    ///
+    /// [`Pipe`]: crate::Pipe
+    pub fn max_buffer_size(mut self, max_size: u64) -> Self {
+        self.max_buffer_size = Some(max_size);
+        self
+    }
+
+    pub(crate) fn check_open_fds(&self, open_fds: usize) -> Result<(), __wasi_errno_t> {
+        check_open_fds(self.max_open_fds, open_fds)
+    }
+
+    pub(crate) fn check_buffer_size(&self, size: u64) -> Result<(), __wasi_errno_t> {
+        match self.max_buffer_size {
+            Some(max_size) if size > max_size => Err(__WASI_EFBIG),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_created_entries(&self, usage: &QuotaUsage) -> Result<(), __wasi_errno_t> {
+        match self.max_created_entries {
+            Some(max_entries) if usage.created_entries >= max_entries => Err(__WASI_EDQUOT),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_bytes_written(
+        &self,
+        usage: &QuotaUsage,
+        inode: Inode,
+        bytes: u64,
+    ) -> Result<(), __wasi_errno_t> {
+        let exceeds = |written: u64, max_bytes: Option<u64>| match max_bytes {
+            Some(max_bytes) => written.saturating_add(bytes) > max_bytes,
+            None => false,
+        };
+
+        let written_to_file = usage
+            .bytes_written_per_file
+            .get(&inode)
+            .copied()
+            .unwrap_or(0);
+        if exceeds(written_to_file, self.max_bytes_written_per_file) {
+            return Err(__WASI_EFBIG);
+        }
+        if exceeds(usage.bytes_written, self.max_bytes_written) {
+            return Err(__WASI_EDQUOT);
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that opening one more file descriptor stays within
+/// `max_open_fds`, shared by [`WasiQuotas`] and [`WasiPolicy`].
+///
+/// [`WasiPolicy`]: crate::WasiPolicy
+pub(crate) fn check_open_fds(
+    max_open_fds: Option<u32>,
+    open_fds: usize,
+) -> Result<(), __wasi_errno_t> {
+    match max_open_fds {
+        Some(max_open_fds) if open_fds >= max_open_fds as usize => Err(__WASI_EMFILE),
+        _ => Ok(()),
+    }
+}
+
+/// The resources used by a program, counted against its [`WasiQuotas`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct QuotaUsage {
+    pub(crate) bytes_written: u64,
+    pub(crate) bytes_written_per_file: HashMap<Inode, u64>,
+    pub(crate) created_entries: u64,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::state::{Fd, Kind, WasiState, ALL_RIGHTS};
+    use crate::syscalls::types::*;
+    use crate::WasiQuotas;
+    use std::path::PathBuf;
+
+    #[test]
+    fn quotas() {
+        let mut state = WasiState::new("test")
+            .quotas(
+                WasiQuotas::new()
+                    .max_open_fds(5)
+                    .max_bytes_written_per_file(10)
+                    .max_bytes_written(15)
+                    .max_created_entries(1)
+                    .max_buffer_size(8),
+            )
+            .build()
+            .unwrap();
+        let fs = &mut state.fs;
+
+        let buffer = fs.create_inode_with_default_stat(
+            Kind::Buffer { buffer: vec![] },
+            false,
+            "buffer".to_string(),
+        );
+        assert_eq!(fs.check_write_quotas(buffer, 0, 8), Ok(()));
+        assert_eq!(fs.check_write_quotas(buffer, 4, 5), Err(__WASI_EFBIG));
+
+        // stdio and the root are already open
+        assert!(fs
+            .create_fd(ALL_RIGHTS, ALL_RIGHTS, 0, Fd::WRITE, buffer)
+            .is_ok());
+        assert_eq!(
+            fs.create_fd(ALL_RIGHTS, ALL_RIGHTS, 0, Fd::WRITE, buffer),
+            Err(__WASI_EMFILE)
+        );
+
+        let file = fs.create_inode_with_default_stat(
+            Kind::File {
+                handle: None,
+                path: PathBuf::from("file"),
+                fd: None,
+            },
+            false,
+            "file".to_string(),
+        );
+        fs.record_write(file, 10);
+        assert_eq!(fs.check_write_quotas(file, 10, 1), Err(__WASI_EFBIG));
+        assert_eq!(fs.check_write_quotas(buffer, 0, 5), Ok(()));
+        assert_eq!(fs.check_write_quotas(buffer, 0, 6), Err(__WASI_EDQUOT));
+
+        assert_eq!(fs.check_created_entries_quota(), Ok(()));
+        fs.record_created_entry();
+        assert_eq!(fs.check_created_entries_quota(), Err(__WASI_EDQUOT));
+    }
+}
@@ -0,0 +1,76 @@
+//! The sources of the random bytes of `random_get`: the host, or a
+//! seeded generator for reproducible runs.
+
+use crate::syscalls::types::*;
+use std::fmt;
+
+/// The source of the random bytes returned by `random_get`.
+///
+/// Implement it to control the randomness seen by a program, and install
+/// it with [`WasiStateBuilder::random`].
+///
+/// [`WasiStateBuilder::random`]: crate::WasiStateBuilder::random
+pub trait WasiRandom: fmt::Debug + Send {
+    /// Fills `buf` with random bytes.
+    fn random_get(&mut self, buf: &mut [u8]) -> Result<(), __wasi_errno_t>;
+}
+
+/// The random number generator of the host, used by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HostRandom;
+
+impl WasiRandom for HostRandom {
+    fn random_get(&mut self, buf: &mut [u8]) -> Result<(), __wasi_errno_t> {
+        getrandom::getrandom(buf).map_err(|_| __WASI_EIO)
+    }
+}
+
+/// A pseudo-random number generator (SplitMix64) producing the same bytes
+/// for the same seed.
+///
+/// It is not cryptographically secure.
+#[derive(Debug, Clone)]
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    /// Creates a generator from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl WasiRandom for SeededRandom {
+    fn random_get(&mut self, buf: &mut [u8]) -> Result<(), __wasi_errno_t> {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seeded_random() {
+        let random_bytes = |seed| {
+            let mut buf = [0; 13];
+            SeededRandom::new(seed).random_get(&mut buf).unwrap();
+            buf
+        };
+        assert_eq!(random_bytes(1), random_bytes(1));
+        assert_ne!(random_bytes(1), random_bytes(2));
+    }
+}
@@ -1,4 +1,5 @@
 /// types for use in the WASI filesystem
+use crate::state::PollWaker;
 use crate::syscalls::types::*;
 use serde::{de, Deserialize, Serialize};
 use std::any::Any;
@@ -206,6 +207,31 @@ pub trait WasiFile: fmt::Debug + Send + Write + Read + Seek + 'static + Upcastab
     /// Returns the number of bytes available.  This function must not block
     fn bytes_available(&self) -> Result<usize, WasiFsError>;
 
+    /// Returns the events, among the polled `events`, that the file is ready for, as well as
+    /// `PollHangUp` and `PollError` if they occurred.  This function must not block
+    ///
+    /// The default implementation polls the host fd returned by `get_raw_fd` if any, and
+    /// otherwise reports the file as always ready, like a regular file.  The files whose reads
+    /// or writes can block, like pipes, must override it
+    fn poll_ready(&self, events: PollEventSet) -> Result<PollEventSet, WasiFsError> {
+        #[cfg(unix)]
+        {
+            if let Some(host_fd) = self.get_raw_fd() {
+                return host_fd_poll_ready(host_fd, events);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Registers `waker` to be woken up once the file may have become ready for more events, so
+    /// that `poll_oneoff` can block until then.  Returns whether the file will wake it up
+    ///
+    /// The files with a host fd are polled through it instead.  The default implementation
+    /// returns `false`: `poll_oneoff` then polls the file again every few milliseconds
+    fn register_poll_waker(&mut self, _waker: &PollWaker) -> bool {
+        false
+    }
+
     /// Used for polling.  Default returns `None` because this method cannot be implemented for most types
     /// Returns the underlying host fd
     fn get_raw_fd(&self) -> Option<i32> {
@@ -313,7 +339,7 @@ pub fn iterate_poll_events(pes: PollEventSet) -> PollEventIter {
 }
 
 #[cfg(unix)]
-fn poll_event_set_to_platform_poll_events(mut pes: PollEventSet) -> i16 {
+pub(crate) fn poll_event_set_to_platform_poll_events(mut pes: PollEventSet) -> i16 {
     let mut out = 0;
     for i in 0..16 {
         out |= match PollEvent::from_i16(pes & (1 << i)) {
@@ -362,45 +388,20 @@ impl PollEventBuilder {
 }
 
 #[cfg(unix)]
-pub(crate) fn poll(
-    selfs: &[&dyn WasiFile],
-    events: &[PollEventSet],
-    seen_events: &mut [PollEventSet],
-) -> Result<u32, WasiFsError> {
-    if !(selfs.len() == events.len() && events.len() == seen_events.len()) {
-        return Err(WasiFsError::InvalidInput);
-    }
-    let mut fds = selfs
-        .iter()
-        .enumerate()
-        .filter_map(|(i, s)| s.get_raw_fd().map(|rfd| (i, rfd)))
-        .map(|(i, host_fd)| libc::pollfd {
-            fd: host_fd,
-            events: poll_event_set_to_platform_poll_events(events[i]),
-            revents: 0,
-        })
-        .collect::<Vec<_>>();
-    let result = unsafe { libc::poll(fds.as_mut_ptr(), selfs.len() as _, 1) };
+fn host_fd_poll_ready(host_fd: i32, events: PollEventSet) -> Result<PollEventSet, WasiFsError> {
+    let mut fd = libc::pollfd {
+        fd: host_fd,
+        events: poll_event_set_to_platform_poll_events(events),
+        revents: 0,
+    };
+    // a timeout of 0 returns immediately
+    let result = unsafe { libc::poll(&mut fd, 1, 0) };
 
     if result < 0 {
         // TODO: check errno and return value
         return Err(WasiFsError::IOError);
     }
-    // convert result and write back values
-    for (i, fd) in fds.into_iter().enumerate() {
-        seen_events[i] = platform_poll_events_to_pollevent_set(fd.revents);
-    }
-    // unwrap is safe because we check for negative values above
-    Ok(result.try_into().unwrap())
-}
-
-#[cfg(not(unix))]
-pub(crate) fn poll(
-    _selfs: &[&dyn WasiFile],
-    _events: &[PollEventSet],
-    _seen_events: &mut [PollEventSet],
-) -> Result<(), WasiFsError> {
-    unimplemented!("HostFile::poll in WasiFile is not implemented for non-Unix-like targets yet");
+    Ok(platform_poll_events_to_pollevent_set(fd.revents))
 }
 
 pub trait WasiPath {}
@@ -975,6 +976,9 @@ impl WasiFile for Stdin {
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Pipe {
     buffer: VecDeque<u8>,
+    /// The polls blocked on the pipe.
+    #[serde(skip)]
+    wakers: Vec<PollWaker>,
 }
 
 impl Pipe {
@@ -996,6 +1000,7 @@ impl Read for Pipe {
 impl Write for Pipe {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.buffer.extend(buf);
+        PollWaker::wake_all(&mut self.wakers);
         Ok(buf.len())
     }
     fn flush(&mut self) -> io::Result<()> {
@@ -1028,6 +1033,7 @@ impl WasiFile for Pipe {
     }
     fn set_len(&mut self, len: u64) -> Result<(), WasiFsError> {
         self.buffer.resize(len as usize, 0);
+        PollWaker::wake_all(&mut self.wakers);
         Ok(())
     }
     fn unlink(&mut self) -> Result<(), WasiFsError> {
@@ -1036,6 +1042,22 @@ impl WasiFile for Pipe {
     fn bytes_available(&self) -> Result<usize, WasiFsError> {
         Ok(self.buffer.len())
     }
+    /// The pipe is readable once some bytes are written to it, and always
+    /// writable.
+    fn poll_ready(&self, events: PollEventSet) -> Result<PollEventSet, WasiFsError> {
+        let mut ready = PollEventBuilder::new();
+        if events & PollEvent::PollIn as PollEventSet != 0 && !self.buffer.is_empty() {
+            ready = ready.add(PollEvent::PollIn);
+        }
+        if events & PollEvent::PollOut as PollEventSet != 0 {
+            ready = ready.add(PollEvent::PollOut);
+        }
+        Ok(ready.build())
+    }
+    fn register_poll_waker(&mut self, waker: &PollWaker) -> bool {
+        waker.register(&mut self.wakers);
+        true
+    }
 }
 
 /*
@@ -0,0 +1,205 @@
+//! Wakeups of the `poll_oneoff` calls blocked on files that can't be
+//! polled through a host fd, like the pipes.
+
+use crate::state::PollEventSet;
+use std::io;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// Wakes up a `poll_oneoff` call blocked on a file, once the file may have
+/// become ready. See [`WasiFile::register_poll_waker`].
+///
+/// A waker only wakes up the call it's been registered for: it expires
+/// when the call returns.
+///
+/// [`WasiFile::register_poll_waker`]: crate::WasiFile::register_poll_waker
+#[derive(Debug, Clone)]
+pub struct PollWaker {
+    waiter: Weak<PollWaiter>,
+}
+
+impl PollWaker {
+    /// Wakes up the poll, if it's still blocked.
+    pub fn wake(&self) {
+        if let Some(waiter) = self.waiter.upgrade() {
+            waiter.wake();
+        }
+    }
+
+    /// Returns whether the poll has returned: waking it up does nothing
+    /// anymore.
+    pub fn is_expired(&self) -> bool {
+        self.waiter.strong_count() == 0
+    }
+
+    /// Adds the waker to `wakers`, and removes the expired ones.
+    pub(crate) fn register(&self, wakers: &mut Vec<PollWaker>) {
+        wakers.retain(|waker| !waker.is_expired());
+        if !wakers
+            .iter()
+            .any(|waker| Weak::ptr_eq(&waker.waiter, &self.waiter))
+        {
+            wakers.push(self.clone());
+        }
+    }
+
+    /// Wakes up all the `wakers`, and empties it: the polls register them
+    /// again before blocking.
+    pub(crate) fn wake_all(wakers: &mut Vec<PollWaker>) {
+        for waker in wakers.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// The blocking side of the [`PollWaker`]s of a `poll_oneoff` call.
+///
+/// On unix, it's a pipe to itself, so that the call also blocks on the
+/// host fds with `poll`.
+#[derive(Debug)]
+pub(crate) struct PollWaiter {
+    #[cfg(unix)]
+    read_fd: libc::c_int,
+    #[cfg(unix)]
+    write_fd: libc::c_int,
+    #[cfg(not(unix))]
+    woken: std::sync::Mutex<bool>,
+    #[cfg(not(unix))]
+    condvar: std::sync::Condvar,
+}
+
+impl PollWaiter {
+    #[cfg(unix)]
+    pub(crate) fn new() -> io::Result<Arc<Self>> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let waiter = Self {
+            read_fd: fds[0],
+            write_fd: fds[1],
+        };
+        for &fd in &fds {
+            // the waker never blocks, and a pending wakeup is enough
+            let set_flags = unsafe {
+                libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK) == 0
+                    && libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) == 0
+            };
+            if !set_flags {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(Arc::new(waiter))
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn new() -> io::Result<Arc<Self>> {
+        Ok(Arc::new(Self {
+            woken: std::sync::Mutex::new(false),
+            condvar: std::sync::Condvar::new(),
+        }))
+    }
+
+    pub(crate) fn waker(self: &Arc<Self>) -> PollWaker {
+        PollWaker {
+            waiter: Arc::downgrade(self),
+        }
+    }
+
+    #[cfg(unix)]
+    fn wake(&self) {
+        // the pipe is only full if a wakeup is already pending
+        unsafe { libc::write(self.write_fd, [0u8].as_ptr() as *const _, 1) };
+    }
+
+    #[cfg(not(unix))]
+    fn wake(&self) {
+        *self.woken.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+
+    /// Blocks until a waker is woken up, until one of the `host_fds` is
+    /// ready for its events, or until `timeout` elapses if any. The
+    /// wakeups happening before the call aren't lost.
+    ///
+    /// It can return early: the files must be polled again.
+    #[cfg(unix)]
+    pub(crate) fn wait(
+        &self,
+        host_fds: &[(i32, PollEventSet)],
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        let mut fds = host_fds
+            .iter()
+            .map(|&(fd, events)| libc::pollfd {
+                fd,
+                events: crate::state::poll_event_set_to_platform_poll_events(events),
+                revents: 0,
+            })
+            .collect::<Vec<_>>();
+        fds.push(libc::pollfd {
+            fd: self.read_fd,
+            events: libc::POLLIN,
+            revents: 0,
+        });
+        let timeout = match timeout {
+            // rounded up, so that the deadlines are reached
+            Some(timeout) => {
+                let timeout = timeout
+                    .checked_add(Duration::from_nanos(999_999))
+                    .unwrap_or(timeout);
+                std::cmp::min(timeout.as_millis(), libc::c_int::MAX as u128) as libc::c_int
+            }
+            None => -1,
+        };
+        let result = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, timeout) };
+        if result < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+
+        // consume the wakeups
+        let mut buffer = [0u8; 64];
+        loop {
+            let read =
+                unsafe { libc::read(self.read_fd, buffer.as_mut_ptr() as *mut _, buffer.len()) };
+            if read <= 0 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn wait(
+        &self,
+        host_fds: &[(i32, PollEventSet)],
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        debug_assert!(host_fds.is_empty(), "the host fds are only polled on unix");
+        let mut woken = self.woken.lock().unwrap();
+        if !*woken {
+            woken = match timeout {
+                Some(timeout) => self.condvar.wait_timeout(woken, timeout).unwrap().0,
+                None => self.condvar.wait(woken).unwrap(),
+            };
+        }
+        *woken = false;
+
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl Drop for PollWaiter {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.read_fd);
+            libc::close(self.write_fd);
+        }
+    }
+}
@@ -17,9 +17,9 @@ use self::types::*;
 use crate::{
     ptr::{Array, WasmPtr},
     state::{
-        self, host_file_type_to_wasi_file_type, iterate_poll_events, poll, Fd, HostFile, Inode,
-        InodeVal, Kind, PollEvent, PollEventBuilder, WasiFile, WasiFsError, WasiState,
-        MAX_SYMLINKS,
+        self, host_file_type_to_wasi_file_type, iterate_poll_events, Fd, HostFile, Inode, InodeVal,
+        Kind, PipeEnd, PollEvent, PollEventBuilder, PollEventSet, PollWaiter, WasiCapability,
+        WasiClock, WasiFile, WasiFs, WasiFsError, WasiState, MAX_SYMLINKS,
     },
     WasiEnv, WasiError,
 };
@@ -52,7 +52,7 @@ fn write_bytes_inner<T: Write>(
         let bytes = iov_inner.buf.deref(memory, 0, iov_inner.buf_len)?;
         write_loc
             .write_all(&bytes.iter().map(|b_cell| b_cell.get()).collect::<Vec<u8>>())
-            .map_err(|_| __WASI_EIO)?;
+            .map_err(|e| WasiFsError::from(e).into_wasi_err())?;
 
         // TODO: handle failure more accurately
         bytes_written += iov_inner.buf_len;
@@ -70,10 +70,35 @@ fn write_bytes<T: Write>(
     result
 }
 
+/// Returns the number of bytes in the buffers of `iovs_arr_cell`.
+fn iovs_size(iovs_arr_cell: &[Cell<__wasi_ciovec_t>]) -> u64 {
+    iovs_arr_cell
+        .iter()
+        .map(|iov| iov.get().buf_len as u64)
+        .sum()
+}
+
 fn read_bytes<T: Read>(
+    reader: T,
+    memory: &Memory,
+    iovs_arr_cell: &[Cell<__wasi_iovec_t>],
+) -> Result<u32, __wasi_errno_t> {
+    read_bytes_inner(reader, memory, iovs_arr_cell, false)
+}
+
+fn read_pipe_bytes(
+    pipe: PipeEnd,
+    memory: &Memory,
+    iovs_arr_cell: &[Cell<__wasi_iovec_t>],
+) -> Result<u32, __wasi_errno_t> {
+    read_bytes_inner(pipe, memory, iovs_arr_cell, true)
+}
+
+fn read_bytes_inner<T: Read>(
     mut reader: T,
     memory: &Memory,
     iovs_arr_cell: &[Cell<__wasi_iovec_t>],
+    stop_on_short_read: bool,
 ) -> Result<u32, __wasi_errno_t> {
     let mut bytes_read = 0;
 
@@ -82,7 +107,15 @@ fn read_bytes<T: Read>(
         let bytes = iov_inner.buf.deref(memory, 0, iov_inner.buf_len)?;
         let mut raw_bytes: &mut [u8] =
             unsafe { &mut *(bytes as *const [_] as *mut [_] as *mut [u8]) };
-        bytes_read += reader.read(raw_bytes).map_err(|_| __WASI_EIO)? as u32;
+        let amt = reader
+            .read(raw_bytes)
+            .map_err(|e| WasiFsError::from(e).into_wasi_err())?;
+        bytes_read += amt as u32;
+        // a short read from a pipe means that nothing more is available for
+        // now, and reading the next buffer would block
+        if stop_on_short_read && amt < raw_bytes.len() {
+            break;
+        }
     }
     Ok(bytes_read)
 }
@@ -204,10 +237,12 @@ pub fn clock_res_get(
     resolution: WasmPtr<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::clock_res_get");
-    let memory = env.memory();
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::Clock));
 
     let out_addr = wasi_try!(resolution.deref(memory));
-    platform_clock_res_get(clock_id, out_addr)
+    out_addr.set(wasi_try!(state.clock.res_get(clock_id)));
+    __WASI_ESUCCESS
 }
 
 /// ### `clock_time_get()`
@@ -230,16 +265,13 @@ pub fn clock_time_get(
         "wasi::clock_time_get clock_id: {}, precision: {}",
         clock_id, precision
     );
-    let memory = env.memory();
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::Clock));
 
     let out_addr = wasi_try!(time.deref(memory));
-    let result = platform_clock_time_get(clock_id, precision, out_addr);
-    debug!(
-        "time: {} => {}",
-        wasi_try!(time.deref(memory)).get(),
-        result
-    );
-    result
+    out_addr.set(wasi_try!(state.clock.time_get(clock_id, precision)));
+    debug!("time: {}", out_addr.get());
+    __WASI_ESUCCESS
 }
 
 /// ### `environ_get()`
@@ -334,6 +366,7 @@ pub fn fd_allocate(
 ) -> __wasi_errno_t {
     debug!("wasi::fd_allocate");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::FsWrite));
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     let inode = fd_entry.inode;
 
@@ -341,6 +374,11 @@ pub fn fd_allocate(
         return __WASI_EACCES;
     }
     let new_size = wasi_try!(offset.checked_add(len), __WASI_EINVAL);
+    // growing the file counts as writing to it
+    let growth = new_size.saturating_sub(state.fs.inodes[inode].stat.st_size);
+    wasi_try!(state
+        .fs
+        .check_write_quotas(inode, new_size - growth, growth));
 
     match &mut state.fs.inodes[inode].kind {
         Kind::File { handle, .. } => {
@@ -357,6 +395,7 @@ pub fn fd_allocate(
         Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
     }
     state.fs.inodes[inode].stat.st_size = new_size;
+    state.fs.record_write(inode, growth);
     debug!("New file size: {}", new_size);
 
     __WASI_ESUCCESS
@@ -530,12 +569,16 @@ pub fn fd_filestat_set_size(
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_set_size");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::FsWrite));
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     let inode = fd_entry.inode;
 
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FILESTAT_SET_SIZE) {
         return __WASI_EACCES;
     }
+    // growing the file counts as writing to it
+    let growth = st_size.saturating_sub(state.fs.inodes[inode].stat.st_size);
+    wasi_try!(state.fs.check_write_quotas(inode, st_size - growth, growth));
 
     match &mut state.fs.inodes[inode].kind {
         Kind::File { handle, .. } => {
@@ -552,6 +595,7 @@ pub fn fd_filestat_set_size(
         Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
     }
     state.fs.inodes[inode].stat.st_size = st_size;
+    state.fs.record_write(inode, growth);
 
     __WASI_ESUCCESS
 }
@@ -574,6 +618,7 @@ pub fn fd_filestat_set_times(
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_set_times");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::FsWrite));
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
 
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FILESTAT_SET_TIMES) {
@@ -690,10 +735,12 @@ pub fn fd_pread(
                     }
                 }
                 Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_pread"),
-                Kind::Buffer { buffer } => {
-                    wasi_try!(read_bytes(&buffer[(offset as usize)..], memory, iov_cells))
-                }
+                Kind::Symlink { .. } => return __WASI_EBADF,
+                Kind::Buffer { buffer } => wasi_try!(read_bytes(
+                    buffer.get((offset as usize)..).unwrap_or_default(),
+                    memory,
+                    iov_cells
+                )),
             }
         }
     };
@@ -745,7 +792,9 @@ pub fn fd_prestat_dir_name(
     // check inode-val.is_preopened?
 
     match inode_val.kind {
-        Kind::Dir { .. } | Kind::Root { .. } => {
+        // the preopened files are named like the preopened directories
+        Kind::File { .. } | Kind::Buffer { .. } if !inode_val.is_preopened => __WASI_ENOTDIR,
+        Kind::Dir { .. } | Kind::Root { .. } | Kind::File { .. } | Kind::Buffer { .. } => {
             // TODO: verify this: null termination, etc
             if inode_val.name.len() <= path_len as usize {
                 let mut i = 0;
@@ -768,7 +817,7 @@ pub fn fd_prestat_dir_name(
                 __WASI_EOVERFLOW
             }
         }
-        Kind::Symlink { .. } | Kind::Buffer { .. } | Kind::File { .. } => __WASI_ENOTDIR,
+        Kind::Symlink { .. } => __WASI_ENOTDIR,
     }
 }
 
@@ -800,6 +849,16 @@ pub fn fd_pwrite(
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nwritten_cell = wasi_try!(nwritten.deref(memory));
 
+    let fd_entry = wasi_try!(state.fs.get_fd(fd));
+    let inode = fd_entry.inode;
+    // other files can be renumbered to the stdio fds: check their rights too
+    if fd != __WASI_STDIN_FILENO && !has_rights(fd_entry.rights, __WASI_RIGHT_FD_WRITE) {
+        return __WASI_EACCES;
+    }
+    wasi_try!(state
+        .fs
+        .check_write_quotas(inode, offset, iovs_size(iovs_arr_cell)));
+
     let bytes_written = match fd {
         __WASI_STDIN_FILENO => return __WASI_EINVAL,
         __WASI_STDOUT_FILENO => {
@@ -845,16 +904,18 @@ pub fn fd_pwrite(
                     // TODO: verify
                     return __WASI_EISDIR;
                 }
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_pwrite"),
-                Kind::Buffer { buffer } => wasi_try!(write_bytes(
-                    &mut buffer[(offset as usize)..],
-                    memory,
-                    iovs_arr_cell
-                )),
+                Kind::Symlink { .. } => return __WASI_EBADF,
+                Kind::Buffer { buffer } => {
+                    // writing past the end of the buffer grows it
+                    let mut cursor = std::io::Cursor::new(buffer);
+                    cursor.set_position(offset as u64);
+                    wasi_try!(write_bytes(&mut cursor, memory, iovs_arr_cell))
+                }
             }
         }
     };
 
+    state.fs.record_write(inode, bytes_written as u64);
     nwritten_cell.set(bytes_written);
 
     __WASI_ESUCCESS
@@ -885,6 +946,20 @@ pub fn fd_read(
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nread_cell = wasi_try!(nread.deref(memory));
 
+    // reading a pipe blocks until another thread writes to it: the state
+    // must not stay locked meanwhile
+    if fd != __WASI_STDOUT_FILENO && fd != __WASI_STDERR_FILENO {
+        if let Some(pipe) = state.fs.get_pipe_end(fd) {
+            let rights = wasi_try!(state.fs.get_fd(fd)).rights;
+            if fd != __WASI_STDIN_FILENO && !has_rights(rights, __WASI_RIGHT_FD_READ) {
+                return __WASI_EACCES;
+            }
+            drop(state);
+            nread_cell.set(wasi_try!(read_pipe_bytes(pipe, memory, iovs_arr_cell)));
+            return __WASI_ESUCCESS;
+        }
+    }
+
     let bytes_read = match fd {
         __WASI_STDIN_FILENO => {
             if let Some(ref mut stdin) =
@@ -921,10 +996,12 @@ pub fn fd_read(
                     // TODO: verify
                     return __WASI_EISDIR;
                 }
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_read"),
-                Kind::Buffer { buffer } => {
-                    wasi_try!(read_bytes(&buffer[offset..], memory, iovs_arr_cell))
-                }
+                Kind::Symlink { .. } => return __WASI_EBADF,
+                Kind::Buffer { buffer } => wasi_try!(read_bytes(
+                    buffer.get(offset..).unwrap_or_default(),
+                    memory,
+                    iovs_arr_cell
+                )),
             };
 
             // reborrow
@@ -1135,17 +1212,16 @@ pub fn fd_seek(
                         return __WASI_EINVAL;
                     }
                 }
-                Kind::Symlink { .. } => {
-                    unimplemented!("wasi::fd_seek not implemented for symlinks")
-                }
+                Kind::Symlink { .. } => return __WASI_EBADF,
                 Kind::Dir { .. } | Kind::Root { .. } => {
                     // TODO: check this
                     return __WASI_EINVAL;
                 }
-                Kind::Buffer { .. } => {
-                    // seeking buffers probably makes sense
-                    // TODO: implement this
-                    return __WASI_EINVAL;
+                Kind::Buffer { ref buffer } => {
+                    let end = buffer.len();
+                    // reborrow
+                    let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
+                    fd_entry.offset = (end as i64 + offset) as u64;
                 }
             }
         }
@@ -1255,6 +1331,26 @@ pub fn fd_write(
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nwritten_cell = wasi_try!(nwritten.deref(memory));
 
+    let fd_entry = wasi_try!(state.fs.get_fd(fd));
+    let inode = fd_entry.inode;
+    // other files can be renumbered to the stdio fds: check their rights too
+    if fd != __WASI_STDIN_FILENO && !has_rights(fd_entry.rights, __WASI_RIGHT_FD_WRITE) {
+        return __WASI_EACCES;
+    }
+    wasi_try!(state
+        .fs
+        .check_write_quotas(inode, fd_entry.offset, iovs_size(iovs_arr_cell)));
+
+    // writing a pipe blocks while it's full: the state must not stay locked
+    // meanwhile
+    if fd != __WASI_STDIN_FILENO {
+        if let Some(pipe) = state.fs.get_pipe_end(fd) {
+            drop(state);
+            nwritten_cell.set(wasi_try!(write_bytes(pipe, memory, iovs_arr_cell)));
+            return __WASI_ESUCCESS;
+        }
+    }
+
     let bytes_written = match fd {
         __WASI_STDIN_FILENO => return __WASI_EINVAL,
         __WASI_STDOUT_FILENO => {
@@ -1299,9 +1395,12 @@ pub fn fd_write(
                     // TODO: verify
                     return __WASI_EISDIR;
                 }
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_write"),
+                Kind::Symlink { .. } => return __WASI_EBADF,
                 Kind::Buffer { buffer } => {
-                    wasi_try!(write_bytes(&mut buffer[offset..], memory, iovs_arr_cell))
+                    // writing past the end of the buffer grows it
+                    let mut cursor = std::io::Cursor::new(buffer);
+                    cursor.set_position(offset as u64);
+                    wasi_try!(write_bytes(&mut cursor, memory, iovs_arr_cell))
                 }
             };
 
@@ -1314,6 +1413,7 @@ pub fn fd_write(
         }
     };
 
+    state.fs.record_write(inode, bytes_written as u64);
     nwritten_cell.set(bytes_written);
 
     __WASI_ESUCCESS
@@ -1340,6 +1440,7 @@ pub fn path_create_directory(
 ) -> __wasi_errno_t {
     debug!("wasi::path_create_directory");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::FsWrite));
 
     let working_dir = wasi_try!(state.fs.get_fd(fd));
     if let Kind::Root { .. } = &state.fs.inodes[working_dir.inode].kind {
@@ -1395,7 +1496,9 @@ pub fn path_create_directory(
                     if adjusted_path.exists() && !adjusted_path.is_dir() {
                         return __WASI_ENOTDIR;
                     } else if !adjusted_path.exists() {
+                        wasi_try!(state.fs.check_created_entries_quota());
                         wasi_try!(std::fs::create_dir(&adjusted_path).ok(), __WASI_EIO);
+                        state.fs.record_created_entry();
                     }
                     let kind = Kind::Dir {
                         parent: Some(cur_dir_inode),
@@ -1504,6 +1607,7 @@ pub fn path_filestat_set_times(
 ) -> __wasi_errno_t {
     debug!("wasi::path_filestat_set_times");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::FsWrite));
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     let fd_inode = fd_entry.inode;
     if !has_rights(fd_entry.rights, __WASI_RIGHT_PATH_FILESTAT_SET_TIMES) {
@@ -1595,6 +1699,7 @@ pub fn path_link(
         debug!("  - will follow symlinks when opening path");
     }
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::FsWrite));
     let old_path_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
     let new_path_str = unsafe { get_input_str!(memory, new_path, new_path_len) };
     let source_fd = wasi_try!(state.fs.get_fd(old_fd));
@@ -1617,13 +1722,12 @@ pub fn path_link(
     ));
     let target_path_arg = std::path::PathBuf::from(new_path_str);
     let (target_parent_inode, new_entry_name) =
-        wasi_try!(state
-            .fs
-            .get_parent_inode_at_path(new_fd, &target_path_arg, false));
+        wasi_try!(state.fs.get_parent_inode_at_path(new_fd, &target_path_arg));
 
     if state.fs.inodes[source_inode].stat.st_nlink == __wasi_linkcount_t::max_value() {
         return __WASI_EMLINK;
     }
+    wasi_try!(state.fs.check_created_entries_quota());
     match &mut state.fs.inodes[target_parent_inode].kind {
         Kind::Dir { entries, .. } => {
             if entries.contains_key(&new_entry_name) {
@@ -1635,6 +1739,7 @@ pub fn path_link(
         Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } => return __WASI_ENOTDIR,
     }
     state.fs.inodes[source_inode].stat.st_nlink += 1;
+    state.fs.record_created_entry();
 
     __WASI_ESUCCESS
 }
@@ -1700,6 +1805,9 @@ pub fn path_open(
     if !has_rights(working_dir.rights, __WASI_RIGHT_PATH_OPEN) {
         return __WASI_EACCES;
     }
+    wasi_try!(state.policy.check_open_fds(state.fs.fd_map.len()));
+    // checked again by `create_fd`, but before creating or truncating the file
+    wasi_try!(state.fs.check_open_fds_quota());
     let path_string = unsafe { get_input_str!(memory, path, path_len) };
 
     debug!("=> fd: {}, path: {}", dirfd, &path_string);
@@ -1715,7 +1823,35 @@ pub fn path_open(
     // TODO: traverse rights of dirs properly
     // COMMENTED OUT: WASI isn't giving appropriate rights here when opening
     //              TODO: look into this; file a bug report if this is a bug
-    let adjusted_rights = /*fs_rights_base &*/ working_dir_rights_inheriting;
+    let mut adjusted_rights = /*fs_rights_base &*/ working_dir_rights_inheriting;
+    let mut fs_rights_inheriting = fs_rights_inheriting;
+    if !state.policy.is_allowed(WasiCapability::FsWrite) {
+        if o_flags & (__WASI_O_CREAT | __WASI_O_TRUNC) != 0
+            || fs_flags & __WASI_FDFLAG_APPEND != 0
+            || fs_rights_base & state::FD_WRITE_RIGHTS != 0
+        {
+            return __WASI_ENOTCAPABLE;
+        }
+        // the rights are inherited, not requested: remove the write rights
+        // so that the file is opened read-only
+        adjusted_rights &= !state::FD_WRITE_RIGHTS;
+        fs_rights_inheriting &= !state::FD_WRITE_RIGHTS;
+    }
+    // a preopened file can't be opened with more rights than its preopened
+    // fd, e.g. from the root
+    if let Some(rights) = maybe_inode
+        .ok()
+        .and_then(|inode| state.fs.preopened_file_rights(inode))
+    {
+        let missing_write_rights = state::FD_WRITE_RIGHTS & !rights;
+        if fs_rights_base & missing_write_rights != 0
+            || (o_flags & __WASI_O_TRUNC != 0 && missing_write_rights != 0)
+        {
+            return __WASI_ENOTCAPABLE;
+        }
+        adjusted_rights &= rights;
+        fs_rights_inheriting &= rights;
+    }
     let inode = if let Ok(inode) = maybe_inode {
         // Happy path, we found the file we're trying to open
         match &mut state.fs.inodes[inode].kind {
@@ -1774,39 +1910,46 @@ pub fn path_open(
                     false,
                 )));
             }
-            Kind::Buffer { .. } => unimplemented!("wasi::path_open for Buffer type files"),
+            Kind::Buffer { buffer } => {
+                if o_flags & __WASI_O_DIRECTORY != 0 {
+                    return __WASI_ENOTDIR;
+                }
+                if o_flags & __WASI_O_EXCL != 0 {
+                    return __WASI_EEXIST;
+                }
+                open_flags |= Fd::READ;
+                if adjusted_rights & __WASI_RIGHT_FD_WRITE != 0 {
+                    open_flags |= Fd::WRITE;
+                    if o_flags & __WASI_O_TRUNC != 0 {
+                        open_flags |= Fd::TRUNCATE;
+                        buffer.clear();
+                    }
+                }
+            }
             Kind::Dir { .. } | Kind::Root { .. } => {
                 // TODO: adjust these to be correct
                 if o_flags & __WASI_O_EXCL != 0 && path_arg.exists() {
                     return __WASI_EEXIST;
                 }
             }
-            Kind::Symlink {
-                base_po_dir,
-                path_to_symlink,
-                relative_path,
-            } => {
-                // I think this should return an error (because symlinks should be resolved away by the path traversal)
-                // TODO: investigate this
-                unimplemented!("SYMLINKS IN PATH_OPEN");
-            }
+            // the last symlink of the path is only resolved when following
+            // symlinks, and a symlink can't be opened itself
+            Kind::Symlink { .. } => return __WASI_ELOOP,
         }
         inode
     } else {
         // less-happy path, we have to try to create the file
         debug!("Maybe creating file");
-        if o_flags & __WASI_O_CREAT != 0 {
+        if o_flags & __WASI_O_CREAT != 0 && maybe_inode == Err(__WASI_ENOENT) {
             if o_flags & __WASI_O_DIRECTORY != 0 {
                 return __WASI_ENOTDIR;
             }
             debug!("Creating file");
+            wasi_try!(state.fs.check_created_entries_quota());
             // strip end file name
 
-            let (parent_inode, new_entity_name) = wasi_try!(state.fs.get_parent_inode_at_path(
-                dirfd,
-                &path_arg,
-                dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0
-            ));
+            let (parent_inode, new_entity_name) =
+                wasi_try!(state.fs.get_parent_inode_at_path(dirfd, &path_arg));
             let new_file_host_path = match &state.fs.inodes[parent_inode].kind {
                 Kind::Dir { path, .. } => {
                     let mut new_path = path.clone();
@@ -1856,6 +1999,7 @@ pub fn path_open(
             {
                 entries.insert(new_entity_name, new_inode);
             }
+            state.fs.record_created_entry();
 
             new_inode
         } else {
@@ -1897,7 +2041,7 @@ pub fn path_open_dynamic(env: &WasiEnv, params: &[Value]) -> Result<Vec<Value>,
     let fs_flags: __wasi_fdflags_t = params[7].unwrap_i32() as _;
     let fd: WasmPtr<__wasi_fd_t> = params[8].unwrap_i32().into();
 
-    Ok(vec![Value::I32(path_open(
+    Ok(vec![Value::I32(crate::trace::path_open(
         env,
         dirfd,
         dirflags,
@@ -1981,15 +2125,15 @@ pub fn path_remove_directory(
     // TODO check if fd is a dir, ensure it's within sandbox, etc.
     debug!("wasi::path_remove_directory");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::FsWrite));
 
     let base_dir = wasi_try!(state.fs.fd_map.get(&fd), __WASI_EBADF);
     let path_str = unsafe { get_input_str!(memory, path, path_len) };
 
     let inode = wasi_try!(state.fs.get_inode_at_path(fd, path_str, false));
-    let (parent_inode, childs_name) =
-        wasi_try!(state
-            .fs
-            .get_parent_inode_at_path(fd, std::path::Path::new(path_str), false));
+    let (parent_inode, childs_name) = wasi_try!(state
+        .fs
+        .get_parent_inode_at_path(fd, std::path::Path::new(path_str)));
 
     let host_path_to_remove = match &state.fs.inodes[inode].kind {
         Kind::Dir { entries, path, .. } => {
@@ -2062,6 +2206,7 @@ pub fn path_rename(
         old_fd, new_fd
     );
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::FsWrite));
     let source_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
     let source_path = std::path::Path::new(source_str);
     let target_str = unsafe { get_input_str!(memory, new_path, new_path_len) };
@@ -2080,18 +2225,16 @@ pub fn path_rename(
     }
 
     let (source_parent_inode, source_entry_name) =
-        wasi_try!(state.fs.get_parent_inode_at_path(old_fd, source_path, true));
+        wasi_try!(state.fs.get_parent_inode_at_path(old_fd, source_path));
     let (target_parent_inode, target_entry_name) =
-        wasi_try!(state.fs.get_parent_inode_at_path(new_fd, target_path, true));
+        wasi_try!(state.fs.get_parent_inode_at_path(new_fd, target_path));
 
     let host_adjusted_target_path = match &state.fs.inodes[target_parent_inode].kind {
         Kind::Dir { entries, path, .. } => {
             if entries.contains_key(&target_entry_name) {
                 return __WASI_EEXIST;
             }
-            let mut out_path = path.clone();
-            out_path.push(target_path);
-            out_path
+            path.join(&target_entry_name)
         }
         Kind::Root { .. } => return __WASI_ENOTCAPABLE,
         Kind::Symlink { .. } | Kind::File { .. } | Kind::Buffer { .. } => {
@@ -2106,35 +2249,41 @@ pub fn path_rename(
         }
     };
 
-    match &mut state.fs.inodes[source_entry].kind {
-        Kind::File {
-            handle,
-            ref mut path,
-            ..
-        } => {
-            let result = if let Some(h) = handle {
+    // a directory can't be moved inside itself
+    let moves_into_itself = state.fs.is_inode_within(target_parent_inode, source_entry);
+    let result = match &mut state.fs.inodes[source_entry].kind {
+        Kind::File { handle, path, .. } => {
+            if let Some(h) = handle {
                 h.rename_file(&host_adjusted_target_path)
                     .map_err(|e| e.into_wasi_err())
             } else {
-                let out =
-                    std::fs::rename(&path, &host_adjusted_target_path).map_err(|_| __WASI_EIO);
-                *path = host_adjusted_target_path;
-                out
-            };
-            // if the above operation failed we have to revert the previous change and then fail
-            if let Err(e) = result {
-                if let Kind::Dir { entries, .. } = &mut state.fs.inodes[source_parent_inode].kind {
-                    entries.insert(source_entry_name, source_entry);
-                    return e;
-                }
+                std::fs::rename(&path, &host_adjusted_target_path).map_err(|_| __WASI_EIO)
             }
         }
-        Kind::Dir { path, .. } => unimplemented!("wasi::path_rename on Directories"),
-        Kind::Buffer { .. } => {}
-        Kind::Symlink { .. } => {}
+        Kind::Dir { .. } if moves_into_itself => Err(__WASI_EINVAL),
+        Kind::Dir { path, .. } => std::fs::rename(&path, &host_adjusted_target_path)
+            .map_err(|e| WasiFsError::from(e).into_wasi_err()),
+        Kind::Buffer { .. } => Ok(()),
+        Kind::Symlink { .. } => Ok(()),
         Kind::Root { .. } => unreachable!("The root can not be moved"),
+    };
+    // if the above operation failed we have to revert the previous change and then fail
+    if let Err(e) = result {
+        if let Kind::Dir { entries, .. } = &mut state.fs.inodes[source_parent_inode].kind {
+            entries.insert(source_entry_name, source_entry);
+        }
+        return e;
     }
 
+    // the files and directories moved on the host, with all their contents
+    state
+        .fs
+        .update_host_paths(source_entry, host_adjusted_target_path);
+    if let Kind::Dir { parent, .. } = &mut state.fs.inodes[source_entry].kind {
+        *parent = Some(target_parent_inode);
+    }
+    state.fs.inodes[source_entry].name = target_entry_name.clone();
+
     if let Kind::Dir { entries, .. } = &mut state.fs.inodes[target_parent_inode].kind {
         let result = entries.insert(target_entry_name, source_entry);
         assert!(
@@ -2169,6 +2318,7 @@ pub fn path_symlink(
 ) -> __wasi_errno_t {
     debug!("wasi::path_symlink");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::FsWrite));
     let old_path_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
     let new_path_str = unsafe { get_input_str!(memory, new_path, new_path_len) };
     let base_fd = wasi_try!(state.fs.get_fd(fd));
@@ -2176,14 +2326,9 @@ pub fn path_symlink(
         return __WASI_EACCES;
     }
 
-    // get the depth of the parent + 1 (UNDER INVESTIGATION HMMMMMMMM THINK FISH ^ THINK FISH)
-    let old_path_path = std::path::Path::new(old_path_str);
-    let (source_inode, _) = wasi_try!(state.fs.get_parent_inode_at_path(fd, old_path_path, true));
-    let depth = wasi_try!(state.fs.path_depth_from_fd(fd, source_inode)) - 1;
-
     let new_path_path = std::path::Path::new(new_path_str);
     let (target_parent_inode, entry_name) =
-        wasi_try!(state.fs.get_parent_inode_at_path(fd, new_path_path, true));
+        wasi_try!(state.fs.get_parent_inode_at_path(fd, new_path_path));
 
     // short circuit if anything is wrong, before we create an inode
     match &state.fs.inodes[target_parent_inode].kind {
@@ -2197,13 +2342,11 @@ pub fn path_symlink(
             unreachable!("get_parent_inode_at_path returned something other than a Dir or Root")
         }
     }
+    wasi_try!(state.fs.check_created_entries_quota());
 
-    let mut source_path = std::path::Path::new(old_path_str);
-    let mut relative_path = std::path::PathBuf::new();
-    for _ in 0..depth {
-        relative_path.push("..");
-    }
-    relative_path.push(source_path);
+    // the value of the symlink is relative to the directory containing it,
+    // it's resolved (and checked to stay in `fd`) when the symlink is followed
+    let relative_path = std::path::PathBuf::from(old_path_str);
     debug!(
         "Symlinking {} to {}",
         new_path_str,
@@ -2225,6 +2368,7 @@ pub fn path_symlink(
     {
         entries.insert(entry_name, new_inode);
     }
+    state.fs.record_created_entry();
 
     __WASI_ESUCCESS
 }
@@ -2246,6 +2390,7 @@ pub fn path_unlink_file(
 ) -> __wasi_errno_t {
     debug!("wasi::path_unlink_file");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::FsWrite));
 
     let base_dir = wasi_try!(state.fs.fd_map.get(&fd).ok_or(__WASI_EBADF));
     if !has_rights(base_dir.rights, __WASI_RIGHT_PATH_UNLINK_FILE) {
@@ -2255,10 +2400,9 @@ pub fn path_unlink_file(
     debug!("Requested file: {}", path_str);
 
     let inode = wasi_try!(state.fs.get_inode_at_path(fd, path_str, false));
-    let (parent_inode, childs_name) =
-        wasi_try!(state
-            .fs
-            .get_parent_inode_at_path(fd, std::path::Path::new(path_str), false));
+    let (parent_inode, childs_name) = wasi_try!(state
+        .fs
+        .get_parent_inode_at_path(fd, std::path::Path::new(path_str)));
 
     let removed_inode = match &mut state.fs.inodes[parent_inode].kind {
         Kind::Dir {
@@ -2293,15 +2437,15 @@ pub fn path_unlink_file(
             Kind::Symlink { .. } => {
                 // TODO: actually delete real symlinks and do nothing for virtual symlinks
             }
-            _ => unimplemented!("wasi::path_unlink_file for Buffer"),
+            // the buffer only lives in memory, there is nothing to delete
+            Kind::Buffer { .. } => {}
         }
         // TODO: test this on Windows and actually make it portable
         // make the file an orphan fd if the fd is still open
-        let fd_is_orphaned = if let Kind::File { handle, .. } = &state.fs.inodes[removed_inode].kind
-        {
-            handle.is_some()
-        } else {
-            false
+        let fd_is_orphaned = match &state.fs.inodes[removed_inode].kind {
+            Kind::File { handle, .. } => handle.is_some(),
+            Kind::Buffer { .. } => state.fs.fd_map.values().any(|fd| fd.inode == removed_inode),
+            _ => false,
         };
         let removed_inode_val = unsafe { state.fs.remove_inode(removed_inode) };
         assert!(
@@ -2322,6 +2466,13 @@ pub fn path_unlink_file(
 
 /// ### `poll_oneoff()`
 /// Concurrently poll for a set of events
+///
+/// Blocks until at least one of the files is ready, or one of the clocks
+/// times out. The clocks are read from the `WasiClock` of the state: a
+/// virtual clock advances to the deadline instead of sleeping.
+///
+/// The invalid subscriptions, to closed files for example, don't fail the
+/// call: they're reported as events with an error.
 /// Inputs:
 /// - `const __wasi_subscription_t *in`
 ///     The events to subscribe to
@@ -2341,186 +2492,275 @@ pub fn poll_oneoff(
 ) -> __wasi_errno_t {
     debug!("wasi::poll_oneoff");
     debug!("  => nsubscriptions = {}", nsubscriptions);
-    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let memory = env.memory();
 
     let subscription_array = wasi_try!(in_.deref(memory, 0, nsubscriptions));
     let event_array = wasi_try!(out_.deref(memory, 0, nsubscriptions));
-    let mut events_seen = 0;
     let out_ptr = wasi_try!(nevents.deref(memory));
 
-    let mut fds = vec![];
+    // the files polled, with their events and their subscriptions
+    let mut fd_subs = vec![];
+    // the deadlines of the clocks, with their subscriptions
     let mut clock_subs = vec![];
-    let mut in_events = vec![];
-    let mut total_ns_slept = 0;
-
-    for sub in subscription_array.iter() {
-        let s: WasiSubscription = wasi_try!(sub.get().try_into());
-        let mut peb = PollEventBuilder::new();
-        let mut ns_to_sleep = 0;
-
-        let fd = match s.event_type {
-            EventType::Read(__wasi_subscription_fs_readwrite_t { fd }) => {
-                match fd {
-                    __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
-                    _ => {
-                        let fd_entry = wasi_try!(state.fs.get_fd(fd));
-                        if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_READ) {
-                            return __WASI_EACCES;
-                        }
-                    }
+    // the events seen, starting with the errors of the invalid subscriptions
+    let mut events = vec![];
+
+    {
+        let mut state = env.state();
+        for sub in subscription_array.iter() {
+            let sub = sub.get();
+            let s: WasiSubscription = match sub.try_into() {
+                Ok(s) => s,
+                Err(error) => {
+                    events.push(poll_event(&sub, error, 0, 0));
+                    continue;
                 }
-                in_events.push(peb.add(PollEvent::PollIn).build());
-                Some(fd)
-            }
-            EventType::Write(__wasi_subscription_fs_readwrite_t { fd }) => {
-                match fd {
-                    __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
-                    _ => {
-                        let fd_entry = wasi_try!(state.fs.get_fd(fd));
-
-                        if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_WRITE) {
-                            return __WASI_EACCES;
-                        }
-                    }
+            };
+
+            let (fd, event, right) = match s.event_type {
+                EventType::Read(__wasi_subscription_fs_readwrite_t { fd }) => {
+                    (fd, PollEvent::PollIn, __WASI_RIGHT_FD_READ)
                 }
-                in_events.push(peb.add(PollEvent::PollOut).build());
-                Some(fd)
-            }
-            EventType::Clock(clock_info) => {
-                if clock_info.clock_id == __WASI_CLOCK_REALTIME {
-                    // this is a hack
-                    // TODO: do this properly
-                    ns_to_sleep = clock_info.timeout;
-                    clock_subs.push(clock_info);
-                    None
-                } else {
-                    unimplemented!("Polling not implemented for clocks yet");
+                EventType::Write(__wasi_subscription_fs_readwrite_t { fd }) => {
+                    (fd, PollEvent::PollOut, __WASI_RIGHT_FD_WRITE)
                 }
-            }
-        };
-
-        if let Some(fd) = fd {
-            let wasi_file_ref: &dyn WasiFile = match fd {
-                __WASI_STDERR_FILENO => wasi_try!(
-                    wasi_try!(state.fs.stderr().map_err(WasiFsError::into_wasi_err)).as_ref(),
-                    __WASI_EBADF
-                )
-                .as_ref(),
-                __WASI_STDIN_FILENO => wasi_try!(
-                    wasi_try!(state.fs.stdin().map_err(WasiFsError::into_wasi_err)).as_ref(),
-                    __WASI_EBADF
-                )
-                .as_ref(),
-                __WASI_STDOUT_FILENO => wasi_try!(
-                    wasi_try!(state.fs.stdout().map_err(WasiFsError::into_wasi_err)).as_ref(),
-                    __WASI_EBADF
-                )
-                .as_ref(),
-                _ => {
-                    let fd_entry = wasi_try!(state.fs.get_fd(fd));
-                    let inode = fd_entry.inode;
-                    if !has_rights(fd_entry.rights, __WASI_RIGHT_POLL_FD_READWRITE) {
-                        return __WASI_EACCES;
-                    }
-
-                    match &state.fs.inodes[inode].kind {
-                        Kind::File { handle, .. } => {
-                            if let Some(h) = handle {
-                                h.as_ref()
-                            } else {
-                                return __WASI_EBADF;
-                            }
-                        }
-                        Kind::Dir { .. }
-                        | Kind::Root { .. }
-                        | Kind::Buffer { .. }
-                        | Kind::Symlink { .. } => {
-                            unimplemented!("polling read on non-files not yet supported")
-                        }
+                EventType::Clock(clock_info) => {
+                    match clock_deadline(state.clock.as_mut(), &clock_info) {
+                        Ok(deadline) => clock_subs.push((clock_info, deadline, sub)),
+                        Err(error) => events.push(poll_event(&sub, error, 0, 0)),
                     }
+                    continue;
                 }
             };
-            fds.push(wasi_file_ref);
-        } else {
-            let remaining_ns = ns_to_sleep as i64 - total_ns_slept as i64;
-            if remaining_ns > 0 {
-                debug!("Sleeping for {} nanoseconds", remaining_ns);
-                let duration = std::time::Duration::from_nanos(remaining_ns as u64);
-                std::thread::sleep(duration);
-                total_ns_slept += remaining_ns;
+            // check the fd and its rights before blocking
+            match poll_fd(&state.fs, fd, 0, right) {
+                Ok(_) => fd_subs.push((fd, PollEventBuilder::new().add(event).build(), right, sub)),
+                Err(error) => events.push(poll_event(&sub, error, 0, 0)),
             }
         }
     }
-    let mut seen_events = vec![Default::default(); in_events.len()];
-    wasi_try!(poll(
-        fds.as_slice(),
-        in_events.as_slice(),
-        seen_events.as_mut_slice()
-    )
-    .map_err(|e| e.into_wasi_err()));
-
-    for (i, seen_event) in seen_events.into_iter().enumerate() {
-        let mut flags = 0;
-        let mut error = __WASI_EAGAIN;
-        let mut bytes_available = 0;
-        let event_iter = iterate_poll_events(seen_event);
-        for event in event_iter {
-            match event {
-                PollEvent::PollError => error = __WASI_EIO,
-                PollEvent::PollHangUp => flags = __WASI_EVENT_FD_READWRITE_HANGUP,
-                PollEvent::PollInvalid => error = __WASI_EINVAL,
-                PollEvent::PollIn => {
-                    bytes_available =
-                        wasi_try!(fds[i].bytes_available().map_err(|e| e.into_wasi_err()));
-                    error = __WASI_ESUCCESS;
+
+    // woken up by the files without a host fd when they may be ready
+    let waiter = if fd_subs.is_empty() {
+        None
+    } else {
+        Some(wasi_try!(PollWaiter::new().map_err(|_| __WASI_EIO)))
+    };
+    // the host fds to block on, with their events
+    let mut host_fds = vec![];
+
+    loop {
+        // the state is only locked while polling, so that other threads can
+        // make the files ready while the poll blocks
+        let mut state = env.state();
+        host_fds.clear();
+        let mut poll_regularly = false;
+        for (fd, in_events, right, sub) in fd_subs.iter() {
+            // the waker is registered before polling, so that no wakeup is
+            // missed once the state is unlocked
+            if let Some(waiter) = &waiter {
+                poll_regularly |=
+                    !register_fd_wakeup(&mut state.fs, *fd, *in_events, waiter, &mut host_fds);
+            }
+            let (seen_events, bytes_available) = match poll_fd(&state.fs, *fd, *in_events, *right) {
+                Ok(polled) => polled,
+                // the file has been closed meanwhile, for example
+                Err(error) => {
+                    events.push(poll_event(sub, error, 0, 0));
+                    continue;
                 }
-                PollEvent::PollOut => {
-                    bytes_available =
-                        wasi_try!(fds[i].bytes_available().map_err(|e| e.into_wasi_err()));
-                    error = __WASI_ESUCCESS;
+            };
+            if seen_events == 0 {
+                continue;
+            }
+            let mut flags = 0;
+            let mut error = __WASI_ESUCCESS;
+            for event in iterate_poll_events(seen_events) {
+                match event {
+                    PollEvent::PollError => error = __WASI_EIO,
+                    PollEvent::PollHangUp => flags = __WASI_EVENT_FD_READWRITE_HANGUP,
+                    PollEvent::PollInvalid => error = __WASI_EINVAL,
+                    PollEvent::PollIn | PollEvent::PollOut => (),
                 }
             }
+            events.push(poll_event(sub, error, bytes_available as u64, flags));
         }
-        let event = __wasi_event_t {
-            userdata: subscription_array[i].get().userdata,
-            error,
-            type_: subscription_array[i].get().type_,
-            u: unsafe {
-                __wasi_event_u {
-                    fd_readwrite: __wasi_event_fd_readwrite_t {
-                        nbytes: bytes_available as u64,
-                        flags,
-                    },
+        // the clock with the nearest deadline, and the time left until then
+        let mut next_timeout: Option<(__wasi_clockid_t, __wasi_timestamp_t)> = None;
+        for (clock_info, deadline, sub) in clock_subs.iter() {
+            let now = match state
+                .clock
+                .time_get(clock_info.clock_id, clock_info.precision)
+            {
+                Ok(now) => now,
+                Err(error) => {
+                    events.push(poll_event(sub, error, 0, 0));
+                    continue;
                 }
-            },
-        };
-        event_array[events_seen].set(event);
-        events_seen += 1;
-    }
-    for clock_info in clock_subs {
-        let event = __wasi_event_t {
-            // TOOD: review userdata value
-            userdata: 0,
-            error: __WASI_ESUCCESS,
-            type_: __WASI_EVENTTYPE_CLOCK,
-            u: unsafe {
-                __wasi_event_u {
-                    fd_readwrite: __wasi_event_fd_readwrite_t {
-                        nbytes: 0,
-                        flags: 0,
-                    },
+            };
+            if now < *deadline {
+                let timeout = *deadline - now;
+                if next_timeout.map_or(true, |(_, next)| timeout < next) {
+                    next_timeout = Some((clock_info.clock_id, timeout));
                 }
-            },
+                continue;
+            }
+            events.push(poll_event(sub, __WASI_ESUCCESS, 0, 0));
+        }
+
+        if !events.is_empty() || (fd_subs.is_empty() && clock_subs.is_empty()) {
+            break;
+        }
+
+        // block until the next deadline, and until the files may be ready:
+        // the clocks are read again meanwhile
+        let mut timeout = match next_timeout {
+            Some((clock_id, timeout)) => Some(wasi_try!(state.clock.sleep(clock_id, timeout))),
+            None => None,
         };
-        event_array[events_seen].set(event);
-        events_seen += 1;
+        if poll_regularly {
+            timeout = Some(timeout.map_or(POLL_INTERVAL, |timeout| timeout.min(POLL_INTERVAL)));
+        }
+        drop(state);
+        trace!("Blocking for {:?}", timeout);
+        match &waiter {
+            Some(waiter) => wasi_try!(waiter.wait(&host_fds, timeout).map_err(|_| __WASI_EIO)),
+            // only the clocks are polled, so there is a timeout
+            None => std::thread::sleep(timeout.unwrap_or_default()),
+        }
     }
-    out_ptr.set(events_seen as u32);
+
+    for (event, out) in events.iter().zip(event_array) {
+        out.set(*event);
+    }
+    out_ptr.set(events.len() as u32);
     __WASI_ESUCCESS
 }
 
+/// The time between two polls of the files that can't wake up `poll_oneoff`
+/// when they are ready, see [`WasiFile::register_poll_waker`].
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1);
+
+/// Returns the event of the subscription `sub`.
+fn poll_event(
+    sub: &__wasi_subscription_t,
+    error: __wasi_errno_t,
+    nbytes: __wasi_filesize_t,
+    flags: __wasi_eventrwflags_t,
+) -> __wasi_event_t {
+    __wasi_event_t {
+        userdata: sub.userdata,
+        error,
+        type_: sub.type_,
+        u: __wasi_event_u {
+            fd_readwrite: __wasi_event_fd_readwrite_t { nbytes, flags },
+        },
+    }
+}
+
+/// Prepares `waiter` to block until the file opened as `fd` may be ready for
+/// `events`: its host fd is added to `host_fds`, or the file is registered
+/// a waker of `waiter`.
+///
+/// Returns `false` if the file can't wake up the poll, which must then poll
+/// it again regularly.
+fn register_fd_wakeup(
+    fs: &mut WasiFs,
+    fd: __wasi_fd_t,
+    events: PollEventSet,
+    waiter: &std::sync::Arc<PollWaiter>,
+    host_fds: &mut Vec<(i32, PollEventSet)>,
+) -> bool {
+    // the errors are reported by `poll_fd`
+    let inode = match fs.get_fd(fd) {
+        Ok(fd_entry) => fd_entry.inode,
+        Err(_) => return true,
+    };
+    match &mut fs.inodes[inode].kind {
+        Kind::File {
+            handle: Some(handle),
+            ..
+        } => {
+            #[cfg(unix)]
+            {
+                if let Some(host_fd) = handle.get_raw_fd() {
+                    host_fds.push((host_fd, events));
+                    return true;
+                }
+            }
+            #[cfg(not(unix))]
+            let _ = (events, host_fds);
+            handle.register_poll_waker(&waiter.waker())
+        }
+        // the buffers never block
+        _ => true,
+    }
+}
+
+/// Polls the file opened as `fd` for `events`, and returns the events it's
+/// ready for with the number of bytes available.
+fn poll_fd(
+    fs: &WasiFs,
+    fd: __wasi_fd_t,
+    events: PollEventSet,
+    right: __wasi_rights_t,
+) -> Result<(PollEventSet, usize), __wasi_errno_t> {
+    let fd_entry = fs.get_fd(fd)?;
+    let is_stdio = matches!(
+        fd,
+        __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO
+    );
+    if !is_stdio
+        && (!has_rights(fd_entry.rights, right)
+            || !has_rights(fd_entry.rights, __WASI_RIGHT_POLL_FD_READWRITE))
+    {
+        return Err(__WASI_EACCES);
+    }
+
+    match &fs.inodes[fd_entry.inode].kind {
+        Kind::File {
+            handle: Some(handle),
+            ..
+        } => {
+            let seen_events = handle
+                .poll_ready(events)
+                .map_err(WasiFsError::into_wasi_err)?;
+            // the number of bytes is only a hint, it shouldn't fail the whole poll
+            Ok((seen_events, handle.bytes_available().unwrap_or(0)))
+        }
+        // the buffers never block
+        Kind::Buffer { buffer } => Ok((
+            events,
+            buffer.len().saturating_sub(fd_entry.offset as usize),
+        )),
+        Kind::File { handle: None, .. }
+        | Kind::Dir { .. }
+        | Kind::Root { .. }
+        | Kind::Symlink { .. } => Err(__WASI_EBADF),
+    }
+}
+
+/// Returns the time of its clock at which the clock subscription `clock_info` times out.
+fn clock_deadline(
+    clock: &mut dyn WasiClock,
+    clock_info: &__wasi_subscription_clock_t,
+) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+    // read the clock even for an absolute deadline, to check its id
+    let now = clock.time_get(clock_info.clock_id, clock_info.precision)?;
+
+    if clock_info.flags & __WASI_SUBSCRIPTION_CLOCK_ABSTIME != 0 {
+        Ok(clock_info.timeout)
+    } else {
+        Ok(now.saturating_add(clock_info.timeout))
+    }
+}
+
 pub fn proc_exit(env: &WasiEnv, code: __wasi_exitcode_t) {
     debug!("wasi::proc_exit, {}", code);
+    let allowed = env.state().policy.is_allowed(WasiCapability::Exit);
+    if !allowed {
+        RuntimeError::raise(Box::new(WasiError::Denied("proc_exit")));
+    }
     RuntimeError::raise(Box::new(WasiError::Exit(code)));
     unreachable!();
 }
@@ -2539,18 +2779,14 @@ pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> __wasi_errno_t {
 ///     The number of bytes that will be written
 pub fn random_get(env: &WasiEnv, buf: WasmPtr<u8, Array>, buf_len: u32) -> __wasi_errno_t {
     debug!("wasi::random_get buf_len: {}", buf_len);
-    let memory = env.memory();
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    wasi_try!(state.policy.check(WasiCapability::Random));
 
     let buf = wasi_try!(buf.deref(memory, 0, buf_len));
 
-    let res = unsafe {
-        let u8_buffer = &mut *(buf as *const [_] as *mut [_] as *mut [u8]);
-        getrandom::getrandom(u8_buffer)
-    };
-    match res {
-        Ok(()) => __WASI_ESUCCESS,
-        Err(_) => __WASI_EIO,
-    }
+    let u8_buffer = unsafe { &mut *(buf as *const [_] as *mut [_] as *mut [u8]) };
+    wasi_try!(state.random.random_get(u8_buffer));
+    __WASI_ESUCCESS
 }
 
 /// ### `sched_yield()`
@@ -2588,3 +2824,430 @@ pub fn sock_shutdown(env: &WasiEnv, sock: __wasi_fd_t, how: __wasi_sdflags_t) ->
     debug!("wasi::sock_shutdown");
     unimplemented!("wasi::sock_shutdown")
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::SyscallTester;
+    use crate::{bounded_pipe, Pipe, VirtualClock};
+    use std::time::{Duration, Instant};
+
+    fn clock_subscription(userdata: u64, timeout: Duration) -> __wasi_subscription_t {
+        __wasi_subscription_t {
+            userdata,
+            type_: __WASI_EVENTTYPE_CLOCK,
+            u: __wasi_subscription_u {
+                clock: __wasi_subscription_clock_t {
+                    clock_id: __WASI_CLOCK_MONOTONIC,
+                    timeout: timeout.as_nanos() as u64,
+                    precision: 0,
+                    flags: 0,
+                },
+            },
+        }
+    }
+
+    fn fd_subscription(
+        userdata: u64,
+        type_: __wasi_eventtype_t,
+        fd: __wasi_fd_t,
+    ) -> __wasi_subscription_t {
+        __wasi_subscription_t {
+            userdata,
+            type_,
+            u: __wasi_subscription_u {
+                fd_readwrite: __wasi_subscription_fs_readwrite_t { fd },
+            },
+        }
+    }
+
+    /// Returns the user data, the type and the number of bytes of `events`.
+    fn summary(events: Vec<__wasi_event_t>) -> Vec<(u64, __wasi_eventtype_t, u64)> {
+        events
+            .into_iter()
+            .map(|event| {
+                assert_eq!(event.error, __WASI_ESUCCESS);
+                let nbytes = match event.tagged() {
+                    Some(EventEnum::FdReadWrite { nbytes, .. }) => nbytes,
+                    None => 0,
+                };
+                (event.userdata, event.type_, nbytes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn poll_clocks() {
+        let tester = SyscallTester::new(WasiState::new("test").build().unwrap());
+
+        let start = Instant::now();
+        let events = tester
+            .poll_oneoff(&[
+                clock_subscription(1, Duration::from_secs(60)),
+                clock_subscription(2, Duration::from_millis(10)),
+            ])
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(10));
+        assert_eq!(summary(events), vec![(2, __WASI_EVENTTYPE_CLOCK, 0)]);
+
+        // a virtual clock advances to the deadline instead of sleeping
+        let tester = SyscallTester::new(
+            WasiState::new("test")
+                .clock(Box::new(VirtualClock::frozen(0)))
+                .build()
+                .unwrap(),
+        );
+        let start = Instant::now();
+        let events = tester
+            .poll_oneoff(&[clock_subscription(1, Duration::from_secs(60))])
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(60));
+        assert_eq!(summary(events), vec![(1, __WASI_EVENTTYPE_CLOCK, 0)]);
+        assert_eq!(
+            tester.env.state().clock.time_get(__WASI_CLOCK_MONOTONIC, 0),
+            Ok(60_000_000_000)
+        );
+    }
+
+    #[test]
+    fn poll_fds() {
+        let (stdin_reader, mut stdin_writer) = bounded_pipe(16);
+        let mut builder = WasiState::new("test");
+        builder
+            .stdin(Box::new(stdin_reader))
+            .stdout(Box::new(Pipe::new()))
+            .stderr(Box::new(Pipe::new()))
+            .preopen_file(|p| {
+                p.buffer(b"act = 1".to_vec())
+                    .alias("config.toml")
+                    .read(true)
+            })
+            .unwrap();
+        let tester = SyscallTester::new(builder.build().unwrap());
+        let buffer_fd = *tester.env.state().fs.preopen_fds.last().unwrap();
+
+        // the empty pipes aren't readable, but the buffers are always ready
+        let events = tester
+            .poll_oneoff(&[
+                fd_subscription(1, __WASI_EVENTTYPE_FD_READ, __WASI_STDIN_FILENO),
+                fd_subscription(2, __WASI_EVENTTYPE_FD_READ, __WASI_STDERR_FILENO),
+                fd_subscription(3, __WASI_EVENTTYPE_FD_WRITE, __WASI_STDOUT_FILENO),
+                fd_subscription(4, __WASI_EVENTTYPE_FD_READ, buffer_fd),
+            ])
+            .unwrap();
+        assert_eq!(
+            summary(events),
+            vec![
+                (3, __WASI_EVENTTYPE_FD_WRITE, 0),
+                (4, __WASI_EVENTTYPE_FD_READ, 7)
+            ]
+        );
+
+        // the poll waits for the pipe to be written, without locking the
+        // state
+        let env = tester.env.clone();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            assert_eq!(env.state().fs.fd_map.len(), 5);
+            stdin_writer.write_all(b"hamlet").unwrap();
+        });
+        let events = tester
+            .poll_oneoff(&[
+                fd_subscription(1, __WASI_EVENTTYPE_FD_READ, __WASI_STDIN_FILENO),
+                clock_subscription(2, Duration::from_secs(60)),
+            ])
+            .unwrap();
+        writer.join().unwrap();
+        assert_eq!(summary(events), vec![(1, __WASI_EVENTTYPE_FD_READ, 6)]);
+
+        // the invalid subscriptions are reported as errors, without failing
+        // the others
+        let events = tester
+            .poll_oneoff(&[
+                fd_subscription(1, __WASI_EVENTTYPE_FD_READ, 42),
+                fd_subscription(2, __WASI_EVENTTYPE_FD_READ, __WASI_STDIN_FILENO),
+                fd_subscription(3, 42, __WASI_STDIN_FILENO),
+            ])
+            .unwrap();
+        let errors = events
+            .iter()
+            .map(|event| (event.userdata, event.error))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![(1, __WASI_EBADF), (3, __WASI_EINVAL), (2, __WASI_ESUCCESS)]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn poll_host_fds() {
+        use std::os::unix::io::FromRawFd;
+
+        // a host pipe, polled through its host fd
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let reader = unsafe { std::fs::File::from_raw_fd(fds[0]) };
+        let mut writer = unsafe { std::fs::File::from_raw_fd(fds[1]) };
+        let tester = SyscallTester::new(
+            WasiState::new("test")
+                .stdin(Box::new(HostFile::new(
+                    reader,
+                    "pipe".into(),
+                    true,
+                    false,
+                    false,
+                )))
+                .build()
+                .unwrap(),
+        );
+
+        let writer_thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            writer.write_all(b"hamlet").unwrap();
+        });
+        let start = Instant::now();
+        let events = tester
+            .poll_oneoff(&[
+                fd_subscription(1, __WASI_EVENTTYPE_FD_READ, __WASI_STDIN_FILENO),
+                clock_subscription(2, Duration::from_secs(60)),
+            ])
+            .unwrap();
+        writer_thread.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(60));
+        assert_eq!(summary(events), vec![(1, __WASI_EVENTTYPE_FD_READ, 6)]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn open_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let preopened = dir.path().join("preopened");
+        std::fs::create_dir_all(preopened.join("act1")).unwrap();
+        std::fs::write(preopened.join("act1/scene1.txt"), "to be").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "or not").unwrap();
+        symlink("act1/scene1.txt", preopened.join("scene")).unwrap();
+        symlink("act1", preopened.join("first_act")).unwrap();
+        symlink("loop_b", preopened.join("loop_a")).unwrap();
+        symlink("loop_a", preopened.join("loop_b")).unwrap();
+        symlink("../secret.txt", preopened.join("escape")).unwrap();
+        symlink(
+            dir.path().join("secret.txt"),
+            preopened.join("absolute_escape"),
+        )
+        .unwrap();
+
+        let mut builder = WasiState::new("test");
+        builder.preopen_dir(&preopened).unwrap();
+        let tester = SyscallTester::new(builder.build().unwrap());
+        let dirfd = tester.env.state().fs.preopen_fds[1];
+        let open =
+            |dirflags, path| tester.path_open(dirfd, dirflags, path, 0, __WASI_RIGHT_FD_READ, 0);
+        let follow = __WASI_LOOKUP_SYMLINK_FOLLOW;
+
+        // the last symlink is only followed with `__WASI_LOOKUP_SYMLINK_FOLLOW`,
+        // the others always are
+        let fd = open(follow, "scene").unwrap();
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"to be".to_vec()));
+        assert_eq!(open(0, "scene"), Err(__WASI_ELOOP));
+        let fd = open(0, "first_act/scene1.txt").unwrap();
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"to be".to_vec()));
+
+        // the loops are detected
+        assert_eq!(open(follow, "loop_a"), Err(__WASI_ELOOP));
+        assert_eq!(open(0, "loop_a/scene1.txt"), Err(__WASI_ELOOP));
+
+        // the symlinks can't resolve outside of their preopened directory
+        assert_eq!(open(follow, "escape"), Err(__WASI_ENOTCAPABLE));
+        assert_eq!(open(follow, "absolute_escape"), Err(__WASI_ENOTCAPABLE));
+    }
+
+    #[test]
+    fn parent_dirs_stay_in_their_preopened_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("act1/scene1")).unwrap();
+        std::fs::create_dir(dir.path().join("act2")).unwrap();
+        std::fs::write(dir.path().join("act1/scene1.txt"), "to be").unwrap();
+        std::fs::write(dir.path().join("act2/scene1.txt"), "or not").unwrap();
+
+        let mut builder = WasiState::new("test");
+        builder
+            .preopen_dir(dir.path().join("act1"))
+            .unwrap()
+            .preopen_dir(dir.path().join("act2"))
+            .unwrap();
+        let tester = SyscallTester::new(builder.build().unwrap());
+        let dirfd = tester.env.state().fs.preopen_fds[1];
+        let open = |path| tester.path_open(dirfd, 0, path, 0, __WASI_RIGHT_FD_READ, 0);
+
+        let fd = open("scene1/../scene1.txt").unwrap();
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"to be".to_vec()));
+
+        // `..` doesn't reach the virtual root, nor the other preopened
+        // directory
+        assert_eq!(open(".."), Err(__WASI_ENOTCAPABLE));
+        assert_eq!(
+            open("scene1/../../act2/scene1.txt"),
+            Err(__WASI_ENOTCAPABLE)
+        );
+        let act2 = dir.path().join("act2/scene1.txt");
+        assert_eq!(
+            open(&format!("..{}", act2.to_string_lossy())),
+            Err(__WASI_ENOTCAPABLE)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_resolve_in_the_innermost_preopened_dir() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("act1")).unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "or not").unwrap();
+        symlink("../secret.txt", dir.path().join("act1/escape")).unwrap();
+
+        // the outer directory is preopened first, but the symlink is in
+        // the inner one
+        let mut builder = WasiState::new("test");
+        builder
+            .preopen_dir(dir.path())
+            .unwrap()
+            .preopen_dir(dir.path().join("act1"))
+            .unwrap();
+        let tester = SyscallTester::new(builder.build().unwrap());
+        let outer_fd = tester.env.state().fs.preopen_fds[1];
+        let inner_fd = tester.env.state().fs.preopen_fds[2];
+        let open = |dirfd, path| {
+            tester.path_open(
+                dirfd,
+                __WASI_LOOKUP_SYMLINK_FOLLOW,
+                path,
+                0,
+                __WASI_RIGHT_FD_READ,
+                0,
+            )
+        };
+
+        assert_eq!(open(inner_fd, "escape"), Err(__WASI_ENOTCAPABLE));
+        let fd = open(outer_fd, "secret.txt").unwrap();
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"or not".to_vec()));
+    }
+
+    #[test]
+    fn rename_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("act1/scene1")).unwrap();
+        std::fs::create_dir(dir.path().join("acts")).unwrap();
+        std::fs::write(dir.path().join("act1/scene1/lines.txt"), "to be").unwrap();
+
+        let mut builder = WasiState::new("test");
+        builder.preopen_dir(dir.path()).unwrap();
+        let tester = SyscallTester::new(builder.build().unwrap());
+        let dirfd = tester.env.state().fs.preopen_fds[1];
+        let open = |path| tester.path_open(dirfd, 0, path, 0, __WASI_RIGHT_FD_READ, 0);
+        open("act1/scene1/lines.txt").unwrap();
+
+        // a directory can't be moved into its own subtree
+        assert_eq!(
+            tester.path_rename(dirfd, "act1", dirfd, "act1/scene1/act1"),
+            Err(__WASI_EINVAL)
+        );
+        assert!(dir.path().join("act1/scene1/lines.txt").exists());
+
+        // the host paths of the loaded contents follow the directory
+        tester
+            .path_rename(dirfd, "act1", dirfd, "acts/first")
+            .unwrap();
+        assert!(dir.path().join("acts/first/scene1/lines.txt").exists());
+        assert_eq!(open("act1/scene1/lines.txt"), Err(__WASI_ENOENT));
+        let fd = open("acts/first/scene1/lines.txt").unwrap();
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"to be".to_vec()));
+        let state = tester.env.state();
+        let inode = state.fs.get_fd(fd).unwrap().inode;
+        assert!(matches!(
+            &state.fs.inodes[inode].kind,
+            Kind::File { path, .. } if *path == dir.path().join("acts/first/scene1/lines.txt")
+        ));
+    }
+
+    #[test]
+    fn buffer_files() {
+        let mut builder = WasiState::new("test");
+        builder
+            .preopen_file(|p| {
+                p.buffer(b"to be".to_vec())
+                    .alias("notes.txt")
+                    .read(true)
+                    .write(true)
+            })
+            .unwrap();
+        let tester = SyscallTester::new(builder.build().unwrap());
+        let file_fd = *tester.env.state().fs.preopen_fds.last().unwrap();
+        let fd = tester
+            .path_open(
+                file_fd,
+                0,
+                ".",
+                0,
+                __WASI_RIGHT_FD_READ | __WASI_RIGHT_FD_WRITE | __WASI_RIGHT_FD_SEEK,
+                0,
+            )
+            .unwrap();
+        let size = |tester: &SyscallTester| {
+            let state = tester.env.state();
+            state.fs.inodes[state.fs.get_fd(fd).unwrap().inode]
+                .stat
+                .st_size
+        };
+
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"to be".to_vec()));
+        assert_eq!(tester.fd_write(fd, b" or not"), Ok(7));
+        assert_eq!(size(&tester), 12);
+
+        // the writes past the end grow the buffer
+        assert_eq!(tester.fd_seek(fd, -3, __WASI_WHENCE_END), Ok(9));
+        assert_eq!(tester.fd_write(fd, b"to be"), Ok(5));
+        assert_eq!(size(&tester), 14);
+        assert_eq!(tester.fd_seek(fd, 0, __WASI_WHENCE_SET), Ok(0));
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"to be or to be".to_vec()));
+        assert_eq!(tester.fd_seek(fd, -5, __WASI_WHENCE_CUR), Ok(9));
+        assert_eq!(tester.fd_read(fd, 16), Ok(b"to be".to_vec()));
+    }
+
+    #[test]
+    fn write_struct_zeroes_padding() {
+        let store = wasmer::Store::default();
+        let memory = Memory::new(&store, wasmer::MemoryType::new(1, None, false)).unwrap();
+        let mut bytes = [0u8; 48];
+
+        // The padding after `type_`, and after `flags` in the union.
+        memory.write(0, &[0xff; 48]).unwrap();
+        let event = __wasi_event_t {
+            userdata: 1,
+            error: __WASI_ESUCCESS,
+            type_: __WASI_EVENTTYPE_FD_READ,
+            u: EventEnum::FdReadWrite {
+                nbytes: 2,
+                flags: 0,
+            }
+            .untagged(),
+        };
+        memory.write_struct(0, &event).unwrap();
+        memory.read(0, &mut bytes).unwrap();
+        assert_eq!(bytes[11..16], [0; 5]);
+        assert_eq!(bytes[26..32], [0; 6]);
+        assert_eq!(memory.read_struct::<__wasi_event_t>(0).unwrap().userdata, 1);
+
+        // The inactive part of the union.
+        memory.write(0, &[0xff; 48]).unwrap();
+        let subscription = fd_subscription(1, __WASI_EVENTTYPE_FD_WRITE, 3);
+        memory.write_struct(0, &subscription).unwrap();
+        memory.read(0, &mut bytes).unwrap();
+        assert_eq!(bytes[9..16], [0; 7]);
+        assert_eq!(bytes[16..20], 3u32.to_le_bytes());
+        assert_eq!(bytes[20..], [0; 28]);
+    }
+}
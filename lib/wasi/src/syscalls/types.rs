@@ -4,9 +4,41 @@ use crate::ptr::{Array, WasmPtr};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use std::mem;
+use std::mem::{self, MaybeUninit};
 use wasmer::ValueType;
 
+/// Zeroes the bytes of `$bytes`, the in-memory representation of `$value`,
+/// which aren't part of one of the given fields: the padding bytes, and
+/// the bytes of the inactive variants of the unions.
+macro_rules! zero_bytes_around_fields {
+    ($value:expr, $bytes:expr, [$($field:expr),* $(,)?]) => {{
+        let base = $value as *const _ as usize;
+        // The fields of the unions are only read for their address.
+        #[allow(unused_unsafe)]
+        let mut fields = unsafe {
+            [$((&$field as *const _ as usize - base, ::std::mem::size_of_val(&$field))),*]
+        };
+        $crate::syscalls::types::zero_bytes_around(&mut fields, $bytes);
+    }};
+}
+
+/// Zeroes the bytes of `bytes` which aren't covered by `fields`, given as
+/// `(offset, size)` pairs.
+fn zero_bytes_around(fields: &mut [(usize, usize)], bytes: &mut [MaybeUninit<u8>]) {
+    fields.sort_unstable();
+
+    let mut end = 0;
+    for &(offset, size) in fields.iter() {
+        for byte in &mut bytes[end..offset] {
+            *byte = MaybeUninit::new(0);
+        }
+        end = offset + size;
+    }
+    for byte in &mut bytes[end..] {
+        *byte = MaybeUninit::new(0);
+    }
+}
+
 pub type __wasi_advice_t = u8;
 pub const __WASI_ADVICE_NORMAL: u8 = 0;
 pub const __WASI_ADVICE_SEQUENTIAL: u8 = 1;
@@ -22,7 +54,10 @@ pub struct __wasi_ciovec_t {
     pub buf_len: u32,
 }
 
-unsafe impl ValueType for __wasi_ciovec_t {}
+unsafe impl ValueType for __wasi_ciovec_t {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
 
 pub type __wasi_clockid_t = u32;
 pub const __WASI_CLOCK_REALTIME: u32 = 0;
@@ -44,7 +79,15 @@ pub struct __wasi_dirent_t {
     pub d_type: __wasi_filetype_t,
 }
 
-unsafe impl ValueType for __wasi_dirent_t {}
+unsafe impl ValueType for __wasi_dirent_t {
+    fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+        zero_bytes_around_fields!(
+            self,
+            bytes,
+            [self.d_next, self.d_ino, self.d_namlen, self.d_type]
+        );
+    }
+}
 
 pub fn dirent_to_le_bytes(ent: &__wasi_dirent_t) -> Vec<u8> {
     use std::mem::transmute;
@@ -148,6 +191,90 @@ pub const __WASI_ETXTBSY: u16 = 74;
 pub const __WASI_EXDEV: u16 = 75;
 pub const __WASI_ENOTCAPABLE: u16 = 76;
 
+/// Returns the name of an errno, as in `"ENOENT"`.
+pub fn errno_to_str(errno: __wasi_errno_t) -> &'static str {
+    match errno {
+        __WASI_ESUCCESS => "ESUCCESS",
+        __WASI_E2BIG => "E2BIG",
+        __WASI_EACCES => "EACCES",
+        __WASI_EADDRINUSE => "EADDRINUSE",
+        __WASI_EADDRNOTAVAIL => "EADDRNOTAVAIL",
+        __WASI_EAFNOSUPPORT => "EAFNOSUPPORT",
+        __WASI_EAGAIN => "EAGAIN",
+        __WASI_EALREADY => "EALREADY",
+        __WASI_EBADF => "EBADF",
+        __WASI_EBADMSG => "EBADMSG",
+        __WASI_EBUSY => "EBUSY",
+        __WASI_ECANCELED => "ECANCELED",
+        __WASI_ECHILD => "ECHILD",
+        __WASI_ECONNABORTED => "ECONNABORTED",
+        __WASI_ECONNREFUSED => "ECONNREFUSED",
+        __WASI_ECONNRESET => "ECONNRESET",
+        __WASI_EDEADLK => "EDEADLK",
+        __WASI_EDESTADDRREQ => "EDESTADDRREQ",
+        __WASI_EDOM => "EDOM",
+        __WASI_EDQUOT => "EDQUOT",
+        __WASI_EEXIST => "EEXIST",
+        __WASI_EFAULT => "EFAULT",
+        __WASI_EFBIG => "EFBIG",
+        __WASI_EHOSTUNREACH => "EHOSTUNREACH",
+        __WASI_EIDRM => "EIDRM",
+        __WASI_EILSEQ => "EILSEQ",
+        __WASI_EINPROGRESS => "EINPROGRESS",
+        __WASI_EINTR => "EINTR",
+        __WASI_EINVAL => "EINVAL",
+        __WASI_EIO => "EIO",
+        __WASI_EISCONN => "EISCONN",
+        __WASI_EISDIR => "EISDIR",
+        __WASI_ELOOP => "ELOOP",
+        __WASI_EMFILE => "EMFILE",
+        __WASI_EMLINK => "EMLINK",
+        __WASI_EMSGSIZE => "EMSGSIZE",
+        __WASI_EMULTIHOP => "EMULTIHOP",
+        __WASI_ENAMETOOLONG => "ENAMETOOLONG",
+        __WASI_ENETDOWN => "ENETDOWN",
+        __WASI_ENETRESET => "ENETRESET",
+        __WASI_ENETUNREACH => "ENETUNREACH",
+        __WASI_ENFILE => "ENFILE",
+        __WASI_ENOBUFS => "ENOBUFS",
+        __WASI_ENODEV => "ENODEV",
+        __WASI_ENOENT => "ENOENT",
+        __WASI_ENOEXEC => "ENOEXEC",
+        __WASI_ENOLCK => "ENOLCK",
+        __WASI_ENOLINK => "ENOLINK",
+        __WASI_ENOMEM => "ENOMEM",
+        __WASI_ENOMSG => "ENOMSG",
+        __WASI_ENOPROTOOPT => "ENOPROTOOPT",
+        __WASI_ENOSPC => "ENOSPC",
+        __WASI_ENOSYS => "ENOSYS",
+        __WASI_ENOTCONN => "ENOTCONN",
+        __WASI_ENOTDIR => "ENOTDIR",
+        __WASI_ENOTEMPTY => "ENOTEMPTY",
+        __WASI_ENOTRECOVERABLE => "ENOTRECOVERABLE",
+        __WASI_ENOTSOCK => "ENOTSOCK",
+        __WASI_ENOTSUP => "ENOTSUP",
+        __WASI_ENOTTY => "ENOTTY",
+        __WASI_ENXIO => "ENXIO",
+        __WASI_EOVERFLOW => "EOVERFLOW",
+        __WASI_EOWNERDEAD => "EOWNERDEAD",
+        __WASI_EPERM => "EPERM",
+        __WASI_EPIPE => "EPIPE",
+        __WASI_EPROTO => "EPROTO",
+        __WASI_EPROTONOSUPPORT => "EPROTONOSUPPORT",
+        __WASI_EPROTOTYPE => "EPROTOTYPE",
+        __WASI_ERANGE => "ERANGE",
+        __WASI_EROFS => "EROFS",
+        __WASI_ESPIPE => "ESPIPE",
+        __WASI_ESRCH => "ESRCH",
+        __WASI_ESTALE => "ESTALE",
+        __WASI_ETIMEDOUT => "ETIMEDOUT",
+        __WASI_ETXTBSY => "ETXTBSY",
+        __WASI_EXDEV => "EXDEV",
+        __WASI_ENOTCAPABLE => "ENOTCAPABLE",
+        _ => "INVALID ERRNO",
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(C)]
 pub struct __wasi_event_fd_readwrite_t {
@@ -209,7 +336,22 @@ impl __wasi_event_t {
     }
 }
 
-unsafe impl ValueType for __wasi_event_t {}
+unsafe impl ValueType for __wasi_event_t {
+    fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+        // `fd_readwrite` is the only variant of `u`.
+        zero_bytes_around_fields!(
+            self,
+            bytes,
+            [
+                self.userdata,
+                self.error,
+                self.type_,
+                self.u.fd_readwrite.nbytes,
+                self.u.fd_readwrite.flags,
+            ]
+        );
+    }
+}
 
 pub type __wasi_eventrwflags_t = u16;
 pub const __WASI_EVENT_FD_READWRITE_HANGUP: u16 = 1 << 0;
@@ -251,7 +393,10 @@ pub struct __wasi_prestat_u_dir_t {
     pub pr_name_len: u32,
 }
 
-unsafe impl ValueType for __wasi_prestat_u_dir_t {}
+unsafe impl ValueType for __wasi_prestat_u_dir_t {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
 
 #[derive(Copy, Clone)]
 #[repr(C)]
@@ -265,7 +410,10 @@ impl fmt::Debug for __wasi_prestat_u {
     }
 }
 
-unsafe impl ValueType for __wasi_prestat_u {}
+unsafe impl ValueType for __wasi_prestat_u {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
 
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
@@ -301,7 +449,12 @@ impl __wasi_prestat_t {
     }
 }
 
-unsafe impl ValueType for __wasi_prestat_t {}
+unsafe impl ValueType for __wasi_prestat_t {
+    fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+        // `dir` is the only variant of `u`.
+        zero_bytes_around_fields!(self, bytes, [self.pr_type, self.u.dir.pr_name_len]);
+    }
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(C)]
@@ -312,7 +465,20 @@ pub struct __wasi_fdstat_t {
     pub fs_rights_inheriting: __wasi_rights_t,
 }
 
-unsafe impl ValueType for __wasi_fdstat_t {}
+unsafe impl ValueType for __wasi_fdstat_t {
+    fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+        zero_bytes_around_fields!(
+            self,
+            bytes,
+            [
+                self.fs_filetype,
+                self.fs_flags,
+                self.fs_rights_base,
+                self.fs_rights_inheriting,
+            ]
+        );
+    }
+}
 
 pub type __wasi_filedelta_t = i64;
 
@@ -374,7 +540,24 @@ impl std::fmt::Debug for __wasi_filestat_t {
     }
 }
 
-unsafe impl ValueType for __wasi_filestat_t {}
+unsafe impl ValueType for __wasi_filestat_t {
+    fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+        zero_bytes_around_fields!(
+            self,
+            bytes,
+            [
+                self.st_dev,
+                self.st_ino,
+                self.st_filetype,
+                self.st_nlink,
+                self.st_size,
+                self.st_atim,
+                self.st_mtim,
+                self.st_ctim,
+            ]
+        );
+    }
+}
 
 pub fn wasi_filetype_to_name(ft: __wasi_filetype_t) -> &'static str {
     match ft {
@@ -415,7 +598,10 @@ pub struct __wasi_iovec_t {
     pub buf_len: u32,
 }
 
-unsafe impl ValueType for __wasi_iovec_t {}
+unsafe impl ValueType for __wasi_iovec_t {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
 
 pub type __wasi_linkcount_t = u64;
 
@@ -672,7 +858,30 @@ impl std::fmt::Debug for __wasi_subscription_t {
     }
 }
 
-unsafe impl ValueType for __wasi_subscription_t {}
+unsafe impl ValueType for __wasi_subscription_t {
+    fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+        match self.type_ {
+            __WASI_EVENTTYPE_CLOCK => zero_bytes_around_fields!(
+                self,
+                bytes,
+                [
+                    self.userdata,
+                    self.type_,
+                    self.u.clock.clock_id,
+                    self.u.clock.timeout,
+                    self.u.clock.precision,
+                    self.u.clock.flags,
+                ]
+            ),
+            __WASI_EVENTTYPE_FD_READ | __WASI_EVENTTYPE_FD_WRITE => zero_bytes_around_fields!(
+                self,
+                bytes,
+                [self.userdata, self.type_, self.u.fd_readwrite.fd]
+            ),
+            _ => zero_bytes_around_fields!(self, bytes, [self.userdata, self.type_]),
+        }
+    }
+}
 
 pub enum SubscriptionEnum {
     Clock(__wasi_subscription_clock_t),
@@ -705,6 +914,7 @@ pub const __WASI_WHENCE_END: u8 = 2;
 pub mod snapshot0 {
     use serde::{Deserialize, Serialize};
     pub type __wasi_linkcount_t = u32;
+    use std::mem::MaybeUninit;
     use wasmer::ValueType;
 
     #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -732,7 +942,33 @@ pub mod snapshot0 {
         pub u: __wasi_subscription_u,
     }
 
-    unsafe impl ValueType for __wasi_subscription_t {}
+    unsafe impl ValueType for __wasi_subscription_t {
+        fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+            match self.type_ {
+                super::__WASI_EVENTTYPE_CLOCK => zero_bytes_around_fields!(
+                    self,
+                    bytes,
+                    [
+                        self.userdata,
+                        self.type_,
+                        self.u.clock.userdata,
+                        self.u.clock.clock_id,
+                        self.u.clock.timeout,
+                        self.u.clock.precision,
+                        self.u.clock.flags,
+                    ]
+                ),
+                super::__WASI_EVENTTYPE_FD_READ | super::__WASI_EVENTTYPE_FD_WRITE => {
+                    zero_bytes_around_fields!(
+                        self,
+                        bytes,
+                        [self.userdata, self.type_, self.u.fd_readwrite.fd]
+                    )
+                }
+                _ => zero_bytes_around_fields!(self, bytes, [self.userdata, self.type_]),
+            }
+        }
+    }
 
     pub type __wasi_whence_t = u8;
     pub const __WASI_WHENCE_CUR: u8 = 0;
@@ -752,7 +988,24 @@ pub mod snapshot0 {
         pub st_ctim: super::__wasi_timestamp_t,
     }
 
-    unsafe impl ValueType for __wasi_filestat_t {}
+    unsafe impl ValueType for __wasi_filestat_t {
+        fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+            zero_bytes_around_fields!(
+                self,
+                bytes,
+                [
+                    self.st_dev,
+                    self.st_ino,
+                    self.st_filetype,
+                    self.st_nlink,
+                    self.st_size,
+                    self.st_atim,
+                    self.st_mtim,
+                    self.st_ctim,
+                ]
+            );
+        }
+    }
 
     impl std::fmt::Debug for __wasi_filestat_t {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -0,0 +1,217 @@
+//! Calls the WASI syscalls from the unit tests, passing their arguments
+//! through the memory like a program does.
+
+use crate::ptr::{Array, WasmPtr};
+use crate::syscalls::{self, types::*};
+use crate::{WasiEnv, WasiState};
+use wasmer::{Memory, MemoryType, Store};
+
+/// Where the syscalls write their outputs.
+const OUTPUT_OFFSET: u32 = 0;
+/// Where the iovec of the reads and writes is.
+const IOVEC_OFFSET: u32 = 16;
+/// Where the paths are.
+const PATH_OFFSET: u32 = 1024;
+/// Where the second path of the renames is.
+const NEW_PATH_OFFSET: u32 = 1536;
+/// Where the subscriptions of `poll_oneoff` are.
+const SUBSCRIPTIONS_OFFSET: u32 = 2048;
+/// Where the events of `poll_oneoff` are.
+const EVENTS_OFFSET: u32 = 4096;
+/// Where the read and written bytes are.
+const DATA_OFFSET: u32 = 8192;
+
+/// A WASI environment with a memory of one page.
+pub(crate) struct SyscallTester {
+    pub(crate) env: WasiEnv,
+}
+
+fn check(errno: __wasi_errno_t) -> Result<(), __wasi_errno_t> {
+    if errno == __WASI_ESUCCESS {
+        Ok(())
+    } else {
+        Err(errno)
+    }
+}
+
+impl SyscallTester {
+    pub(crate) fn new(state: WasiState) -> Self {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+        let mut env = WasiEnv::new(state);
+        env.memory.initialize(memory);
+        Self { env }
+    }
+
+    pub(crate) fn write_memory(&self, offset: u32, bytes: &[u8]) {
+        let view = self.env.memory().view::<u8>();
+        for (i, byte) in bytes.iter().enumerate() {
+            view[offset as usize + i].set(*byte);
+        }
+    }
+
+    pub(crate) fn read_memory(&self, offset: u32, len: u32) -> Vec<u8> {
+        let view = self.env.memory().view::<u8>();
+        view[offset as usize..(offset + len) as usize]
+            .iter()
+            .map(|cell| cell.get())
+            .collect()
+    }
+
+    pub(crate) fn read_u32(&self, offset: u32) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&self.read_memory(offset, 4));
+        u32::from_le_bytes(bytes)
+    }
+
+    pub(crate) fn read_u64(&self, offset: u32) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.read_memory(offset, 8));
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Writes `path` to the memory, and returns its pointer and length.
+    fn path(&self, path: &str) -> (WasmPtr<u8, Array>, u32) {
+        self.path_at(PATH_OFFSET, path)
+    }
+
+    fn path_at(&self, offset: u32, path: &str) -> (WasmPtr<u8, Array>, u32) {
+        self.write_memory(offset, path.as_bytes());
+        (WasmPtr::new(offset), path.len() as u32)
+    }
+
+    /// Writes an iovec of `len` bytes at the data offset.
+    fn iovec<T: Copy>(&self, len: u32) -> WasmPtr<T, Array> {
+        self.write_memory(IOVEC_OFFSET, &DATA_OFFSET.to_le_bytes());
+        self.write_memory(IOVEC_OFFSET + 4, &len.to_le_bytes());
+        WasmPtr::new(IOVEC_OFFSET)
+    }
+
+    /// Opens `path` relatively to `dirfd`, with `rights` as both the base
+    /// and the inheriting rights.
+    pub(crate) fn path_open(
+        &self,
+        dirfd: __wasi_fd_t,
+        dirflags: __wasi_lookupflags_t,
+        path: &str,
+        o_flags: __wasi_oflags_t,
+        rights: __wasi_rights_t,
+        fd_flags: __wasi_fdflags_t,
+    ) -> Result<__wasi_fd_t, __wasi_errno_t> {
+        let (path, path_len) = self.path(path);
+        check(syscalls::path_open(
+            &self.env,
+            dirfd,
+            dirflags,
+            path,
+            path_len,
+            o_flags,
+            rights,
+            rights,
+            fd_flags,
+            WasmPtr::new(OUTPUT_OFFSET),
+        ))?;
+        Ok(self.read_u32(OUTPUT_OFFSET))
+    }
+
+    /// Writes `data` to `fd`, and returns the number of bytes written.
+    pub(crate) fn fd_write(&self, fd: __wasi_fd_t, data: &[u8]) -> Result<u32, __wasi_errno_t> {
+        self.write_memory(DATA_OFFSET, data);
+        let iovs = self.iovec(data.len() as u32);
+        check(syscalls::fd_write(
+            &self.env,
+            fd,
+            iovs,
+            1,
+            WasmPtr::new(OUTPUT_OFFSET),
+        ))?;
+        Ok(self.read_u32(OUTPUT_OFFSET))
+    }
+
+    /// Reads up to `len` bytes from `fd`.
+    pub(crate) fn fd_read(&self, fd: __wasi_fd_t, len: u32) -> Result<Vec<u8>, __wasi_errno_t> {
+        let iovs = self.iovec(len);
+        check(syscalls::fd_read(
+            &self.env,
+            fd,
+            iovs,
+            1,
+            WasmPtr::new(OUTPUT_OFFSET),
+        ))?;
+        let read = self.read_u32(OUTPUT_OFFSET);
+        Ok(self.read_memory(DATA_OFFSET, read))
+    }
+
+    /// Moves the offset of `fd`, and returns the new one.
+    pub(crate) fn fd_seek(
+        &self,
+        fd: __wasi_fd_t,
+        offset: __wasi_filedelta_t,
+        whence: __wasi_whence_t,
+    ) -> Result<__wasi_filesize_t, __wasi_errno_t> {
+        check(syscalls::fd_seek(
+            &self.env,
+            fd,
+            offset,
+            whence,
+            WasmPtr::new(OUTPUT_OFFSET),
+        ))?;
+        Ok(self.read_u64(OUTPUT_OFFSET))
+    }
+
+    pub(crate) fn fd_renumber(
+        &self,
+        from: __wasi_fd_t,
+        to: __wasi_fd_t,
+    ) -> Result<(), __wasi_errno_t> {
+        check(syscalls::fd_renumber(&self.env, from, to))
+    }
+
+    pub(crate) fn path_rename(
+        &self,
+        old_fd: __wasi_fd_t,
+        old_path: &str,
+        new_fd: __wasi_fd_t,
+        new_path: &str,
+    ) -> Result<(), __wasi_errno_t> {
+        let (old_path, old_path_len) = self.path(old_path);
+        let (new_path, new_path_len) = self.path_at(NEW_PATH_OFFSET, new_path);
+        check(syscalls::path_rename(
+            &self.env,
+            old_fd,
+            old_path,
+            old_path_len,
+            new_fd,
+            new_path,
+            new_path_len,
+        ))
+    }
+
+    /// Polls the `subscriptions`, and returns the events seen.
+    pub(crate) fn poll_oneoff(
+        &self,
+        subscriptions: &[__wasi_subscription_t],
+    ) -> Result<Vec<__wasi_event_t>, __wasi_errno_t> {
+        let memory = self.env.memory();
+        let len = subscriptions.len() as u32;
+        let in_ = WasmPtr::<__wasi_subscription_t, Array>::new(SUBSCRIPTIONS_OFFSET);
+        for (cell, subscription) in in_.deref(memory, 0, len).unwrap().iter().zip(subscriptions) {
+            cell.set(*subscription);
+        }
+        let out = WasmPtr::<__wasi_event_t, Array>::new(EVENTS_OFFSET);
+        check(syscalls::poll_oneoff(
+            &self.env,
+            in_,
+            out,
+            len,
+            WasmPtr::new(OUTPUT_OFFSET),
+        ))?;
+        let nevents = self.read_u32(OUTPUT_OFFSET);
+        Ok(out
+            .deref(memory, 0, nevents)
+            .unwrap()
+            .iter()
+            .map(|cell| cell.get())
+            .collect())
+    }
+}
@@ -0,0 +1,381 @@
+//! Structured tracing of the WASI syscalls.
+//!
+//! Install a [`SyscallTracer`] with [`WasiEnv::set_syscall_tracer`] to be
+//! told about every syscall made by the program: its name, its decoded
+//! arguments, its result and how long it took.
+//!
+//! The functions of this module wrap the syscalls of the same name, and
+//! are the ones registered in the import objects.
+
+#![allow(clippy::too_many_arguments)]
+
+use crate::ptr::{Array, WasmPtr};
+use crate::syscalls::{self, types::*};
+use crate::WasiEnv;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[cfg(all(target_os = "macos", target_arch = "aarch64",))]
+pub(crate) use crate::syscalls::path_open_dynamic;
+
+/// A decoded argument of a syscall.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyscallArg {
+    /// A file descriptor.
+    Fd(__wasi_fd_t),
+    /// An integer: a size, an offset, a clock id...
+    Int(u64),
+    /// A signed integer, like the offset of `fd_seek`.
+    SignedInt(i64),
+    /// A set of flags or rights.
+    Flags(u64),
+    /// A path read from the memory of the program. Invalid UTF-8 is
+    /// replaced by `U+FFFD`.
+    Path(String),
+}
+
+/// A syscall made by the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyscallEvent {
+    /// The name of the syscall, like `path_open`.
+    pub name: &'static str,
+    /// The decoded arguments, by name. The pointers to the buffers and to
+    /// the outputs are not included.
+    pub args: Vec<(&'static str, SyscallArg)>,
+    /// The errno returned by the syscall, or `None` for `proc_exit`, which
+    /// doesn't return.
+    pub errno: Option<__wasi_errno_t>,
+    /// The time spent in the syscall.
+    pub duration: Duration,
+}
+
+/// A sink for the syscalls made by a program.
+pub trait SyscallTracer: fmt::Debug + Send + Sync {
+    /// Called on the thread of the program once a syscall has returned,
+    /// with its decoded arguments, its errno and its duration. For
+    /// `proc_exit`, it's called before exiting, without an errno.
+    ///
+    /// The syscalls are reported in the order they are made, and the
+    /// program is blocked until this returns: slow tracers slow the
+    /// program down.
+    fn on_syscall(&self, event: &SyscallEvent);
+}
+
+fn read_path(env: &WasiEnv, path: WasmPtr<u8, Array>, path_len: u32) -> Option<String> {
+    let memory = env.memory_ref()?;
+    let cells = path.deref(memory, 0, path_len).ok()?;
+    let bytes = cells.iter().map(|cell| cell.get()).collect::<Vec<u8>>();
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+macro_rules! trace_arg {
+    ($env:expr, fd, $value:expr) => {
+        Some(SyscallArg::Fd($value))
+    };
+    ($env:expr, int, $value:expr) => {
+        Some(SyscallArg::Int($value.into()))
+    };
+    ($env:expr, signed, $value:expr) => {
+        Some(SyscallArg::SignedInt($value))
+    };
+    ($env:expr, flags, $value:expr) => {
+        Some(SyscallArg::Flags($value.into()))
+    };
+    ($env:expr, path, $value:expr, $len:expr) => {
+        read_path($env, $value, $len).map(SyscallArg::Path)
+    };
+}
+
+/// Defines wrappers reporting the calls to the syscalls of `$module` to
+/// the tracer of the environment. The arguments annotated with a kind are
+/// decoded into the event.
+macro_rules! traced_syscalls {
+    ($module:ident { $(
+        fn $name:ident($($arg:ident: $ty:ty $([$kind:ident $(($len:ident))?])?),* $(,)?);
+    )* }) => { $(
+        pub(crate) fn $name(env: &WasiEnv, $($arg: $ty),*) -> __wasi_errno_t {
+            let tracer = match &env.syscall_tracer {
+                Some(tracer) => tracer,
+                None => return $module::$name(env, $($arg),*),
+            };
+
+            #[allow(unused_mut)]
+            let mut args = Vec::new();
+            $($(
+                if let Some(value) = trace_arg!(env, $kind, $arg $(, $len)?) {
+                    args.push((stringify!($arg), value));
+                }
+            )?)*
+
+            let start = Instant::now();
+            let errno = $module::$name(env, $($arg),*);
+            tracer.on_syscall(&SyscallEvent {
+                name: stringify!($name),
+                args,
+                errno: Some(errno),
+                duration: start.elapsed(),
+            });
+            errno
+        }
+    )* };
+}
+
+traced_syscalls!(syscalls {
+    fn args_get(argv: WasmPtr<WasmPtr<u8, Array>, Array>, argv_buf: WasmPtr<u8, Array>);
+    fn args_sizes_get(argc: WasmPtr<u32>, argv_buf_size: WasmPtr<u32>);
+    fn clock_res_get(clock_id: __wasi_clockid_t [int], resolution: WasmPtr<__wasi_timestamp_t>);
+    fn clock_time_get(
+        clock_id: __wasi_clockid_t [int],
+        precision: __wasi_timestamp_t [int],
+        time: WasmPtr<__wasi_timestamp_t>,
+    );
+    fn environ_get(environ: WasmPtr<WasmPtr<u8, Array>, Array>, environ_buf: WasmPtr<u8, Array>);
+    fn environ_sizes_get(environ_count: WasmPtr<u32>, environ_buf_size: WasmPtr<u32>);
+    fn fd_advise(
+        fd: __wasi_fd_t [fd],
+        offset: __wasi_filesize_t [int],
+        len: __wasi_filesize_t [int],
+        advice: __wasi_advice_t [int],
+    );
+    fn fd_allocate(
+        fd: __wasi_fd_t [fd],
+        offset: __wasi_filesize_t [int],
+        len: __wasi_filesize_t [int],
+    );
+    fn fd_close(fd: __wasi_fd_t [fd]);
+    fn fd_datasync(fd: __wasi_fd_t [fd]);
+    fn fd_fdstat_get(fd: __wasi_fd_t [fd], buf_ptr: WasmPtr<__wasi_fdstat_t>);
+    fn fd_fdstat_set_flags(fd: __wasi_fd_t [fd], flags: __wasi_fdflags_t [flags]);
+    fn fd_fdstat_set_rights(
+        fd: __wasi_fd_t [fd],
+        fs_rights_base: __wasi_rights_t [flags],
+        fs_rights_inheriting: __wasi_rights_t [flags],
+    );
+    fn fd_filestat_get(fd: __wasi_fd_t [fd], buf: WasmPtr<__wasi_filestat_t>);
+    fn fd_filestat_set_size(fd: __wasi_fd_t [fd], st_size: __wasi_filesize_t [int]);
+    fn fd_filestat_set_times(
+        fd: __wasi_fd_t [fd],
+        st_atim: __wasi_timestamp_t [int],
+        st_mtim: __wasi_timestamp_t [int],
+        fst_flags: __wasi_fstflags_t [flags],
+    );
+    fn fd_pread(
+        fd: __wasi_fd_t [fd],
+        iovs: WasmPtr<__wasi_iovec_t, Array>,
+        iovs_len: u32 [int],
+        offset: __wasi_filesize_t [int],
+        nread: WasmPtr<u32>,
+    );
+    fn fd_prestat_get(fd: __wasi_fd_t [fd], buf: WasmPtr<__wasi_prestat_t>);
+    fn fd_prestat_dir_name(fd: __wasi_fd_t [fd], path: WasmPtr<u8, Array>, path_len: u32 [int]);
+    fn fd_pwrite(
+        fd: __wasi_fd_t [fd],
+        iovs: WasmPtr<__wasi_ciovec_t, Array>,
+        iovs_len: u32 [int],
+        offset: __wasi_filesize_t [int],
+        nwritten: WasmPtr<u32>,
+    );
+    fn fd_read(
+        fd: __wasi_fd_t [fd],
+        iovs: WasmPtr<__wasi_iovec_t, Array>,
+        iovs_len: u32 [int],
+        nread: WasmPtr<u32>,
+    );
+    fn fd_readdir(
+        fd: __wasi_fd_t [fd],
+        buf: WasmPtr<u8, Array>,
+        buf_len: u32 [int],
+        cookie: __wasi_dircookie_t [int],
+        bufused: WasmPtr<u32>,
+    );
+    fn fd_renumber(from: __wasi_fd_t [fd], to: __wasi_fd_t [fd]);
+    fn fd_seek(
+        fd: __wasi_fd_t [fd],
+        offset: __wasi_filedelta_t [signed],
+        whence: __wasi_whence_t [int],
+        newoffset: WasmPtr<__wasi_filesize_t>,
+    );
+    fn fd_sync(fd: __wasi_fd_t [fd]);
+    fn fd_tell(fd: __wasi_fd_t [fd], offset: WasmPtr<__wasi_filesize_t>);
+    fn fd_write(
+        fd: __wasi_fd_t [fd],
+        iovs: WasmPtr<__wasi_ciovec_t, Array>,
+        iovs_len: u32 [int],
+        nwritten: WasmPtr<u32>,
+    );
+    fn path_create_directory(
+        fd: __wasi_fd_t [fd],
+        path: WasmPtr<u8, Array> [path(path_len)],
+        path_len: u32,
+    );
+    fn path_filestat_get(
+        fd: __wasi_fd_t [fd],
+        flags: __wasi_lookupflags_t [flags],
+        path: WasmPtr<u8, Array> [path(path_len)],
+        path_len: u32,
+        buf: WasmPtr<__wasi_filestat_t>,
+    );
+    fn path_filestat_set_times(
+        fd: __wasi_fd_t [fd],
+        flags: __wasi_lookupflags_t [flags],
+        path: WasmPtr<u8, Array> [path(path_len)],
+        path_len: u32,
+        st_atim: __wasi_timestamp_t [int],
+        st_mtim: __wasi_timestamp_t [int],
+        fst_flags: __wasi_fstflags_t [flags],
+    );
+    fn path_link(
+        old_fd: __wasi_fd_t [fd],
+        old_flags: __wasi_lookupflags_t [flags],
+        old_path: WasmPtr<u8, Array> [path(old_path_len)],
+        old_path_len: u32,
+        new_fd: __wasi_fd_t [fd],
+        new_path: WasmPtr<u8, Array> [path(new_path_len)],
+        new_path_len: u32,
+    );
+    fn path_open(
+        dirfd: __wasi_fd_t [fd],
+        dirflags: __wasi_lookupflags_t [flags],
+        path: WasmPtr<u8, Array> [path(path_len)],
+        path_len: u32,
+        o_flags: __wasi_oflags_t [flags],
+        fs_rights_base: __wasi_rights_t [flags],
+        fs_rights_inheriting: __wasi_rights_t [flags],
+        fs_flags: __wasi_fdflags_t [flags],
+        fd: WasmPtr<__wasi_fd_t>,
+    );
+    fn path_readlink(
+        dir_fd: __wasi_fd_t [fd],
+        path: WasmPtr<u8, Array> [path(path_len)],
+        path_len: u32,
+        buf: WasmPtr<u8, Array>,
+        buf_len: u32 [int],
+        buf_used: WasmPtr<u32>,
+    );
+    fn path_remove_directory(
+        fd: __wasi_fd_t [fd],
+        path: WasmPtr<u8, Array> [path(path_len)],
+        path_len: u32,
+    );
+    fn path_rename(
+        old_fd: __wasi_fd_t [fd],
+        old_path: WasmPtr<u8, Array> [path(old_path_len)],
+        old_path_len: u32,
+        new_fd: __wasi_fd_t [fd],
+        new_path: WasmPtr<u8, Array> [path(new_path_len)],
+        new_path_len: u32,
+    );
+    fn path_symlink(
+        old_path: WasmPtr<u8, Array> [path(old_path_len)],
+        old_path_len: u32,
+        fd: __wasi_fd_t [fd],
+        new_path: WasmPtr<u8, Array> [path(new_path_len)],
+        new_path_len: u32,
+    );
+    fn path_unlink_file(
+        fd: __wasi_fd_t [fd],
+        path: WasmPtr<u8, Array> [path(path_len)],
+        path_len: u32,
+    );
+    fn poll_oneoff(
+        in_: WasmPtr<__wasi_subscription_t, Array>,
+        out_: WasmPtr<__wasi_event_t, Array>,
+        nsubscriptions: u32 [int],
+        nevents: WasmPtr<u32>,
+    );
+    fn proc_raise(sig: __wasi_signal_t [int]);
+    fn random_get(buf: WasmPtr<u8, Array>, buf_len: u32 [int]);
+    fn sched_yield();
+    fn sock_recv(
+        sock: __wasi_fd_t [fd],
+        ri_data: WasmPtr<__wasi_iovec_t, Array>,
+        ri_data_len: u32 [int],
+        ri_flags: __wasi_riflags_t [flags],
+        ro_datalen: WasmPtr<u32>,
+        ro_flags: WasmPtr<__wasi_roflags_t>,
+    );
+    fn sock_send(
+        sock: __wasi_fd_t [fd],
+        si_data: WasmPtr<__wasi_ciovec_t, Array>,
+        si_data_len: u32 [int],
+        si_flags: __wasi_siflags_t [flags],
+        so_datalen: WasmPtr<u32>,
+    );
+    fn sock_shutdown(sock: __wasi_fd_t [fd], how: __wasi_sdflags_t [flags]);
+});
+
+pub(crate) fn proc_exit(env: &WasiEnv, code: __wasi_exitcode_t) {
+    if let Some(tracer) = &env.syscall_tracer {
+        tracer.on_syscall(&SyscallEvent {
+            name: "proc_exit",
+            args: vec![("code", SyscallArg::Int(code.into()))],
+            errno: None,
+            duration: Duration::default(),
+        });
+    }
+    syscalls::proc_exit(env, code)
+}
+
+pub(crate) mod legacy {
+    pub(crate) mod snapshot0 {
+        use super::super::*;
+        use crate::syscalls::legacy::snapshot0 as syscalls;
+
+        traced_syscalls!(syscalls {
+            fn fd_filestat_get(fd: __wasi_fd_t [fd], buf: WasmPtr<snapshot0::__wasi_filestat_t>);
+            fn path_filestat_get(
+                fd: __wasi_fd_t [fd],
+                flags: __wasi_lookupflags_t [flags],
+                path: WasmPtr<u8, Array> [path(path_len)],
+                path_len: u32,
+                buf: WasmPtr<snapshot0::__wasi_filestat_t>,
+            );
+            fn fd_seek(
+                fd: __wasi_fd_t [fd],
+                offset: __wasi_filedelta_t [signed],
+                whence: snapshot0::__wasi_whence_t [int],
+                newoffset: WasmPtr<__wasi_filesize_t>,
+            );
+            fn poll_oneoff(
+                in_: WasmPtr<snapshot0::__wasi_subscription_t, Array>,
+                out_: WasmPtr<__wasi_event_t, Array>,
+                nsubscriptions: u32 [int],
+                nevents: WasmPtr<u32>,
+            );
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::WasiState;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default)]
+    struct RecordingTracer {
+        events: Mutex<Vec<SyscallEvent>>,
+    }
+
+    impl SyscallTracer for RecordingTracer {
+        fn on_syscall(&self, event: &SyscallEvent) {
+            self.events.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn traced_syscalls_are_reported() {
+        let mut env = WasiState::new("test").finalize().unwrap();
+        assert_eq!(sched_yield(&env), __WASI_ESUCCESS);
+
+        let tracer = Arc::new(RecordingTracer::default());
+        env.set_syscall_tracer(tracer.clone());
+        assert_eq!(sched_yield(&env), __WASI_ESUCCESS);
+
+        let events = tracer.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, "sched_yield");
+        assert!(events[0].args.is_empty());
+        assert_eq!(events[0].errno, Some(__WASI_ESUCCESS));
+    }
+}
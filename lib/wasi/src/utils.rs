@@ -7,6 +7,21 @@ pub fn is_wasi_module(module: &Module) -> bool {
     get_wasi_version(module, false).is_some()
 }
 
+/// Check if a provided WASI module is a reactor: a library whose exports
+/// are called by the host, once initialized by its `_initialize` function.
+///
+/// A module is a reactor if it exports `_initialize`, or if it doesn't
+/// export a `_start` function to run it as a command.
+pub fn is_wasi_reactor(module: &Module) -> bool {
+    let exports_function = |name: &str| {
+        module
+            .exports()
+            .any(|export| export.name() == name && matches!(export.ty(), ExternType::Function(_)))
+    };
+
+    exports_function("_initialize") || !exports_function("_start")
+}
+
 /// The version of WASI. This is determined by the imports namespace
 /// string.
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -38,13 +38,18 @@ mod lib {
     #[cfg(feature = "core")]
     pub mod std {
         pub use alloc::{borrow, boxed, format, rc, slice, string, vec};
-        pub use core::{any, cell, convert, fmt, hash, marker, ops, ptr, sync};
+        pub use core::{any, cell, convert, fmt, hash, marker, mem, ops, ptr};
+
+        pub mod sync {
+            pub use alloc::sync::{Arc, Weak};
+            pub use core::sync::atomic;
+        }
     }
 
     #[cfg(feature = "std")]
     pub mod std {
         pub use std::{
-            any, borrow, boxed, cell, convert, fmt, format, hash, marker, ops, ptr, rc, slice,
+            any, borrow, boxed, cell, convert, fmt, format, hash, marker, mem, ops, ptr, rc, slice,
             string, sync, vec,
         };
     }
@@ -76,7 +81,8 @@ pub use crate::initializers::{
 };
 pub use crate::memory_view::{Atomically, MemoryView};
 pub use crate::native::{NativeWasmType, ValueType};
-pub use crate::r#ref::{ExternRef, HostInfo, HostRef};
+#[allow(deprecated)]
+pub use crate::r#ref::{ExternRef, HostInfo, HostRef, RawExternRef};
 pub use crate::units::{
     Bytes, PageCountOutOfRange, Pages, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
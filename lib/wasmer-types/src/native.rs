@@ -2,6 +2,8 @@
 //! easily in Rust, thanks to its advanced typing system.
 
 use crate::lib::std::fmt;
+use crate::lib::std::mem::{self, MaybeUninit};
+use crate::r#ref::RawExternRef;
 use crate::types::Type;
 use crate::values::Value;
 
@@ -172,6 +174,31 @@ impl NativeWasmType for u128 {
     }
 }
 
+impl NativeWasmType for RawExternRef {
+    const WASM_TYPE: Type = Type::ExternRef;
+    type Abi = Self;
+
+    #[inline]
+    fn from_abi(abi: Self::Abi) -> Self {
+        abi
+    }
+
+    #[inline]
+    fn into_abi(self) -> Self::Abi {
+        self
+    }
+
+    #[inline]
+    fn to_binary(self) -> i128 {
+        self.to_bits() as _
+    }
+
+    #[inline]
+    fn from_binary(bits: i128) -> Self {
+        Self::from_bits(bits as _)
+    }
+}
+
 #[cfg(test)]
 mod test_native_type {
     use super::*;
@@ -184,6 +211,7 @@ mod test_native_type {
         assert_eq!(f32::WASM_TYPE, Type::F32);
         assert_eq!(f64::WASM_TYPE, Type::F64);
         assert_eq!(u128::WASM_TYPE, Type::V128);
+        assert_eq!(RawExternRef::WASM_TYPE, Type::ExternRef);
     }
 
     #[test]
@@ -193,6 +221,10 @@ mod test_native_type {
         assert_eq!(f32::from_binary(42f32.to_binary()), 42f32);
         assert_eq!(f64::from_binary(42f64.to_binary()), 42f64);
         assert_eq!(u128::from_binary(42u128.to_binary()), 42u128);
+        assert_eq!(
+            RawExternRef::from_binary(RawExternRef::null().to_binary()),
+            RawExternRef::null()
+        );
     }
 }
 
@@ -223,18 +255,56 @@ mod test_native_type {
 /// a valid `u32`. However a `bool` is _not_ a Value type because any bit patterns
 /// other than `0` and `1` are invalid in Rust and may cause undefined behavior if
 /// a `bool` is constructed from those bytes.
+///
+/// A Value type must implement [`ValueType::zero_padding_bytes`], so that
+/// its bytes can be written to memory without exposing uninitialized
+/// data. It has no default implementation, as it would silently be wrong
+/// for the types with padding.
 pub unsafe trait ValueType: Copy
 where
     Self: Sized,
 {
+    /// Zeroes the padding bytes of `bytes`, the in-memory representation
+    /// of `self`.
+    ///
+    /// The types without padding implement it as a no-op.
+    fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]);
 }
 
 macro_rules! impl_value_type_for {
     ( $($type:ty),* ) => {
         $(
-            unsafe impl ValueType for $type {}
+            unsafe impl ValueType for $type {
+                #[inline]
+                fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+            }
         )*
     };
 }
 
 impl_value_type_for!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+macro_rules! impl_value_type_for_array {
+    ( $($len:expr),* ) => {
+        $(
+            unsafe impl<T: ValueType> ValueType for [T; $len] {
+                fn zero_padding_bytes(&self, bytes: &mut [MaybeUninit<u8>]) {
+                    let item_size = mem::size_of::<T>();
+
+                    if item_size == 0 {
+                        return;
+                    }
+
+                    for (item, item_bytes) in self.iter().zip(bytes.chunks_mut(item_size)) {
+                        item.zero_padding_bytes(item_bytes);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_value_type_for_array!(
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+    26, 27, 28, 29, 30, 31, 32
+);
@@ -1,167 +1,202 @@
-#![allow(missing_docs)]
-
 use crate::lib::std::any::Any;
 use crate::lib::std::boxed::Box;
 use crate::lib::std::cell::{self, RefCell};
 use crate::lib::std::fmt;
 use crate::lib::std::hash;
-use crate::lib::std::rc::{Rc, Weak};
-
-pub trait HostInfo {
-    fn finalize(&mut self) {}
+use crate::lib::std::mem;
+use crate::lib::std::ptr;
+use crate::lib::std::rc::Rc;
+use crate::lib::std::sync::Arc;
+
+/// The data an `ExternRef` points to.
+struct ExternRefData {
+    value: Box<dyn Any + Send + Sync>,
 }
 
-trait InternalRefBase: Any {
-    fn as_any(&self) -> &dyn Any;
-    fn host_info(&self) -> Option<cell::RefMut<Box<dyn HostInfo>>>;
-    fn set_host_info(&self, info: Option<Box<dyn HostInfo>>);
-    fn ptr_eq(&self, other: &dyn InternalRefBase) -> bool;
+/// Represents an opaque reference to any data within WebAssembly.
+///
+/// An `ExternRef` is a reference-counted handle to a host value of any
+/// type, or a null reference. Cloning it clones the handle, not the
+/// value: the clones are the same reference, see
+/// [`ExternRef::ptr_eq`].
+///
+/// ```
+/// use wasmer_types::ExternRef;
+///
+/// let reference = ExternRef::new(String::from("hello"));
+///
+/// assert_eq!(reference.downcast::<String>().map(String::as_str), Some("hello"));
+/// assert_eq!(reference.downcast::<u32>(), None);
+/// assert!(ExternRef::null().is_null());
+/// ```
+#[derive(Clone, Default)]
+#[repr(transparent)]
+pub struct ExternRef {
+    inner: Option<Arc<ExternRefData>>,
 }
 
-#[derive(Clone)]
-pub struct InternalRef(Rc<dyn InternalRefBase>);
+/// The raw representation of an [`ExternRef`], as passed to and from
+/// WebAssembly.
+///
+/// It is a borrowed pointer: it doesn't hold a reference count, so the
+/// reference must be kept alive by other means while it is in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct RawExternRef(*const ExternRefData);
+
+impl RawExternRef {
+    /// The raw representation of a null reference.
+    pub fn null() -> Self {
+        Self(ptr::null())
+    }
 
-impl InternalRef {
-    pub fn is_ref<T: 'static>(&self) -> bool {
-        let r = self.0.as_any();
-        Any::is::<HostRef<T>>(r)
+    /// Whether it represents a null reference.
+    pub fn is_null(self) -> bool {
+        self.0.is_null()
     }
-    pub fn get_ref<T: 'static>(&self) -> HostRef<T> {
-        let r = self.0.as_any();
-        r.downcast_ref::<HostRef<T>>()
-            .expect("reference is not T type")
-            .clone()
+
+    /// The raw pointer, as an integer.
+    pub fn to_bits(self) -> usize {
+        self.0 as usize
     }
-}
 
-struct AnyAndHostInfo {
-    any: Box<dyn Any>,
-    host_info: Option<Box<dyn HostInfo>>,
+    /// Creates a `RawExternRef` from the integer returned by
+    /// [`RawExternRef::to_bits`].
+    pub fn from_bits(bits: usize) -> Self {
+        Self(bits as *const ExternRefData)
+    }
 }
 
-impl Drop for AnyAndHostInfo {
-    fn drop(&mut self) {
-        if let Some(info) = &mut self.host_info {
-            info.finalize();
+impl ExternRef {
+    /// Creates a new `ExternRef` pointing to `value`.
+    pub fn new<T>(value: T) -> Self
+    where
+        T: Any + Send + Sync,
+    {
+        Self {
+            inner: Some(Arc::new(ExternRefData {
+                value: Box::new(value),
+            })),
         }
     }
-}
 
-#[derive(Clone)]
-pub struct OtherRef(Rc<RefCell<AnyAndHostInfo>>);
-
-/// Represents an opaque reference to any data within WebAssembly.
-#[derive(Clone)]
-pub enum ExternRef {
-    /// A reference to no data.
-    Null,
-    /// A reference to data stored internally.
-    Ref(InternalRef),
-    /// A reference to data located outside.
-    Other(OtherRef),
-}
+    /// Creates a null reference.
+    pub fn null() -> Self {
+        Self { inner: None }
+    }
 
-impl hash::Hash for ExternRef {
-    fn hash<H: hash::Hasher>(&self, _state: &mut H) {}
-}
+    /// Whether it is a null reference.
+    pub fn is_null(&self) -> bool {
+        self.inner.is_none()
+    }
 
-impl PartialEq for ExternRef {
-    fn eq(&self, other: &Self) -> bool {
-        // The `ExternRef`s are the same if they point to the same value
-        self.ptr_eq(other)
+    /// Returns the value it points to, if it is a `T`.
+    ///
+    /// It returns `None` for null references.
+    pub fn downcast<T>(&self) -> Option<&T>
+    where
+        T: Any + Send + Sync,
+    {
+        self.inner.as_ref()?.value.downcast_ref::<T>()
     }
-}
-impl Eq for ExternRef {}
 
-impl ExternRef {
-    /// Creates a new instance of `ExternRef` from `Box<dyn Any>`.
-    pub fn new(data: Box<dyn Any>) -> Self {
-        let info = AnyAndHostInfo {
-            any: data,
-            host_info: None,
-        };
-        Self::Other(OtherRef(Rc::new(RefCell::new(info))))
+    /// Returns true if the two `ExternRef`s point to the same value (not
+    /// just values that compare as equal).
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.as_raw() == other.as_raw()
     }
 
-    /// Creates a `Null` reference.
-    pub fn null() -> Self {
-        Self::Null
+    /// Returns the number of `ExternRef`s pointing to the same value,
+    /// including this one, or 0 for null references.
+    pub fn strong_count(&self) -> usize {
+        self.inner.as_ref().map_or(0, Arc::strong_count)
     }
 
-    /// Returns the data stored in the reference if available.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the variant isn't `ExternRef::Other`.
-    pub fn data(&self) -> cell::Ref<Box<dyn Any>> {
-        match self {
-            Self::Other(OtherRef(r)) => cell::Ref::map(r.borrow(), |r| &r.any),
-            _ => panic!("expected ExternRef::Other"),
-        }
+    /// Returns the raw representation of this reference, without
+    /// affecting the reference count.
+    pub fn as_raw(&self) -> RawExternRef {
+        RawExternRef(self.inner.as_ref().map_or_else(ptr::null, Arc::as_ptr))
     }
 
-    /// Returns true if the two `ExternRef<T>`'s point to the same value (not just
-    /// values that compare as equal).
-    pub fn ptr_eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::Null, Self::Null) => true,
-            (Self::Ref(InternalRef(ref a)), Self::Ref(InternalRef(ref b))) => a.ptr_eq(b.as_ref()),
-            (Self::Other(OtherRef(ref a)), Self::Other(OtherRef(ref b))) => Rc::ptr_eq(a, b),
-            _ => false,
-        }
+    /// Consumes this reference, returning its raw representation.
+    ///
+    /// The reference count isn't decremented: the reference must be
+    /// reclaimed with [`ExternRef::from_raw`], or it will leak.
+    pub fn into_raw(self) -> RawExternRef {
+        let raw = self.as_raw();
+        mem::forget(self);
+        raw
     }
 
-    /// Returns a mutable reference to the host information if available.
+    /// Reclaims a reference consumed by [`ExternRef::into_raw`].
     ///
-    /// # Panics
+    /// # Safety
     ///
-    /// Panics if `ExternRef` is already borrowed or `ExternRef` is `Null`.
-    pub fn host_info(&self) -> Option<cell::RefMut<Box<dyn HostInfo>>> {
-        match self {
-            Self::Null => panic!("null"),
-            Self::Ref(r) => r.0.host_info(),
-            Self::Other(r) => {
-                let info = cell::RefMut::map(r.0.borrow_mut(), |b| &mut b.host_info);
-                if info.is_none() {
-                    return None;
-                }
-                Some(cell::RefMut::map(info, |info| info.as_mut().unwrap()))
-            }
+    /// `raw` must come from [`ExternRef::into_raw`], and must be reclaimed
+    /// only once.
+    pub unsafe fn from_raw(raw: RawExternRef) -> Self {
+        Self {
+            inner: if raw.is_null() {
+                None
+            } else {
+                Some(Arc::from_raw(raw.0))
+            },
         }
     }
 
-    /// Sets the host information for an `ExternRef`.
+    /// Creates a new `ExternRef` from a borrowed raw representation,
+    /// incrementing the reference count.
     ///
-    /// # Panics
+    /// # Safety
     ///
-    /// Panics if `ExternRef` is already borrowed or `ExternRef` is `Null`.
-    pub fn set_host_info(&self, info: Option<Box<dyn HostInfo>>) {
-        match self {
-            Self::Null => panic!("null"),
-            Self::Ref(r) => r.0.set_host_info(info),
-            Self::Other(r) => {
-                r.0.borrow_mut().host_info = info;
-            }
-        }
+    /// `raw` must come from [`ExternRef::as_raw`] or
+    /// [`ExternRef::into_raw`], and the reference it was created from
+    /// must still be alive.
+    pub unsafe fn clone_from_raw(raw: RawExternRef) -> Self {
+        let borrowed = mem::ManuallyDrop::new(Self::from_raw(raw));
+        (*borrowed).clone()
+    }
+}
+
+impl hash::Hash for ExternRef {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.as_raw().to_bits().hash(state)
+    }
+}
+
+impl PartialEq for ExternRef {
+    fn eq(&self, other: &Self) -> bool {
+        // The `ExternRef`s are the same if they point to the same value
+        self.ptr_eq(other)
     }
 }
 
+impl Eq for ExternRef {}
+
 impl fmt::Debug for ExternRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Null => write!(f, "null"),
-            Self::Ref(_) => write!(f, "externref"),
-            Self::Other(_) => write!(f, "other ref"),
+        if self.is_null() {
+            write!(f, "null")
+        } else {
+            write!(f, "externref({:p})", self.as_raw().0)
         }
     }
 }
 
+/// Information attached by the host to a [`HostRef`].
+#[deprecated(note = "attach the information to the value of an `ExternRef` instead")]
+pub trait HostInfo {
+    /// Called when the `HostRef` it is attached to is dropped.
+    fn finalize(&mut self) {}
+}
+
+#[allow(deprecated)]
 struct ContentBox<T> {
     content: T,
     host_info: Option<Box<dyn HostInfo>>,
-    externref_data: Weak<dyn InternalRefBase>,
 }
 
+#[allow(deprecated)]
 impl<T> Drop for ContentBox<T> {
     fn drop(&mut self) {
         if let Some(info) = &mut self.host_info {
@@ -171,16 +206,18 @@ impl<T> Drop for ContentBox<T> {
 }
 
 /// Represents a piece of data located in the host environment.
+///
+/// It can't be handed over to WebAssembly: an [`ExternRef`] can.
+#[deprecated(note = "use `ExternRef::new` to pass host data to WebAssembly")]
 pub struct HostRef<T>(Rc<RefCell<ContentBox<T>>>);
 
+#[allow(deprecated)]
 impl<T: 'static> HostRef<T> {
     /// Creates a new `HostRef<T>` from `T`.
     pub fn new(item: T) -> Self {
-        let externref_data: Weak<Self> = Weak::new();
         let content = ContentBox {
             content: item,
             host_info: None,
-            externref_data,
         };
         Self(Rc::new(RefCell::new(content)))
     }
@@ -209,37 +246,12 @@ impl<T: 'static> HostRef<T> {
         Rc::ptr_eq(&self.0, &other.0)
     }
 
-    /// Returns an opaque reference to the wrapped data in the form of
-    /// an `ExternRef`.
+    /// Returns a mutable reference to the host information if available.
     ///
     /// # Panics
     ///
-    /// Panics if `HostRef<T>` is already mutably borrowed.
-    pub fn externref(&self) -> ExternRef {
-        let r = self.0.borrow_mut().externref_data.upgrade();
-        if let Some(r) = r {
-            return ExternRef::Ref(InternalRef(r));
-        }
-        let externref_data: Rc<dyn InternalRefBase> = Rc::new(self.clone());
-        self.0.borrow_mut().externref_data = Rc::downgrade(&externref_data);
-        ExternRef::Ref(InternalRef(externref_data))
-    }
-}
-
-impl<T: 'static> InternalRefBase for HostRef<T> {
-    fn ptr_eq(&self, other: &dyn InternalRefBase) -> bool {
-        if let Some(other) = other.as_any().downcast_ref() {
-            self.ptr_eq(other)
-        } else {
-            false
-        }
-    }
-
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
-    fn host_info(&self) -> Option<cell::RefMut<Box<dyn HostInfo>>> {
+    /// Panics if the `HostRef<T>` is already borrowed.
+    pub fn host_info(&self) -> Option<cell::RefMut<Box<dyn HostInfo>>> {
         let info = cell::RefMut::map(self.0.borrow_mut(), |b| &mut b.host_info);
         if info.is_none() {
             return None;
@@ -247,17 +259,24 @@ impl<T: 'static> InternalRefBase for HostRef<T> {
         Some(cell::RefMut::map(info, |info| info.as_mut().unwrap()))
     }
 
-    fn set_host_info(&self, info: Option<Box<dyn HostInfo>>) {
+    /// Sets the host information of the `HostRef<T>`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `HostRef<T>` is already borrowed.
+    pub fn set_host_info(&self, info: Option<Box<dyn HostInfo>>) {
         self.0.borrow_mut().host_info = info;
     }
 }
 
+#[allow(deprecated)]
 impl<T> Clone for HostRef<T> {
     fn clone(&self) -> Self {
         Self(self.0.clone())
     }
 }
 
+#[allow(deprecated)]
 impl<T: fmt::Debug> fmt::Debug for HostRef<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Ref(")?;
@@ -265,3 +284,37 @@ impl<T: fmt::Debug> fmt::Debug for HostRef<T> {
         write!(f, ")")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downcast() {
+        let reference = ExternRef::new(42u32);
+
+        assert_eq!(reference.downcast::<u32>(), Some(&42));
+        assert_eq!(reference.downcast::<u64>(), None);
+        assert_eq!(ExternRef::null().downcast::<u32>(), None);
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let reference = ExternRef::new(42u32);
+        assert_eq!(reference.strong_count(), 1);
+
+        let raw = reference.clone().into_raw();
+        assert_eq!(reference.strong_count(), 2);
+
+        let borrowed = unsafe { ExternRef::clone_from_raw(raw) };
+        assert!(borrowed.ptr_eq(&reference));
+        assert_eq!(reference.strong_count(), 3);
+
+        drop(borrowed);
+        drop(unsafe { ExternRef::from_raw(raw) });
+        assert_eq!(reference.strong_count(), 1);
+
+        assert!(ExternRef::null().into_raw().is_null());
+        assert!(unsafe { ExternRef::from_raw(RawExternRef::null()) }.is_null());
+    }
+}
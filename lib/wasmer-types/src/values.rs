@@ -2,7 +2,7 @@ use crate::lib::std::convert::TryFrom;
 use crate::lib::std::fmt;
 use crate::lib::std::ptr;
 use crate::lib::std::string::{String, ToString};
-use crate::r#ref::ExternRef;
+use crate::r#ref::{ExternRef, RawExternRef};
 use crate::types::Type;
 
 /// Possible runtime values that a WebAssembly module can either consume or
@@ -82,6 +82,9 @@ impl<T> Value<T> {
 
     /// Writes it's value to a given pointer
     ///
+    /// An `externref` is written as a [`RawExternRef`], which doesn't
+    /// hold a reference count.
+    ///
     /// # Safety
     /// `p` must be:
     /// - Sufficiently aligned for the Rust equivalent of the type in `self`
@@ -93,16 +96,21 @@ impl<T> Value<T> {
             Self::F32(u) => ptr::write(p as *mut f32, *u),
             Self::F64(u) => ptr::write(p as *mut f64, *u),
             Self::V128(b) => ptr::write(p as *mut u128, *b),
+            Self::ExternRef(r) => ptr::write(p as *mut RawExternRef, r.as_raw()),
             _ => unimplemented!("Value::write_value_to"),
         }
     }
 
     /// Gets a `Value` given a pointer and a `Type`
     ///
+    /// An `externref` is read from a [`RawExternRef`], incrementing its
+    /// reference count.
+    ///
     /// # Safety
     /// `p` must be:
     /// - Properly aligned to the specified `ty`'s Rust equivalent
     /// - Non-null and pointing to valid memory
+    /// - For `externref`s, pointing to a live [`RawExternRef`]
     pub unsafe fn read_value_from(p: *const i128, ty: Type) -> Self {
         match ty {
             Type::I32 => Self::I32(ptr::read(p as *const i32)),
@@ -110,6 +118,9 @@ impl<T> Value<T> {
             Type::F32 => Self::F32(ptr::read(p as *const f32)),
             Type::F64 => Self::F64(ptr::read(p as *const f64)),
             Type::V128 => Self::V128(ptr::read(p as *const u128)),
+            Type::ExternRef => Self::ExternRef(ExternRef::clone_from_raw(ptr::read(
+                p as *const RawExternRef,
+            ))),
             _ => unimplemented!("Value::read_value_from"),
         }
     }
@@ -0,0 +1,157 @@
+//! Testing the `externref`s handed over to compiled WebAssembly code.
+
+use crate::utils::get_store_with_features;
+use anyhow::Result;
+use wasmer::*;
+
+fn get_store() -> Store {
+    let mut features = Features::new();
+    features.reference_types(true);
+    get_store_with_features(features)
+}
+
+#[test]
+fn externref_through_wasm() -> Result<()> {
+    let store = get_store();
+    let wat = r#"(module
+        (import "env" "host_id" (func $host_id (param externref) (result externref)))
+        (func (export "id") (param externref) (result externref)
+            local.get 0
+            call $host_id))"#;
+    let module = Module::new(&store, wat)?;
+    let host_id = Function::new_native(&store, |extern_ref: ExternRef| extern_ref);
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "host_id" => host_id,
+            },
+        },
+    )?;
+
+    let reference = ExternRef::new(String::from("hello"));
+    let id = instance
+        .exports
+        .get_native_function::<ExternRef, ExternRef>("id")?;
+    let result = id.call(reference.clone())?;
+    assert!(result.ptr_eq(&reference));
+    assert_eq!(reference.strong_count(), 2);
+    drop(result);
+    assert_eq!(reference.strong_count(), 1);
+
+    let id = instance.exports.get_function("id")?;
+    let results = id.call(&[Value::ExternRef(reference.clone())])?;
+    assert!(results[0].clone().unwrap_externref().ptr_eq(&reference));
+    drop(results);
+    assert_eq!(reference.strong_count(), 1);
+
+    assert!(id.call(&[Value::ExternRef(ExternRef::null())])?[0]
+        .clone()
+        .unwrap_externref()
+        .is_null());
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(feature = "test-singlepass", ignore)]
+fn externref_global_set_by_wasm() -> Result<()> {
+    let store = get_store();
+    let wat = r#"(module
+        (global $g (export "g") (mut externref) (ref.null extern))
+        (func (export "set") (param externref)
+            local.get 0
+            global.set $g)
+        (func (export "get") (result externref)
+            global.get $g))"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let set = instance
+        .exports
+        .get_native_function::<ExternRef, ()>("set")?;
+    let get = instance
+        .exports
+        .get_native_function::<(), ExternRef>("get")?;
+    let global = instance.exports.get_global("g")?;
+
+    let reference = ExternRef::new(String::from("hello"));
+    set.call(reference.clone())?;
+    // The global holds a reference of its own.
+    assert_eq!(reference.strong_count(), 2);
+    assert!(global.get().unwrap_externref().ptr_eq(&reference));
+
+    // The value set by WebAssembly outlives the references of the host.
+    drop(reference);
+    let value = get.call()?;
+    assert_eq!(
+        value.downcast::<String>().map(String::as_str),
+        Some("hello")
+    );
+    assert_eq!(value.strong_count(), 2);
+
+    // Overwriting the value from WebAssembly releases its reference.
+    set.call(ExternRef::null())?;
+    assert!(get.call()?.is_null());
+    assert_eq!(value.strong_count(), 1);
+
+    // The value set by the host is released by WebAssembly, and the other
+    // way around.
+    global.set(Value::ExternRef(value.clone()))?;
+    assert!(get.call()?.ptr_eq(&value));
+    assert_eq!(value.strong_count(), 2);
+    set.call(ExternRef::null())?;
+    assert_eq!(value.strong_count(), 1);
+    set.call(value.clone())?;
+    global.set(Value::ExternRef(ExternRef::null()))?;
+    assert_eq!(value.strong_count(), 1);
+
+    // Dropping the instance releases the reference of the global.
+    set.call(value.clone())?;
+    assert_eq!(value.strong_count(), 2);
+    drop((set, get));
+    drop(instance);
+    assert_eq!(value.strong_count(), 1);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(feature = "test-singlepass", ignore)]
+fn externref_imported_global() -> Result<()> {
+    let store = get_store();
+    let wat = r#"(module
+        (global $g (import "env" "g") (mut externref))
+        (func (export "swap") (param externref) (result externref)
+            global.get $g
+            local.get 0
+            global.set $g))"#;
+    let module = Module::new(&store, wat)?;
+    let first = ExternRef::new(1u32);
+    let global = Global::new_mut(&store, Value::ExternRef(first.clone()));
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "g" => global.clone(),
+            },
+        },
+    )?;
+    let swap = instance
+        .exports
+        .get_native_function::<ExternRef, ExternRef>("swap")?;
+
+    let second = ExternRef::new(2u32);
+    let previous = swap.call(second.clone())?;
+    assert!(previous.ptr_eq(&first));
+    assert!(global.get().unwrap_externref().ptr_eq(&second));
+    assert_eq!(first.strong_count(), 2);
+    assert_eq!(second.strong_count(), 2);
+
+    drop(previous);
+    assert_eq!(first.strong_count(), 1);
+    drop(global);
+    drop((swap, instance));
+    assert_eq!(second.strong_count(), 1);
+
+    Ok(())
+}
@@ -2,6 +2,7 @@ use crate::utils::get_store_with_middlewares;
 use anyhow::Result;
 
 use std::sync::Arc;
+use wasmer::vm::ModuleInfo;
 use wasmer::wasmparser::Operator;
 use wasmer::*;
 
@@ -208,3 +209,105 @@ fn middleware_chain_order_2() -> Result<()> {
     assert_eq!(result, 48);
     Ok(())
 }
+
+#[derive(Debug, Default)]
+struct CallCounterGen {
+    helper_index: std::sync::Mutex<Option<FunctionIndex>>,
+    import_index: std::sync::Mutex<Option<FunctionIndex>>,
+}
+
+#[derive(Debug)]
+struct CallCounter {
+    helper_index: FunctionIndex,
+    entered: bool,
+}
+
+impl ModuleMiddleware for CallCounterGen {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(CallCounter {
+            helper_index: self.helper_index.lock().unwrap().unwrap(),
+            entered: false,
+        })
+    }
+
+    fn add_imports(&self, _: &ModuleInfo, additions: &mut ModuleAdditions) {
+        let import_index =
+            additions.add_function_import("host", "count", FunctionType::new(vec![], vec![]));
+        *self.import_index.lock().unwrap() = Some(import_index);
+    }
+
+    fn add_definitions(&self, _: &ModuleInfo, additions: &mut ModuleAdditions) {
+        let import_index = self.import_index.lock().unwrap().unwrap();
+        // No locals, `call $count`, `end`.
+        let body = vec![0x00, 0x10, import_index.as_u32() as u8, 0x0b];
+        let helper_index =
+            additions.add_function("count_calls", FunctionType::new(vec![], vec![]), body);
+        *self.helper_index.lock().unwrap() = Some(helper_index);
+    }
+}
+
+impl FunctionMiddleware for CallCounter {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.entered {
+            self.entered = true;
+            state.push_operator(Operator::Call {
+                function_index: self.helper_index.as_u32(),
+            });
+        }
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+#[test]
+fn middleware_additions() -> Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let store = get_store_with_middlewares(std::iter::once(
+        Arc::new(CallCounterGen::default()) as Arc<dyn ModuleMiddleware>
+    ));
+    let wat = r#"(module
+        (import "env" "double" (func $double (param i32) (result i32)))
+        (func $add_one (param $x i32) (result i32)
+           (i32.add (local.get $x) (i32.const 1)))
+        (func (export "run") (param i32) (result i32)
+           (call $double (call $add_one (local.get 0))))
+)"#;
+    let module = Module::new(&store, wat).unwrap();
+    assert_eq!(module.imports().count(), 2);
+
+    // The local functions are shifted after the added import, and the
+    // helper function comes last.
+    let add_one = FunctionIndex::from_u32(2);
+    let helper = FunctionIndex::from_u32(4);
+    assert_eq!(module.function_name(add_one), Some("add_one"));
+    assert_eq!(module.local_name(add_one, 0), Some("x"));
+    assert_eq!(module.function_name(helper), Some("count_calls"));
+    assert_eq!(module.function_body_size(helper), Some(4));
+
+    let import_object = imports! {
+        "env" => {
+            "double" => Function::new_native(&store, |x: i32| x * 2),
+        },
+        "host" => {
+            "count" => Function::new_native(&store, || {
+                CALLS.fetch_add(1, Ordering::SeqCst);
+            }),
+        },
+    };
+
+    let instance = Instance::new(&module, &import_object)?;
+
+    let f: NativeFunc<i32, i32> = instance.exports.get_native_function("run")?;
+    let result = f.call(4)?;
+    assert_eq!(result, 10);
+    // Both `run` and `$add_one` have been entered.
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+    Ok(())
+}
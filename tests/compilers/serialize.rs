@@ -75,3 +75,21 @@ fn test_deserialize() -> Result<()> {
     assert_eq!(result.to_vec(), vec![Value::I64(1500)]);
     Ok(())
 }
+
+#[test]
+#[cfg(feature = "test-jit")]
+fn test_deserialize_rejects_other_format_versions() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, "(module (func (export \"run\")))")?;
+    let mut serialized_bytes = module.serialize()?;
+
+    // the format version follows the `\0wasmer-jit` header
+    let version_offset = b"\0wasmer-jit".len();
+    let version = wasmer_engine::SERIALIZED_ARTIFACT_VERSION + 1;
+    serialized_bytes[version_offset..version_offset + 4].copy_from_slice(&version.to_le_bytes());
+
+    let headless_store = get_headless_store();
+    let result = unsafe { Module::deserialize(&headless_store, &serialized_bytes) };
+    assert!(matches!(result, Err(DeserializeError::Incompatible(_))));
+    Ok(())
+}
@@ -38,3 +38,112 @@ pub fn run_wasi(wast_path: &str, base_dir: &str, compiler: &str) -> anyhow::Resu
 
     Ok(())
 }
+
+#[test]
+fn wasi_reactors_are_initialized_once() -> anyhow::Result<()> {
+    use wasmer::{Instance, Module};
+    use wasmer_wasi::{is_wasi_reactor, WasiInstanceError, WasiState};
+
+    let store = get_store(false);
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "sched_yield" (func (result i32)))
+            (memory (export "memory") 1)
+            (global $initializations (mut i32) (i32.const 0))
+            (func (export "_initialize")
+                (global.set $initializations
+                    (i32.add (global.get $initializations) (i32.const 1))))
+            (func (export "initializations") (result i32)
+                (global.get $initializations))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    assert!(is_wasi_reactor(&module));
+
+    let mut wasi_env = WasiState::new("reactor").finalize()?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+
+    wasi_env.initialize(&instance)?;
+    wasi_env.initialize(&instance)?;
+    let initializations = instance.exports.get_function("initializations")?;
+    assert_eq!(initializations.call(&[])?[0].unwrap_i32(), 1);
+
+    // the other instances sharing the environment are initialized too
+    let other_instance = Instance::new(&module, &import_object)?;
+    wasi_env.initialize(&other_instance)?;
+    let initializations = other_instance.exports.get_function("initializations")?;
+    assert_eq!(initializations.call(&[])?[0].unwrap_i32(), 1);
+
+    // the reactors can't be started
+    assert!(matches!(
+        wasi_env.start(&instance),
+        Err(WasiInstanceError::Reactor)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn wasi_reactors_initializations_can_be_retried() -> anyhow::Result<()> {
+    use wasmer::{Instance, Module};
+    use wasmer_wasi::WasiState;
+
+    let store = get_store(false);
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "sched_yield" (func (result i32)))
+            (memory (export "memory") 1)
+            (global $attempts (mut i32) (i32.const 0))
+            (func (export "_initialize")
+                (global.set $attempts (i32.add (global.get $attempts) (i32.const 1)))
+                (if (i32.eq (global.get $attempts) (i32.const 1))
+                    (then unreachable)))
+            (func (export "attempts") (result i32)
+                (global.get $attempts))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let mut wasi_env = WasiState::new("reactor").finalize()?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+
+    // the first attempt traps, so the instance isn't initialized yet
+    assert!(wasi_env.initialize(&instance).is_err());
+    wasi_env.initialize(&instance)?;
+    wasi_env.initialize(&instance)?;
+    let attempts = instance.exports.get_function("attempts")?;
+    assert_eq!(attempts.call(&[])?[0].unwrap_i32(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn wasi_modules_can_be_instantiated_through_the_linker() -> anyhow::Result<()> {
+    use wasmer::{Linker, Module};
+    use wasmer_wasi::WasiState;
+
+    let store = get_store(false);
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "args_sizes_get"
+                (func $args_sizes_get (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "argc") (result i32)
+                (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))
+                (i32.load (i32.const 0)))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+
+    let wasi_env = WasiState::new("program").arg("a").arg("b").finalize()?;
+    let mut linker = Linker::new(&store);
+    wasi_env.add_to_linker(&mut linker)?;
+    let instance = linker.instantiate(&module)?;
+
+    // the environment is initialized with the memory of the instance
+    let argc = instance.exports.get_native_function::<(), i32>("argc")?;
+    assert_eq!(argc.call()?, 3);
+
+    Ok(())
+}